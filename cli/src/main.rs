@@ -18,7 +18,11 @@ use color_eyre::eyre::{
     self,
     WrapErr,
 };
-use frame_metadata::RuntimeMetadataPrefixed;
+use frame_metadata::{
+    RuntimeMetadata,
+    RuntimeMetadataLastVersion,
+    RuntimeMetadataPrefixed,
+};
 use scale::{
     Decode,
     Input,
@@ -64,6 +68,10 @@ enum Command {
     /// # Example (with code formatting)
     ///
     /// `subxt codegen | rustfmt --edition=2018 --emit=stdout`
+    ///
+    /// # Example (checked into the repository for review)
+    ///
+    /// `subxt codegen --url http://localhost:9933 -o src/runtime.rs && rustfmt src/runtime.rs`
     Codegen {
         /// the url of the substrate node to query for metadata for codegen.
         #[structopt(name = "url", long, parse(try_from_str))]
@@ -74,6 +82,32 @@ enum Command {
         /// Additional derives
         #[structopt(long = "derive")]
         derives: Vec<String>,
+        /// Use the given type in place of a generated one, e.g.
+        /// `sp_runtime::multiaddress::MultiAddress=::subxt::sp_runtime::MultiAddress`.
+        #[structopt(long = "substitute-type")]
+        substitutes: Vec<String>,
+        /// Write the generated code to the given file, instead of to stdout.
+        #[structopt(short, long, parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+    /// Compare two versions of the metadata, to review what a runtime upgrade changes.
+    ///
+    /// Prints a line for each pallet and item (call, storage entry, event or constant)
+    /// that differs: `+` for added, `-` for removed and `~` for changed.
+    ///
+    /// # Example
+    ///
+    /// `subxt diff old.scale --url http://localhost:9933`
+    Diff {
+        /// the path to the encoded metadata to compare against.
+        #[structopt(parse(from_os_str))]
+        old: PathBuf,
+        /// the url of the substrate node to query for the metadata to compare.
+        #[structopt(name = "url", long, parse(try_from_str))]
+        url: Option<url::Url>,
+        /// the path to the encoded metadata to compare, instead of querying a node.
+        #[structopt(short, long, parse(from_os_str))]
+        file: Option<PathBuf>,
     },
 }
 
@@ -106,29 +140,74 @@ fn main() -> color_eyre::Result<()> {
                 }
             }
         }
-        Command::Codegen { url, file, derives } => {
-            if let Some(file) = file.as_ref() {
-                if url.is_some() {
-                    eyre::bail!("specify one of `--url` or `--file` but not both")
-                };
-
-                let mut file = fs::File::open(file)?;
-                let mut bytes = Vec::new();
-                file.read_to_end(&mut bytes)?;
-                codegen(&mut &bytes[..], derives)?;
-                return Ok(())
+        Command::Codegen {
+            url,
+            file,
+            derives,
+            substitutes,
+            output,
+        } => {
+            let bytes = read_metadata(url, file)?;
+            let runtime_api = codegen(&mut &bytes[..], derives, substitutes)?;
+            match output {
+                Some(path) => {
+                    fs::write(&path, runtime_api).wrap_err_with(|| {
+                        format!("error writing the generated code to {}", path.display())
+                    })
+                }
+                None => {
+                    println!("{}", runtime_api);
+                    Ok(())
+                }
+            }
+        }
+        Command::Diff { old, url, file } => {
+            let old = decode_v14(&fs::read(&old).wrap_err_with(|| {
+                format!("error reading the metadata from {}", old.display())
+            })?)?;
+            let new = decode_v14(&read_metadata(url, file)?)?;
+            let diff = subxt_metadata::diff(&old, &new);
+            if diff.is_empty() {
+                println!("No differences");
+            } else {
+                print!("{}", diff);
             }
-
-            let url = url.unwrap_or_else(|| {
-                url::Url::parse("http://localhost:9933").expect("default url is valid")
-            });
-            let (_, bytes) = fetch_metadata(&url)?;
-            codegen(&mut &bytes[..], derives)?;
             Ok(())
         }
     }
 }
 
+/// Read the encoded metadata from the file, if given, or else fetch it from the node
+/// at the url, which defaults to a local node.
+fn read_metadata(
+    url: Option<url::Url>,
+    file: Option<PathBuf>,
+) -> color_eyre::Result<Vec<u8>> {
+    if let Some(file) = file.as_ref() {
+        if url.is_some() {
+            eyre::bail!("specify one of `--url` or `--file` but not both")
+        };
+
+        let mut file = fs::File::open(file)?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        Ok(bytes)
+    } else {
+        let url = url.unwrap_or_else(|| {
+            url::Url::parse("http://localhost:9933").expect("default url is valid")
+        });
+        Ok(fetch_metadata(&url)?.1)
+    }
+}
+
+/// Decode the encoded metadata, which must be V14, the only version compared.
+fn decode_v14(bytes: &[u8]) -> color_eyre::Result<RuntimeMetadataLastVersion> {
+    match <RuntimeMetadataPrefixed as Decode>::decode(&mut &bytes[..])?.1 {
+        RuntimeMetadata::V14(metadata) => Ok(metadata),
+        _ => Err(eyre::eyre!("only metadata V14 can be compared")),
+    }
+}
+
 fn fetch_metadata(url: &url::Url) -> color_eyre::Result<(String, Vec<u8>)> {
     let resp = ureq::post(url.as_str())
         .set("Content-Type", "application/json")
@@ -152,11 +231,18 @@ fn fetch_metadata(url: &url::Url) -> color_eyre::Result<(String, Vec<u8>)> {
 fn codegen<I: Input>(
     encoded: &mut I,
     raw_derives: Vec<String>,
-) -> color_eyre::Result<()> {
+    raw_substitutes: Vec<String>,
+) -> color_eyre::Result<String> {
     let metadata = <RuntimeMetadataPrefixed as Decode>::decode(encoded)?;
     let generator = subxt_codegen::RuntimeGenerator::new(metadata);
+    let substitutes = raw_substitutes
+        .iter()
+        .map(|raw| type_substitute(raw))
+        .collect::<color_eyre::Result<Vec<_>>>()?;
     let item_mod = syn::parse_quote!(
-        pub mod api {}
+        pub mod api {
+            #( #substitutes )*
+        }
     );
 
     let p = raw_derives
@@ -167,6 +253,22 @@ fn codegen<I: Input>(
     derives.append(p.into_iter());
 
     let runtime_api = generator.generate_runtime(item_mod, derives);
-    println!("{}", runtime_api);
-    Ok(())
+    Ok(runtime_api.to_string())
+}
+
+/// Parse a `generated::Type=replacement::Type` type substitution into the item the
+/// `subxt` macro would be given for it.
+fn type_substitute(raw: &str) -> color_eyre::Result<syn::Item> {
+    let (generated, replacement) = raw.split_once('=').ok_or_else(|| {
+        eyre::eyre!(
+            "Invalid type substitution `{}`, expected `generated::Type=replacement::Type`",
+            raw
+        )
+    })?;
+    let replacement: syn::Path = syn::parse_str(replacement)
+        .wrap_err_with(|| format!("Invalid type path `{}`", replacement))?;
+    Ok(syn::parse_quote!(
+        #[subxt(substitute_type = #generated)]
+        use #replacement;
+    ))
 }
@@ -33,22 +33,30 @@ use quote::{
     quote,
 };
 use scale_info::form::PortableForm;
+use std::collections::HashMap;
 
 pub fn generate_calls(
     type_gen: &TypeGenerator,
     pallet: &PalletMetadata<PortableForm>,
     call: &PalletCallMetadata<PortableForm>,
+    renames: &HashMap<String, syn::Ident>,
     types_mod_ident: &syn::Ident,
 ) -> TokenStream2 {
+    let renamed = |name: &str| renames.get(&format!("{}::{}", pallet.name, name));
     let struct_defs = super::generate_structs_from_variants(
         type_gen,
         call.ty.id(),
-        |name| name.to_upper_camel_case().into(),
+        |name| {
+            match renamed(name) {
+                Some(new_name) => new_name.to_string().to_upper_camel_case().into(),
+                None => name.to_upper_camel_case().into(),
+            }
+        },
         "Call",
     );
     let (call_structs, call_fns): (Vec<_>, Vec<_>) = struct_defs
         .iter()
-        .map(|struct_def| {
+        .map(|(variant, struct_def)| {
             let (call_fn_args, call_args): (Vec<_>, Vec<_>) =
                 match struct_def.fields {
                     CompositeDefFields::Named(ref named_fields) => {
@@ -74,9 +82,22 @@ pub fn generate_calls(
                 };
 
             let pallet_name = &pallet.name;
+            let pallet_index = proc_macro2::Literal::u8_unsuffixed(pallet.index);
+            let call_index = proc_macro2::Literal::u8_unsuffixed(variant.index());
             let call_struct_name = &struct_def.name;
-            let function_name = struct_def.name.to_string().to_snake_case();
-            let fn_name = format_ident!("{}", function_name);
+            let function_name = variant.name().to_snake_case();
+            let call_hash =
+                subxt_metadata::get_call_hash(type_gen.registry(), pallet, variant.name())
+                    .unwrap_or_else(|| {
+                        abort_call_site!(
+                            "Call {} not found in pallet {}",
+                            variant.name(),
+                            pallet.name
+                        )
+                    });
+            let fn_name = renamed(variant.name())
+                .cloned()
+                .unwrap_or_else(|| format_ident!("{}", function_name));
 
             let call_struct = quote! {
                 #struct_def
@@ -84,9 +105,18 @@ pub fn generate_calls(
                 impl ::subxt::Call for #call_struct_name {
                     const PALLET: &'static str = #pallet_name;
                     const FUNCTION: &'static str = #function_name;
+                    const METADATA_HASH: ::core::option::Option<[u8; 32]> =
+                        ::core::option::Option::Some([#(#call_hash,)*]);
+                }
+
+                impl ::subxt::StaticCall for #call_struct_name {
+                    const PALLET_INDEX: u8 = #pallet_index;
+                    const CALL_INDEX: u8 = #call_index;
                 }
             };
+            let docs = variant.docs();
             let client_fn = quote! {
+                #( #[doc = #docs] )*
                 pub fn #fn_name(
                     &self,
                     #( #call_fn_args, )*
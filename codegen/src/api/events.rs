@@ -22,23 +22,30 @@ use frame_metadata::{
 use proc_macro2::TokenStream as TokenStream2;
 use quote::quote;
 use scale_info::form::PortableForm;
+use std::collections::HashMap;
 
 pub fn generate_events(
     type_gen: &TypeGenerator,
     pallet: &PalletMetadata<PortableForm>,
     event: &PalletEventMetadata<PortableForm>,
+    renames: &HashMap<String, syn::Ident>,
     types_mod_ident: &syn::Ident,
 ) -> TokenStream2 {
     let struct_defs = super::generate_structs_from_variants(
         type_gen,
         event.ty.id(),
-        |name| name.into(),
+        |name| {
+            match renames.get(&format!("{}::{}", pallet.name, name)) {
+                Some(new_name) => new_name.to_string().into(),
+                None => name.into(),
+            }
+        },
         "Event",
     );
-    let event_structs = struct_defs.iter().map(|struct_def| {
+    let event_structs = struct_defs.iter().map(|(variant, struct_def)| {
         let pallet_name = &pallet.name;
         let event_struct = &struct_def.name;
-        let event_name = struct_def.name.to_string();
+        let event_name = variant.name();
 
         quote! {
             #struct_def
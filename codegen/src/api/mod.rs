@@ -36,6 +36,8 @@ mod constants;
 mod errors;
 mod events;
 mod storage;
+#[cfg(test)]
+mod tests;
 
 use super::GeneratedTypeDerives;
 use crate::{
@@ -44,11 +46,15 @@ use crate::{
         CompositeDef,
         CompositeDefFields,
         TypeGenerator,
+        TypeSubstitute,
     },
 };
 use codec::Decode;
 use frame_metadata::{
-    v14::RuntimeMetadataV14,
+    v14::{
+        PalletMetadata,
+        RuntimeMetadataV14,
+    },
     RuntimeMetadata,
     RuntimeMetadataPrefixed,
 };
@@ -59,6 +65,10 @@ use quote::{
     format_ident,
     quote,
 };
+use scale_info::{
+    form::PortableForm,
+    Variant,
+};
 use std::{
     collections::HashMap,
     fs,
@@ -66,15 +76,14 @@ use std::{
     path,
     string::ToString,
 };
-use syn::{
-    parse_quote,
-    punctuated::Punctuated,
-};
+use syn::parse_quote;
 
 pub fn generate_runtime_api<P>(
     item_mod: syn::ItemMod,
     path: P,
-    generated_type_derives: Option<Punctuated<syn::Path, syn::Token![,]>>,
+    derives: GeneratedTypeDerives,
+    type_substitutes: Vec<(String, TypeSubstitute)>,
+    pallets: PalletSelection,
 ) -> TokenStream2
 where
     P: AsRef<path::Path>,
@@ -90,31 +99,92 @@ where
     let metadata = frame_metadata::RuntimeMetadataPrefixed::decode(&mut &bytes[..])
         .unwrap_or_else(|e| abort_call_site!("Failed to decode metadata: {}", e));
 
-    let mut derives = GeneratedTypeDerives::default();
-    if let Some(user_derives) = generated_type_derives {
-        derives.append(user_derives.iter().cloned())
+    let mut generator = RuntimeGenerator::new(metadata);
+    if let Err(unknown) = generator.select_pallets(&pallets) {
+        abort_call_site!("Pallet {} is not in the metadata", unknown)
     }
+    generator.generate_runtime_with_substitutes(item_mod, derives, type_substitutes)
+}
+
+/// The pallets of the runtime to generate an API for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PalletSelection {
+    /// Generate the API for every pallet in the metadata.
+    All,
+    /// Generate the API for only the named pallets.
+    Only(Vec<String>),
+    /// Generate the API for every pallet except the named ones.
+    Skip(Vec<String>),
+}
 
-    let generator = RuntimeGenerator::new(metadata);
-    generator.generate_runtime(item_mod, derives)
+impl Default for PalletSelection {
+    fn default() -> Self {
+        Self::All
+    }
 }
 
 pub struct RuntimeGenerator {
     metadata: RuntimeMetadataV14,
+    // Pallets without a generated API, whose events are still part of the outer `Event`.
+    skipped_pallets: Vec<PalletMetadata<PortableForm>>,
 }
 
 impl RuntimeGenerator {
     pub fn new(metadata: RuntimeMetadataPrefixed) -> Self {
         match metadata.1 {
-            RuntimeMetadata::V14(v14) => Self { metadata: v14 },
+            RuntimeMetadata::V14(v14) => {
+                Self {
+                    metadata: v14,
+                    skipped_pallets: Vec::new(),
+                }
+            }
             _ => panic!("Unsupported metadata version {:?}", metadata.1),
         }
     }
 
+    /// Drop the pallets which are not selected, so that no API is generated for them.
+    ///
+    /// The types in the metadata are left untouched, so `runtime_types` is still generated
+    /// in full, and the outer `Event` enum still has a variant for the events of every
+    /// pallet, so that the events of any block can be decoded. Returns the first named
+    /// pallet which isn't in the metadata, if any.
+    pub fn select_pallets(&mut self, pallets: &PalletSelection) -> Result<(), String> {
+        let (names, keep) = match pallets {
+            PalletSelection::All => return Ok(()),
+            PalletSelection::Only(names) => (names, true),
+            PalletSelection::Skip(names) => (names, false),
+        };
+        if let Some(unknown) = names
+            .iter()
+            .find(|name| !self.metadata.pallets.iter().any(|p| &p.name == *name))
+        {
+            return Err(unknown.clone())
+        }
+        let (selected, skipped): (Vec<_>, Vec<_>) =
+            std::mem::take(&mut self.metadata.pallets)
+                .into_iter()
+                .partition(|pallet| names.contains(&pallet.name) == keep);
+        self.metadata.pallets = selected;
+        self.skipped_pallets.extend(skipped);
+        Ok(())
+    }
+
     pub fn generate_runtime(
         &self,
         item_mod: syn::ItemMod,
         derives: GeneratedTypeDerives,
+    ) -> TokenStream2 {
+        self.generate_runtime_with_substitutes(item_mod, derives, Vec::new())
+    }
+
+    /// Like [`RuntimeGenerator::generate_runtime`], with the given types used in place
+    /// of the generated types at the given paths. These take precedence over the
+    /// substitutions given in the module.
+    pub fn generate_runtime_with_substitutes(
+        &self,
+        item_mod: syn::ItemMod,
+        derives: GeneratedTypeDerives,
+        extra_substitutes: Vec<(String, TypeSubstitute)>,
     ) -> TokenStream2 {
         let item_mod_ir = ir::ItemMod::from(item_mod);
 
@@ -144,6 +214,10 @@ impl RuntimeGenerator {
                 "frame_support::traits::misc::WrapperKeepOpaque",
                 parse_quote!(::subxt::WrapperKeepOpaque),
             ),
+            (
+                "sp_weights::weight_v2::Weight",
+                parse_quote!(::subxt::weights::Weight),
+            ),
             // BTreeMap and BTreeSet impose an `Ord` constraint on their key types. This
             // can cause an issue with generated code that doesn't impl `Ord` by default.
             // Decoding them to Vec by default (KeyedVec is just an alias for Vec with
@@ -153,18 +227,22 @@ impl RuntimeGenerator {
         ]
         .iter()
         .map(|(path, substitute): &(&str, syn::TypePath)| {
-            (path.to_string(), substitute.clone())
+            (path.to_string(), TypeSubstitute::from(substitute.clone()))
         })
         .collect::<HashMap<_, _>>();
 
         for (path, substitute) in item_mod_ir.type_substitutes().iter() {
-            type_substitutes.insert(path.to_string(), substitute.clone());
+            type_substitutes.insert(path.to_string(), substitute.clone().into());
         }
+        type_substitutes.extend(extra_substitutes);
+
+        let renames = item_mod_ir.renames();
 
         let type_gen = TypeGenerator::new(
             &self.metadata.types,
             "runtime_types",
             type_substitutes,
+            renames.clone(),
             derives.clone(),
         );
         let types_mod = type_gen.generate_types_mod();
@@ -174,28 +252,40 @@ impl RuntimeGenerator {
             .pallets
             .iter()
             .map(|pallet| {
-                (
-                    pallet,
-                    format_ident!("{}", pallet.name.to_string().to_snake_case()),
-                )
+                let mod_name = renames.get(&pallet.name).cloned().unwrap_or_else(|| {
+                    format_ident!("{}", pallet.name.to_string().to_snake_case())
+                });
+                (pallet, mod_name)
             })
             .collect::<Vec<_>>();
 
         let modules = pallets_with_mod_names.iter().map(|(pallet, mod_name)| {
             let calls = if let Some(ref calls) = pallet.calls {
-                calls::generate_calls(&type_gen, pallet, calls, types_mod_ident)
+                calls::generate_calls(&type_gen, pallet, calls, &renames, types_mod_ident)
             } else {
                 quote!()
             };
 
             let event = if let Some(ref event) = pallet.event {
-                events::generate_events(&type_gen, pallet, event, types_mod_ident)
+                events::generate_events(
+                    &type_gen,
+                    pallet,
+                    event,
+                    &renames,
+                    types_mod_ident,
+                )
             } else {
                 quote!()
             };
 
             let storage_mod = if let Some(ref storage) = pallet.storage {
-                storage::generate_storage(&type_gen, pallet, storage, types_mod_ident)
+                storage::generate_storage(
+                    &type_gen,
+                    pallet,
+                    storage,
+                    &renames,
+                    types_mod_ident,
+                )
             } else {
                 quote!()
             };
@@ -203,6 +293,7 @@ impl RuntimeGenerator {
             let constants_mod = if !pallet.constants.is_empty() {
                 constants::generate_constants(
                     &type_gen,
+                    pallet,
                     &pallet.constants,
                     types_mod_ident,
                 )
@@ -222,18 +313,34 @@ impl RuntimeGenerator {
             }
         });
 
-        let outer_event_variants = self.metadata.pallets.iter().filter_map(|p| {
-            let variant_name = format_ident!("{}", p.name);
-            let mod_name = format_ident!("{}", p.name.to_string().to_snake_case());
-            let index = proc_macro2::Literal::u8_unsuffixed(p.index);
-
-            p.event.as_ref().map(|_| {
-                quote! {
-                    #[codec(index = #index)]
-                    #variant_name(#mod_name::Event),
-                }
+        // The events of pallets without a generated API are given as their type in
+        // `runtime_types`, rather than through the pallet's module.
+        let mut outer_event_variants = pallets_with_mod_names
+            .iter()
+            .filter_map(|(p, mod_name)| {
+                p.event
+                    .as_ref()
+                    .map(|_| (p.index, &p.name, quote!(#mod_name::Event)))
             })
-        });
+            .chain(self.skipped_pallets.iter().filter_map(|p| {
+                p.event.as_ref().map(|event| {
+                    let event_type = type_gen.resolve_type_path(event.ty.id(), &[]);
+                    (p.index, &p.name, quote!(#event_type))
+                })
+            }))
+            .collect::<Vec<_>>();
+        outer_event_variants.sort_by_key(|(index, _, _)| *index);
+        let outer_event_variants =
+            outer_event_variants
+                .into_iter()
+                .map(|(index, name, event_type)| {
+                    let variant_name = format_ident!("{}", name);
+                    let index = proc_macro2::Literal::u8_unsuffixed(index);
+                    quote! {
+                        #[codec(index = #index)]
+                        #variant_name(#event_type),
+                    }
+                });
 
         let outer_event = quote! {
             #derives
@@ -255,6 +362,93 @@ impl RuntimeGenerator {
                 .filter_map(|(pallet, pallet_mod_name)| {
                     pallet.storage.as_ref().map(|_| pallet_mod_name)
                 });
+        // The hashes of each pallet, to check the metadata of a node against.
+        let pallet_hashes = self.metadata.pallets.iter().map(|pallet| {
+            let name = &pallet.name;
+            let hashes = subxt_metadata::get_pallet_hashes(&self.metadata.types, pallet);
+            let (calls, events, errors, storage, constants) = (
+                hashes.calls,
+                hashes.events,
+                hashes.errors,
+                hashes.storage,
+                hashes.constants,
+            );
+            quote! {
+                (#name, ::subxt::PalletHashes {
+                    calls: [#(#calls,)*],
+                    events: [#(#events,)*],
+                    errors: [#(#errors,)*],
+                    storage: [#(#storage,)*],
+                    constants: [#(#constants,)*],
+                })
+            }
+        });
+        // Batching calls of any pallets together only needs the utility pallet to be present.
+        let has_utility_batch = self
+            .metadata
+            .pallets
+            .iter()
+            .any(|pallet| pallet.name == "Utility" && pallet.calls.is_some());
+        let batch_fns = if has_utility_batch {
+            quote! {
+                pub fn batch(
+                    &self,
+                    calls: ::std::vec::Vec<::subxt::Encoded>,
+                ) -> ::subxt::SubmittableExtrinsic<'a, T, X, ::subxt::BatchCall, #types_mod_ident::sp_runtime::DispatchError, Event> {
+                    ::subxt::SubmittableExtrinsic::new(self.client, ::subxt::BatchCall { calls })
+                }
+
+                pub fn batch_all(
+                    &self,
+                    calls: ::std::vec::Vec<::subxt::Encoded>,
+                ) -> ::subxt::SubmittableExtrinsic<'a, T, X, ::subxt::BatchAllCall, #types_mod_ident::sp_runtime::DispatchError, Event> {
+                    ::subxt::SubmittableExtrinsic::new(self.client, ::subxt::BatchAllCall { calls })
+                }
+            }
+        } else {
+            quote!()
+        };
+        // Proxying calls of any pallets only needs the proxy pallet, and the types of the
+        // real account and proxy type of its `proxy` call.
+        let proxy_fn = self
+            .metadata
+            .pallets
+            .iter()
+            .find(|pallet| pallet.name == "Proxy")
+            .and_then(|pallet| pallet.calls.as_ref())
+            .and_then(|calls| {
+                match type_gen.resolve_type(calls.ty.id()).type_def() {
+                    scale_info::TypeDef::Variant(variant) => {
+                        variant.variants().iter().find(|v| v.name() == "proxy").cloned()
+                    }
+                    _ => None,
+                }
+            })
+            .and_then(|proxy| {
+                let field_ty = |name: &str| {
+                    proxy
+                        .fields()
+                        .iter()
+                        .find(|f| f.name().map(String::as_str) == Some(name))
+                        .map(|f| f.ty().id())
+                };
+                let real_ty = type_gen.resolve_type_path(field_ty("real")?, &[]);
+                let force_ty = type_gen.resolve_type(field_ty("force_proxy_type")?);
+                // `force_proxy_type` is an `Option<ProxyType>`.
+                let proxy_ty_id = force_ty.type_params().first()?.ty()?.id();
+                let proxy_ty = type_gen.resolve_type_path(proxy_ty_id, &[]);
+                Some(quote! {
+                    pub fn proxied(
+                        &self,
+                        real: #real_ty,
+                        force_proxy_type: ::core::option::Option<#proxy_ty>,
+                        call: ::subxt::Encoded,
+                    ) -> ::subxt::SubmittableExtrinsic<'a, T, X, ::subxt::proxy::ProxyCall<#real_ty, #proxy_ty>, #types_mod_ident::sp_runtime::DispatchError, Event> {
+                        ::subxt::SubmittableExtrinsic::new(self.client, ::subxt::proxy::ProxyCall { real, force_proxy_type, call })
+                    }
+                })
+            })
+            .unwrap_or_default();
         let pallets_with_calls =
             pallets_with_mod_names
                 .iter()
@@ -295,13 +489,19 @@ impl RuntimeGenerator {
                     }
                 }
 
+                impl<T: ::subxt::Config, X> ::subxt::MetadataHashes for RuntimeApi<T, X> {
+                    fn pallet_hashes() -> &'static [(&'static str, ::subxt::PalletHashes)] {
+                        &[#( #pallet_hashes, )*]
+                    }
+                }
+
                 impl<'a, T, X> RuntimeApi<T, X>
                 where
                     T: ::subxt::Config,
                     X: ::subxt::SignedExtra<T>,
                 {
-                    pub fn constants(&'a self) -> ConstantsApi {
-                        ConstantsApi
+                    pub fn constants(&'a self) -> ConstantsApi<'a, T> {
+                        ConstantsApi { client: &self.client }
                     }
 
                     pub fn storage(&'a self) -> StorageApi<'a, T> {
@@ -315,6 +515,12 @@ impl RuntimeGenerator {
                     pub fn events(&'a self) -> EventsApi<'a, T> {
                         EventsApi { client: &self.client }
                     }
+
+                    /// Check that the node's metadata is compatible with the metadata
+                    /// this API was generated from.
+                    pub fn validate_metadata(&'a self) -> Result<(), ::subxt::MetadataError> {
+                        self.client.validate_metadata::<Self>()
+                    }
                 }
 
                 pub struct EventsApi<'a, T: ::subxt::Config> {
@@ -335,12 +541,14 @@ impl RuntimeGenerator {
                     }
                 }
 
-                pub struct ConstantsApi;
+                pub struct ConstantsApi<'a, T: ::subxt::Config> {
+                    client: &'a ::subxt::Client<T>,
+                }
 
-                impl ConstantsApi {
+                impl<'a, T: ::subxt::Config> ConstantsApi<'a, T> {
                     #(
-                        pub fn #pallets_with_constants(&self) -> #pallets_with_constants::constants::ConstantsApi {
-                            #pallets_with_constants::constants::ConstantsApi
+                        pub fn #pallets_with_constants(&self) -> #pallets_with_constants::constants::ConstantsApi<'a, T> {
+                            #pallets_with_constants::constants::ConstantsApi::new(self.client)
                         }
                     )*
                 }
@@ -375,6 +583,20 @@ impl RuntimeGenerator {
                             #pallets_with_calls::calls::TransactionApi::new(self.client)
                         }
                     )*
+
+                    #batch_fns
+
+                    #proxy_fn
+
+                    pub fn dynamic<C: ::core::convert::Into<::subxt::dynamic::Composite>>(
+                        &self,
+                        pallet: &str,
+                        call: &str,
+                        args: C,
+                    ) -> ::core::result::Result<::subxt::SubmittableExtrinsic<'a, T, X, ::subxt::dynamic::DynamicCall, #types_mod_ident::sp_runtime::DispatchError, Event>, ::subxt::BasicError> {
+                        let call = ::subxt::dynamic::DynamicCall::new(&self.client.metadata(), pallet, call, args)?;
+                        ::core::result::Result::Ok(::subxt::SubmittableExtrinsic::new(self.client, call))
+                    }
                 }
             }
         }
@@ -386,7 +608,7 @@ pub fn generate_structs_from_variants<'a, F>(
     type_id: u32,
     variant_to_struct_name: F,
     error_message_type_name: &str,
-) -> Vec<CompositeDef>
+) -> Vec<(Variant<PortableForm>, CompositeDef)>
 where
     F: Fn(&str) -> std::borrow::Cow<str>,
 {
@@ -403,13 +625,16 @@ where
                     &[],
                     type_gen,
                 );
-                CompositeDef::struct_def(
+                let struct_def = CompositeDef::struct_def(
                     struct_name.as_ref(),
                     Default::default(),
                     fields,
                     Some(parse_quote!(pub)),
+                    type_gen.derives().clone(),
                     type_gen,
                 )
+                .with_docs(var.docs());
+                (var.clone(), struct_def)
             })
             .collect()
     } else {
@@ -23,7 +23,10 @@ use frame_metadata::{
     StorageEntryType,
     StorageHasher,
 };
-use heck::ToSnakeCase as _;
+use heck::{
+    ToSnakeCase as _,
+    ToUpperCamelCase as _,
+};
 use proc_macro2::TokenStream as TokenStream2;
 use proc_macro_error::abort_call_site;
 use quote::{
@@ -34,17 +37,22 @@ use scale_info::{
     form::PortableForm,
     TypeDef,
 };
+use std::collections::HashMap;
 
 pub fn generate_storage(
     type_gen: &TypeGenerator,
     pallet: &PalletMetadata<PortableForm>,
     storage: &PalletStorageMetadata<PortableForm>,
+    renames: &HashMap<String, syn::Ident>,
     types_mod_ident: &syn::Ident,
 ) -> TokenStream2 {
     let (storage_structs, storage_fns): (Vec<_>, Vec<_>) = storage
         .entries
         .iter()
-        .map(|entry| generate_storage_entry_fns(type_gen, pallet, entry))
+        .map(|entry| {
+            let rename = renames.get(&format!("{}::{}", pallet.name, entry.name));
+            generate_storage_entry_fns(type_gen, pallet, entry, rename)
+        })
         .unzip();
 
     quote! {
@@ -72,8 +80,12 @@ fn generate_storage_entry_fns(
     type_gen: &TypeGenerator,
     pallet: &PalletMetadata<PortableForm>,
     storage_entry: &StorageEntryMetadata<PortableForm>,
+    rename: Option<&syn::Ident>,
 ) -> (TokenStream2, TokenStream2) {
-    let entry_struct_ident = format_ident!("{}", storage_entry.name);
+    let entry_struct_ident = match rename {
+        Some(new_name) => format_ident!("{}", new_name.to_string().to_upper_camel_case()),
+        None => format_ident!("{}", storage_entry.name),
+    };
     let (fields, entry_struct, constructor, key_impl, should_ref) = match storage_entry.ty
     {
         StorageEntryType::Plain(_) => {
@@ -207,8 +219,11 @@ fn generate_storage_entry_fns(
     };
     let pallet_name = &pallet.name;
     let storage_name = &storage_entry.name;
-    let fn_name = format_ident!("{}", storage_entry.name.to_snake_case());
+    let fn_name = rename
+        .cloned()
+        .unwrap_or_else(|| format_ident!("{}", storage_entry.name.to_snake_case()));
     let fn_name_iter = format_ident!("{}_iter", fn_name);
+    let fn_name_iter_with_keys = format_ident!("{}_iter_with_keys", fn_name);
     let storage_entry_ty = match storage_entry.ty {
         StorageEntryType::Plain(ref ty) => ty,
         StorageEntryType::Map { ref value, .. } => value,
@@ -232,16 +247,32 @@ fn generate_storage_entry_fns(
         (quote!(), quote!(), quote!())
     };
 
+    let storage_hash = subxt_metadata::get_storage_hash(
+        type_gen.registry(),
+        pallet,
+        &storage_entry.name,
+    )
+    .unwrap_or_else(|| {
+        abort_call_site!(
+            "Storage entry {} not found in pallet {}",
+            storage_entry.name,
+            pallet.name
+        )
+    });
     let storage_entry_impl = quote! (
         const PALLET: &'static str = #pallet_name;
         const STORAGE: &'static str = #storage_name;
         type Value = #storage_entry_value_ty;
+        const METADATA_HASH: ::core::option::Option<[u8; 32]> =
+            ::core::option::Option::Some([#(#storage_hash,)*]);
         fn key(&self) -> ::subxt::StorageEntryKey {
             #key_impl
         }
     );
 
+    let docs = &storage_entry.docs;
     let storage_entry_type = quote! {
+        #( #[doc = #docs] )*
         #entry_struct
         impl ::subxt::StorageEntry for #entry_struct_ident #anon_lifetime {
             #storage_entry_impl
@@ -249,6 +280,14 @@ fn generate_storage_entry_fns(
     };
 
     let client_iter_fn = if matches!(storage_entry.ty, StorageEntryType::Map { .. }) {
+        // A single key is decoded as is, the keys of double and N-maps as a tuple.
+        let decoded_key_ty = match fields.as_slice() {
+            [(_, field_type)] => quote!( #field_type ),
+            fields => {
+                let field_types = fields.iter().map(|(_, t)| t);
+                quote!( ( #( #field_types, )* ) )
+            }
+        };
         quote! (
             pub async fn #fn_name_iter(
                 &self,
@@ -256,6 +295,13 @@ fn generate_storage_entry_fns(
             ) -> ::core::result::Result<::subxt::KeyIter<'a, T, #entry_struct_ident #lifetime_param>, ::subxt::BasicError> {
                 self.client.storage().iter(hash).await
             }
+
+            pub async fn #fn_name_iter_with_keys(
+                &self,
+                hash: ::core::option::Option<T::Hash>,
+            ) -> ::core::result::Result<::subxt::DecodedKeyIter<'a, T, #entry_struct_ident #lifetime_param, #decoded_key_ty>, ::subxt::BasicError> {
+                ::core::result::Result::Ok(self.client.storage().iter(hash).await?.with_keys())
+            }
         )
     } else {
         quote!()
@@ -272,6 +318,7 @@ fn generate_storage_entry_fns(
         quote!( #field_name: #reference #field_ty )
     });
     let client_fns = quote! {
+        #( #[doc = #docs] )*
         pub async fn #fn_name(
             &self,
             #( #key_args, )*
@@ -0,0 +1,307 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::*;
+use frame_metadata::{
+    v14::{
+        ExtrinsicMetadata,
+        PalletCallMetadata,
+        PalletEventMetadata,
+        PalletMetadata,
+        PalletStorageMetadata,
+        RuntimeMetadataLastVersion,
+        StorageEntryMetadata,
+        StorageEntryModifier,
+        StorageEntryType,
+    },
+    RuntimeMetadataPrefixed,
+};
+use scale_info::{
+    build::{
+        Fields,
+        Variants,
+    },
+    meta_type,
+    Path,
+    Type,
+    TypeInfo,
+};
+
+#[allow(unused, non_camel_case_types)]
+#[derive(TypeInfo)]
+enum BalancesCall {
+    transfer { dest: u32, value: u128 },
+}
+
+#[allow(unused)]
+#[derive(TypeInfo)]
+enum BalancesEvent {
+    Transfer { amount: u128 },
+}
+
+#[allow(unused)]
+#[derive(TypeInfo)]
+enum SystemEvent {
+    ExtrinsicSuccess,
+}
+
+/// `sp_runtime::DispatchError`, which the generated API expects to find in the metadata.
+struct DispatchError;
+
+impl TypeInfo for DispatchError {
+    type Identity = Self;
+
+    fn type_info() -> Type {
+        Type::builder()
+            .path(Path::new("DispatchError", "sp_runtime"))
+            .variant(Variants::new().variant("Module", |v| {
+                v.index(0).fields(
+                    Fields::named()
+                        .field(|f| f.ty::<u8>().name("index").type_name("u8"))
+                        .field(|f| f.ty::<u8>().name("error").type_name("u8")),
+                )
+            }))
+    }
+}
+
+/// Metadata with a `System` pallet which only has events, and a `Balances` pallet
+/// with a call, an event and a storage entry.
+fn metadata() -> RuntimeMetadataPrefixed {
+    let system = PalletMetadata {
+        name: "System",
+        storage: None,
+        calls: None,
+        event: Some(PalletEventMetadata {
+            ty: meta_type::<SystemEvent>(),
+        }),
+        constants: vec![],
+        error: None,
+        index: 0,
+    };
+    let balances = PalletMetadata {
+        name: "Balances",
+        storage: Some(PalletStorageMetadata {
+            prefix: "Balances",
+            entries: vec![StorageEntryMetadata {
+                name: "TotalIssuance",
+                modifier: StorageEntryModifier::Default,
+                ty: StorageEntryType::Plain(meta_type::<u128>()),
+                default: vec![0; 16],
+                docs: vec![],
+            }],
+        }),
+        calls: Some(PalletCallMetadata {
+            ty: meta_type::<BalancesCall>(),
+        }),
+        event: Some(PalletEventMetadata {
+            ty: meta_type::<BalancesEvent>(),
+        }),
+        constants: vec![],
+        error: None,
+        index: 5,
+    };
+    let extrinsic = ExtrinsicMetadata {
+        ty: meta_type::<()>(),
+        version: 4,
+        signed_extensions: vec![],
+    };
+    // The runtime type isn't used by the generator, so it's a handy way to get
+    // `DispatchError` into the type registry.
+    RuntimeMetadataLastVersion::new(
+        vec![system, balances],
+        extrinsic,
+        meta_type::<DispatchError>(),
+    )
+    .into()
+}
+
+fn generate(item_mod: syn::ItemMod) -> String {
+    RuntimeGenerator::new(metadata())
+        .generate_runtime(item_mod, Default::default())
+        .to_string()
+}
+
+#[test]
+fn pallets_can_be_renamed() {
+    let code = generate(parse_quote!(
+        pub mod api {
+            #[subxt(rename = "Balances")]
+            use coins;
+        }
+    ));
+    assert!(code.contains(&quote!(pub mod coins).to_string()));
+    assert!(!code.contains(&quote!(pub mod balances).to_string()));
+    assert!(code.contains(&quote!(Balances(coins::Event)).to_string()));
+}
+
+#[test]
+fn calls_can_be_renamed() {
+    let code = generate(parse_quote!(
+        pub mod api {
+            #[subxt(rename = "Balances::transfer")]
+            use send;
+        }
+    ));
+    assert!(code.contains(&quote!(pub struct Send).to_string()));
+    assert!(code.contains(&quote!(pub fn send).to_string()));
+    assert!(code.contains(
+        &quote!(
+            const FUNCTION: &'static str = "transfer";
+        )
+        .to_string()
+    ));
+    assert!(!code.contains(&quote!(pub fn transfer).to_string()));
+}
+
+#[test]
+fn storage_entries_can_be_renamed() {
+    let code = generate(parse_quote!(
+        pub mod api {
+            #[subxt(rename = "Balances::TotalIssuance")]
+            use issuance;
+        }
+    ));
+    assert!(code.contains(
+        &quote!(
+            pub struct Issuance;
+        )
+        .to_string()
+    ));
+    assert!(code.contains(&quote!(pub async fn issuance).to_string()));
+    assert!(code.contains(
+        &quote!(
+            const STORAGE: &'static str = "TotalIssuance";
+        )
+        .to_string()
+    ));
+    assert!(!code.contains(&quote!(pub async fn total_issuance).to_string()));
+}
+
+#[test]
+fn events_can_be_renamed() {
+    let code = generate(parse_quote!(
+        pub mod api {
+            #[subxt(rename = "Balances::Transfer")]
+            use Transferred;
+        }
+    ));
+    assert!(code.contains(&quote!(pub struct Transferred).to_string()));
+    assert!(code.contains(
+        &quote!(
+            const EVENT: &'static str = "Transfer";
+        )
+        .to_string()
+    ));
+
+    // The call of the same name is generated as `Transfer` too, so only the events are
+    // checked for it.
+    let events = pallet_events(&code, "balances");
+    assert!(events.contains("pub struct Transferred {"));
+    assert!(!events.contains("pub struct Transfer {"));
+}
+
+/// The generated `events` module of the given pallet module.
+fn pallet_events<'a>(code: &'a str, pallet: &str) -> &'a str {
+    let pallet_start = code
+        .find(&format!("pub mod {} {{", pallet))
+        .expect("the pallet module is generated");
+    let start = pallet_start
+        + code[pallet_start..]
+            .find("pub mod events {")
+            .expect("the events module is generated");
+    let mut depth = 0;
+    for (i, c) in code[start..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 1 => return &code[start..=start + i],
+            '}' => depth -= 1,
+            _ => (),
+        }
+    }
+    panic!("the events module is closed")
+}
+
+fn generate_selected(pallets: PalletSelection) -> Result<String, String> {
+    let mut generator = RuntimeGenerator::new(metadata());
+    generator.select_pallets(&pallets)?;
+    Ok(generator
+        .generate_runtime(
+            parse_quote!(
+                pub mod api {}
+            ),
+            Default::default(),
+        )
+        .to_string())
+}
+
+/// The generated outer `Event` enum, up to the end of its variants.
+fn outer_event(code: &str) -> &str {
+    let start = code
+        .find(&quote!(pub enum Event).to_string())
+        .expect("the outer Event is generated");
+    let end = start + code[start..].find('}').expect("the outer Event is closed");
+    &code[start..end]
+}
+
+#[test]
+fn only_the_selected_pallets_get_an_api() {
+    let code = generate_selected(PalletSelection::Only(vec!["Balances".into()])).unwrap();
+    assert!(code.contains(&quote!(pub mod balances).to_string()));
+    assert!(!code.contains(&quote!(pub mod system).to_string()));
+
+    let outer_event = outer_event(&code);
+    assert!(outer_event.contains(&quote!(Balances(balances::Event)).to_string()));
+    assert!(outer_event.contains(
+        &quote!(
+            #[codec(index = 0)]
+            System
+        )
+        .to_string()
+    ));
+}
+
+#[test]
+fn skipped_pallets_keep_their_outer_event_variant() {
+    let code = generate_selected(PalletSelection::Skip(vec!["Balances".into()])).unwrap();
+    assert!(!code.contains(&quote!(pub mod balances).to_string()));
+    assert!(code.contains(&quote!(pub mod system).to_string()));
+
+    // Events are decoded by the index of their variant, so the variant must be there
+    // even though the pallet has no module to take its `Event` type from.
+    let outer_event = outer_event(&code);
+    assert!(outer_event.contains(&quote!(System(system::Event)).to_string()));
+    assert!(outer_event.contains(
+        &quote!(
+            #[codec(index = 5)]
+            Balances
+        )
+        .to_string()
+    ));
+    assert!(outer_event.contains(&quote!(BalancesEvent).to_string()));
+    assert!(!outer_event.contains(&quote!(balances::Event).to_string()));
+}
+
+#[test]
+fn selecting_an_unknown_pallet_fails() {
+    assert_eq!(
+        generate_selected(PalletSelection::Only(vec!["Staking".into()])),
+        Err("Staking".to_string())
+    );
+    assert_eq!(
+        generate_selected(PalletSelection::Skip(vec!["Staking".into()])),
+        Err("Staking".to_string())
+    );
+}
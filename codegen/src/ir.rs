@@ -70,6 +70,21 @@ impl ItemMod {
             })
             .collect()
     }
+
+    /// The new names given to generated pallets, pallet items and types, keyed by
+    /// the original (see [`SubxtItem::Rename`]).
+    pub fn renames(&self) -> HashMap<String, syn::Ident> {
+        self.items
+            .iter()
+            .filter_map(|item| {
+                if let Item::Subxt(SubxtItem::Rename { original, new_name }) = item {
+                    Some((original.clone(), new_name.clone()))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
 }
 
 #[allow(clippy::large_enum_variant)]
@@ -95,19 +110,36 @@ impl From<syn::Item> for Item {
                 })
                 .collect::<Vec<_>>();
             if substitute_attrs.len() > 1 {
-                abort!(
-                    use_.attrs[0].span(),
-                    "Duplicate `substitute_type` attributes"
-                )
+                abort!(use_.attrs[0].span(), "Duplicate `subxt` attributes")
             }
             if let Some(attr) = substitute_attrs.get(0) {
                 let use_path = &use_.tree;
-                let substitute_with: syn::TypePath = syn::parse_quote!( #use_path );
-                let type_substitute = SubxtItem::TypeSubstitute {
-                    generated_type_path: attr.substitute_type(),
-                    substitute_with,
+                let subxt_item = match attr {
+                    attrs::Subxt::SubstituteType(path) => {
+                        let substitute_with: syn::TypePath =
+                            syn::parse_quote!( #use_path );
+                        SubxtItem::TypeSubstitute {
+                            generated_type_path: path.clone(),
+                            substitute_with,
+                        }
+                    }
+                    attrs::Subxt::Rename(original) => {
+                        let new_name = match use_path {
+                            syn::UseTree::Name(name) => name.ident.clone(),
+                            _ => {
+                                abort!(
+                                    use_path.span(),
+                                    "The new name given to `rename` should be a single identifier"
+                                )
+                            }
+                        };
+                        SubxtItem::Rename {
+                            original: original.clone(),
+                            new_name,
+                        }
+                    }
                 };
-                Self::Subxt(type_substitute)
+                Self::Subxt(subxt_item)
             } else {
                 Self::Rust(item)
             }
@@ -123,6 +155,13 @@ pub enum SubxtItem {
         generated_type_path: String,
         substitute_with: syn::TypePath,
     },
+    /// Give a generated pallet, pallet item or type a different name. The original is
+    /// identified by the pallet name (e.g. `Balances`), the pallet name and item name
+    /// (e.g. `Balances::transfer`) or the full type path (e.g. `sp_runtime::DispatchError`).
+    Rename {
+        original: String,
+        new_name: syn::Ident,
+    },
 }
 
 mod attrs {
@@ -132,13 +171,6 @@ mod attrs {
     #[darling(rename_all = "snake_case")]
     pub enum Subxt {
         SubstituteType(String),
-    }
-
-    impl Subxt {
-        pub fn substitute_type(&self) -> String {
-            match self {
-                Self::SubstituteType(path) => path.clone(),
-            }
-        }
+        Rename(String),
     }
 }
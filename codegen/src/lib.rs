@@ -23,11 +23,13 @@ mod types;
 pub use self::{
     api::{
         generate_runtime_api,
+        PalletSelection,
         RuntimeGenerator,
     },
     types::{
         GeneratedTypeDerives,
         Module,
         TypeGenerator,
+        TypeSubstitute,
     },
 };
@@ -45,6 +45,8 @@ pub struct CompositeDef {
     pub kind: CompositeDefKind,
     /// The fields of the type, which are either all named or all unnamed.
     pub fields: CompositeDefFields,
+    /// Documentation of the type from the metadata, one entry per line.
+    pub docs: Vec<String>,
 }
 
 impl CompositeDef {
@@ -54,9 +56,9 @@ impl CompositeDef {
         type_params: TypeDefParameters,
         fields_def: CompositeDefFields,
         field_visibility: Option<syn::Visibility>,
+        mut derives: GeneratedTypeDerives,
         type_gen: &TypeGenerator,
     ) -> Self {
-        let mut derives = type_gen.derives().clone();
         let fields: Vec<_> = fields_def.field_types().collect();
 
         if fields.len() == 1 {
@@ -94,6 +96,7 @@ impl CompositeDef {
                 field_visibility,
             },
             fields: fields_def,
+            docs: Vec::new(),
         }
     }
 
@@ -104,13 +107,21 @@ impl CompositeDef {
             name,
             kind: CompositeDefKind::EnumVariant,
             fields,
+            docs: Vec::new(),
         }
     }
+
+    /// Annotate the generated code with the given documentation.
+    pub fn with_docs(mut self, docs: &[String]) -> Self {
+        self.docs = docs.to_vec();
+        self
+    }
 }
 
 impl quote::ToTokens for CompositeDef {
     fn to_tokens(&self, tokens: &mut TokenStream) {
         let name = &self.name;
+        let docs = &self.docs;
 
         let decl = match &self.kind {
             CompositeDefKind::Struct {
@@ -129,6 +140,7 @@ impl quote::ToTokens for CompositeDef {
                 .then(|| quote!(;));
 
                 quote! {
+                    #( #[doc = #docs] )*
                     #derives
                     pub struct #name #type_params #fields #trailing_semicolon
                 }
@@ -137,6 +149,7 @@ impl quote::ToTokens for CompositeDef {
                 let fields = self.fields.to_enum_variant_field_tokens();
 
                 quote! {
+                    #( #[doc = #docs] )*
                     #name #fields
                 }
             }
@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with subxt.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::HashMap;
 use syn::{
     parse_quote,
     punctuated::Punctuated,
@@ -22,11 +23,17 @@ use syn::{
 #[derive(Debug, Clone)]
 pub struct GeneratedTypeDerives {
     derives: Punctuated<syn::Path, syn::Token![,]>,
+    /// Additional derives for particular types, keyed by the path of the type in the metadata
+    /// e.g. `frame_support::PalletId`.
+    type_derives: HashMap<String, Vec<syn::Path>>,
 }
 
 impl GeneratedTypeDerives {
     pub fn new(derives: Punctuated<syn::Path, syn::Token!(,)>) -> Self {
-        Self { derives }
+        Self {
+            derives,
+            type_derives: HashMap::new(),
+        }
     }
 
     /// Add `::subxt::codec::CompactAs` to the derives.
@@ -43,6 +50,27 @@ impl GeneratedTypeDerives {
     pub fn push(&mut self, derive: syn::Path) {
         self.derives.push(derive);
     }
+
+    /// Add derives for only the type with the given path, on top of those for all types.
+    pub fn append_for_type(
+        &mut self,
+        type_path: impl Into<String>,
+        derives: impl Iterator<Item = syn::Path>,
+    ) {
+        self.type_derives
+            .entry(type_path.into())
+            .or_default()
+            .extend(derives)
+    }
+
+    /// Returns the derives with which to annotate the type with the given path.
+    pub fn for_type(&self, type_path: &str) -> Self {
+        let mut derives = Self::new(self.derives.clone());
+        if let Some(type_derives) = self.type_derives.get(type_path) {
+            derives.append(type_derives.iter().cloned())
+        }
+        derives
+    }
 }
 
 impl Default for GeneratedTypeDerives {
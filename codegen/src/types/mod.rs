@@ -16,6 +16,7 @@
 
 mod composite_def;
 mod derives;
+mod substitutes;
 #[cfg(test)]
 mod tests;
 mod type_def;
@@ -49,6 +50,7 @@ pub use self::{
         CompositeDefFields,
     },
     derives::GeneratedTypeDerives,
+    substitutes::TypeSubstitute,
     type_def::TypeDefGen,
     type_def_params::TypeDefParameters,
     type_path::{
@@ -69,7 +71,9 @@ pub struct TypeGenerator<'a> {
     /// Registry of type definitions to be transformed into Rust type definitions.
     type_registry: &'a PortableRegistry,
     /// User defined overrides for generated types.
-    type_substitutes: HashMap<String, syn::TypePath>,
+    type_substitutes: HashMap<String, TypeSubstitute>,
+    /// User defined names for generated types, keyed by the path of the type.
+    type_renames: HashMap<String, syn::Ident>,
     /// Set of derives with which to annotate generated types.
     derives: GeneratedTypeDerives,
 }
@@ -79,7 +83,8 @@ impl<'a> TypeGenerator<'a> {
     pub fn new(
         type_registry: &'a PortableRegistry,
         root_mod: &'static str,
-        type_substitutes: HashMap<String, syn::TypePath>,
+        type_substitutes: HashMap<String, TypeSubstitute>,
+        type_renames: HashMap<String, syn::Ident>,
         derives: GeneratedTypeDerives,
     ) -> Self {
         let root_mod_ident = Ident::new(root_mod, Span::call_site());
@@ -87,12 +92,13 @@ impl<'a> TypeGenerator<'a> {
             types_mod_ident: root_mod_ident,
             type_registry,
             type_substitutes,
+            type_renames,
             derives,
         }
     }
 
     /// Generate a module containing all types defined in the supplied type registry.
-    pub fn generate_types_mod(&'a self) -> Module<'a> {
+    pub fn generate_types_mod(&self) -> Module {
         let mut root_mod =
             Module::new(self.types_mod_ident.clone(), self.types_mod_ident.clone());
 
@@ -101,6 +107,12 @@ impl<'a> TypeGenerator<'a> {
                 // prelude types e.g. Option/Result have no namespace, so we don't generate them
                 continue
             }
+            // substituted types are used in place of the generated ones, so we don't
+            // generate them either
+            let joined_path = ty.ty().path().segments().join("::");
+            if self.type_substitutes.contains_key(&joined_path) {
+                continue
+            }
             self.insert_type(
                 ty.ty().clone(),
                 id as u32,
@@ -114,18 +126,13 @@ impl<'a> TypeGenerator<'a> {
     }
 
     fn insert_type(
-        &'a self,
+        &self,
         ty: Type<PortableForm>,
         id: u32,
         path: Vec<String>,
         root_mod_ident: &Ident,
-        module: &mut Module<'a>,
+        module: &mut Module,
     ) {
-        let joined_path = path.join("::");
-        if self.type_substitutes.contains_key(&joined_path) {
-            return
-        }
-
         let segment = path.first().expect("path has at least one segment");
         let mod_ident = Ident::new(segment, Span::call_site());
 
@@ -203,7 +210,22 @@ impl<'a> TypeGenerator<'a> {
         let joined_path = ty.path().segments().join("::");
         if let Some(substitute_type_path) = self.type_substitutes.get(&joined_path) {
             TypePath::Substitute(TypePathSubstitute {
-                path: substitute_type_path.clone(),
+                substitute: substitute_type_path.clone(),
+                params,
+            })
+        } else if let Some(new_name) = self.type_renames.get(&joined_path) {
+            // Renamed types are still generated in the same module, so point at
+            // them as we would a substitute.
+            let root_mod_ident = &self.types_mod_ident;
+            let namespace = ty
+                .path()
+                .namespace()
+                .iter()
+                .map(|segment| Ident::new(segment, Span::call_site()));
+            let path: syn::TypePath =
+                syn::parse_quote!( #root_mod_ident :: #( #namespace :: )* #new_name );
+            TypePath::Substitute(TypePathSubstitute {
+                substitute: path.into(),
                 params,
             })
         } else {
@@ -215,22 +237,36 @@ impl<'a> TypeGenerator<'a> {
         }
     }
 
+    /// Returns the name of the generated type, taking into account any user defined renames.
+    pub fn type_name(&self, ty: &Type<PortableForm>) -> Option<String> {
+        let joined_path = ty.path().segments().join("::");
+        match self.type_renames.get(&joined_path) {
+            Some(new_name) => Some(new_name.to_string()),
+            None => ty.path().ident(),
+        }
+    }
+
     /// Returns the derives with which all generated type will be decorated.
     pub fn derives(&self) -> &GeneratedTypeDerives {
         &self.derives
     }
+
+    /// Returns the registry of the types being generated.
+    pub fn registry(&self) -> &'a PortableRegistry {
+        self.type_registry
+    }
 }
 
 /// Represents a Rust `mod`, containing generated types and child `mod`s.
 #[derive(Debug)]
-pub struct Module<'a> {
+pub struct Module {
     name: Ident,
     root_mod: Ident,
-    children: BTreeMap<Ident, Module<'a>>,
-    types: BTreeMap<scale_info::Path<scale_info::form::PortableForm>, TypeDefGen<'a>>,
+    children: BTreeMap<Ident, Module>,
+    types: BTreeMap<scale_info::Path<scale_info::form::PortableForm>, TypeDefGen>,
 }
 
-impl<'a> ToTokens for Module<'a> {
+impl ToTokens for Module {
     fn to_tokens(&self, tokens: &mut TokenStream) {
         let name = &self.name;
         let root_mod = &self.root_mod;
@@ -248,7 +284,7 @@ impl<'a> ToTokens for Module<'a> {
     }
 }
 
-impl<'a> Module<'a> {
+impl Module {
     /// Create a new [`Module`], with a reference to the root `mod` for resolving type paths.
     pub(crate) fn new(name: Ident, root_mod: Ident) -> Self {
         Self {
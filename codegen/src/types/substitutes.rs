@@ -0,0 +1,175 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::TypePath;
+use proc_macro2::{
+    Group,
+    Ident,
+    TokenStream,
+    TokenTree,
+};
+use quote::ToTokens;
+
+/// A type to use in place of a generated type.
+#[derive(Clone, Debug)]
+pub struct TypeSubstitute {
+    path: syn::TypePath,
+    /// The names given to the parameters of the generated type, in order, if the
+    /// replacement refers to them by name, as in `BoundedVec<T> = Vec<T>`. Otherwise
+    /// they're all appended to the replacement.
+    param_names: Option<Vec<Ident>>,
+}
+
+impl From<syn::TypePath> for TypeSubstitute {
+    fn from(path: syn::TypePath) -> Self {
+        Self {
+            path,
+            param_names: None,
+        }
+    }
+}
+
+impl TypeSubstitute {
+    /// Parse a substitution of the form `generated::Type<A, B> = replacement::Type<B, A>`
+    /// into the path of the generated type (as used to look it up, e.g.
+    /// `frame_support::storage::bounded_vec::BoundedVec`) and its substitute.
+    pub fn parse(substitution: &str) -> syn::Result<(String, Self)> {
+        let (generated, replacement) = substitution.split_once('=').ok_or_else(|| {
+            syn::Error::new(
+                proc_macro2::Span::call_site(),
+                format!(
+                    "Expected `generated::Type<T> = replacement::Type<T>`, got `{}`",
+                    substitution
+                ),
+            )
+        })?;
+        let generated: syn::TypePath = syn::parse_str(generated)?;
+        let path: syn::TypePath = syn::parse_str(replacement)?;
+
+        let segments = &generated.path.segments;
+        let generated_path = segments
+            .iter()
+            .map(|segment| segment.ident.to_string())
+            .collect::<Vec<_>>()
+            .join("::");
+        let param_names = match segments.last().map(|segment| &segment.arguments) {
+            Some(syn::PathArguments::AngleBracketed(args)) => {
+                let names = args
+                    .args
+                    .iter()
+                    .map(|arg| {
+                        match arg {
+                            syn::GenericArgument::Type(syn::Type::Path(ty))
+                                if ty.qself.is_none() =>
+                            {
+                                ty.path.get_ident().cloned()
+                            }
+                            _ => None,
+                        }
+                        .ok_or_else(|| {
+                            syn::Error::new_spanned(
+                                arg,
+                                "Type parameters of the generated type must be names",
+                            )
+                        })
+                    })
+                    .collect::<syn::Result<Vec<_>>>()?;
+                Some(names)
+            }
+            Some(syn::PathArguments::None) | None => None,
+            Some(args) => {
+                return Err(syn::Error::new_spanned(
+                    args,
+                    "Expected type parameters in angle brackets",
+                ))
+            }
+        };
+        Ok((generated_path, Self { path, param_names }))
+    }
+
+    /// The substitute for a generated type with the given parameters.
+    pub(super) fn to_syn_type(&self, params: &[TypePath]) -> syn::Type {
+        let path = &self.path;
+        match &self.param_names {
+            Some(names) => {
+                let tokens = substitute_params(path.to_token_stream(), names, params);
+                syn::parse2(tokens).expect("parameters are substituted with types; qed")
+            }
+            None if params.is_empty() => syn::Type::Path(path.clone()),
+            None => syn::parse_quote!( #path< #( #params ),* > ),
+        }
+    }
+
+    /// The parameters which the substitute makes use of.
+    pub(super) fn used_params<'a>(
+        &self,
+        params: &'a [TypePath],
+    ) -> impl Iterator<Item = &'a TypePath> {
+        let used = match &self.param_names {
+            Some(names) => {
+                names
+                    .iter()
+                    .map(|name| contains_ident(self.path.to_token_stream(), name))
+                    .collect()
+            }
+            None => vec![true; params.len()],
+        };
+        params
+            .iter()
+            .zip(used.into_iter().chain(std::iter::repeat(false)))
+            .filter_map(|(param, used)| used.then(|| param))
+    }
+}
+
+fn contains_ident(tokens: TokenStream, name: &Ident) -> bool {
+    tokens.into_iter().any(|token| {
+        match token {
+            TokenTree::Ident(ident) => ident == *name,
+            TokenTree::Group(group) => contains_ident(group.stream(), name),
+            _ => false,
+        }
+    })
+}
+
+/// Replace each of the named parameters in the tokens with the given types.
+fn substitute_params(
+    tokens: TokenStream,
+    names: &[Ident],
+    params: &[TypePath],
+) -> TokenStream {
+    tokens
+        .into_iter()
+        .map(|token| {
+            match token {
+                TokenTree::Ident(ident) => {
+                    match names.iter().position(|name| *name == ident) {
+                        Some(index) if index < params.len() => {
+                            params[index].to_token_stream()
+                        }
+                        _ => TokenTree::Ident(ident).into_token_stream(),
+                    }
+                }
+                TokenTree::Group(group) => {
+                    let stream = substitute_params(group.stream(), names, params);
+                    let mut substituted = Group::new(group.delimiter(), stream);
+                    substituted.set_span(group.span());
+                    TokenTree::Group(substituted).into_token_stream()
+                }
+                other => other.into_token_stream(),
+            }
+        })
+        .collect()
+}
@@ -24,7 +24,7 @@ use scale_info::{
 
 const MOD_PATH: &[&str] = &["subxt_codegen", "types", "tests"];
 
-fn get_mod<'a>(module: &'a Module, path_segs: &[&'static str]) -> Option<&'a Module<'a>> {
+fn get_mod<'a>(module: &'a Module, path_segs: &[&'static str]) -> Option<&'a Module> {
     let (mod_name, rest) = path_segs.split_first()?;
     let mod_ident = Ident::new(mod_name, Span::call_site());
     let module = module.children.get(&mod_ident)?;
@@ -54,6 +54,7 @@ fn generate_struct_with_primitives() {
         "root",
         Default::default(),
         Default::default(),
+        Default::default(),
     );
     let types = type_gen.generate_types_mod();
     let tests_mod = get_mod(&types, MOD_PATH).unwrap();
@@ -76,6 +77,64 @@ fn generate_struct_with_primitives() {
     )
 }
 
+#[test]
+fn substitute_generic_types_by_parameter_name() {
+    #[allow(unused)]
+    #[derive(TypeInfo)]
+    struct Bounded<T>(Vec<T>);
+
+    #[allow(unused)]
+    #[derive(TypeInfo)]
+    struct Pair<A, B> {
+        a: A,
+        b: B,
+    }
+
+    #[allow(unused)]
+    #[derive(TypeInfo)]
+    struct S {
+        a: Bounded<u8>,
+        b: Pair<u32, bool>,
+    }
+
+    let mut registry = Registry::new();
+    registry.register_type(&meta_type::<S>());
+    let portable_types: PortableRegistry = registry.into();
+
+    let substitutes = [
+        "subxt_codegen::types::tests::Bounded<T> = ::std::vec::Vec<T>",
+        "subxt_codegen::types::tests::Pair<A, B> = ::std::vec::Vec<(B, A)>",
+    ]
+    .iter()
+    .map(|substitution| TypeSubstitute::parse(substitution).unwrap())
+    .collect();
+    let type_gen = TypeGenerator::new(
+        &portable_types,
+        "root",
+        substitutes,
+        Default::default(),
+        Default::default(),
+    );
+    let types = type_gen.generate_types_mod();
+    let tests_mod = get_mod(&types, MOD_PATH).unwrap();
+
+    assert_eq!(
+        tests_mod.into_token_stream().to_string(),
+        quote! {
+            pub mod tests {
+                use super::root;
+
+                #[derive(::subxt::codec::Encode, ::subxt::codec::Decode, Debug)]
+                pub struct S {
+                    pub a: ::std::vec::Vec<::core::primitive::u8>,
+                    pub b: ::std::vec::Vec<(::core::primitive::bool, ::core::primitive::u32)>,
+                }
+            }
+        }
+        .to_string()
+    )
+}
+
 #[test]
 fn generate_struct_with_a_struct_field() {
     #[allow(unused)]
@@ -100,6 +159,7 @@ fn generate_struct_with_a_struct_field() {
         "root",
         Default::default(),
         Default::default(),
+        Default::default(),
     );
     let types = type_gen.generate_types_mod();
     let tests_mod = get_mod(&types, MOD_PATH).unwrap();
@@ -126,6 +186,64 @@ fn generate_struct_with_a_struct_field() {
     )
 }
 
+#[test]
+fn generate_renamed_struct() {
+    #[allow(unused)]
+    #[derive(TypeInfo)]
+    struct Parent {
+        a: bool,
+        b: Child,
+    }
+
+    #[allow(unused)]
+    #[derive(TypeInfo)]
+    struct Child {
+        a: i32,
+    }
+
+    let mut registry = Registry::new();
+    registry.register_type(&meta_type::<Parent>());
+    let portable_types: PortableRegistry = registry.into();
+
+    let type_renames = [(
+        "subxt_codegen::types::tests::Child".to_string(),
+        Ident::new("Kid", Span::call_site()),
+    )]
+    .into_iter()
+    .collect();
+
+    let type_gen = TypeGenerator::new(
+        &portable_types,
+        "root",
+        Default::default(),
+        type_renames,
+        Default::default(),
+    );
+    let types = type_gen.generate_types_mod();
+    let tests_mod = get_mod(&types, MOD_PATH).unwrap();
+
+    assert_eq!(
+        tests_mod.into_token_stream().to_string(),
+        quote! {
+            pub mod tests {
+                use super::root;
+
+                #[derive(::subxt::codec::Encode, ::subxt::codec::Decode, Debug)]
+                pub struct Kid {
+                    pub a: ::core::primitive::i32,
+                }
+
+                #[derive(::subxt::codec::Encode, ::subxt::codec::Decode, Debug)]
+                pub struct Parent {
+                    pub a: ::core::primitive::bool,
+                    pub b: root::subxt_codegen::types::tests::Kid,
+                }
+            }
+        }
+        .to_string()
+    )
+}
+
 #[test]
 fn generate_tuple_struct() {
     #[allow(unused)]
@@ -145,6 +263,7 @@ fn generate_tuple_struct() {
         "root",
         Default::default(),
         Default::default(),
+        Default::default(),
     );
     let types = type_gen.generate_types_mod();
     let tests_mod = get_mod(&types, MOD_PATH).unwrap();
@@ -227,6 +346,7 @@ fn derive_compact_as_for_uint_wrapper_structs() {
         "root",
         Default::default(),
         Default::default(),
+        Default::default(),
     );
     let types = type_gen.generate_types_mod();
     let tests_mod = get_mod(&types, MOD_PATH).unwrap();
@@ -291,6 +411,7 @@ fn generate_enum() {
         "root",
         Default::default(),
         Default::default(),
+        Default::default(),
     );
     let types = type_gen.generate_types_mod();
     let tests_mod = get_mod(&types, MOD_PATH).unwrap();
@@ -349,6 +470,7 @@ fn compact_fields() {
         "root",
         Default::default(),
         Default::default(),
+        Default::default(),
     );
     let types = type_gen.generate_types_mod();
     let tests_mod = get_mod(&types, MOD_PATH).unwrap();
@@ -399,6 +521,7 @@ fn generate_array_field() {
         "root",
         Default::default(),
         Default::default(),
+        Default::default(),
     );
     let types = type_gen.generate_types_mod();
     let tests_mod = get_mod(&types, MOD_PATH).unwrap();
@@ -436,6 +559,7 @@ fn option_fields() {
         "root",
         Default::default(),
         Default::default(),
+        Default::default(),
     );
     let types = type_gen.generate_types_mod();
     let tests_mod = get_mod(&types, MOD_PATH).unwrap();
@@ -476,6 +600,7 @@ fn box_fields_struct() {
         "root",
         Default::default(),
         Default::default(),
+        Default::default(),
     );
     let types = type_gen.generate_types_mod();
     let tests_mod = get_mod(&types, MOD_PATH).unwrap();
@@ -516,6 +641,7 @@ fn box_fields_enum() {
         "root",
         Default::default(),
         Default::default(),
+        Default::default(),
     );
     let types = type_gen.generate_types_mod();
     let tests_mod = get_mod(&types, MOD_PATH).unwrap();
@@ -556,6 +682,7 @@ fn range_fields() {
         "root",
         Default::default(),
         Default::default(),
+        Default::default(),
     );
     let types = type_gen.generate_types_mod();
     let tests_mod = get_mod(&types, MOD_PATH).unwrap();
@@ -600,6 +727,7 @@ fn generics() {
         "root",
         Default::default(),
         Default::default(),
+        Default::default(),
     );
     let types = type_gen.generate_types_mod();
     let tests_mod = get_mod(&types, MOD_PATH).unwrap();
@@ -648,6 +776,7 @@ fn generics_nested() {
         "root",
         Default::default(),
         Default::default(),
+        Default::default(),
     );
     let types = type_gen.generate_types_mod();
     let tests_mod = get_mod(&types, MOD_PATH).unwrap();
@@ -699,6 +828,7 @@ fn generate_bitvec() {
         "root",
         Default::default(),
         Default::default(),
+        Default::default(),
     );
     let types = type_gen.generate_types_mod();
     let tests_mod = get_mod(&types, MOD_PATH).unwrap();
@@ -752,6 +882,7 @@ fn generics_with_alias_adds_phantom_data_marker() {
         "root",
         Default::default(),
         Default::default(),
+        Default::default(),
     );
     let types = type_gen.generate_types_mod();
     let tests_mod = get_mod(&types, MOD_PATH).unwrap();
@@ -812,6 +943,7 @@ fn modules() {
         "root",
         Default::default(),
         Default::default(),
+        Default::default(),
     );
     let types = type_gen.generate_types_mod();
     let tests_mod = get_mod(&types, MOD_PATH).unwrap();
@@ -869,6 +1001,7 @@ fn dont_force_struct_names_camel_case() {
         "root",
         Default::default(),
         Default::default(),
+        Default::default(),
     );
     let types = type_gen.generate_types_mod();
     let tests_mod = get_mod(&types, MOD_PATH).unwrap();
@@ -886,3 +1019,109 @@ fn dont_force_struct_names_camel_case() {
         .to_string()
     )
 }
+
+#[test]
+fn derives_for_a_specific_type() {
+    #[allow(unused)]
+    #[derive(TypeInfo)]
+    struct A(B);
+
+    #[allow(unused)]
+    #[derive(TypeInfo)]
+    struct B(bool);
+
+    let mut registry = Registry::new();
+    registry.register_type(&meta_type::<A>());
+    let portable_types: PortableRegistry = registry.into();
+
+    let mut derives = GeneratedTypeDerives::default();
+    derives.push(syn::parse_quote!(Clone));
+    derives.append_for_type(
+        "subxt_codegen::types::tests::B",
+        [syn::parse_quote!(Eq), syn::parse_quote!(Hash)].into_iter(),
+    );
+    let type_gen = TypeGenerator::new(
+        &portable_types,
+        "root",
+        Default::default(),
+        Default::default(),
+        derives,
+    );
+    let types = type_gen.generate_types_mod();
+    let tests_mod = get_mod(&types, MOD_PATH).unwrap();
+
+    assert_eq!(
+        tests_mod.into_token_stream().to_string(),
+        quote! {
+            pub mod tests {
+                use super::root;
+
+                #[derive(::subxt::codec::Encode, ::subxt::codec::Decode, Debug, Clone)]
+                pub struct A(pub root::subxt_codegen::types::tests::B,);
+
+                #[derive(::subxt::codec::Encode, ::subxt::codec::Decode, Debug, Clone, Eq, Hash)]
+                pub struct B(pub ::core::primitive::bool,);
+            }
+        }
+        .to_string()
+    )
+}
+
+#[test]
+fn generate_docs_from_metadata() {
+    /// A struct.
+    #[allow(unused)]
+    #[derive(TypeInfo)]
+    struct S {
+        a: E,
+    }
+
+    /// An enum.
+    #[allow(unused)]
+    #[derive(TypeInfo)]
+    enum E {
+        /// The first variant.
+        A,
+        B,
+    }
+
+    let mut registry = Registry::new();
+    registry.register_type(&meta_type::<S>());
+    let portable_types: PortableRegistry = registry.into();
+
+    let type_gen = TypeGenerator::new(
+        &portable_types,
+        "root",
+        Default::default(),
+        Default::default(),
+        Default::default(),
+    );
+    let types = type_gen.generate_types_mod();
+    let tests_mod = get_mod(&types, MOD_PATH).unwrap();
+
+    assert_eq!(
+        tests_mod.into_token_stream().to_string(),
+        quote! {
+            pub mod tests {
+                use super::root;
+
+                #[doc = "An enum."]
+                #[derive(::subxt::codec::Encode, ::subxt::codec::Decode, Debug)]
+                pub enum E {
+                    #[codec(index = 0)]
+                    #[doc = "The first variant."]
+                    A,
+                    #[codec(index = 1)]
+                    B,
+                }
+
+                #[doc = "A struct."]
+                #[derive(::subxt::codec::Encode, ::subxt::codec::Decode, Debug)]
+                pub struct S {
+                    pub a: root::subxt_codegen::types::tests::E,
+                }
+            }
+        }
+        .to_string()
+    )
+}
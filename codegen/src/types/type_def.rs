@@ -39,19 +39,23 @@ use syn::parse_quote;
 /// Field type paths are resolved via the `TypeGenerator`, which contains the registry of all
 /// generated types in the module.
 #[derive(Debug)]
-pub struct TypeDefGen<'a> {
+pub struct TypeDefGen {
     /// The type parameters of the type to be generated
     type_params: TypeDefParameters,
     /// The derives with which to annotate the generated type.
-    derives: &'a GeneratedTypeDerives,
+    derives: GeneratedTypeDerives,
     /// The kind of type to be generated.
     ty_kind: TypeDefGenKind,
+    /// Documentation of the type from the metadata.
+    docs: Vec<String>,
 }
 
-impl<'a> TypeDefGen<'a> {
+impl TypeDefGen {
     /// Construct a type definition for codegen from the given [`scale_info::Type`].
-    pub fn from_type(ty: Type<PortableForm>, type_gen: &'a TypeGenerator) -> Self {
-        let derives = type_gen.derives();
+    pub fn from_type(ty: Type<PortableForm>, type_gen: &TypeGenerator) -> Self {
+        let derives = type_gen
+            .derives()
+            .for_type(&ty.path().segments().join("::"));
 
         let type_params = ty
             .type_params()
@@ -76,7 +80,8 @@ impl<'a> TypeDefGen<'a> {
 
         let ty_kind = match ty.type_def() {
             TypeDef::Composite(composite) => {
-                let type_name = ty.path().ident().expect("structs should have a name");
+                let type_name =
+                    type_gen.type_name(&ty).expect("structs should have a name");
                 let fields = CompositeDefFields::from_scale_info_fields(
                     &type_name,
                     composite.fields(),
@@ -89,12 +94,16 @@ impl<'a> TypeDefGen<'a> {
                     type_params.clone(),
                     fields,
                     Some(parse_quote!(pub)),
+                    derives.clone(),
                     type_gen,
-                );
+                )
+                .with_docs(ty.docs());
                 TypeDefGenKind::Struct(composite_def)
             }
             TypeDef::Variant(variant) => {
-                let type_name = ty.path().ident().expect("variants should have a name");
+                let type_name = type_gen
+                    .type_name(&ty)
+                    .expect("variants should have a name");
                 let variants = variant
                     .variants()
                     .iter()
@@ -107,7 +116,8 @@ impl<'a> TypeDefGen<'a> {
                         );
                         type_params.update_unused(fields.field_types());
                         let variant_def =
-                            CompositeDef::enum_variant_def(v.name(), fields);
+                            CompositeDef::enum_variant_def(v.name(), fields)
+                                .with_docs(v.docs());
                         (v.index(), variant_def)
                     })
                     .collect();
@@ -121,11 +131,12 @@ impl<'a> TypeDefGen<'a> {
             type_params,
             derives,
             ty_kind,
+            docs: ty.docs().to_vec(),
         }
     }
 }
 
-impl<'a> quote::ToTokens for TypeDefGen<'a> {
+impl quote::ToTokens for TypeDefGen {
     fn to_tokens(&self, tokens: &mut TokenStream) {
         match &self.ty_kind {
             TypeDefGenKind::Struct(composite) => composite.to_tokens(tokens),
@@ -149,8 +160,10 @@ impl<'a> quote::ToTokens for TypeDefGen<'a> {
 
                 let enum_ident = format_ident!("{}", type_name);
                 let type_params = &self.type_params;
-                let derives = self.derives;
+                let derives = &self.derives;
+                let docs = &self.docs;
                 let ty_toks = quote! {
+                    #( #[doc = #docs] )*
                     #derives
                     pub enum #enum_ident #type_params {
                         #( #variants, )*
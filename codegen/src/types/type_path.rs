@@ -14,14 +14,12 @@
 // You should have received a copy of the GNU General Public License
 // along with subxt.  If not, see <http://www.gnu.org/licenses/>.
 
+use super::TypeSubstitute;
 use proc_macro2::{
     Ident,
     TokenStream,
 };
-use quote::{
-    format_ident,
-    quote,
-};
+use quote::format_ident;
 use scale_info::{
     form::PortableForm,
     Type,
@@ -232,38 +230,24 @@ impl quote::ToTokens for TypeParameter {
 
 #[derive(Clone, Debug)]
 pub struct TypePathSubstitute {
-    pub(super) path: syn::TypePath,
+    pub(super) substitute: TypeSubstitute,
     pub(super) params: Vec<TypePath>,
 }
 
 impl quote::ToTokens for TypePathSubstitute {
     fn to_tokens(&self, tokens: &mut TokenStream) {
-        if self.params.is_empty() {
-            self.path.to_tokens(tokens)
-        } else {
-            let substitute_path = &self.path;
-            let params = &self.params;
-            tokens.extend(quote! {
-                #substitute_path< #( #params ),* >
-            })
-        }
+        self.to_syn_type().to_tokens(tokens)
     }
 }
 
 impl TypePathSubstitute {
     fn parent_type_params(&self, acc: &mut BTreeSet<TypeParameter>) {
-        for p in &self.params {
+        for p in self.substitute.used_params(&self.params) {
             p.parent_type_params(acc);
         }
     }
 
     fn to_syn_type(&self) -> syn::Type {
-        if self.params.is_empty() {
-            syn::Type::Path(self.path.clone())
-        } else {
-            let substitute_path = &self.path;
-            let params = &self.params;
-            parse_quote! ( #substitute_path< #( #params ),* > )
-        }
+        self.substitute.to_syn_type(&self.params)
     }
 }
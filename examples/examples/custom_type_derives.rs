@@ -24,7 +24,9 @@
     // a comma separated list to the below attribute. Most useful for adding `Clone`.
     // The derives that we can add ultimately is limited to the traits that the base
     // types relied upon by the codegen implement.
-    generated_type_derives = "Clone, PartialEq, Hash"
+    generated_type_derives = "Clone, PartialEq, Hash",
+    // Derives can also be added to a single type, given its path in the metadata.
+    derive_for_type(type = "frame_support::PalletId", derive = "Eq, Ord, PartialOrd")
 )]
 pub mod polkadot {}
 
@@ -34,5 +36,6 @@ use polkadot::runtime_types::frame_support::PalletId;
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let pallet_id = PalletId([1u8; 8]);
     let _ = pallet_id.clone();
+    assert!(pallet_id <= PalletId([2u8; 8]));
     Ok(())
 }
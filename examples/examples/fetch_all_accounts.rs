@@ -35,7 +35,10 @@ pub mod polkadot {}
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     env_logger::init();
 
+    // Fetching every account takes many requests; keep them from overwhelming the node.
     let api = ClientBuilder::new()
+        .set_rate_limit(50)
+        .set_max_concurrent_requests(4)
         .build()
         .await?
         .to_runtime_api::<polkadot::RuntimeApi<DefaultConfig, DefaultExtra<DefaultConfig>>>();
@@ -0,0 +1,36 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Verifying the KZG proofs of cells of the data matrix of Avail blocks locally, and
+//! sampling blocks to check that their data is available.
+//!
+//! This lives outside of `subxt` because it depends on a fork of `dusk-plonk` which
+//! isn't published to crates.io, and so it isn't published either. It's not a member
+//! of the workspace, so that the workspace still builds without fetching the fork.
+
+#![deny(missing_docs)]
+
+mod sampling;
+pub mod verification;
+
+pub use sampling::{
+    sample_block,
+    Sample,
+};
+pub use verification::{
+    VerificationError,
+    Verifier,
+};
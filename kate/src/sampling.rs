@@ -0,0 +1,142 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Data availability sampling: checking that the data of a block is available by
+//! verifying the proofs of randomly chosen cells.
+
+use crate::verification::Verifier;
+use codec::Decode;
+use rand::seq::index;
+use subxt::{
+    avail::AvailHeader,
+    rpc::Cell,
+    BasicError,
+    Client,
+    Config,
+};
+
+/// The outcome of sampling the data matrix of a block.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sample {
+    /// The cells which were sampled.
+    pub cells: Vec<Cell>,
+    /// How many of the sampled cells had a valid proof.
+    pub verified: usize,
+    /// The confidence that the data of the block is available, between 0 and 1.
+    pub confidence: f64,
+}
+
+impl Sample {
+    /// Did every sampled cell have a valid proof?
+    pub fn all_verified(&self) -> bool {
+        self.verified == self.cells.len()
+    }
+}
+
+/// Sample the data matrix of the block with the given hash: verify the proofs of enough
+/// randomly chosen cells to be at least `confidence` (between 0 and 1) sure that the
+/// data of the block is available, if every proof is valid.
+///
+/// The data matrix is erasure coded such that at least half of it must be withheld to
+/// make the data unavailable, and so each cell with a valid proof halves the chance
+/// that unavailable data goes unnoticed.
+pub async fn sample_block<T>(
+    client: &Client<T>,
+    verifier: &Verifier,
+    block_hash: T::Hash,
+    confidence: f64,
+) -> Result<Sample, BasicError>
+where
+    T: Config<Header = AvailHeader>,
+    T::Signature: Decode,
+{
+    if !(0.0..1.0).contains(&confidence) {
+        return Err(BasicError::Other(format!(
+            "Confidence must be at least 0 and less than 1, but is {}",
+            confidence
+        )))
+    }
+
+    let header = client
+        .rpc()
+        .header(Some(block_hash))
+        .await?
+        .ok_or_else(|| BasicError::Other(format!("Block {:?} not found", block_hash)))?;
+    let commitment = header.extension.commitment();
+    let rows = commitment.commitments().len();
+    let cols = commitment.cols as usize;
+
+    let needed = cells_for_confidence(confidence).min(rows * cols);
+    let cells: Vec<Cell> = index::sample(&mut rand::thread_rng(), rows * cols, needed)
+        .into_iter()
+        .map(|i| Cell::new((i / cols) as u32, (i % cols) as u32))
+        .collect();
+    if cells.is_empty() {
+        return Ok(Sample {
+            cells,
+            verified: 0,
+            confidence: 0.0,
+        })
+    }
+
+    let proofs = client
+        .rpc()
+        .kate_query_proof(&cells, Some(block_hash))
+        .await?;
+    // A malformed proof is as bad as an invalid one.
+    let verified = cells
+        .iter()
+        .zip(&proofs)
+        .filter(|(cell, proof)| {
+            verifier.verify(commitment, **cell, proof).unwrap_or(false)
+        })
+        .count();
+
+    Ok(Sample {
+        cells,
+        verified,
+        confidence: confidence_of(verified),
+    })
+}
+
+/// The number of cells which need a valid proof to reach the given confidence.
+fn cells_for_confidence(confidence: f64) -> usize {
+    (-(1.0 - confidence).log2()).ceil() as usize
+}
+
+/// The confidence that data is available given this many cells with a valid proof.
+fn confidence_of(verified: usize) -> f64 {
+    1.0 - 0.5f64.powi(verified as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cells_needed_for_confidence() {
+        assert_eq!(cells_for_confidence(0.0), 0);
+        assert_eq!(cells_for_confidence(0.5), 1);
+        assert_eq!(cells_for_confidence(0.9), 4);
+        assert_eq!(cells_for_confidence(0.99), 7);
+        assert_eq!(cells_for_confidence(0.999), 10);
+
+        for confidence in [0.5, 0.9, 0.99, 0.999] {
+            assert!(confidence_of(cells_for_confidence(confidence)) >= confidence);
+        }
+        assert_eq!(confidence_of(0), 0.0);
+    }
+}
@@ -0,0 +1,232 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Verifying the KZG proofs of cells of the data matrix of Avail blocks locally, as
+//! returned by [`subxt::rpc::Rpc::kate_query_proof()`], against the commitments in the
+//! block header.
+
+use dusk_bytes::Serializable;
+use dusk_plonk::{
+    bls12_381::G1Affine,
+    commitment_scheme::kzg10::{
+        commitment::Commitment,
+        proof::Proof as KzgProof,
+        PublicParameters,
+    },
+    fft::EvaluationDomain,
+    prelude::BlsScalar,
+};
+use rand_chacha::{
+    rand_core::SeedableRng,
+    ChaChaRng,
+};
+use subxt::{
+    avail::KateCommitment,
+    rpc::{
+        Cell,
+        Proof,
+    },
+};
+
+/// The seed from which Avail testnets generate their public parameters.
+const TESTNET_SEED: u64 = 42;
+
+/// Something went wrong verifying the proof of a cell, other than the proof being
+/// wrong. A proof which is merely wrong is reported by returning `false`.
+#[derive(Clone, Debug, Eq, thiserror::Error, PartialEq)]
+pub enum VerificationError {
+    /// The cell is in a row which the header has no commitment for.
+    #[error("Row {row} is out of range; the header has commitments for {rows} rows")]
+    RowOutOfRange {
+        /// The row of the cell.
+        row: u32,
+        /// The number of rows that the header has commitments for.
+        rows: usize,
+    },
+    /// The cell is in a column outside of the data matrix.
+    #[error("Column {col} is out of range; the data matrix has {cols} columns")]
+    ColumnOutOfRange {
+        /// The column of the cell.
+        col: u32,
+        /// The number of columns in the data matrix.
+        cols: u16,
+    },
+    /// The data matrix is wider than the public parameters allow for.
+    #[error("A data matrix {0} columns wide is not supported")]
+    UnsupportedWidth(u16),
+    /// The commitment in the header isn't a valid curve point.
+    #[error("The commitment to row {0} is malformed")]
+    MalformedCommitment(u32),
+    /// The proof isn't a valid curve point.
+    #[error("The proof is malformed")]
+    MalformedProof,
+    /// The cell data isn't a valid scalar.
+    #[error("The cell data is malformed")]
+    MalformedData,
+}
+
+/// Verifies cell proofs against the commitments in block headers, using the public
+/// parameters that Avail generates them with.
+///
+/// Setting up the parameters is fairly expensive, so a verifier is best created once
+/// and reused.
+pub struct Verifier {
+    public_parameters: PublicParameters,
+    max_cols: usize,
+}
+
+impl std::fmt::Debug for Verifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Verifier")
+            .field("max_cols", &self.max_cols)
+            .finish()
+    }
+}
+
+impl Verifier {
+    /// Create a verifier with the public parameters used by Avail testnets, for data
+    /// matrices up to `max_cols` columns wide.
+    pub fn testnet(max_cols: usize) -> Self {
+        let mut rng = ChaChaRng::seed_from_u64(TESTNET_SEED);
+        let public_parameters = PublicParameters::setup(max_cols, &mut rng)
+            .expect("max_cols is within the supported degree; qed");
+        Self::new(public_parameters, max_cols)
+    }
+
+    /// Create a verifier with the given public parameters, which support data matrices
+    /// up to `max_cols` columns wide.
+    pub fn new(public_parameters: PublicParameters, max_cols: usize) -> Self {
+        Self {
+            public_parameters,
+            max_cols,
+        }
+    }
+
+    /// Verify that the proof of the given cell is valid for the commitments in a block
+    /// header. Returns `false` if the proof is wrong.
+    pub fn verify(
+        &self,
+        commitment: &KateCommitment,
+        cell: Cell,
+        proof: &Proof,
+    ) -> Result<bool, VerificationError> {
+        let commitments = commitment.commitments();
+        let row_commitment = commitments.get(cell.row as usize).ok_or(
+            VerificationError::RowOutOfRange {
+                row: cell.row,
+                rows: commitments.len(),
+            },
+        )?;
+        if cell.col >= commitment.cols as u32 {
+            return Err(VerificationError::ColumnOutOfRange {
+                col: cell.col,
+                cols: commitment.cols,
+            })
+        }
+        if commitment.cols as usize > self.max_cols {
+            return Err(VerificationError::UnsupportedWidth(commitment.cols))
+        }
+
+        let commitment_to_polynomial = G1Affine::from_bytes(row_commitment)
+            .map(Commitment::from)
+            .map_err(|_| VerificationError::MalformedCommitment(cell.row))?;
+        let commitment_to_witness = G1Affine::from_bytes(&proof.proof)
+            .map(Commitment::from)
+            .map_err(|_| VerificationError::MalformedProof)?;
+        let evaluated_point = BlsScalar::from_bytes(&proof.data)
+            .map_err(|_| VerificationError::MalformedData)?;
+
+        let point = EvaluationDomain::new(commitment.cols as usize)
+            .map_err(|_| VerificationError::UnsupportedWidth(commitment.cols))?
+            .elements()
+            .nth(cell.col as usize)
+            .ok_or(VerificationError::ColumnOutOfRange {
+                col: cell.col,
+                cols: commitment.cols,
+            })?;
+
+        let proof = KzgProof {
+            commitment_to_witness,
+            evaluated_point,
+            commitment_to_polynomial,
+        };
+        Ok(self.public_parameters.opening_key().check(point, proof))
+    }
+
+    /// Verify the proofs of several cells, returning whether each one is valid.
+    pub fn verify_all(
+        &self,
+        commitment: &KateCommitment,
+        cells: &[Cell],
+        proofs: &[Proof],
+    ) -> Result<Vec<bool>, VerificationError> {
+        cells
+            .iter()
+            .zip(proofs)
+            .map(|(cell, proof)| self.verify(commitment, *cell, proof))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use subxt::{
+        avail::COMMITMENT_LEN,
+        rpc::{
+            CELL_DATA_LEN,
+            CELL_PROOF_LEN,
+        },
+    };
+
+    fn commitment() -> KateCommitment {
+        KateCommitment {
+            rows: 1,
+            cols: 4,
+            data_root: Default::default(),
+            commitment: vec![0xff; 2 * COMMITMENT_LEN],
+        }
+    }
+
+    fn proof() -> Proof {
+        Proof {
+            proof: [0xff; CELL_PROOF_LEN],
+            data: [0; CELL_DATA_LEN],
+        }
+    }
+
+    #[test]
+    fn cells_outside_the_matrix_are_rejected() {
+        let verifier = Verifier::testnet(4);
+        assert_eq!(
+            verifier.verify(&commitment(), Cell::new(2, 0), &proof()),
+            Err(VerificationError::RowOutOfRange { row: 2, rows: 2 })
+        );
+        assert_eq!(
+            verifier.verify(&commitment(), Cell::new(0, 4), &proof()),
+            Err(VerificationError::ColumnOutOfRange { col: 4, cols: 4 })
+        );
+    }
+
+    #[test]
+    fn malformed_points_are_rejected() {
+        let verifier = Verifier::testnet(4);
+        assert_eq!(
+            verifier.verify(&commitment(), Cell::new(0, 0), &proof()),
+            Err(VerificationError::MalformedCommitment(0))
+        );
+    }
+}
@@ -29,11 +29,68 @@ struct RuntimeMetadataArgs {
     runtime_metadata_path: String,
     #[darling(default)]
     generated_type_derives: Option<GeneratedTypeDerives>,
+    /// Extra derives for all generated types, in addition to `generated_type_derives`.
+    #[darling(default)]
+    derive_for_all_types: Option<GeneratedTypeDerives>,
+    /// Extra derives for a single generated type, as
+    /// `derive_for_type(type = "frame_support::PalletId", derive = "Eq, Hash")`.
+    /// May be given more than once.
+    #[darling(multiple)]
+    derive_for_type: Vec<DeriveForType>,
+    /// Generate the API for only these pallets, given as a comma separated list of names.
+    #[darling(default)]
+    pallets: Option<PalletNames>,
+    /// Generate the API for every pallet except these, given as a comma separated list.
+    #[darling(default)]
+    skip_pallets: Option<PalletNames>,
+    /// Types to use in place of generated ones, as
+    /// `"generated::Type<T> = replacement::Type<T>"`. May be given more than once.
+    #[darling(multiple)]
+    substitute_type: Vec<String>,
 }
 
 #[derive(Debug, FromMeta)]
 struct GeneratedTypeDerives(Punctuated<syn::Path, syn::Token![,]>);
 
+#[derive(Debug)]
+struct PalletNames(Vec<String>);
+
+impl FromMeta for PalletNames {
+    fn from_string(value: &str) -> darling::Result<Self> {
+        Ok(Self(
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(ToString::to_string)
+                .collect(),
+        ))
+    }
+}
+
+#[derive(Debug, FromMeta)]
+struct DeriveForType {
+    #[darling(rename = "type")]
+    ty: String,
+    derive: GeneratedTypeDerives,
+}
+
+fn pallet_selection(
+    pallets: Option<PalletNames>,
+    skip_pallets: Option<PalletNames>,
+) -> darling::Result<subxt_codegen::PalletSelection> {
+    match (pallets, skip_pallets) {
+        (None, None) => Ok(subxt_codegen::PalletSelection::All),
+        (Some(only), None) => Ok(subxt_codegen::PalletSelection::Only(only.0)),
+        (None, Some(skip)) => Ok(subxt_codegen::PalletSelection::Skip(skip.0)),
+        (Some(_), Some(_)) => {
+            Err(darling::Error::custom(
+                "`pallets` and `skip_pallets` cannot be used together",
+            ))
+        }
+    }
+}
+
 #[proc_macro_attribute]
 #[proc_macro_error]
 pub fn subxt(args: TokenStream, input: TokenStream) -> TokenStream {
@@ -44,11 +101,83 @@ pub fn subxt(args: TokenStream, input: TokenStream) -> TokenStream {
         Err(e) => return TokenStream::from(e.write_errors()),
     };
 
+    let pallets = match pallet_selection(args.pallets, args.skip_pallets) {
+        Ok(pallets) => pallets,
+        Err(e) => return TokenStream::from(e.write_errors()),
+    };
+
     let root = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_else(|_| ".".into());
     let root_path = std::path::Path::new(&root);
     let path = root_path.join(args.runtime_metadata_path);
 
-    let generated_type_derives = args.generated_type_derives.map(|derives| derives.0);
+    let mut derives = subxt_codegen::GeneratedTypeDerives::default();
+    for user_derives in args
+        .generated_type_derives
+        .into_iter()
+        .chain(args.derive_for_all_types)
+    {
+        derives.append(user_derives.0.into_iter())
+    }
+    for derive_for_type in args.derive_for_type {
+        derives.append_for_type(derive_for_type.ty, derive_for_type.derive.0.into_iter())
+    }
+    let type_substitutes = match args
+        .substitute_type
+        .iter()
+        .map(|substitution| subxt_codegen::TypeSubstitute::parse(substitution))
+        .collect::<Result<Vec<_>, _>>()
+    {
+        Ok(substitutes) => substitutes,
+        Err(e) => return TokenStream::from(e.to_compile_error()),
+    };
+
+    subxt_codegen::generate_runtime_api(
+        item_mod,
+        &path,
+        derives,
+        type_substitutes,
+        pallets,
+    )
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use subxt_codegen::PalletSelection;
+    use syn::parse_quote;
+
+    fn selection(args: Vec<syn::NestedMeta>) -> darling::Result<PalletSelection> {
+        let mut attr_args: Vec<syn::NestedMeta> =
+            vec![parse_quote!(runtime_metadata_path = "metadata.scale")];
+        attr_args.extend(args);
+        let args = RuntimeMetadataArgs::from_list(&attr_args)?;
+        pallet_selection(args.pallets, args.skip_pallets)
+    }
+
+    #[test]
+    fn every_pallet_is_selected_by_default() {
+        assert_eq!(selection(vec![]).unwrap(), PalletSelection::All);
+    }
+
+    #[test]
+    fn pallets_are_parsed_from_a_comma_separated_list() {
+        assert_eq!(
+            selection(vec![parse_quote!(pallets = "System, Balances,")]).unwrap(),
+            PalletSelection::Only(vec!["System".into(), "Balances".into()])
+        );
+        assert_eq!(
+            selection(vec![parse_quote!(skip_pallets = "Staking")]).unwrap(),
+            PalletSelection::Skip(vec!["Staking".into()])
+        );
+    }
 
-    subxt_codegen::generate_runtime_api(item_mod, &path, generated_type_derives).into()
+    #[test]
+    fn pallets_and_skip_pallets_cannot_be_combined() {
+        assert!(selection(vec![
+            parse_quote!(pallets = "System"),
+            parse_quote!(skip_pallets = "Staking"),
+        ])
+        .is_err());
+    }
 }
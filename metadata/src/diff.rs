@@ -0,0 +1,375 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Comparison of two versions of the metadata of a runtime, to review what a runtime
+//! upgrade changes before updating the code that talks to it.
+
+use crate::{
+    get_call_hash,
+    get_constant_hash,
+    get_event_hash,
+    get_storage_hash,
+    Hash,
+};
+use frame_metadata::{
+    PalletMetadata,
+    RuntimeMetadataLastVersion,
+};
+use scale_info::{
+    form::PortableForm,
+    PortableRegistry,
+    TypeDef,
+};
+
+/// How an item differs between the old and the new metadata.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Change {
+    /// The item is only in the new metadata.
+    Added,
+    /// The item is only in the old metadata.
+    Removed,
+    /// The item is in both, but its types (or value, for a constant) differ.
+    Changed,
+}
+
+/// The kind of an item of a pallet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ItemKind {
+    /// A call.
+    Call,
+    /// A storage entry.
+    Storage,
+    /// An event.
+    Event,
+    /// A constant.
+    Constant,
+}
+
+impl std::fmt::Display for ItemKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Call => "call",
+            Self::Storage => "storage",
+            Self::Event => "event",
+            Self::Constant => "constant",
+        })
+    }
+}
+
+/// An item of a pallet which differs between the old and the new metadata.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ItemDiff {
+    /// The name of the pallet of the item.
+    pub pallet: String,
+    /// The kind of the item.
+    pub kind: ItemKind,
+    /// The name of the item.
+    pub name: String,
+    /// How the item differs.
+    pub change: Change,
+}
+
+/// The differences between two versions of the metadata of a runtime.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MetadataDiff {
+    /// The pallets which are only in the new metadata.
+    pub added_pallets: Vec<String>,
+    /// The pallets which are only in the old metadata.
+    pub removed_pallets: Vec<String>,
+    /// The items which differ, including those of added and removed pallets.
+    pub items: Vec<ItemDiff>,
+}
+
+impl MetadataDiff {
+    /// Returns `true` if nothing that this compares differs.
+    pub fn is_empty(&self) -> bool {
+        self.added_pallets.is_empty()
+            && self.removed_pallets.is_empty()
+            && self.items.is_empty()
+    }
+
+    /// The differing items of the given kind.
+    pub fn items_of(&self, kind: ItemKind) -> impl Iterator<Item = &ItemDiff> {
+        self.items.iter().filter(move |item| item.kind == kind)
+    }
+}
+
+impl std::fmt::Display for MetadataDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for pallet in &self.added_pallets {
+            writeln!(f, "+ pallet {}", pallet)?;
+        }
+        for pallet in &self.removed_pallets {
+            writeln!(f, "- pallet {}", pallet)?;
+        }
+        for item in &self.items {
+            let sign = match item.change {
+                Change::Added => '+',
+                Change::Removed => '-',
+                Change::Changed => '~',
+            };
+            writeln!(f, "{} {} {}::{}", sign, item.kind, item.pallet, item.name)?;
+        }
+        Ok(())
+    }
+}
+
+/// Compare the calls, storage entries, events and constants of the pallets in `old`
+/// with those in `new`.
+///
+/// Items are compared by their hashes (see [`get_call_hash`] and friends), which cover
+/// the shape of their types, so renumbering types doesn't count as a change. Constants
+/// are also compared by value.
+pub fn diff(
+    old: &RuntimeMetadataLastVersion,
+    new: &RuntimeMetadataLastVersion,
+) -> MetadataDiff {
+    let mut diff = MetadataDiff::default();
+
+    let new_only = new
+        .pallets
+        .iter()
+        .filter(|pallet| !old.pallets.iter().any(|p| p.name == pallet.name));
+    let pallets = old
+        .pallets
+        .iter()
+        .map(|pallet| {
+            let new_pallet = new.pallets.iter().find(|p| p.name == pallet.name);
+            (Some(pallet), new_pallet)
+        })
+        .chain(new_only.map(|pallet| (None, Some(pallet))));
+
+    for (old_pallet, new_pallet) in pallets {
+        let name = match (old_pallet, new_pallet) {
+            (Some(pallet), None) => {
+                diff.removed_pallets.push(pallet.name.clone());
+                &pallet.name
+            }
+            (None, Some(pallet)) => {
+                diff.added_pallets.push(pallet.name.clone());
+                &pallet.name
+            }
+            (Some(pallet), Some(_)) => &pallet.name,
+            (None, None) => unreachable!("each pallet is in at least one metadata"),
+        };
+        for kind in [
+            ItemKind::Call,
+            ItemKind::Storage,
+            ItemKind::Event,
+            ItemKind::Constant,
+        ] {
+            let old_items = old_pallet
+                .map(|pallet| item_fingerprints(&old.types, pallet, kind))
+                .unwrap_or_default();
+            let new_items = new_pallet
+                .map(|pallet| item_fingerprints(&new.types, pallet, kind))
+                .unwrap_or_default();
+            let item = |item_name: &str, change| {
+                ItemDiff {
+                    pallet: name.clone(),
+                    kind,
+                    name: item_name.to_string(),
+                    change,
+                }
+            };
+
+            for (item_name, fingerprint) in &old_items {
+                match new_items.iter().find(|(n, _)| n == item_name) {
+                    None => diff.items.push(item(item_name, Change::Removed)),
+                    Some((_, new_fingerprint)) if new_fingerprint != fingerprint => {
+                        diff.items.push(item(item_name, Change::Changed))
+                    }
+                    Some(_) => (),
+                }
+            }
+            for (item_name, _) in &new_items {
+                if !old_items.iter().any(|(n, _)| n == item_name) {
+                    diff.items.push(item(item_name, Change::Added))
+                }
+            }
+        }
+    }
+    diff
+}
+
+/// What an item is compared by: the hash of its types, and the value of a constant.
+type Fingerprint = (Hash, Vec<u8>);
+
+/// The names and fingerprints of the items of the given kind in the pallet.
+fn item_fingerprints(
+    registry: &PortableRegistry,
+    pallet: &PalletMetadata<PortableForm>,
+    kind: ItemKind,
+) -> Vec<(String, Fingerprint)> {
+    let variant_names = |id: Option<u32>| {
+        match id
+            .and_then(|id| registry.resolve(id))
+            .map(|ty| ty.type_def())
+        {
+            Some(TypeDef::Variant(variant)) => {
+                variant
+                    .variants()
+                    .iter()
+                    .map(|v| v.name().clone())
+                    .collect()
+            }
+            _ => Vec::new(),
+        }
+    };
+    let names: Vec<String> = match kind {
+        ItemKind::Call => variant_names(pallet.calls.as_ref().map(|c| c.ty.id())),
+        ItemKind::Event => variant_names(pallet.event.as_ref().map(|e| e.ty.id())),
+        ItemKind::Storage => {
+            pallet
+                .storage
+                .iter()
+                .flat_map(|storage| storage.entries.iter().map(|e| e.name.clone()))
+                .collect()
+        }
+        ItemKind::Constant => pallet.constants.iter().map(|c| c.name.clone()).collect(),
+    };
+
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let fingerprint = match kind {
+                ItemKind::Call => (get_call_hash(registry, pallet, &name)?, Vec::new()),
+                ItemKind::Event => (get_event_hash(registry, pallet, &name)?, Vec::new()),
+                ItemKind::Storage => {
+                    (get_storage_hash(registry, pallet, &name)?, Vec::new())
+                }
+                ItemKind::Constant => {
+                    let constant = pallet.constants.iter().find(|c| c.name == name)?;
+                    (
+                        get_constant_hash(registry, pallet, &name)?,
+                        constant.value.clone(),
+                    )
+                }
+            };
+            Some((name, fingerprint))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use frame_metadata::{
+        ExtrinsicMetadata,
+        PalletCallMetadata,
+        PalletConstantMetadata,
+    };
+    use scale_info::{
+        meta_type,
+        TypeInfo,
+    };
+
+    mod v1 {
+        #[allow(dead_code, non_camel_case_types)]
+        #[derive(scale_info::TypeInfo)]
+        pub enum Call {
+            remark { data: Vec<u8> },
+            transfer { amount: u64 },
+            burn { amount: u64 },
+        }
+    }
+
+    mod v2 {
+        #[allow(dead_code, non_camel_case_types)]
+        #[derive(scale_info::TypeInfo)]
+        pub enum Call {
+            remark { data: Vec<u8> },
+            transfer { amount: u128 },
+            mint { amount: u128 },
+        }
+    }
+
+    fn metadata<Call: TypeInfo + 'static>(
+        pallets: &[&'static str],
+        deposit: u64,
+    ) -> RuntimeMetadataLastVersion {
+        let pallets = pallets
+            .iter()
+            .enumerate()
+            .map(|(index, name)| {
+                PalletMetadata {
+                    name: *name,
+                    storage: None,
+                    calls: Some(PalletCallMetadata {
+                        ty: meta_type::<Call>(),
+                    }),
+                    event: None,
+                    constants: vec![PalletConstantMetadata {
+                        name: "Deposit",
+                        ty: meta_type::<u64>(),
+                        value: codec::Encode::encode(&deposit),
+                        docs: vec![],
+                    }],
+                    error: None,
+                    index: index as u8,
+                }
+            })
+            .collect();
+        let extrinsic = ExtrinsicMetadata {
+            ty: meta_type::<()>(),
+            version: 0,
+            signed_extensions: vec![],
+        };
+        RuntimeMetadataLastVersion::new(pallets, extrinsic, meta_type::<()>())
+    }
+
+    #[test]
+    fn identical_metadata_has_no_differences() {
+        let metadata = metadata::<v1::Call>(&["Test"], 1);
+        assert!(diff(&metadata, &metadata).is_empty());
+    }
+
+    #[test]
+    fn added_removed_and_changed_items_are_reported() {
+        let old = metadata::<v1::Call>(&["Test", "Old"], 1);
+        let new = metadata::<v2::Call>(&["Test", "New"], 2);
+        let diff = diff(&old, &new);
+
+        assert_eq!(diff.added_pallets, vec!["New".to_string()]);
+        assert_eq!(diff.removed_pallets, vec!["Old".to_string()]);
+
+        let test_items = diff
+            .items
+            .iter()
+            .filter(|item| item.pallet == "Test")
+            .map(|item| (item.kind, item.name.as_str(), item.change))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            test_items,
+            vec![
+                (ItemKind::Call, "transfer", Change::Changed),
+                (ItemKind::Call, "burn", Change::Removed),
+                (ItemKind::Call, "mint", Change::Added),
+                (ItemKind::Constant, "Deposit", Change::Changed),
+            ]
+        );
+        // Every item of a removed pallet is reported as removed.
+        assert_eq!(
+            diff.items
+                .iter()
+                .filter(|item| item.pallet == "Old")
+                .map(|item| item.change)
+                .collect::<Vec<_>>(),
+            vec![Change::Removed; 4]
+        );
+        assert!(diff.to_string().contains("~ call Test::transfer\n"));
+    }
+}
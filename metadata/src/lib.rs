@@ -0,0 +1,452 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Hashes of the parts of FRAME metadata which code generated from it depends on.
+//!
+//! Types are hashed by their shape (the names and types of their fields and variants,
+//! and so on) rather than by their ids, which change whenever a type is added to or
+//! removed from the registry. So a hash only changes if the way the values it covers
+//! are encoded does.
+//!
+//! The same hashes are used by [`diff`] to compare two versions of the metadata.
+
+mod diff;
+
+pub use diff::{
+    diff,
+    Change,
+    ItemDiff,
+    ItemKind,
+    MetadataDiff,
+};
+
+use codec::Encode;
+use frame_metadata::{
+    PalletConstantMetadata,
+    PalletMetadata,
+    RuntimeMetadataLastVersion,
+    StorageEntryMetadata,
+    StorageEntryType,
+};
+use scale_info::{
+    form::PortableForm,
+    Field,
+    PortableRegistry,
+    TypeDef,
+    Variant,
+};
+use std::collections::HashSet;
+
+/// Hashed in place of a type which contains itself, when it's reached again.
+const RECURSIVE_TYPE: &[u8] = &[123];
+/// Hashed in place of a type which is missing from the registry.
+const MISSING_TYPE: &[u8] = &[255];
+
+/// A 256 bit hash.
+pub type Hash = [u8; 32];
+
+fn hash(data: &[u8]) -> Hash {
+    sp_core_hashing::twox_256(data)
+}
+
+/// Combine two hashes, in order.
+fn hash_hashes(a: Hash, b: Hash) -> Hash {
+    let mut bytes = [0u8; 64];
+    bytes[..32].copy_from_slice(&a);
+    bytes[32..].copy_from_slice(&b);
+    hash(&bytes)
+}
+
+fn hash_all(tag: u8, hashes: impl IntoIterator<Item = Hash>) -> Hash {
+    hashes.into_iter().fold(hash(&[tag]), hash_hashes)
+}
+
+fn field_hash(
+    registry: &PortableRegistry,
+    field: &Field<PortableForm>,
+    visiting: &mut HashSet<u32>,
+) -> Hash {
+    let ty = type_hash(registry, field.ty().id(), visiting);
+    let name = field.name().map(|name| name.as_bytes()).unwrap_or_default();
+    hash_hashes(hash(name), ty)
+}
+
+fn variant_hash(
+    registry: &PortableRegistry,
+    variant: &Variant<PortableForm>,
+    visiting: &mut HashSet<u32>,
+) -> Hash {
+    let name = hash(&(variant.name(), variant.index()).encode());
+    let fields = variant
+        .fields()
+        .iter()
+        .map(|field| field_hash(registry, field, visiting))
+        .collect::<Vec<_>>();
+    hash_hashes(name, hash_all(0, fields))
+}
+
+fn type_def_hash(
+    registry: &PortableRegistry,
+    def: &TypeDef<PortableForm>,
+    visiting: &mut HashSet<u32>,
+) -> Hash {
+    match def {
+        TypeDef::Composite(composite) => {
+            let fields = composite
+                .fields()
+                .iter()
+                .map(|field| field_hash(registry, field, visiting))
+                .collect::<Vec<_>>();
+            hash_all(0, fields)
+        }
+        TypeDef::Variant(variants) => {
+            let variants = variants
+                .variants()
+                .iter()
+                .map(|variant| variant_hash(registry, variant, visiting))
+                .collect::<Vec<_>>();
+            hash_all(1, variants)
+        }
+        TypeDef::Sequence(seq) => {
+            hash_all(2, [type_hash(registry, seq.type_param().id(), visiting)])
+        }
+        TypeDef::Array(array) => {
+            hash_hashes(
+                hash(&(3u8, array.len()).encode()),
+                type_hash(registry, array.type_param().id(), visiting),
+            )
+        }
+        TypeDef::Tuple(tuple) => {
+            let fields = tuple
+                .fields()
+                .iter()
+                .map(|field| type_hash(registry, field.id(), visiting))
+                .collect::<Vec<_>>();
+            hash_all(4, fields)
+        }
+        TypeDef::Primitive(primitive) => hash(&(5u8, primitive).encode()),
+        TypeDef::Compact(compact) => {
+            hash_all(
+                6,
+                [type_hash(registry, compact.type_param().id(), visiting)],
+            )
+        }
+        TypeDef::BitSequence(bits) => {
+            let store = type_hash(registry, bits.bit_store_type().id(), visiting);
+            let order = type_hash(registry, bits.bit_order_type().id(), visiting);
+            hash_all(7, [store, order])
+        }
+    }
+}
+
+fn type_hash(registry: &PortableRegistry, id: u32, visiting: &mut HashSet<u32>) -> Hash {
+    // Only the types being hashed further up are tracked, so that a type used in
+    // several places is hashed the same way in each of them.
+    if !visiting.insert(id) {
+        return hash(RECURSIVE_TYPE)
+    }
+    let hash = match registry.resolve(id) {
+        Some(ty) => type_def_hash(registry, ty.type_def(), visiting),
+        None => hash(MISSING_TYPE),
+    };
+    visiting.remove(&id);
+    hash
+}
+
+/// The hash of the shape of the type with the given id.
+pub fn get_type_hash(registry: &PortableRegistry, id: u32) -> Hash {
+    type_hash(registry, id, &mut HashSet::new())
+}
+
+fn storage_entry_hash(
+    registry: &PortableRegistry,
+    entry: &StorageEntryMetadata<PortableForm>,
+) -> Hash {
+    let name = hash(&(&entry.name, &entry.modifier).encode());
+    let ty = match &entry.ty {
+        StorageEntryType::Plain(ty) => hash_all(0, [get_type_hash(registry, ty.id())]),
+        StorageEntryType::Map {
+            hashers,
+            key,
+            value,
+        } => {
+            hash_all(
+                1,
+                [
+                    hash(&hashers.encode()),
+                    get_type_hash(registry, key.id()),
+                    get_type_hash(registry, value.id()),
+                ],
+            )
+        }
+    };
+    hash_hashes(name, ty)
+}
+
+/// The hash of the call with the given name in the pallet, or `None` if there's no such
+/// call.
+pub fn get_call_hash(
+    registry: &PortableRegistry,
+    pallet: &PalletMetadata<PortableForm>,
+    call_name: &str,
+) -> Option<Hash> {
+    named_variant_hash(registry, pallet.calls.as_ref()?.ty.id(), call_name)
+}
+
+/// The hash of the event with the given name in the pallet, or `None` if there's no
+/// such event.
+pub fn get_event_hash(
+    registry: &PortableRegistry,
+    pallet: &PalletMetadata<PortableForm>,
+    event_name: &str,
+) -> Option<Hash> {
+    named_variant_hash(registry, pallet.event.as_ref()?.ty.id(), event_name)
+}
+
+/// The hash of the name and type (but not the value) of the constant with the given
+/// name in the pallet, or `None` if there's no such constant.
+pub fn get_constant_hash(
+    registry: &PortableRegistry,
+    pallet: &PalletMetadata<PortableForm>,
+    constant_name: &str,
+) -> Option<Hash> {
+    let constant = pallet
+        .constants
+        .iter()
+        .find(|constant| constant.name == constant_name)?;
+    Some(constant_hash(registry, constant))
+}
+
+fn named_variant_hash(registry: &PortableRegistry, id: u32, name: &str) -> Option<Hash> {
+    let variant = match registry.resolve(id)?.type_def() {
+        TypeDef::Variant(variants) => {
+            variants.variants().iter().find(|v| v.name() == name)?
+        }
+        _ => return None,
+    };
+    Some(variant_hash(registry, variant, &mut HashSet::new()))
+}
+
+fn constant_hash(
+    registry: &PortableRegistry,
+    constant: &PalletConstantMetadata<PortableForm>,
+) -> Hash {
+    hash_hashes(
+        hash(constant.name.as_bytes()),
+        get_type_hash(registry, constant.ty.id()),
+    )
+}
+
+/// The hash of the storage entry with the given name in the pallet, or `None` if
+/// there's no such entry.
+pub fn get_storage_hash(
+    registry: &PortableRegistry,
+    pallet: &PalletMetadata<PortableForm>,
+    entry_name: &str,
+) -> Option<Hash> {
+    let entry = pallet
+        .storage
+        .as_ref()?
+        .entries
+        .iter()
+        .find(|entry| entry.name == entry_name)?;
+    Some(storage_entry_hash(registry, entry))
+}
+
+/// The hashes of each part of a pallet's metadata.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PalletHashes {
+    /// The hash of the pallet's calls.
+    pub calls: Hash,
+    /// The hash of the pallet's events.
+    pub events: Hash,
+    /// The hash of the pallet's errors.
+    pub errors: Hash,
+    /// The hash of the pallet's storage entries.
+    pub storage: Hash,
+    /// The hash of the names and types (but not the values) of the pallet's constants.
+    pub constants: Hash,
+}
+
+impl PalletHashes {
+    /// The names of the parts of the pallet whose hashes differ from `other`'s.
+    pub fn differences(&self, other: &Self) -> Vec<&'static str> {
+        [
+            ("calls", self.calls == other.calls),
+            ("events", self.events == other.events),
+            ("errors", self.errors == other.errors),
+            ("storage", self.storage == other.storage),
+            ("constants", self.constants == other.constants),
+        ]
+        .into_iter()
+        .filter(|(_, same)| !same)
+        .map(|(part, _)| part)
+        .collect()
+    }
+}
+
+/// The hashes of each part of the given pallet's metadata.
+pub fn get_pallet_hashes(
+    registry: &PortableRegistry,
+    pallet: &PalletMetadata<PortableForm>,
+) -> PalletHashes {
+    let optional_type = |ty: Option<u32>| {
+        match ty {
+            Some(id) => get_type_hash(registry, id),
+            None => hash(&[]),
+        }
+    };
+    let storage = pallet.storage.as_ref().map(|storage| {
+        storage
+            .entries
+            .iter()
+            .map(|entry| storage_entry_hash(registry, entry))
+            .collect::<Vec<_>>()
+    });
+    let constants = pallet
+        .constants
+        .iter()
+        .map(|constant| constant_hash(registry, constant));
+    PalletHashes {
+        calls: optional_type(pallet.calls.as_ref().map(|calls| calls.ty.id())),
+        events: optional_type(pallet.event.as_ref().map(|event| event.ty.id())),
+        errors: optional_type(pallet.error.as_ref().map(|error| error.ty.id())),
+        storage: hash_all(0, storage.unwrap_or_default()),
+        constants: hash_all(0, constants),
+    }
+}
+
+/// The hashes of each pallet in the metadata, by name.
+pub fn get_metadata_hashes(
+    metadata: &RuntimeMetadataLastVersion,
+) -> Vec<(String, PalletHashes)> {
+    metadata
+        .pallets
+        .iter()
+        .map(|pallet| {
+            (
+                pallet.name.clone(),
+                get_pallet_hashes(&metadata.types, pallet),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use frame_metadata::{
+        ExtrinsicMetadata,
+        PalletCallMetadata,
+        RuntimeMetadataPrefixed,
+    };
+    use scale_info::{
+        meta_type,
+        TypeInfo,
+    };
+
+    #[allow(dead_code)]
+    #[derive(TypeInfo)]
+    struct Recursive {
+        value: u32,
+        children: Vec<Recursive>,
+    }
+
+    mod v1 {
+        #[allow(dead_code, non_camel_case_types)]
+        #[derive(scale_info::TypeInfo)]
+        pub enum Call {
+            remark { data: Vec<u8> },
+            transfer { amount: u64 },
+        }
+    }
+
+    mod v2 {
+        #[allow(dead_code, non_camel_case_types)]
+        #[derive(scale_info::TypeInfo)]
+        pub enum Call {
+            remark { data: Vec<u8> },
+            transfer { amount: u128 },
+        }
+    }
+
+    /// Metadata with a pallet calling `Other` registered before the `Test` pallet.
+    fn metadata_with<Call: TypeInfo + 'static, Other: TypeInfo + 'static>(
+    ) -> RuntimeMetadataLastVersion {
+        let pallet = |name, index, ty| {
+            PalletMetadata {
+                name,
+                storage: None,
+                calls: Some(PalletCallMetadata { ty }),
+                event: None,
+                constants: vec![],
+                error: None,
+                index,
+            }
+        };
+        let pallets = vec![
+            pallet("Other", 0, meta_type::<Other>()),
+            pallet("Test", 1, meta_type::<Call>()),
+        ];
+        let extrinsic = ExtrinsicMetadata {
+            ty: meta_type::<()>(),
+            version: 4,
+            signed_extensions: vec![],
+        };
+        let metadata: RuntimeMetadataPrefixed =
+            RuntimeMetadataLastVersion::new(pallets, extrinsic, meta_type::<()>()).into();
+        match metadata.1 {
+            frame_metadata::RuntimeMetadata::V14(metadata) => metadata,
+            _ => unreachable!("V14 metadata was constructed"),
+        }
+    }
+
+    #[test]
+    fn hashes_depend_on_shape_and_not_type_ids() {
+        let hashes = |metadata: &RuntimeMetadataLastVersion| {
+            get_metadata_hashes(metadata).remove(1).1
+        };
+        let v1 = metadata_with::<v1::Call, ()>();
+        // Registering other types first shifts the ids of the call types.
+        let v1_shifted = metadata_with::<v1::Call, (bool, String, [u8; 3])>();
+        let v2 = metadata_with::<v2::Call, ()>();
+
+        assert_eq!(hashes(&v1), hashes(&v1_shifted));
+        assert_eq!(hashes(&v1).differences(&hashes(&v2)), vec!["calls"]);
+    }
+
+    #[test]
+    fn call_hashes_only_change_with_their_call() {
+        let call_hash = |metadata: &RuntimeMetadataLastVersion, call| {
+            get_call_hash(&metadata.types, &metadata.pallets[1], call)
+        };
+        let v1 = metadata_with::<v1::Call, ()>();
+        let v2 = metadata_with::<v2::Call, (bool, String, [u8; 3])>();
+
+        assert_eq!(call_hash(&v1, "remark"), call_hash(&v2, "remark"));
+        assert_ne!(call_hash(&v1, "transfer"), call_hash(&v2, "transfer"));
+        assert_eq!(call_hash(&v1, "missing"), None);
+    }
+
+    #[test]
+    fn recursive_types_are_hashed() {
+        let metadata = metadata_with::<Recursive, ()>();
+        let id = metadata.pallets[1].calls.as_ref().unwrap().ty.id();
+        let hash = get_type_hash(&metadata.types, id);
+        assert_eq!(hash, get_type_hash(&metadata.types, id));
+        assert_ne!(hash, get_type_hash(&metadata.types, id - 1));
+    }
+}
@@ -0,0 +1,194 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Looking up the nonce and balances of accounts, from the `System` pallet's
+//! `Account` storage map.
+
+use crate::{
+    error::BasicError,
+    rpc::Rpc,
+    Config,
+};
+use codec::{
+    Decode,
+    Encode,
+};
+use sp_core::{
+    blake2_128,
+    storage::StorageKey,
+    twox_128,
+};
+
+/// The nonce and balances of an account.
+///
+/// Balances are in the smallest unit of the chain's native token, which is assumed
+/// to be stored as a `u128`, as it is on Avail (and most other Substrate chains).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AccountInfo<Index> {
+    /// The number of transactions the account has sent.
+    pub nonce: Index,
+    /// The number of other modules which depend on the account existing.
+    pub consumers: u32,
+    /// The number of other modules which allow the account to exist.
+    pub providers: u32,
+    /// The number of modules which allow the account to exist by themselves.
+    pub sufficients: u32,
+    /// The balance which isn't reserved, though part of it may be frozen.
+    pub free: u128,
+    /// The balance which is reserved, such as for deposits, and can't be used.
+    pub reserved: u128,
+    /// How much of the free balance can't be transferred or spent on fees, because
+    /// it's locked (for instance for staking or voting). This is the larger of the
+    /// `misc_frozen` and `fee_frozen` amounts of the underlying account data.
+    pub frozen: u128,
+}
+
+impl<Index> AccountInfo<Index> {
+    /// The free balance which isn't frozen, and so can be transferred.
+    pub fn transferable(&self) -> u128 {
+        self.free.saturating_sub(self.frozen)
+    }
+}
+
+/// `frame_system::AccountInfo<Index, pallet_balances::AccountData<u128>>`, as it's
+/// encoded in storage.
+#[derive(Decode, Encode)]
+struct RawAccountInfo<Index> {
+    nonce: Index,
+    consumers: u32,
+    providers: u32,
+    sufficients: u32,
+    free: u128,
+    reserved: u128,
+    misc_frozen: u128,
+    fee_frozen: u128,
+}
+
+impl<Index> From<RawAccountInfo<Index>> for AccountInfo<Index> {
+    fn from(raw: RawAccountInfo<Index>) -> Self {
+        Self {
+            nonce: raw.nonce,
+            consumers: raw.consumers,
+            providers: raw.providers,
+            sufficients: raw.sufficients,
+            free: raw.free,
+            reserved: raw.reserved,
+            frozen: raw.misc_frozen.max(raw.fee_frozen),
+        }
+    }
+}
+
+/// Client for looking up the nonce and balances of accounts, without the need for
+/// code generated from the metadata of the chain.
+pub struct AccountsClient<'a, T: Config> {
+    rpc: &'a Rpc<T>,
+}
+
+impl<'a, T: Config> AccountsClient<'a, T> {
+    /// Create a new [`AccountsClient`].
+    pub fn new(rpc: &'a Rpc<T>) -> Self {
+        Self { rpc }
+    }
+
+    /// The nonce and balances of the given account at the given block, or at the
+    /// latest one if no hash is given. Accounts which don't exist have a nonce and
+    /// balances of zero.
+    pub async fn info(
+        &self,
+        account_id: &T::AccountId,
+        at: Option<T::Hash>,
+    ) -> Result<AccountInfo<T::Index>, BasicError> {
+        let info = match self.rpc.storage(&account_key(account_id), at).await? {
+            Some(data) => {
+                self.rpc
+                    .decode_limits()
+                    .decode::<RawAccountInfo<T::Index>>(&data.0)?
+                    .into()
+            }
+            None => AccountInfo::default(),
+        };
+        Ok(info)
+    }
+
+    /// The nonce of the given account at the given block, or at the latest one if no
+    /// hash is given. Unlike [`Rpc::system_account_next_index()`], this doesn't count
+    /// the account's transactions which are waiting in the transaction pool.
+    pub async fn nonce(
+        &self,
+        account_id: &T::AccountId,
+        at: Option<T::Hash>,
+    ) -> Result<T::Index, BasicError> {
+        match self.rpc.storage(&account_key(account_id), at).await? {
+            // The nonce comes first, whatever the account data of the chain looks like.
+            Some(data) => Ok(T::Index::decode(&mut &data.0[..])?),
+            None => Ok(Default::default()),
+        }
+    }
+}
+
+/// The key of the given account in the `System` pallet's `Account` map, which is
+/// hashed with `Blake2_128Concat`.
+fn account_key<AccountId: Encode>(account_id: &AccountId) -> StorageKey {
+    let encoded = account_id.encode();
+    let mut key = twox_128(b"System").to_vec();
+    key.extend(twox_128(b"Account"));
+    key.extend(blake2_128(&encoded));
+    key.extend(encoded);
+    StorageKey(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        rpc::MockRpcClient,
+        DefaultConfig,
+    };
+    use sp_runtime::AccountId32;
+
+    #[async_std::test]
+    async fn account_info_is_decoded_from_storage() {
+        let alice = AccountId32::new([1; 32]);
+        let bob = AccountId32::new([2; 32]);
+        let raw = RawAccountInfo {
+            nonce: 5u32,
+            consumers: 1,
+            providers: 1,
+            sufficients: 0,
+            free: 1_000,
+            reserved: 50,
+            misc_frozen: 100,
+            fee_frozen: 200,
+        };
+        let mock = MockRpcClient::new();
+        mock.set_storage(&account_key(&alice), Some(raw.encode()));
+        mock.set_storage(&account_key(&bob), None);
+        let rpc = Rpc::<DefaultConfig>::new_mock(mock);
+        let accounts = AccountsClient::new(&rpc);
+
+        let info = accounts.info(&alice, None).await.unwrap();
+        assert_eq!(info.nonce, 5);
+        assert_eq!((info.free, info.reserved, info.frozen), (1_000, 50, 200));
+        assert_eq!(info.transferable(), 800);
+        assert_eq!(accounts.nonce(&alice, None).await.unwrap(), 5);
+
+        assert_eq!(
+            accounts.info(&bob, None).await.unwrap(),
+            AccountInfo::default()
+        );
+        assert_eq!(accounts.nonce(&bob, None).await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,211 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Onboarding applications to Avail: creating the application key which the
+//! `DataAvailability` pallet assigns an `app_id` to, and looking up the `app_id` of
+//! an existing key.
+
+use super::PALLET;
+use crate::{
+    error::{
+        BasicError,
+        Error,
+        HasModuleError,
+    },
+    extrinsic::{
+        SignedExtra,
+        Signer,
+    },
+    storage::{
+        StorageEntry,
+        StorageEntryKey,
+        StorageMapKey,
+    },
+    Call,
+    Client,
+    Config,
+    Event,
+    StorageHasher,
+    SubmittableExtrinsic,
+};
+use codec::{
+    Decode,
+    Encode,
+};
+use sp_runtime::traits::SignedExtension;
+use std::marker::PhantomData;
+
+/// The `DataAvailability::create_application_key` call: create a new application key,
+/// owned by the sender, which is assigned the next free `app_id`.
+#[derive(Clone, Debug, Encode)]
+pub struct CreateApplicationKey {
+    /// The name of the application.
+    pub key: Vec<u8>,
+}
+
+impl Call for CreateApplicationKey {
+    const PALLET: &'static str = PALLET;
+    const FUNCTION: &'static str = "create_application_key";
+}
+
+/// A new application key has been created.
+#[derive(Clone, Debug, Decode, PartialEq, Eq)]
+pub struct ApplicationKeyCreated<AccountId> {
+    /// The name of the application.
+    pub key: Vec<u8>,
+    /// The account which created it.
+    pub owner: AccountId,
+    /// The `app_id` assigned to it.
+    #[codec(compact)]
+    pub id: u32,
+}
+
+impl<AccountId: Decode> Event for ApplicationKeyCreated<AccountId> {
+    const PALLET: &'static str = PALLET;
+    const EVENT: &'static str = "ApplicationKeyCreated";
+}
+
+/// The owner and `app_id` of an application key.
+#[derive(Clone, Debug, Decode, PartialEq, Eq)]
+pub struct AppKeyInfo<AccountId> {
+    /// The account which created the key.
+    pub owner: AccountId,
+    /// The `app_id` assigned to the key.
+    #[codec(compact)]
+    pub id: u32,
+}
+
+/// The `DataAvailability::AppKeys` storage entry: the owner and `app_id` of each
+/// application key.
+pub struct AppKeys<'a, T: Config> {
+    /// The name of the application.
+    pub key: &'a [u8],
+    marker: PhantomData<T>,
+}
+
+impl<'a, T: Config> AppKeys<'a, T> {
+    /// The entry of the given application key.
+    pub fn new(key: &'a [u8]) -> Self {
+        Self {
+            key,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T: Config> StorageEntry for AppKeys<'_, T> {
+    const PALLET: &'static str = PALLET;
+    const STORAGE: &'static str = "AppKeys";
+    type Value = AppKeyInfo<T::AccountId>;
+
+    fn key(&self) -> StorageEntryKey {
+        StorageEntryKey::Map(vec![StorageMapKey::new(
+            &self.key,
+            StorageHasher::Blake2_128Concat,
+        )])
+    }
+}
+
+/// The `DataAvailability::NextAppId` storage entry: the `app_id` which the next
+/// application key will be assigned.
+pub struct NextAppId;
+
+impl StorageEntry for NextAppId {
+    const PALLET: &'static str = PALLET;
+    const STORAGE: &'static str = "NextAppId";
+    type Value = AppId;
+
+    fn key(&self) -> StorageEntryKey {
+        StorageEntryKey::Plain
+    }
+}
+
+/// An `app_id`, as it is stored.
+#[derive(Clone, Copy, Debug, Decode, PartialEq, Eq)]
+pub struct AppId(#[codec(compact)] pub u32);
+
+/// The `app_id` of the application key with the given name, or `None` if no such key
+/// has been created.
+pub async fn app_id_of<T: Config>(
+    client: &Client<T>,
+    key: &[u8],
+    hash: Option<T::Hash>,
+) -> Result<Option<u32>, BasicError> {
+    let info = client
+        .storage()
+        .fetch(&AppKeys::<T>::new(key), hash)
+        .await?;
+    Ok(info.map(|info| info.id))
+}
+
+/// Create an application key with the given name, owned by the signer, and wait for
+/// the block creating it to be finalized. Returns the `app_id` assigned to the key.
+///
+/// `E` is the `DispatchError` type of the runtime, so that the reason for a failure
+/// (such as the key already existing) can be reported.
+pub async fn create_application_key<T, X, E>(
+    client: &Client<T>,
+    signer: &(dyn Signer<T, X> + Send + Sync),
+    key: Vec<u8>,
+) -> Result<u32, Error<E>>
+where
+    T: Config,
+    X: SignedExtra<T>,
+    <<X as SignedExtra<T>>::Extra as SignedExtension>::AdditionalSigned:
+        Send + Sync + 'static,
+    E: Decode + HasModuleError,
+{
+    let events =
+        SubmittableExtrinsic::<T, X, _, E, ()>::new(client, CreateApplicationKey { key })
+            .sign_and_submit_then_watch(signer)
+            .await?
+            .wait_for_finalized_success()
+            .await?;
+    let created = events
+        .find_first::<ApplicationKeyCreated<T::AccountId>>()?
+        .ok_or_else(|| {
+            BasicError::Other("No ApplicationKeyCreated event was emitted".into())
+        })?;
+    Ok(created.id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DefaultConfig;
+    use sp_core::crypto::AccountId32;
+
+    #[test]
+    fn created_keys_are_decoded_with_compact_ids() {
+        let encoded = (
+            b"rollup".to_vec(),
+            AccountId32::new([1; 32]),
+            codec::Compact(7u32),
+        )
+            .encode();
+        let event = ApplicationKeyCreated::<AccountId32>::decode(&mut &*encoded).unwrap();
+        assert_eq!(event.key, b"rollup");
+        assert_eq!(event.id, 7);
+
+        let call = CreateApplicationKey {
+            key: b"rollup".to_vec(),
+        };
+        assert_eq!(call.encode(), b"rollup".to_vec().encode());
+
+        let entry = AppKeys::<DefaultConfig>::new(b"rollup");
+        assert!(matches!(entry.key(), StorageEntryKey::Map(keys) if keys.len() == 1));
+    }
+}
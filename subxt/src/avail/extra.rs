@@ -0,0 +1,308 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The signed extensions of Avail runtimes, which tag every transaction with the
+//! application it belongs to.
+
+use crate::{
+    error::BasicError,
+    extrinsic::{
+        ChargeTransactionPayment,
+        CheckGenesis,
+        CheckMortality,
+        CheckNonce,
+        CheckSpecVersion,
+        CheckTxVersion,
+        CheckWeight,
+        PaymentExtension,
+        RawBytes,
+        SignedExtra,
+        TxPaymentParameters,
+    },
+    Config,
+    Metadata,
+    PhantomDataSendSync,
+};
+use codec::{
+    Decode,
+    Encode,
+};
+use derivative::Derivative;
+use scale_info::TypeInfo;
+use sp_runtime::{
+    generic::Era,
+    traits::{
+        DispatchInfoOf,
+        SignedExtension,
+    },
+    transaction_validity::TransactionValidityError,
+};
+
+/// Tag the transaction with the application it belongs to.
+#[derive(Derivative, Encode, Decode, TypeInfo)]
+#[derivative(
+    Clone(bound = ""),
+    PartialEq(bound = ""),
+    Debug(bound = ""),
+    Eq(bound = ""),
+    Default(bound = "")
+)]
+#[scale_info(skip_type_params(T))]
+pub struct CheckAppId<T: Config>(#[codec(compact)] pub u32, pub PhantomDataSendSync<T>);
+
+impl<T: Config> CheckAppId<T> {
+    /// The id of the application.
+    pub fn app_id(&self) -> u32 {
+        self.0
+    }
+}
+
+impl<T: Config> SignedExtension for CheckAppId<T> {
+    const IDENTIFIER: &'static str = "CheckAppId";
+    type AccountId = T::AccountId;
+    type Call = ();
+    type AdditionalSigned = ();
+    type Pre = ();
+    fn additional_signed(
+        &self,
+    ) -> Result<Self::AdditionalSigned, TransactionValidityError> {
+        Ok(())
+    }
+    fn pre_dispatch(
+        self,
+        _who: &Self::AccountId,
+        _call: &Self::Call,
+        _info: &DispatchInfoOf<Self::Call>,
+        _len: usize,
+    ) -> Result<Self::Pre, TransactionValidityError> {
+        Ok(())
+    }
+}
+
+/// Parameters for [`AvailExtra`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AvailExtraParameters {
+    tip: u128,
+    app_id: u32,
+    mortal_period: Option<u64>,
+}
+
+impl AvailExtraParameters {
+    /// Creates new parameters, without a tip and with an `app_id` of 0.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the tip for the block author.
+    pub fn set_tip(mut self, tip: u128) -> Self {
+        self.tip = tip;
+        self
+    }
+
+    /// Set the application the transaction belongs to.
+    pub fn set_app_id(mut self, app_id: u32) -> Self {
+        self.app_id = app_id;
+        self
+    }
+
+    /// Make the transaction mortal, so that it's only valid for roughly the given
+    /// number of blocks from the current best block. See [`Era::mortal()`] for how the
+    /// period is rounded.
+    pub fn set_mortal(mut self, period: u64) -> Self {
+        self.mortal_period = Some(period);
+        self
+    }
+
+    /// The tip for the block author.
+    pub fn tip(&self) -> u128 {
+        self.tip
+    }
+
+    /// The application the transaction belongs to.
+    pub fn app_id(&self) -> u32 {
+        self.app_id
+    }
+}
+
+/// The `SignedExtra` of Avail runtimes: the default extensions followed by
+/// [`CheckAppId`].
+///
+/// Unlike that of [`crate::extrinsic::DynamicExtra`], the extra data of an
+/// [`AvailExtrinsic`] can be decoded, so the application of any signed extrinsic in a
+/// block body can be read back with [`app_id()`].
+#[derive(Derivative, Encode, Decode, TypeInfo)]
+#[derivative(
+    Clone(bound = ""),
+    PartialEq(bound = ""),
+    Debug(bound = ""),
+    Eq(bound = "")
+)]
+#[scale_info(skip_type_params(T))]
+pub struct AvailExtra<T: Config> {
+    spec_version: u32,
+    tx_version: u32,
+    nonce: T::Index,
+    genesis_hash: T::Hash,
+    era: Era,
+    // The hash of the block the era starts at; the genesis hash if immortal.
+    checkpoint: T::Hash,
+    tip: u128,
+    app_id: u32,
+}
+
+impl<T: Config> SignedExtra<T> for AvailExtra<T> {
+    type Extra = (
+        CheckSpecVersion<T>,
+        CheckTxVersion<T>,
+        CheckGenesis<T>,
+        CheckMortality<T>,
+        CheckNonce<T>,
+        CheckWeight<T>,
+        ChargeTransactionPayment<T>,
+        CheckAppId<T>,
+    );
+    type Parameters = AvailExtraParameters;
+
+    fn new(
+        spec_version: u32,
+        tx_version: u32,
+        nonce: T::Index,
+        genesis_hash: T::Hash,
+        params: Self::Parameters,
+    ) -> Self {
+        AvailExtra {
+            spec_version,
+            tx_version,
+            nonce,
+            genesis_hash,
+            era: Era::Immortal,
+            checkpoint: genesis_hash,
+            tip: params.tip,
+            app_id: params.app_id,
+        }
+    }
+
+    fn mortal_period(params: &Self::Parameters) -> Option<u64> {
+        params.mortal_period
+    }
+
+    fn new_mortal(
+        spec_version: u32,
+        tx_version: u32,
+        nonce: T::Index,
+        genesis_hash: T::Hash,
+        era: Era,
+        checkpoint: T::Hash,
+        params: Self::Parameters,
+        _metadata: &Metadata,
+    ) -> Result<Self, BasicError> {
+        Ok(AvailExtra {
+            era,
+            checkpoint,
+            ..Self::new(spec_version, tx_version, nonce, genesis_hash, params)
+        })
+    }
+
+    fn extra(&self) -> Self::Extra {
+        let payment = TxPaymentParameters::new().set_tip(self.tip);
+        (
+            CheckSpecVersion(PhantomDataSendSync::new(), self.spec_version),
+            CheckTxVersion(PhantomDataSendSync::new(), self.tx_version),
+            CheckGenesis(PhantomDataSendSync::new(), self.genesis_hash),
+            CheckMortality((self.era, PhantomDataSendSync::new()), self.checkpoint),
+            CheckNonce(self.nonce),
+            CheckWeight(PhantomDataSendSync::new()),
+            PaymentExtension::<T>::new(payment),
+            CheckAppId(self.app_id, PhantomDataSendSync::new()),
+        )
+    }
+}
+
+impl<T: Config> SignedExtension for AvailExtra<T> {
+    const IDENTIFIER: &'static str = "AvailExtra";
+    type AccountId = T::AccountId;
+    type Call = ();
+    type AdditionalSigned =
+        <<Self as SignedExtra<T>>::Extra as SignedExtension>::AdditionalSigned;
+    type Pre = ();
+
+    fn additional_signed(
+        &self,
+    ) -> Result<Self::AdditionalSigned, TransactionValidityError> {
+        self.extra().additional_signed()
+    }
+    fn pre_dispatch(
+        self,
+        _who: &Self::AccountId,
+        _call: &Self::Call,
+        _info: &DispatchInfoOf<Self::Call>,
+        _len: usize,
+    ) -> Result<Self::Pre, TransactionValidityError> {
+        Ok(())
+    }
+}
+
+/// An extrinsic of an Avail runtime, which can be decoded from a block body. The call
+/// is left encoded unless a type to decode it into, such as the generated `Call` enum,
+/// is given.
+pub type AvailExtrinsic<T, Call = RawBytes> = sp_runtime::generic::UncheckedExtrinsic<
+    <T as Config>::Address,
+    Call,
+    <T as Config>::Signature,
+    <AvailExtra<T> as SignedExtra<T>>::Extra,
+>;
+
+/// The application a decoded extrinsic belongs to, or `None` if it is unsigned.
+pub fn app_id<T: Config, Call>(extrinsic: &AvailExtrinsic<T, Call>) -> Option<u32> {
+    extrinsic
+        .signature
+        .as_ref()
+        .map(|(_, _, extra)| extra.7.app_id())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::avail::AvailConfig;
+    use sp_core::sr25519;
+    use sp_runtime::{
+        AccountId32,
+        MultiAddress,
+        MultiSignature,
+    };
+
+    #[test]
+    fn app_id_is_read_back_from_decoded_extrinsics() {
+        let params = AvailExtraParameters::new().set_app_id(7).set_tip(5);
+        let extra =
+            AvailExtra::<AvailConfig>::new(1, 1, 3, Default::default(), params).extra();
+        let signed = AvailExtrinsic::<AvailConfig>::new_signed(
+            RawBytes(vec![29, 1, 0]),
+            MultiAddress::Id(AccountId32::new([1; 32])),
+            MultiSignature::Sr25519(sr25519::Signature::from_raw([2; 64])),
+            extra,
+        );
+
+        let decoded = AvailExtrinsic::<AvailConfig>::decode(&mut &*signed.encode())
+            .expect("decodes");
+        assert_eq!(app_id(&decoded), Some(7));
+        let (_, _, extra) = decoded.signature.as_ref().unwrap();
+        assert_eq!(extra.4 .0, 3);
+
+        let unsigned = AvailExtrinsic::<AvailConfig>::new_unsigned(RawBytes(vec![29, 1]));
+        assert_eq!(app_id(&unsigned), None);
+    }
+}
@@ -0,0 +1,366 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The Avail block header, which extends the Substrate header with commitments to the
+//! data in the block.
+
+use crate::{
+    rpc::NumberOrHex,
+    Config,
+};
+use codec::{
+    Decode,
+    Encode,
+};
+use parity_util_mem::MallocSizeOf;
+use serde::{
+    Deserialize,
+    Deserializer,
+    Serialize,
+    Serializer,
+};
+use sp_core::{
+    H256,
+    U256,
+};
+use sp_runtime::{
+    generic::Digest,
+    traits::BlakeTwo256,
+    AccountId32,
+    MultiAddress,
+    MultiSignature,
+    OpaqueExtrinsic,
+};
+use std::convert::TryFrom;
+
+/// The length of each KZG commitment in a [`KateCommitment`], in bytes.
+pub const COMMITMENT_LEN: usize = 48;
+
+/// The types used by Avail chains. This is like [`crate::DefaultConfig`], except that
+/// blocks have an [`AvailHeader`].
+// Note: We only use this at the type level, so it should be impossible to
+// create an instance of it.
+pub enum AvailConfig {}
+
+impl Config for AvailConfig {
+    type Index = u32;
+    type BlockNumber = u32;
+    type Hash = H256;
+    type Hashing = BlakeTwo256;
+    type AccountId = AccountId32;
+    type Address = MultiAddress<Self::AccountId, u32>;
+    type Header = AvailHeader;
+    type Signature = MultiSignature;
+    type Extrinsic = OpaqueExtrinsic;
+}
+
+/// The header of an Avail block.
+#[derive(
+    Clone, Debug, PartialEq, Eq, Encode, Decode, Serialize, Deserialize, MallocSizeOf,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct AvailHeader {
+    /// The parent hash.
+    pub parent_hash: H256,
+    /// The block number.
+    #[serde(
+        serialize_with = "serialize_number",
+        deserialize_with = "deserialize_number"
+    )]
+    #[codec(compact)]
+    pub number: u32,
+    /// The state trie merkle root.
+    pub state_root: H256,
+    /// The merkle root of the extrinsics.
+    pub extrinsics_root: H256,
+    /// A chain-specific digest of data useful for light clients or referencing auxiliary data.
+    pub digest: Digest,
+    /// Commitments to the data in the block.
+    pub extension: HeaderExtension,
+}
+
+impl AvailHeader {
+    /// The root of the merkle tree of the data submitted in the block.
+    pub fn data_root(&self) -> H256 {
+        self.extension.commitment().data_root
+    }
+
+    /// The KZG commitments to the rows of the block's data matrix.
+    pub fn commitments(&self) -> Vec<[u8; COMMITMENT_LEN]> {
+        self.extension.commitment().commitments()
+    }
+}
+
+impl sp_runtime::traits::Header for AvailHeader {
+    type Number = u32;
+    type Hash = H256;
+    type Hashing = BlakeTwo256;
+
+    fn new(
+        number: Self::Number,
+        extrinsics_root: Self::Hash,
+        state_root: Self::Hash,
+        parent_hash: Self::Hash,
+        digest: Digest,
+    ) -> Self {
+        Self {
+            parent_hash,
+            number,
+            state_root,
+            extrinsics_root,
+            digest,
+            extension: Default::default(),
+        }
+    }
+
+    fn number(&self) -> &Self::Number {
+        &self.number
+    }
+
+    fn set_number(&mut self, number: Self::Number) {
+        self.number = number
+    }
+
+    fn extrinsics_root(&self) -> &Self::Hash {
+        &self.extrinsics_root
+    }
+
+    fn set_extrinsics_root(&mut self, root: Self::Hash) {
+        self.extrinsics_root = root
+    }
+
+    fn state_root(&self) -> &Self::Hash {
+        &self.state_root
+    }
+
+    fn set_state_root(&mut self, root: Self::Hash) {
+        self.state_root = root
+    }
+
+    fn parent_hash(&self) -> &Self::Hash {
+        &self.parent_hash
+    }
+
+    fn set_parent_hash(&mut self, hash: Self::Hash) {
+        self.parent_hash = hash
+    }
+
+    fn digest(&self) -> &Digest {
+        &self.digest
+    }
+
+    fn digest_mut(&mut self) -> &mut Digest {
+        &mut self.digest
+    }
+}
+
+/// The extension of an [`AvailHeader`]. New versions of the extension are added as
+/// new variants, so match on it with a wildcard arm, or use the accessors which work
+/// across versions.
+#[derive(
+    Clone, Debug, PartialEq, Eq, Encode, Decode, Serialize, Deserialize, MallocSizeOf,
+)]
+#[non_exhaustive]
+pub enum HeaderExtension {
+    /// The first version of the extension.
+    V1(HeaderExtensionV1),
+}
+
+impl HeaderExtension {
+    /// The commitments to the data in the block.
+    pub fn commitment(&self) -> &KateCommitment {
+        match self {
+            HeaderExtension::V1(ext) => &ext.commitment,
+        }
+    }
+
+    /// Where the data of each application is in the block's data matrix.
+    pub fn app_lookup(&self) -> &DataLookup {
+        match self {
+            HeaderExtension::V1(ext) => &ext.app_lookup,
+        }
+    }
+}
+
+impl Default for HeaderExtension {
+    fn default() -> Self {
+        HeaderExtension::V1(Default::default())
+    }
+}
+
+/// The first version of the [`HeaderExtension`].
+#[derive(
+    Clone,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Encode,
+    Decode,
+    Serialize,
+    Deserialize,
+    MallocSizeOf,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct HeaderExtensionV1 {
+    /// The commitments to the data in the block.
+    pub commitment: KateCommitment,
+    /// Where the data of each application is in the block's data matrix.
+    pub app_lookup: DataLookup,
+}
+
+/// The commitments to the data in a block, which is arranged in a matrix of cells.
+#[derive(
+    Clone,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Encode,
+    Decode,
+    Serialize,
+    Deserialize,
+    MallocSizeOf,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct KateCommitment {
+    /// The number of rows in the data matrix.
+    pub rows: u16,
+    /// The number of columns in the data matrix.
+    pub cols: u16,
+    /// The root of the merkle tree of the data submitted in the block.
+    pub data_root: H256,
+    /// The KZG commitments to each row of the extended data matrix, concatenated.
+    pub commitment: Vec<u8>,
+}
+
+impl KateCommitment {
+    /// The KZG commitment to each row of the extended data matrix. Any trailing bytes
+    /// which don't make up a whole commitment are ignored.
+    pub fn commitments(&self) -> Vec<[u8; COMMITMENT_LEN]> {
+        self.commitment
+            .chunks_exact(COMMITMENT_LEN)
+            .map(|c| c.try_into().expect("chunks are COMMITMENT_LEN long; qed"))
+            .collect()
+    }
+}
+
+/// Where the data of each application is in a block's data matrix.
+#[derive(
+    Clone,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Encode,
+    Decode,
+    Serialize,
+    Deserialize,
+    MallocSizeOf,
+)]
+pub struct DataLookup {
+    /// The number of cells of data in the matrix.
+    #[codec(compact)]
+    pub size: u32,
+    /// The cell at which the data of each application starts, in ascending order.
+    pub index: Vec<DataLookupIndexItem>,
+}
+
+/// The cell at which the data of an application starts.
+#[derive(
+    Clone, Debug, PartialEq, Eq, Encode, Decode, Serialize, Deserialize, MallocSizeOf,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct DataLookupIndexItem {
+    /// The application id.
+    #[codec(compact)]
+    pub app_id: u32,
+    /// The index of the first cell of the application's data.
+    #[codec(compact)]
+    pub start: u32,
+}
+
+fn serialize_number<S: Serializer>(number: &u32, s: S) -> Result<S::Ok, S::Error> {
+    NumberOrHex::Hex(U256::from(*number)).serialize(s)
+}
+
+fn deserialize_number<'de, D: Deserializer<'de>>(d: D) -> Result<u32, D::Error> {
+    let number = match NumberOrHex::deserialize(d)? {
+        NumberOrHex::Number(n) => U256::from(n),
+        NumberOrHex::Hex(n) => n,
+    };
+    u32::try_from(number).map_err(|_| serde::de::Error::custom("block number overflow"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sp_runtime::traits::Header as _;
+
+    fn header() -> AvailHeader {
+        let mut header = AvailHeader::new(
+            7,
+            H256::repeat_byte(1),
+            H256::repeat_byte(2),
+            H256::repeat_byte(3),
+            Default::default(),
+        );
+        header.extension = HeaderExtension::V1(HeaderExtensionV1 {
+            commitment: KateCommitment {
+                rows: 1,
+                cols: 4,
+                data_root: H256::repeat_byte(4),
+                commitment: [[5; COMMITMENT_LEN], [6; COMMITMENT_LEN]].concat(),
+            },
+            app_lookup: DataLookup {
+                size: 2,
+                index: vec![DataLookupIndexItem {
+                    app_id: 1,
+                    start: 0,
+                }],
+            },
+        });
+        header
+    }
+
+    #[test]
+    fn header_extension_accessors() {
+        let header = header();
+        assert_eq!(header.data_root(), H256::repeat_byte(4));
+        assert_eq!(
+            header.commitments(),
+            vec![[5; COMMITMENT_LEN], [6; COMMITMENT_LEN]]
+        );
+        assert_eq!(header.extension.app_lookup().index[0].app_id, 1);
+    }
+
+    #[test]
+    fn header_roundtrips_through_scale_and_json() {
+        let header = header();
+
+        let encoded = header.encode();
+        assert_eq!(AvailHeader::decode(&mut &*encoded).unwrap(), header);
+
+        let json = serde_json::to_value(&header).unwrap();
+        assert_eq!(json["number"], "0x7");
+        assert_eq!(
+            json["extension"]["V1"]["commitment"]["dataRoot"],
+            format!("{:?}", H256::repeat_byte(4))
+        );
+        let decoded: AvailHeader = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded, header);
+    }
+}
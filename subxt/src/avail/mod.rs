@@ -0,0 +1,714 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Support for Avail chains: their block header and signed extensions, and submitting
+//! data to the `DataAvailability` pallet and accessing it, by application. The
+//! [`app_keys`] module helps with creating the application keys identifying them.
+
+pub mod app_keys;
+mod extra;
+mod header;
+
+pub use extra::{
+    app_id,
+    AvailExtra,
+    AvailExtraParameters,
+    AvailExtrinsic,
+    CheckAppId,
+};
+pub use header::{
+    AvailConfig,
+    AvailHeader,
+    DataLookup,
+    DataLookupIndexItem,
+    HeaderExtension,
+    HeaderExtensionV1,
+    KateCommitment,
+    COMMITMENT_LEN,
+};
+
+use crate::{
+    error::{
+        BasicError,
+        Error,
+        HasModuleError,
+    },
+    events::{
+        self,
+        Events,
+    },
+    extrinsic::{
+        SignedExtra,
+        Signer,
+    },
+    finality::AuthoritySetChange,
+    Call,
+    Client,
+    Config,
+    Metadata,
+    SubmittableExtrinsic,
+};
+use codec::{
+    Compact,
+    Decode,
+    Encode,
+};
+use futures::{
+    future,
+    stream,
+    Stream,
+    StreamExt,
+};
+use sp_runtime::traits::{
+    Hash,
+    Header,
+    SignedExtension,
+};
+use std::ops::Range;
+
+const PALLET: &str = "DataAvailability";
+const SUBMIT_DATA: &str = "submit_data";
+const DATA_SUBMITTED: &str = "DataSubmitted";
+const CHECK_APP_ID: &str = "CheckAppId";
+const MAX_APP_DATA_LENGTH: &str = "MaxAppDataLength";
+
+/// Room left in each `submit_data` extrinsic for everything but the data: the
+/// signature, the signed extensions, the call index and the length prefixes.
+const SUBMIT_DATA_OVERHEAD: u32 = 256;
+
+/// The `DataAvailability::submit_data` call.
+#[derive(Clone, Debug, Encode)]
+pub struct SubmitData {
+    /// The data to submit.
+    pub data: Vec<u8>,
+}
+
+impl Call for SubmitData {
+    const PALLET: &'static str = PALLET;
+    const FUNCTION: &'static str = SUBMIT_DATA;
+}
+
+/// A chunk of the data given to [`AvailClient::submit_data()`], which made it into a
+/// block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SubmittedChunk<Hash> {
+    /// The range of the data which was submitted in this chunk.
+    pub range: Range<usize>,
+    /// The hash of the `submit_data` extrinsic.
+    pub extrinsic_hash: Hash,
+    /// The hash of the block it was included in.
+    pub block_hash: Hash,
+}
+
+/// Data submitted to the chain for an application.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AppData<Address, Hash> {
+    /// The index of the `submit_data` extrinsic in its block.
+    pub extrinsic_index: u32,
+    /// The address of the account which signed the extrinsic.
+    pub submitter: Address,
+    /// The hash of the data.
+    pub data_hash: Hash,
+    /// The data itself.
+    pub data: Vec<u8>,
+}
+
+/// The [`AppData`] of a given [`Config`].
+pub type AppDataOf<T> = AppData<<T as Config>::Address, <T as Config>::Hash>;
+
+/// Client for finding the data submitted to the chain for an application, which is
+/// identified by the `app_id` given in the `CheckAppId` signed extension.
+pub struct AvailClient<'a, T: Config> {
+    client: &'a Client<T>,
+}
+
+impl<'a, T: Config> AvailClient<'a, T>
+where
+    T::Signature: Decode,
+{
+    /// Create a new [`AvailClient`].
+    pub fn new(client: &'a Client<T>) -> Self {
+        Self { client }
+    }
+
+    /// Subscribe to the data submitted for the given application in finalized blocks.
+    /// Only data from `submit_data` extrinsics which succeeded (and so emitted a
+    /// `DataSubmitted` event) is reported. No blocks are skipped, even if the node
+    /// reports that several have been finalized at once.
+    pub async fn subscribe_app_data(
+        &self,
+        app_id: u32,
+    ) -> Result<impl Stream<Item = Result<AppDataOf<T>, BasicError>> + 'a, BasicError>
+    {
+        let client = self.client;
+        let last_finalized_number = client
+            .rpc()
+            .header(Some(client.rpc().finalized_head().await?))
+            .await?
+            .map(|h| (*h.number()).into());
+        let headers = events::subscribe_to_block_headers_filling_in_gaps(
+            client,
+            last_finalized_number,
+            client.rpc().subscribe_finalized_blocks().await?,
+        );
+
+        let app_data = headers
+            .then(move |header| {
+                async move {
+                    let block_hash = header?.hash();
+                    AvailClient::new(client)
+                        .submitted_app_data(block_hash, app_id)
+                        .await
+                }
+            })
+            .flat_map(|app_data| {
+                let items: Vec<_> = match app_data {
+                    Ok(app_data) => app_data.into_iter().map(Ok).collect(),
+                    Err(e) => vec![Err(e)],
+                };
+                stream::iter(items)
+            });
+        Ok(app_data)
+    }
+
+    /// The most data which fits in a single `submit_data` extrinsic: the maximum length
+    /// of the normal extrinsics of a block (less some room for the rest of the
+    /// extrinsic), or the `MaxAppDataLength` of the pallet if it's smaller.
+    pub async fn max_submit_data_length(&self) -> Result<usize, BasicError> {
+        let block_length = self.client.rpc().kate_block_length(None).await?;
+        let mut max = block_length.max.normal.saturating_sub(SUBMIT_DATA_OVERHEAD);
+        if let Ok(max_app_data) = self.client.constant::<u32>(PALLET, MAX_APP_DATA_LENGTH)
+        {
+            max = max.min(max_app_data);
+        }
+        if max == 0 {
+            return Err(BasicError::Other(format!(
+                "Blocks are too small to submit data: {:?}",
+                block_length
+            )))
+        }
+        Ok(max as usize)
+    }
+
+    /// Submit data for the given application, split into as many `submit_data`
+    /// extrinsics as needed to keep each within
+    /// [`AvailClient::max_submit_data_length()`]. The extrinsics are signed with
+    /// consecutive nonces and submitted in order, then each is waited on until it's in
+    /// a block.
+    ///
+    /// One result is returned per chunk, in order. If a chunk can't be submitted, the
+    /// chunks after it aren't either, as their nonces would never become valid. Nothing
+    /// is submitted for empty data.
+    pub async fn submit_data<X, E>(
+        &self,
+        app_id: u32,
+        data: &[u8],
+        signer: &(dyn Signer<T, X> + Send + Sync),
+    ) -> Result<Vec<Result<SubmittedChunk<T::Hash>, Error<E>>>, BasicError>
+    where
+        X: SignedExtra<T, Parameters = AvailExtraParameters>,
+        <<X as SignedExtra<T>>::Extra as SignedExtension>::AdditionalSigned:
+            Send + Sync + 'static,
+        E: Decode + HasModuleError,
+    {
+        let ranges = chunk_ranges(data.len(), self.max_submit_data_length().await?);
+        let params = self
+            .client
+            .default_extra_params::<AvailExtraParameters>()?
+            .set_app_id(app_id);
+
+        let mut progresses = Vec::with_capacity(ranges.len());
+        let mut failure = None;
+        for (index, range) in ranges.iter().enumerate() {
+            let extrinsic = SubmittableExtrinsic::<T, X, _, E, ()>::new(
+                self.client,
+                SubmitData {
+                    data: data[range.clone()].to_vec(),
+                },
+            );
+            // A signer with a fixed nonce would otherwise sign every chunk with it;
+            // the nonce manager or the node hand out consecutive nonces themselves.
+            let progress = match signer.nonce() {
+                Some(nonce) => {
+                    let nonce = nonce + T::Index::from(index as u32);
+                    extrinsic
+                        .sign_and_submit_then_watch_with_nonce(
+                            signer,
+                            nonce,
+                            params.clone(),
+                        )
+                        .await
+                }
+                None => {
+                    extrinsic
+                        .sign_and_submit_then_watch_with_params(signer, params.clone())
+                        .await
+                }
+            };
+            match progress {
+                Ok(progress) => progresses.push(progress),
+                Err(e) => {
+                    failure = Some(e);
+                    break
+                }
+            }
+        }
+
+        let mut results = Vec::with_capacity(ranges.len());
+        let mut ranges = ranges.into_iter();
+        for (progress, range) in progresses.into_iter().zip(ranges.by_ref()) {
+            let included = progress.wait_for_in_block_success().await.map(|events| {
+                SubmittedChunk {
+                    range,
+                    extrinsic_hash: events.extrinsic_hash(),
+                    block_hash: events.block_hash(),
+                }
+            });
+            results.push(included);
+        }
+        if let Some(e) = failure {
+            results.push(Err(e.into()));
+            ranges.next();
+            for range in ranges {
+                results.push(Err(BasicError::Other(format!(
+                    "Data {:?} not submitted, as an earlier chunk failed",
+                    range
+                ))
+                .into()));
+            }
+        }
+        Ok(results)
+    }
+
+    /// Subscribe to changes of the GRANDPA authority set. Finalized headers are watched
+    /// for the changes scheduled in their digests, and an item is yielded once a change
+    /// has been enacted. No blocks are skipped, even if the node reports that several
+    /// have been finalized at once.
+    ///
+    /// A change scheduled before the current finalized block, and so not in the digest
+    /// of any block watched, is read from `Grandpa::PendingChange` when subscribing,
+    /// and is yielded once it has been enacted too.
+    pub async fn subscribe_authority_set_changes(
+        &self,
+    ) -> Result<impl Stream<Item = Result<AuthoritySetChange, BasicError>> + 'a, BasicError>
+    {
+        let client = self.client;
+        let finalized_head = client.rpc().finalized_head().await?;
+        // This includes the pending change, if there is one.
+        let authority_set = client
+            .finality()
+            .authority_set(Some(finalized_head))
+            .await?;
+        let finalized_number: u64 = client
+            .rpc()
+            .header(Some(finalized_head))
+            .await?
+            .map(|h| (*h.number()).into())
+            .ok_or_else(|| {
+                BasicError::Other("Finalized block header not found".into())
+            })?;
+        let headers = events::subscribe_to_block_headers_filling_in_gaps(
+            client,
+            Some(finalized_number),
+            client.rpc().subscribe_finalized_blocks().await?,
+        )
+        // The node starts by sending the finalized head, whose changes are already
+        // accounted for in the authority set.
+        .filter(move |header| {
+            future::ready(!matches!(
+                header,
+                Ok(header) if (*header.number()).into() <= finalized_number
+            ))
+        });
+
+        let changes = headers
+            .scan(authority_set, |authority_set, header| {
+                let change = match header {
+                    Ok(header) => {
+                        authority_set.apply_finalized(&header).then(|| {
+                            Ok(AuthoritySetChange {
+                                set_id: authority_set.set_id,
+                                authorities: authority_set.authorities.clone(),
+                            })
+                        })
+                    }
+                    Err(e) => Some(Err(e)),
+                };
+                future::ready(Some(change))
+            })
+            .filter_map(future::ready);
+        Ok(changes)
+    }
+
+    /// The data submitted for the given application in a block by `submit_data`
+    /// extrinsics which succeeded.
+    async fn submitted_app_data(
+        &self,
+        block_hash: T::Hash,
+        app_id: u32,
+    ) -> Result<Vec<AppDataOf<T>>, BasicError> {
+        let metadata = self.client.metadata_at(block_hash).await?;
+        let app_data = self
+            .app_data_with_metadata(&metadata, block_hash, app_id)
+            .await?;
+        if app_data.is_empty() {
+            return Ok(app_data)
+        }
+
+        let events: Events<T, ()> =
+            events::at_with_metadata(self.client.rpc(), metadata, block_hash).await?;
+        let mut submitted = Vec::with_capacity(app_data.len());
+        for data in app_data {
+            for ev in events.iter_raw_for_extrinsic(data.extrinsic_index) {
+                let ev = ev?;
+                if ev.pallet == PALLET && ev.variant == DATA_SUBMITTED {
+                    submitted.push(data);
+                    break
+                }
+            }
+        }
+        Ok(submitted)
+    }
+
+    /// The data submitted for the given application by the `submit_data` extrinsics in
+    /// the block with the given hash, in the order they appear in the block.
+    ///
+    /// This includes extrinsics which failed; use [`AvailClient::subscribe_app_data()`]
+    /// to only see data which was successfully submitted.
+    pub async fn block_app_data(
+        &self,
+        block_hash: T::Hash,
+        app_id: u32,
+    ) -> Result<Vec<AppDataOf<T>>, BasicError> {
+        let metadata = self.client.metadata_at(block_hash).await?;
+        self.app_data_with_metadata(&metadata, block_hash, app_id)
+            .await
+    }
+
+    async fn app_data_with_metadata(
+        &self,
+        metadata: &Metadata,
+        block_hash: T::Hash,
+        app_id: u32,
+    ) -> Result<Vec<AppDataOf<T>>, BasicError> {
+        let block = self
+            .client
+            .rpc()
+            .block(Some(block_hash))
+            .await?
+            .ok_or_else(|| {
+                BasicError::Other(format!("Block {:?} not found", block_hash))
+            })?
+            .block;
+
+        let mut app_data = Vec::new();
+        for (index, extrinsic) in block.extrinsics.iter().enumerate() {
+            let encoded = extrinsic.encode();
+            // Extrinsics are encoded with a compact length prefix.
+            let cursor = &mut &*encoded;
+            <Compact<u32>>::decode(cursor)?;
+            if let Some(data) = decode_app_data::<T>(metadata, index as u32, cursor)? {
+                if data.0 == app_id {
+                    app_data.push(data.1);
+                }
+            }
+        }
+        Ok(app_data)
+    }
+}
+
+/// Split data of the given length into consecutive ranges of at most `max` bytes.
+fn chunk_ranges(len: usize, max: usize) -> Vec<Range<usize>> {
+    (0..len)
+        .step_by(max)
+        .map(|start| start..len.min(start + max))
+        .collect()
+}
+
+/// Decode a `submit_data` extrinsic, returning its `app_id` and data, or `None` if the
+/// extrinsic is unsigned or calls anything else.
+fn decode_app_data<T: Config>(
+    metadata: &Metadata,
+    extrinsic_index: u32,
+    input: &mut &[u8],
+) -> Result<Option<(u32, AppDataOf<T>)>, BasicError>
+where
+    T::Signature: Decode,
+{
+    // The top bit of the version byte is set for signed extrinsics.
+    let version = u8::decode(input)?;
+    if version & 0b1000_0000 == 0 {
+        return Ok(None)
+    }
+
+    let submitter = T::Address::decode(input)?;
+    T::Signature::decode(input)?;
+
+    let runtime_metadata = metadata.runtime_metadata();
+    let mut app_id = 0;
+    for extension in &runtime_metadata.extrinsic.signed_extensions {
+        if extension.identifier == CHECK_APP_ID {
+            app_id = <Compact<u32>>::decode(input)?.0;
+        } else {
+            events::decode_and_consume_type(
+                extension.ty.id(),
+                &runtime_metadata.types,
+                input,
+            )?;
+        }
+    }
+
+    let pallet = metadata.pallet(PALLET)?;
+    let pallet_index = u8::decode(input)?;
+    let call_index = u8::decode(input)?;
+    if pallet_index != pallet.index() || call_index != pallet.call_index(SUBMIT_DATA)? {
+        return Ok(None)
+    }
+
+    let data = Vec::<u8>::decode(input)?;
+    Ok(Some((
+        app_id,
+        AppData {
+            extrinsic_index,
+            submitter,
+            data_hash: T::Hashing::hash(&data),
+            data,
+        },
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        rpc::{
+            MockRpcClient,
+            RuntimeVersion,
+        },
+        ClientBuilder,
+        DefaultConfig,
+    };
+    use frame_metadata::{
+        v14::{
+            ExtrinsicMetadata,
+            PalletCallMetadata,
+            PalletMetadata,
+            RuntimeMetadataLastVersion,
+            SignedExtensionMetadata,
+        },
+        RuntimeMetadataPrefixed,
+    };
+    use scale_info::{
+        meta_type,
+        TypeInfo,
+    };
+    use sp_core::{
+        sr25519,
+        storage::StorageKey,
+        twox_128,
+        H256,
+    };
+    use sp_runtime::{
+        AccountId32,
+        Digest,
+        MultiAddress,
+        MultiSignature,
+    };
+    use std::convert::TryFrom;
+
+    #[allow(non_camel_case_types, dead_code)]
+    #[derive(TypeInfo)]
+    enum Call {
+        #[codec(index = 0)]
+        remark { data: Vec<u8> },
+        #[codec(index = 1)]
+        submit_data { data: Vec<u8> },
+    }
+
+    fn metadata() -> Metadata {
+        let pallets = vec![PalletMetadata {
+            name: PALLET,
+            storage: None,
+            calls: Some(PalletCallMetadata {
+                ty: meta_type::<Call>(),
+            }),
+            event: None,
+            constants: vec![],
+            error: None,
+            index: 29,
+        }];
+        let extension = |identifier| {
+            SignedExtensionMetadata {
+                identifier,
+                ty: meta_type::<Compact<u32>>(),
+                additional_signed: meta_type::<()>(),
+            }
+        };
+        let extrinsic = ExtrinsicMetadata {
+            ty: meta_type::<()>(),
+            version: 4,
+            signed_extensions: vec![extension("CheckNonce"), extension(CHECK_APP_ID)],
+        };
+        let v14 = RuntimeMetadataLastVersion::new(pallets, extrinsic, meta_type::<()>());
+        let runtime_metadata: RuntimeMetadataPrefixed = v14.into();
+        Metadata::try_from(runtime_metadata).unwrap()
+    }
+
+    fn extrinsic(version: u8, call_index: u8, app_id: u32, data: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![version];
+        MultiAddress::<AccountId32, u32>::Id(AccountId32::new([1; 32]))
+            .encode_to(&mut bytes);
+        MultiSignature::Sr25519(sr25519::Signature::from_raw([0; 64]))
+            .encode_to(&mut bytes);
+        Compact(5u32).encode_to(&mut bytes);
+        Compact(app_id).encode_to(&mut bytes);
+        bytes.extend([29, call_index]);
+        data.encode_to(&mut bytes);
+        bytes
+    }
+
+    #[test]
+    fn submitted_data_is_decoded_with_its_app_id() {
+        let metadata = metadata();
+        let bytes = extrinsic(0b1000_0100, 1, 7, b"hello");
+
+        let (app_id, app_data) =
+            decode_app_data::<DefaultConfig>(&metadata, 3, &mut &*bytes)
+                .unwrap()
+                .unwrap();
+        assert_eq!(app_id, 7);
+        assert_eq!(
+            app_data,
+            AppData {
+                extrinsic_index: 3,
+                submitter: MultiAddress::Id(AccountId32::new([1; 32])),
+                data_hash: <DefaultConfig as Config>::Hashing::hash(b"hello"),
+                data: b"hello".to_vec(),
+            }
+        );
+    }
+
+    #[test]
+    fn other_extrinsics_are_skipped() {
+        let metadata = metadata();
+
+        let other_call = extrinsic(0b1000_0100, 0, 7, b"hello");
+        assert!(
+            decode_app_data::<DefaultConfig>(&metadata, 0, &mut &*other_call)
+                .unwrap()
+                .is_none()
+        );
+
+        let unsigned = [0b0000_0100, 29, 1];
+        assert!(
+            decode_app_data::<DefaultConfig>(&metadata, 0, &mut &unsigned[..])
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn data_is_split_into_chunks_of_at_most_max_bytes() {
+        assert_eq!(chunk_ranges(10, 4), vec![0..4, 4..8, 8..10]);
+        assert_eq!(chunk_ranges(8, 4), vec![0..4, 4..8]);
+        assert_eq!(chunk_ranges(3, 4), vec![0..3]);
+        assert!(chunk_ranges(0, 4).is_empty());
+    }
+
+    fn grandpa_key(storage: &str) -> StorageKey {
+        let mut key = twox_128(b"Grandpa").to_vec();
+        key.extend(twox_128(storage.as_bytes()));
+        StorageKey(key)
+    }
+
+    #[async_std::test]
+    async fn a_change_scheduled_before_subscribing_is_reported() {
+        let v14 = RuntimeMetadataLastVersion::new(
+            vec![],
+            ExtrinsicMetadata {
+                ty: meta_type::<()>(),
+                version: 4,
+                signed_extensions: vec![],
+            },
+            meta_type::<()>(),
+        );
+        let mock = MockRpcClient::new();
+        mock.set_metadata(&v14.into());
+        mock.set_genesis_hash(H256::zero());
+        mock.set_runtime_version(&RuntimeVersion {
+            spec_version: 1,
+            transaction_version: 1,
+            other: Default::default(),
+        });
+
+        // Blocks 10 to 13, the first of which is finalized when subscribing.
+        let headers: Vec<_> = (10..=13)
+            .map(|number| {
+                <DefaultConfig as Config>::Header::new(
+                    number,
+                    Default::default(),
+                    Default::default(),
+                    H256::repeat_byte(number as u8 - 1),
+                    Digest::default(),
+                )
+            })
+            .collect();
+        for header in &headers {
+            let hash = H256::repeat_byte(header.number as u8);
+            mock.set_header(hash, header);
+            mock.set_response_for("chain_getBlockHash", vec![header.number.into()], hash);
+        }
+        mock.set_response("chain_getFinalizedHead", H256::repeat_byte(10));
+
+        // A change scheduled at block 9 takes effect at block 12. The set id has been
+        // bumped already, but the authorities are still the old ones.
+        let old_authorities = vec![([1; 32], 1u64)];
+        let new_authorities = vec![([2; 32], 1u64)];
+        mock.set_storage(&grandpa_key("CurrentSetId"), Some(8u64.encode()));
+        mock.set_storage(
+            &grandpa_key("PendingChange"),
+            Some((9u32, 3u32, new_authorities.clone(), None::<u32>).encode()),
+        );
+        mock.set_storage(
+            &StorageKey(b":grandpa_authorities".to_vec()),
+            Some((1u8, old_authorities.clone()).encode()),
+        );
+        // The node sends the finalized head first, and then skips a block.
+        mock.push_subscription(
+            "chain_subscribeFinalizedHeads",
+            vec![&headers[0], &headers[1], &headers[3]],
+        );
+
+        let client = ClientBuilder::new()
+            .set_mock_client(mock)
+            .build::<DefaultConfig>()
+            .await
+            .unwrap();
+        let set = client.finality().authority_set(None).await.unwrap();
+        assert_eq!(set.set_id, 7);
+        assert_eq!(set.authorities, old_authorities);
+        assert_eq!(set.pending_change_at(), Some(12));
+
+        let changes = AvailClient::new(&client)
+            .subscribe_authority_set_changes()
+            .await
+            .unwrap();
+        let changes: Vec<_> = changes.take(1).collect().await;
+        let change = changes[0].as_ref().unwrap();
+        assert_eq!(change.set_id, 8);
+        assert_eq!(change.authorities, new_authorities);
+    }
+}
@@ -0,0 +1,204 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Access to the state of BABE consensus: epochs, slots and randomness.
+
+use crate::{
+    digest::{
+        AuthorityId,
+        AuthorityWeight,
+    },
+    error::BasicError,
+    metadata::{
+        Metadata,
+        MetadataError,
+    },
+    storage::StorageClient,
+    Config,
+};
+use codec::Decode;
+use futures::future;
+use sp_core::{
+    storage::StorageKey,
+    twox_128,
+};
+use std::sync::Arc;
+
+/// Information about a BABE epoch.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EpochInfo {
+    /// The index of the epoch.
+    pub index: u64,
+    /// The first slot of the epoch.
+    pub start_slot: u64,
+    /// The number of slots in the epoch.
+    pub duration: u64,
+    /// The slot of the block the information was obtained at.
+    pub current_slot: u64,
+    /// The randomness of the epoch.
+    pub randomness: [u8; 32],
+}
+
+impl EpochInfo {
+    /// The first slot of the next epoch.
+    pub fn end_slot(&self) -> u64 {
+        self.start_slot.saturating_add(self.duration)
+    }
+
+    /// The number of slots left in the epoch after the current one.
+    pub fn remaining_slots(&self) -> u64 {
+        self.end_slot()
+            .saturating_sub(self.current_slot)
+            .saturating_sub(1)
+    }
+}
+
+/// Client for querying the state of BABE consensus.
+pub struct BabeClient<'a, T: Config> {
+    storage: StorageClient<'a, T>,
+    metadata: Arc<Metadata>,
+}
+
+impl<'a, T: Config> BabeClient<'a, T> {
+    /// Create a new [`BabeClient`].
+    pub fn new(storage: StorageClient<'a, T>, metadata: Arc<Metadata>) -> Self {
+        Self { storage, metadata }
+    }
+
+    /// The index of the current epoch.
+    pub async fn epoch_index(&self, hash: Option<T::Hash>) -> Result<u64, BasicError> {
+        self.fetch::<u64>("EpochIndex", hash).await
+    }
+
+    /// The current slot.
+    pub async fn current_slot(&self, hash: Option<T::Hash>) -> Result<u64, BasicError> {
+        self.fetch::<u64>("CurrentSlot", hash).await
+    }
+
+    /// The first slot of the chain.
+    pub async fn genesis_slot(&self, hash: Option<T::Hash>) -> Result<u64, BasicError> {
+        self.fetch::<u64>("GenesisSlot", hash).await
+    }
+
+    /// The block numbers at which the previous and the current epoch started.
+    pub async fn epoch_start(
+        &self,
+        hash: Option<T::Hash>,
+    ) -> Result<(T::BlockNumber, T::BlockNumber), BasicError> {
+        self.fetch("EpochStart", hash).await
+    }
+
+    /// The randomness of the current epoch.
+    pub async fn randomness(
+        &self,
+        hash: Option<T::Hash>,
+    ) -> Result<[u8; 32], BasicError> {
+        self.fetch("Randomness", hash).await
+    }
+
+    /// The randomness of the next epoch.
+    pub async fn next_randomness(
+        &self,
+        hash: Option<T::Hash>,
+    ) -> Result<[u8; 32], BasicError> {
+        self.fetch("NextRandomness", hash).await
+    }
+
+    /// The authorities of the current epoch, with their weights.
+    pub async fn authorities(
+        &self,
+        hash: Option<T::Hash>,
+    ) -> Result<Vec<(AuthorityId, AuthorityWeight)>, BasicError> {
+        self.fetch("Authorities", hash).await
+    }
+
+    /// The number of slots in an epoch.
+    pub fn epoch_duration(&self) -> Result<u64, BasicError> {
+        self.constant("EpochDuration")
+    }
+
+    /// The expected time between blocks, in milliseconds.
+    pub fn expected_block_time(&self) -> Result<u64, BasicError> {
+        self.constant("ExpectedBlockTime")
+    }
+
+    /// Information about the current epoch.
+    ///
+    /// The start slot of the epoch is derived from its index, which assumes that no
+    /// epochs were skipped.
+    pub async fn current_epoch(
+        &self,
+        hash: Option<T::Hash>,
+    ) -> Result<EpochInfo, BasicError> {
+        let duration = self.epoch_duration()?;
+        let (index, genesis_slot, current_slot, randomness) = future::try_join4(
+            self.epoch_index(hash),
+            self.genesis_slot(hash),
+            self.current_slot(hash),
+            self.randomness(hash),
+        )
+        .await?;
+        Ok(EpochInfo {
+            index,
+            start_slot: genesis_slot.saturating_add(index.saturating_mul(duration)),
+            duration,
+            current_slot,
+            randomness,
+        })
+    }
+
+    async fn fetch<V: Decode>(
+        &self,
+        storage: &'static str,
+        hash: Option<T::Hash>,
+    ) -> Result<V, BasicError> {
+        let mut key = twox_128(b"Babe").to_vec();
+        key.extend(twox_128(storage.as_bytes()));
+        let key = StorageKey(key);
+        if let Some(value) = self.storage.fetch_unhashed(key, hash).await? {
+            return Ok(value)
+        }
+        let storage_metadata = self.metadata.pallet("Babe")?.storage(storage)?;
+        let default = Decode::decode(&mut &storage_metadata.default[..])
+            .map_err(MetadataError::DefaultError)?;
+        Ok(default)
+    }
+
+    fn constant<V: Decode>(&self, constant: &'static str) -> Result<V, BasicError> {
+        let constant = self.metadata.pallet("Babe")?.constant(constant)?;
+        let value = Decode::decode(&mut &constant.value[..])
+            .map_err(MetadataError::ConstantValueError)?;
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn epoch_slots() {
+        let epoch = EpochInfo {
+            index: 2,
+            start_slot: 120,
+            duration: 60,
+            current_slot: 150,
+            randomness: [0; 32],
+        };
+        assert_eq!(epoch.end_slot(), 180);
+        assert_eq!(epoch.remaining_slots(), 29);
+    }
+}
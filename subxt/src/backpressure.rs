@@ -0,0 +1,278 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Buffering of subscriptions whose consumers may not keep up.
+
+use crate::error::BasicError;
+use futures::{
+    Stream,
+    StreamExt,
+};
+use std::{
+    collections::VecDeque,
+    future::Future,
+    pin::Pin,
+    sync::{
+        Arc,
+        Mutex,
+    },
+    task::{
+        Context,
+        Poll,
+        Waker,
+    },
+};
+
+/// What to do with a new item when the buffer of a subscription is full.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BackpressurePolicy {
+    /// Stop reading from the subscription until the consumer makes room.
+    Wait,
+    /// Drop the oldest buffered item to make room. The consumer is told how many
+    /// items it missed with a [`BasicError::SubscriptionLagged`] error.
+    DropOldest,
+    /// Close the subscription. The consumer receives the items that were buffered,
+    /// followed by a [`BasicError::SubscriptionLagged`] error.
+    Fail,
+}
+
+/// Buffer the items from a subscription, handling a full buffer according to `policy`.
+///
+/// Returns the [`BufferDriver`] which reads items from the subscription into the buffer,
+/// and must be spawned (or otherwise polled) alongside the [`Buffered`] stream of items.
+pub fn with_backpressure<S, T, E>(
+    subscription: S,
+    capacity: usize,
+    policy: BackpressurePolicy,
+) -> (BufferDriver<S, T>, Buffered<T>)
+where
+    S: Stream<Item = Result<T, E>> + Unpin,
+    E: Into<BasicError>,
+{
+    let shared = Arc::new(Mutex::new(BufferState {
+        queue: VecDeque::with_capacity(capacity),
+        capacity: capacity.max(1),
+        dropped: 0,
+        failed: false,
+        closed: false,
+        consumer_gone: false,
+        consumer_waker: None,
+        driver_waker: None,
+    }));
+    let driver = BufferDriver {
+        subscription,
+        policy,
+        shared: shared.clone(),
+    };
+    (driver, Buffered { shared })
+}
+
+struct BufferState<T> {
+    queue: VecDeque<Result<T, BasicError>>,
+    capacity: usize,
+    // Items dropped to make room since the consumer was last told about it.
+    dropped: usize,
+    // The buffer overflowed with the `Fail` policy; reported after the buffered items.
+    failed: bool,
+    // No more items will be added to the queue.
+    closed: bool,
+    consumer_gone: bool,
+    consumer_waker: Option<Waker>,
+    driver_waker: Option<Waker>,
+}
+
+impl<T> BufferState<T> {
+    fn wake_consumer(&mut self) {
+        if let Some(waker) = self.consumer_waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Reads items from a subscription into the buffer of a [`Buffered`] stream. Resolves
+/// once the subscription ends or the [`Buffered`] stream is dropped.
+pub struct BufferDriver<S, T> {
+    subscription: S,
+    policy: BackpressurePolicy,
+    shared: Arc<Mutex<BufferState<T>>>,
+}
+
+impl<S, T> std::fmt::Debug for BufferDriver<S, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BufferDriver")
+            .field("policy", &self.policy)
+            .finish()
+    }
+}
+
+impl<S: Unpin, T> Unpin for BufferDriver<S, T> {}
+
+impl<S, T, E> Future for BufferDriver<S, T>
+where
+    S: Stream<Item = Result<T, E>> + Unpin,
+    E: Into<BasicError>,
+{
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        loop {
+            {
+                let mut shared = self.shared.lock().expect("buffer lock poisoned");
+                if shared.consumer_gone {
+                    return Poll::Ready(())
+                }
+                if self.policy == BackpressurePolicy::Wait
+                    && shared.queue.len() >= shared.capacity
+                {
+                    shared.driver_waker = Some(cx.waker().clone());
+                    return Poll::Pending
+                }
+            }
+
+            let item = match futures::ready!(self.subscription.poll_next_unpin(cx)) {
+                Some(item) => item.map_err(Into::into),
+                None => {
+                    let mut shared = self.shared.lock().expect("buffer lock poisoned");
+                    shared.closed = true;
+                    shared.wake_consumer();
+                    return Poll::Ready(())
+                }
+            };
+
+            let mut shared = self.shared.lock().expect("buffer lock poisoned");
+            if shared.queue.len() >= shared.capacity {
+                match self.policy {
+                    // We don't read from the subscription while the buffer is full.
+                    BackpressurePolicy::Wait => {}
+                    BackpressurePolicy::DropOldest => {
+                        shared.queue.pop_front();
+                        shared.dropped += 1;
+                    }
+                    BackpressurePolicy::Fail => {
+                        shared.failed = true;
+                        shared.closed = true;
+                        shared.wake_consumer();
+                        return Poll::Ready(())
+                    }
+                }
+            }
+            shared.queue.push_back(item);
+            shared.wake_consumer();
+        }
+    }
+}
+
+/// A buffered subscription, created with [`with_backpressure()`].
+pub struct Buffered<T> {
+    shared: Arc<Mutex<BufferState<T>>>,
+}
+
+impl<T> std::fmt::Debug for Buffered<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let shared = self.shared.lock().expect("buffer lock poisoned");
+        f.debug_struct("Buffered")
+            .field("buffered", &shared.queue.len())
+            .field("capacity", &shared.capacity)
+            .finish()
+    }
+}
+
+impl<T> Stream for Buffered<T> {
+    type Item = Result<T, BasicError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut shared = self.shared.lock().expect("buffer lock poisoned");
+
+        // Items dropped when the buffer was full were older than anything in it,
+        // so report them first.
+        if shared.dropped > 0 {
+            let dropped = std::mem::take(&mut shared.dropped);
+            return Poll::Ready(Some(Err(BasicError::SubscriptionLagged(dropped))))
+        }
+        if let Some(item) = shared.queue.pop_front() {
+            if let Some(waker) = shared.driver_waker.take() {
+                waker.wake();
+            }
+            return Poll::Ready(Some(item))
+        }
+        // The item which didn't fit came after everything that was buffered.
+        if std::mem::take(&mut shared.failed) {
+            return Poll::Ready(Some(Err(BasicError::SubscriptionLagged(1))))
+        }
+        if shared.closed {
+            return Poll::Ready(None)
+        }
+        shared.consumer_waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl<T> Drop for Buffered<T> {
+    fn drop(&mut self) {
+        if let Ok(mut shared) = self.shared.lock() {
+            shared.consumer_gone = true;
+            if let Some(waker) = shared.driver_waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{
+        executor::block_on,
+        stream,
+    };
+
+    fn source(len: u32) -> impl Stream<Item = Result<u32, BasicError>> + Unpin {
+        stream::iter((1..=len).map(Ok))
+    }
+
+    async fn items(buffered: Buffered<u32>) -> Vec<Result<u32, String>> {
+        buffered
+            .map(|item| item.map_err(|e| e.to_string()))
+            .collect()
+            .await
+    }
+
+    #[test]
+    fn wait_delivers_everything() {
+        let (driver, buffered) =
+            with_backpressure(source(5), 2, BackpressurePolicy::Wait);
+        let (_, received) = block_on(futures::future::join(driver, items(buffered)));
+        assert_eq!(received, (1..=5).map(Ok).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn drop_oldest_reports_missed_items() {
+        let (driver, buffered) =
+            with_backpressure(source(5), 2, BackpressurePolicy::DropOldest);
+        block_on(driver);
+        let lagged = BasicError::SubscriptionLagged(3).to_string();
+        assert_eq!(block_on(items(buffered)), vec![Err(lagged), Ok(4), Ok(5)]);
+    }
+
+    #[test]
+    fn fail_closes_the_subscription() {
+        let (driver, buffered) =
+            with_backpressure(source(5), 2, BackpressurePolicy::Fail);
+        block_on(driver);
+        let lagged = BasicError::SubscriptionLagged(1).to_string();
+        assert_eq!(block_on(items(buffered)), vec![Ok(1), Ok(2), Err(lagged)]);
+    }
+}
@@ -0,0 +1,141 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Dispatching calls of any pallets together in one `Utility::batch` or
+//! `Utility::batch_all` extrinsic.
+//!
+//! The calls are given as [`Encoded`] call data, for instance from
+//! [`crate::SubmittableExtrinsic::encoded()`], so that calls of different types can be
+//! batched without constructing the runtime's outer call enum.
+
+use crate::{
+    error::BasicError,
+    transaction::TransactionEvents,
+    Call,
+    Config,
+    Encoded,
+    Event,
+};
+use codec::{
+    Decode,
+    Encode,
+};
+
+/// The `Utility::batch` call: dispatch the calls in turn, stopping at the first one
+/// which fails. The calls before it are not reverted, and the batch itself succeeds;
+/// see [`BatchOutcome`] for finding out how far it got.
+#[derive(Clone, Debug, Encode)]
+pub struct BatchCall {
+    /// The encoded calls to dispatch.
+    pub calls: Vec<Encoded>,
+}
+
+impl Call for BatchCall {
+    const PALLET: &'static str = "Utility";
+    const FUNCTION: &'static str = "batch";
+}
+
+/// The `Utility::batch_all` call: dispatch the calls in turn, reverting all of them if
+/// any one fails.
+#[derive(Clone, Debug, Encode)]
+pub struct BatchAllCall {
+    /// The encoded calls to dispatch.
+    pub calls: Vec<Encoded>,
+}
+
+impl Call for BatchAllCall {
+    const PALLET: &'static str = "Utility";
+    const FUNCTION: &'static str = "batch_all";
+}
+
+/// A call of a batch was dispatched successfully.
+#[derive(Clone, Debug, Decode, PartialEq, Eq)]
+pub struct ItemCompleted;
+
+impl Event for ItemCompleted {
+    const PALLET: &'static str = "Utility";
+    const EVENT: &'static str = "ItemCompleted";
+}
+
+/// All of the calls of a batch were dispatched successfully.
+#[derive(Clone, Debug, Decode, PartialEq, Eq)]
+pub struct BatchCompleted;
+
+impl Event for BatchCompleted {
+    const PALLET: &'static str = "Utility";
+    const EVENT: &'static str = "BatchCompleted";
+}
+
+/// A call of a batch failed, and the calls after it were not dispatched. `E` is the
+/// `DispatchError` type of the runtime.
+#[derive(Clone, Debug, Decode, PartialEq, Eq)]
+pub struct BatchInterrupted<E> {
+    /// The index of the call which failed.
+    pub index: u32,
+    /// The error it failed with.
+    pub error: E,
+}
+
+impl<E: Decode> Event for BatchInterrupted<E> {
+    const PALLET: &'static str = "Utility";
+    const EVENT: &'static str = "BatchInterrupted";
+}
+
+/// How far the calls of a `Utility::batch` extrinsic got.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BatchOutcome<E> {
+    /// The number of calls which were dispatched successfully.
+    pub completed: usize,
+    /// The call which failed, if any; the calls after it were not dispatched.
+    pub interrupted: Option<BatchInterrupted<E>>,
+}
+
+impl<E: Decode> BatchOutcome<E> {
+    /// Work out the outcome of a batch from the events of its extrinsic.
+    pub fn from_events<T: Config, Evs: Decode>(
+        events: &TransactionEvents<'_, T, Evs>,
+    ) -> Result<Self, BasicError> {
+        let completed = events
+            .find::<ItemCompleted>()
+            .collect::<Result<Vec<_>, _>>()?
+            .len();
+        let interrupted = events.find_first::<BatchInterrupted<E>>()?;
+        Ok(Self {
+            completed,
+            interrupted,
+        })
+    }
+
+    /// Whether all of the calls of the batch were dispatched successfully.
+    pub fn is_complete(&self) -> bool {
+        self.interrupted.is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn batches_encode_calls_as_a_vec_of_raw_call_data() {
+        let batch = BatchCall {
+            calls: vec![Encoded(vec![1, 2, 3]), Encoded(vec![4, 5])],
+        };
+        // A compact length, followed by the calls themselves without length prefixes,
+        // which is how a `Vec<RuntimeCall>` is encoded.
+        assert_eq!(batch.encode(), vec![8, 1, 2, 3, 4, 5]);
+    }
+}
@@ -0,0 +1,214 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Fetching and subscribing to blocks, with their extrinsics decoded using the metadata.
+
+use crate::{
+    error::BasicError,
+    events::{
+        self,
+        subscribe_to_block_headers_filling_in_gaps,
+        Events,
+    },
+    extrinsic::{
+        decode_extrinsic,
+        DecodedExtrinsic,
+    },
+    Client,
+    Config,
+    Metadata,
+};
+use codec::{
+    Decode,
+    Encode,
+};
+use futures::{
+    stream::BoxStream,
+    StreamExt,
+};
+use sp_runtime::traits::{
+    Hash,
+    Header,
+};
+use std::sync::Arc;
+
+/// Client for fetching blocks, and subscribing to new ones.
+pub struct BlocksClient<'a, T: Config> {
+    client: &'a Client<T>,
+}
+
+impl<'a, T: Config> BlocksClient<'a, T> {
+    /// Create a new [`BlocksClient`].
+    pub fn new(client: &'a Client<T>) -> Self {
+        Self { client }
+    }
+
+    /// Fetch the block with the given hash, or the best block if `None` is given.
+    pub async fn at(
+        &self,
+        block_hash: Option<T::Hash>,
+    ) -> Result<Option<Block<'a, T>>, BasicError> {
+        let block_hash = match block_hash {
+            Some(hash) => hash,
+            None => {
+                self.client.rpc().block_hash(None).await?.ok_or_else(|| {
+                    BasicError::Other("Best block hash not found".into())
+                })?
+            }
+        };
+        fetch(self.client, block_hash).await
+    }
+
+    /// Subscribe to new blocks as they are imported.
+    ///
+    /// **Note:** these blocks haven't necessarily been finalized yet; prefer
+    /// [`BlocksClient::subscribe_finalized()`] if that is important.
+    pub async fn subscribe(
+        &self,
+    ) -> Result<BoxStream<'a, Result<Block<'a, T>, BasicError>>, BasicError> {
+        let client = self.client;
+        let headers = client.rpc().subscribe_blocks().await?;
+        Ok(headers
+            .then(move |header| {
+                async move {
+                    let header = header?;
+                    expect_block(client, header.hash()).await
+                }
+            })
+            .boxed())
+    }
+
+    /// Subscribe to finalized blocks. Every block after the finalized block at the time
+    /// of subscribing is handed back in order, even those which the node skips over when
+    /// it finalizes several blocks at once.
+    pub async fn subscribe_finalized(
+        &self,
+    ) -> Result<BoxStream<'a, Result<Block<'a, T>, BasicError>>, BasicError> {
+        let client = self.client;
+        let last_finalized_hash = client.rpc().finalized_head().await?;
+        let last_finalized_number = client
+            .rpc()
+            .header(Some(last_finalized_hash))
+            .await?
+            .map(|h| (*h.number()).into());
+        let headers = subscribe_to_block_headers_filling_in_gaps(
+            client,
+            last_finalized_number,
+            client.rpc().subscribe_finalized_blocks().await?,
+        );
+        Ok(headers
+            .then(move |header| {
+                async move {
+                    let header = header?;
+                    expect_block(client, header.hash()).await
+                }
+            })
+            .boxed())
+    }
+}
+
+async fn fetch<T: Config>(
+    client: &Client<T>,
+    block_hash: T::Hash,
+) -> Result<Option<Block<'_, T>>, BasicError> {
+    let block = match client.rpc().block(Some(block_hash)).await? {
+        Some(block) => block.block,
+        None => return Ok(None),
+    };
+    let metadata = client.metadata_at(block_hash).await?;
+    Ok(Some(Block {
+        client,
+        hash: block_hash,
+        header: block.header,
+        extrinsics: block.extrinsics.iter().map(Encode::encode).collect(),
+        metadata,
+    }))
+}
+
+async fn expect_block<T: Config>(
+    client: &Client<T>,
+    block_hash: T::Hash,
+) -> Result<Block<'_, T>, BasicError> {
+    fetch(client, block_hash)
+        .await?
+        .ok_or_else(|| BasicError::Other(format!("Block {:?} not found", block_hash)))
+}
+
+/// A block, with its extrinsics decoded using the metadata of the runtime that was
+/// active at it. Its events are only fetched when asked for.
+pub struct Block<'a, T: Config> {
+    client: &'a Client<T>,
+    hash: T::Hash,
+    header: T::Header,
+    // Each extrinsic is SCALE encoded, and so prefixed with its length.
+    extrinsics: Vec<Vec<u8>>,
+    metadata: Arc<Metadata>,
+}
+
+impl<'a, T: Config> Block<'a, T> {
+    /// The hash of the block.
+    pub fn hash(&self) -> T::Hash {
+        self.hash
+    }
+
+    /// The header of the block.
+    pub fn header(&self) -> &T::Header {
+        &self.header
+    }
+
+    /// The number of the block.
+    pub fn number(&self) -> T::BlockNumber {
+        *self.header.number()
+    }
+
+    /// The metadata of the runtime that was active at this block.
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// The number of extrinsics in the block.
+    pub fn extrinsic_count(&self) -> usize {
+        self.extrinsics.len()
+    }
+
+    /// The SCALE encoded extrinsics in the block.
+    pub fn raw_extrinsics(&self) -> &[Vec<u8>] {
+        &self.extrinsics
+    }
+
+    /// The hashes of the extrinsics in the block, in order.
+    pub fn extrinsic_hashes(&self) -> impl Iterator<Item = T::Hash> + '_ {
+        self.extrinsics.iter().map(|xt| T::Hashing::hash(xt))
+    }
+
+    /// The extrinsics in the block, in order, decoded into their signers, nonces and
+    /// calls. An extrinsic which can't be decoded doesn't stop the rest from being
+    /// decoded.
+    pub fn extrinsics(
+        &self,
+    ) -> impl Iterator<Item = Result<DecodedExtrinsic, BasicError>> + '_ {
+        self.extrinsics
+            .iter()
+            .map(move |xt| decode_extrinsic(&self.metadata, xt))
+    }
+
+    /// Fetch the events emitted in this block. Events can be matched up to the
+    /// extrinsics which emitted them with [`Events::iter_raw_for_extrinsic()`].
+    pub async fn events<Evs: Decode>(&self) -> Result<Events<'a, T, Evs>, BasicError> {
+        events::at_with_metadata(self.client.rpc(), self.metadata.clone(), self.hash)
+            .await
+    }
+}
@@ -14,43 +14,155 @@
 // You should have received a copy of the GNU General Public License
 // along with subxt.  If not, see <http://www.gnu.org/licenses/>.
 
-use futures::future;
-use sp_runtime::traits::Hash;
+use futures::{
+    future,
+    Future,
+    Stream,
+    StreamExt,
+};
+use futures_timer::Delay;
 pub use sp_runtime::traits::SignedExtension;
+use sp_runtime::{
+    generic::Era,
+    traits::{
+        Hash,
+        Header,
+    },
+    transaction_validity::TransactionValidityError,
+};
 
 use crate::{
+    accounts::AccountsClient,
+    avail::AvailClient,
+    babe::BabeClient,
+    blocks::BlocksClient,
+    decode_limits::DecodeLimits,
+    digest::{
+        self,
+        BabePreDigest,
+        DecodedDigestItem,
+    },
     error::{
         BasicError,
+        DispatchErrorDetails,
+        Error,
         HasModuleError,
     },
+    events::{
+        self,
+        Events,
+        EventsClient,
+    },
     extrinsic::{
         self,
+        DecodedExtrinsic,
         SignedExtra,
         Signer,
         UncheckedExtrinsic,
     },
+    finality::FinalityClient,
+    keepalive::{
+        KeepaliveConfig,
+        KeepaliveDriver,
+    },
+    metadata::{
+        MetadataError,
+        MetadataHashes,
+    },
+    metadata_cache::MetadataCache,
+    metadata_registry::MetadataRegistry,
+    metrics::ClientMetrics,
+    nonce_manager::NonceManager,
+    reconnect::ReconnectPolicy,
     rpc::{
+        ChainBlock,
+        ConnectionEvents,
+        FeeDetails,
+        MockRpcClient,
         Rpc,
         RpcClient,
+        RpcSubscription,
+        RuntimeDispatchInfo,
         RuntimeVersion,
+        SubstrateTransactionStatus,
         SystemProperties,
     },
-    storage::StorageClient,
-    transaction::TransactionProgress,
+    rpc_middleware::{
+        CacheMiddleware,
+        RetryMiddleware,
+        RetryPolicy,
+        RpcMiddleware,
+    },
+    rpc_recording::RpcRecorder,
+    runtime_api::RuntimeApiClient,
+    storage::{
+        ChildStorageClient,
+        StorageClient,
+        StorageEntry,
+    },
+    submission_log::SubmissionLogging,
+    timestamp::TimestampClient,
+    transaction::{
+        ResubmitPolicy,
+        SubmissionStatus,
+        TransactionProgress,
+    },
     Call,
     Config,
+    Encoded,
     Metadata,
 };
-use codec::Decode;
+use codec::{
+    Decode,
+    Encode,
+};
 use derivative::Derivative;
-use std::sync::Arc;
+use sp_core::{
+    storage::{
+        ChildInfo,
+        StorageData,
+        StorageKey,
+    },
+    twox_128,
+};
+use std::{
+    any::Any,
+    ops::RangeInclusive,
+    path::PathBuf,
+    sync::{
+        Arc,
+        RwLock,
+    },
+    time::Duration,
+};
 
 /// ClientBuilder for constructing a Client.
 #[derive(Default)]
 pub struct ClientBuilder {
     url: Option<String>,
+    urls: Vec<String>,
+    http_url: Option<String>,
     client: Option<RpcClient>,
+    mock_client: Option<MockRpcClient>,
+    recording: Option<PathBuf>,
     page_size: Option<u32>,
+    storage_prefetch: Option<usize>,
+    metadata_cache_dir: Option<PathBuf>,
+    metadata: Option<SuppliedMetadata>,
+    middleware: Vec<Arc<dyn RpcMiddleware>>,
+    decode_limits: DecodeLimits,
+    submission_logging: Option<SubmissionLogging>,
+    reconnect_policy: Option<ReconnectPolicy>,
+    nonce_manager: bool,
+    transaction_timeout: Option<Duration>,
+    request_timeout: Option<Duration>,
+    retry_policy: Option<RetryPolicy>,
+    connect_retry: Option<(u32, Duration)>,
+    metrics: Option<Arc<dyn ClientMetrics>>,
+    rate_limit: Option<u32>,
+    max_concurrent_requests: Option<usize>,
+    query_cache_size: Option<usize>,
+    default_extra: Option<DefaultExtraParams>,
 }
 
 impl ClientBuilder {
@@ -58,8 +170,29 @@ impl ClientBuilder {
     pub fn new() -> Self {
         Self {
             url: None,
+            urls: Vec::new(),
+            http_url: None,
             client: None,
+            mock_client: None,
+            recording: None,
             page_size: None,
+            storage_prefetch: None,
+            metadata_cache_dir: None,
+            metadata: None,
+            middleware: Vec::new(),
+            decode_limits: DecodeLimits::default(),
+            submission_logging: None,
+            reconnect_policy: None,
+            nonce_manager: false,
+            transaction_timeout: None,
+            request_timeout: None,
+            retry_policy: None,
+            connect_retry: None,
+            metrics: None,
+            rate_limit: None,
+            max_concurrent_requests: None,
+            query_cache_size: None,
+            default_extra: None,
         }
     }
 
@@ -69,57 +202,494 @@ impl ClientBuilder {
         self
     }
 
+    /// Answer requests with the canned responses of the given [`MockRpcClient`] rather
+    /// than connecting to a node, for testing. This takes precedence over the other
+    /// ways of connecting.
+    pub fn set_mock_client(mut self, client: MockRpcClient) -> Self {
+        self.mock_client = Some(client);
+        self
+    }
+
+    /// Record the traffic between the client and the node to the file at the given
+    /// path, replacing it, so that it can be replayed later with
+    /// [`MockRpcClient::from_recording`]. See [`RpcRecorder`].
+    pub fn set_recording<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.recording = Some(path.into());
+        self
+    }
+
     /// Set the substrate rpc address.
     pub fn set_url<P: Into<String>>(mut self, url: P) -> Self {
         self.url = Some(url.into());
         self
     }
 
+    /// Connect to the first of these endpoints that can be reached, and fail over to
+    /// the others in turn whenever the connection is lost (or, with
+    /// [`Client::keepalive`], the node stops responding). Active
+    /// subscriptions carry on over the new connection, and endpoints of a chain with a
+    /// different genesis hash are never used. See [`Rpc::with_failover`].
+    ///
+    /// This takes precedence over [`ClientBuilder::set_url`]. The reconnect policy (or
+    /// the default one, if none is set) decides how long to wait once every endpoint
+    /// has been tried.
+    pub fn set_urls<U: Into<String>>(mut self, urls: Vec<U>) -> Self {
+        self.urls = urls.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Talk to the node over HTTP(S) at the given address, rather than over a
+    /// WebSocket connection. This takes precedence over [`ClientBuilder::set_url`].
+    ///
+    /// Subscriptions aren't possible over HTTP, and fail with
+    /// [`crate::BasicError::SubscriptionsUnsupported`]; see [`Rpc::new_http`]. The
+    /// reconnect policy doesn't apply either, since every call is a request of its own.
+    pub fn set_http_url<P: Into<String>>(mut self, url: P) -> Self {
+        self.http_url = Some(url.into());
+        self
+    }
+
     /// Set the page size.
     pub fn set_page_size(mut self, size: u32) -> Self {
         self.page_size = Some(size);
         self
     }
 
+    /// Set how many pages of storage values are fetched ahead when streaming storage.
+    /// Defaults to [`crate::storage::DEFAULT_STORAGE_PREFETCH`]; see
+    /// [`StorageClient::stream`].
+    pub fn set_storage_prefetch(mut self, pages: usize) -> Self {
+        self.storage_prefetch = Some(pages);
+        self
+    }
+
+    /// Cache the runtime metadata in the given directory.
+    ///
+    /// On startup the metadata for the runtime version reported by the node is read from
+    /// this directory if present, rather than being downloaded from the node. A runtime
+    /// upgrade bumps the `spec_version`, and so metadata for the new runtime will be
+    /// downloaded and cached as soon as the node reports it.
+    pub fn set_metadata_cache_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.metadata_cache_dir = Some(dir.into());
+        self
+    }
+
+    /// Use the given metadata rather than downloading it from the node when building the
+    /// client. It must describe the runtime the node is currently running.
+    ///
+    /// This takes precedence over [`ClientBuilder::set_metadata_cache_dir`].
+    pub fn set_metadata(mut self, metadata: Metadata) -> Self {
+        self.metadata = Some(SuppliedMetadata::Decoded(metadata));
+        self
+    }
+
+    /// Like [`ClientBuilder::set_metadata`], but with SCALE encoded metadata (as returned
+    /// by `state_getMetadata`), which is decoded when the client is built.
+    pub fn set_metadata_bytes(mut self, bytes: &[u8]) -> Self {
+        self.metadata = Some(SuppliedMetadata::Encoded(bytes.to_vec()));
+        self
+    }
+
+    /// Set the limits applied to data received from the node, so that a malicious or
+    /// buggy endpoint can't cause huge allocations. See [`DecodeLimits`].
+    pub fn set_decode_limits(mut self, limits: DecodeLimits) -> Self {
+        self.decode_limits = limits;
+        self
+    }
+
+    /// Log every call submitted through the client, according to the given configuration.
+    /// See [`SubmissionLogging`].
+    pub fn set_submission_logging(mut self, logging: SubmissionLogging) -> Self {
+        self.submission_logging = Some(logging);
+        self
+    }
+
+    /// Re-establish the connection to the node whenever it's lost, according to the given
+    /// policy. Active subscriptions (such as to finalized blocks, storage changes or the
+    /// progress of a transaction) are made again on the new connection, and carry on as
+    /// before. See [`Client::connection_events`] to find out when this happens.
+    ///
+    /// This only applies when connecting by URL, and not to a client given with
+    /// [`ClientBuilder::set_client`].
+    pub fn set_reconnect_policy(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect_policy = Some(policy);
+        self
+    }
+
+    /// Hand out nonces locally when submitting transactions with a signer that has no
+    /// nonce set, so that many transactions can be submitted from one account at once.
+    /// See [`NonceManager`].
+    pub fn set_nonce_manager(mut self, enabled: bool) -> Self {
+        self.nonce_manager = enabled;
+        self
+    }
+
+    /// Give up waiting on a submitted transaction after this long. This applies to
+    /// [`TransactionProgress::wait_for_in_block`] and the other `wait_for_*` methods,
+    /// which then fail with [`crate::TransactionError::TimedOut`]. There is no
+    /// timeout by default.
+    pub fn set_transaction_timeout(mut self, timeout: Duration) -> Self {
+        self.transaction_timeout = Some(timeout);
+        self
+    }
+
+    /// Give up on RPC method calls which the node hasn't answered after this long,
+    /// failing them with a request timeout error. See [`RetryMiddleware`].
+    pub fn set_request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = Some(timeout);
+        self
+    }
+
+    /// Retry RPC method calls which only read data (such as `state_getStorage` and
+    /// `chain_getHeader`) when they time out or can't reach the node, according to
+    /// the given policy. See [`RetryMiddleware`].
+    pub fn set_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
+    /// Try connecting to the node up to `attempts` times rather than failing straight
+    /// away if it can't be reached, waiting `backoff` after the first attempt and
+    /// doubling the wait after each further one, up to 30 seconds (or `backoff`, if
+    /// that's longer). This is useful when starting the client alongside the node,
+    /// which may take a while before its RPC endpoint is ready.
+    ///
+    /// This only applies when connecting by URL (see [`ClientBuilder::set_url`] and
+    /// [`ClientBuilder::set_urls`]).
+    pub fn set_connect_retry(mut self, attempts: u32, backoff: Duration) -> Self {
+        self.connect_retry = Some((attempts.max(1), backoff));
+        self
+    }
+
+    /// Send at most this many requests to the node a second, evenly spaced, holding
+    /// back the rest until it's their turn. Useful for bulk jobs against public
+    /// endpoints, which throttle clients making too many requests.
+    ///
+    /// Method calls, batches of calls (see [`Rpc::batch`]) and new subscriptions each
+    /// count as one request. Notifications from existing subscriptions aren't limited.
+    pub fn set_rate_limit(mut self, requests_per_second: u32) -> Self {
+        self.rate_limit = Some(requests_per_second);
+        self
+    }
+
+    /// Have at most `max` requests waiting on the node at once, holding back the rest
+    /// until one of them is answered. Requests count as for
+    /// [`ClientBuilder::set_rate_limit`].
+    pub fn set_max_concurrent_requests(mut self, max: usize) -> Self {
+        self.max_concurrent_requests = Some(max);
+        self
+    }
+
+    /// Remember the results of up to `size` queries made at a given block hash, such as
+    /// headers, blocks and storage values, and answer them again from memory. See
+    /// [`CacheMiddleware`].
+    pub fn set_query_cache_size(mut self, size: usize) -> Self {
+        self.query_cache_size = Some(size);
+        self
+    }
+
+    /// Report metrics about the client, such as how many RPC method calls it makes and
+    /// how long they take, to the given [`ClientMetrics`].
+    pub fn set_metrics<M: ClientMetrics>(mut self, metrics: M) -> Self {
+        self.metrics = Some(Arc::new(metrics));
+        self
+    }
+
+    /// Add a layer of middleware around the RPC method calls made by the client. The
+    /// first layer added is the outermost one. See [`Rpc::with_middleware`].
+    pub fn add_rpc_middleware<M: RpcMiddleware>(mut self, middleware: M) -> Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// Use the given parameters to construct the signed extra data of transactions
+    /// signed without explicit parameters, such as by
+    /// [`SubmittableExtrinsic::sign_and_submit()`], rather than the default ones. For
+    /// instance, Avail applications can give their `app_id` here:
+    ///
+    /// ```no_run
+    /// # use subxt::{avail::AvailExtraParameters, ClientBuilder};
+    /// let builder = ClientBuilder::new()
+    ///     .set_default_extra(AvailExtraParameters::new().set_app_id(7));
+    /// ```
+    ///
+    /// **Note:** transactions whose `SignedExtra` takes parameters of another type fail
+    /// to sign with [`BasicError::DefaultExtraMismatch`], rather than being submitted
+    /// without them; give their parameters explicitly with the `*_with_params`
+    /// methods instead.
+    pub fn set_default_extra<P>(mut self, params: P) -> Self
+    where
+        P: Clone + Send + Sync + 'static,
+    {
+        self.default_extra = Some(Arc::new(move || {
+            Box::new(params.clone()) as Box<dyn Any + Send>
+        }));
+        self
+    }
+
     /// Creates a new Client.
-    pub async fn build<T: Config>(self) -> Result<Client<T>, BasicError> {
-        let client = if let Some(client) = self.client {
-            client
+    pub async fn build<T: Config>(mut self) -> Result<Client<T>, BasicError> {
+        if let Some(size) = self.query_cache_size {
+            // Inside the other layers, so that they see every call, but outside of
+            // retries, which a cached result has no need for.
+            self.middleware.push(Arc::new(CacheMiddleware::new(size)));
+        }
+        if self.request_timeout.is_some() || self.retry_policy.is_some() {
+            // Innermost, so that every attempt passes through the other layers.
+            self.middleware.push(Arc::new(RetryMiddleware::new(
+                self.request_timeout,
+                self.retry_policy,
+            )));
+        }
+        let (rpc, url) = if let Some(client) = self.mock_client {
+            (Rpc::new_mock(client), None)
+        } else if let Some(client) = self.client {
+            (Rpc::new(client), None)
+        } else if let Some(url) = &self.http_url {
+            (http_rpc(url, &self.decode_limits)?, None)
+        } else if !self.urls.is_empty() {
+            let (index, client) = connect_with_retry(self.connect_retry, || {
+                connect_to_any(&self.urls, &self.decode_limits)
+            })
+            .await?;
+            let rpc = Rpc::new(client).with_failover_from(
+                self.urls.clone(),
+                index,
+                self.reconnect_policy.unwrap_or_default(),
+            );
+            (rpc, None)
         } else {
             let url = self.url.as_deref().unwrap_or("ws://127.0.0.1:9944");
-            crate::rpc::ws_client(url).await?
+            let limits = &self.decode_limits;
+            let client = connect_with_retry(self.connect_retry, || {
+                async move {
+                    crate::rpc::ws_client_with_limits(url, limits)
+                        .await
+                        .map_err(BasicError::from)
+                }
+            })
+            .await?;
+            (Rpc::new(client), Some(url))
         };
-        let rpc = Rpc::new(client);
-        let (metadata, genesis_hash, runtime_version, properties) = future::join4(
-            rpc.metadata(),
-            rpc.genesis_hash(),
-            rpc.runtime_version(None),
-            rpc.system_properties(),
-        )
-        .await;
-        let metadata = metadata?;
+        let mut rpc = rpc
+            .with_decode_limits(self.decode_limits)
+            .with_middleware_layers(self.middleware);
+        if let (Some(url), Some(policy)) = (url, self.reconnect_policy) {
+            rpc = rpc.with_reconnect(url, policy);
+        }
+        if let Some(path) = &self.recording {
+            rpc = rpc.with_recorder(RpcRecorder::create(path)?);
+        }
+        if let Some(metrics) = self.metrics {
+            rpc = rpc.with_metrics(metrics);
+        }
+        if let Some(requests_per_second) = self.rate_limit {
+            rpc = rpc.with_rate_limit(requests_per_second);
+        }
+        if let Some(max) = self.max_concurrent_requests {
+            rpc = rpc.with_max_concurrent_requests(max);
+        }
+        let metadata_cache = self.metadata_cache_dir.map(MetadataCache::new);
+        let supplied_metadata = match self.metadata {
+            Some(SuppliedMetadata::Decoded(metadata)) => Some(metadata),
+            Some(SuppliedMetadata::Encoded(bytes)) => {
+                Some(crate::metadata_cache::decode_metadata(&bytes)?)
+            }
+            None => None,
+        };
+        let (metadata, genesis_hash, runtime_version, properties) =
+            if let Some(metadata) = supplied_metadata {
+                let (genesis_hash, runtime_version, properties) = future::join3(
+                    rpc.genesis_hash(),
+                    rpc.runtime_version(None),
+                    rpc.system_properties(),
+                )
+                .await;
+                (metadata, genesis_hash?, runtime_version?, properties)
+            } else if let Some(cache) = &metadata_cache {
+                // We need to know the runtime version to find the right cache entry,
+                // so metadata can only be fetched once that has been obtained.
+                let (genesis_hash, runtime_version, properties) = future::join3(
+                    rpc.genesis_hash(),
+                    rpc.runtime_version(None),
+                    rpc.system_properties(),
+                )
+                .await;
+                let genesis_hash = genesis_hash?;
+                let runtime_version = runtime_version?;
+                let metadata = cache
+                    .fetch(&rpc, &genesis_hash, runtime_version.spec_version, None)
+                    .await?;
+                (metadata, genesis_hash, runtime_version, properties)
+            } else {
+                let (metadata, genesis_hash, runtime_version, properties) =
+                    future::join4(
+                        rpc.metadata(),
+                        rpc.genesis_hash(),
+                        rpc.runtime_version(None),
+                        rpc.system_properties(),
+                    )
+                    .await;
+                (metadata?, genesis_hash?, runtime_version?, properties)
+            };
+
+        rpc.pin_genesis(&genesis_hash);
+
+        let metadata = Arc::new(metadata);
+        let metadata_registry = MetadataRegistry::new();
+        metadata_registry.insert(runtime_version.spec_version, metadata.clone());
+        let failovers = rpc.failovers();
 
         Ok(Client {
             rpc,
-            genesis_hash: genesis_hash?,
-            metadata: Arc::new(metadata),
+            genesis_hash,
+            runtime: Arc::new(RwLock::new(CurrentRuntime {
+                metadata,
+                runtime_version,
+                failovers,
+            })),
+            metadata_registry,
+            metadata_cache,
             properties: properties.unwrap_or_else(|_| Default::default()),
-            runtime_version: runtime_version?,
             iter_page_size: self.page_size.unwrap_or(10),
+            storage_prefetch: self
+                .storage_prefetch
+                .unwrap_or(crate::storage::DEFAULT_STORAGE_PREFETCH),
+            submission_logging: self.submission_logging,
+            nonce_manager: self.nonce_manager.then(|| Arc::new(NonceManager::new())),
+            transaction_timeout: self.transaction_timeout,
+            default_extra: self.default_extra,
         })
     }
 }
 
+/// Produces the default parameters given to [`ClientBuilder::set_default_extra()`].
+type DefaultExtraParams = Arc<dyn Fn() -> Box<dyn Any + Send> + Send + Sync>;
+
+/// An [`Rpc`] which sends method calls over HTTP to the given URL.
+#[cfg(feature = "native")]
+fn http_rpc<T: Config>(url: &str, limits: &DecodeLimits) -> Result<Rpc<T>, BasicError> {
+    let client = crate::rpc::http_client_with_limits(url, limits)?;
+    Ok(Rpc::new_http(client))
+}
+
+#[cfg(not(feature = "native"))]
+fn http_rpc<T: Config>(url: &str, _limits: &DecodeLimits) -> Result<Rpc<T>, BasicError> {
+    Err(BasicError::Other(format!(
+        "Cannot connect to {} without the `native` feature",
+        url
+    )))
+}
+
+/// Connect to the first of the given endpoints that can be reached, returning its
+/// index along with the client.
+async fn connect_to_any(
+    urls: &[String],
+    limits: &DecodeLimits,
+) -> Result<(usize, RpcClient), BasicError> {
+    let mut last_error = None;
+    for (index, url) in urls.iter().enumerate() {
+        match crate::rpc::ws_client_with_limits(url, limits).await {
+            Ok(client) => return Ok((index, client)),
+            Err(e) => {
+                log::warn!("Could not connect to {}: {}", url, e);
+                last_error = Some(e);
+            }
+        }
+    }
+    Err(last_error
+        .map(Into::into)
+        .unwrap_or_else(|| BasicError::Other("No endpoints to connect to".into())))
+}
+
+/// The longest [`connect_with_retry`] waits between attempts, unless the initial
+/// backoff is longer still.
+const MAX_CONNECT_BACKOFF: Duration = Duration::from_secs(30);
+
+/// How long to wait after the given failed attempt to connect, counting from 1: the
+/// backoff doubles with each attempt, up to [`MAX_CONNECT_BACKOFF`].
+fn connect_backoff(backoff: Duration, attempt: u32) -> Duration {
+    let cap = MAX_CONNECT_BACKOFF.max(backoff);
+    2u32.checked_pow(attempt - 1)
+        .and_then(|factor| backoff.checked_mul(factor))
+        .map_or(cap, |wait| wait.min(cap))
+}
+
+/// Connect to the node with `connect`, trying again after a while if it fails, as
+/// configured by [`ClientBuilder::set_connect_retry`].
+async fn connect_with_retry<F, Fut, R>(
+    retry: Option<(u32, Duration)>,
+    mut connect: F,
+) -> Result<R, BasicError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<R, BasicError>>,
+{
+    let (attempts, backoff) = retry.unwrap_or((1, Duration::ZERO));
+    let mut attempt = 1;
+    loop {
+        match connect().await {
+            Ok(client) => return Ok(client),
+            Err(e) if attempt < attempts => {
+                log::warn!(
+                    "Could not connect to the node (attempt {}/{}): {}",
+                    attempt,
+                    attempts,
+                    e
+                );
+                Delay::new(connect_backoff(backoff, attempt)).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Metadata given to the [`ClientBuilder`], rather than downloaded from the node.
+enum SuppliedMetadata {
+    Decoded(Metadata),
+    Encoded(Vec<u8>),
+}
+
+/// The runtime that a [`Client`] builds transactions for. Its metadata and version are
+/// replaced together when the runtime is upgraded.
+#[derive(Debug)]
+struct CurrentRuntime {
+    metadata: Arc<Metadata>,
+    runtime_version: RuntimeVersion,
+    /// The number of failovers (see [`Rpc::failovers`]) when the runtime was last
+    /// checked against the node connected to.
+    failovers: u64,
+}
+
+/// Reported by [`Client::runtime_upgrades()`] and [`Client::refresh_metadata()`] when the
+/// runtime of the chain has been upgraded, once the client has switched to the metadata
+/// of the new runtime.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UpgradeDetected {
+    /// The version of the runtime that the client was using.
+    pub old_version: RuntimeVersion,
+    /// The version of the runtime that the client now uses.
+    pub new_version: RuntimeVersion,
+}
+
 /// Client to interface with a substrate node.
 #[derive(Derivative)]
 #[derivative(Clone(bound = ""))]
 pub struct Client<T: Config> {
     rpc: Rpc<T>,
     genesis_hash: T::Hash,
-    metadata: Arc<Metadata>,
+    // Shared by all clones, so that they all pick up runtime upgrades.
+    runtime: Arc<RwLock<CurrentRuntime>>,
+    metadata_registry: MetadataRegistry,
+    metadata_cache: Option<MetadataCache>,
     properties: SystemProperties,
-    runtime_version: RuntimeVersion,
     iter_page_size: u32,
+    storage_prefetch: usize,
+    submission_logging: Option<SubmissionLogging>,
+    nonce_manager: Option<Arc<NonceManager<T>>>,
+    transaction_timeout: Option<Duration>,
+    default_extra: Option<DefaultExtraParams>,
 }
 
 impl<T: Config> std::fmt::Debug for Client<T> {
@@ -128,10 +698,16 @@ impl<T: Config> std::fmt::Debug for Client<T> {
             .field("rpc", &"<Rpc>")
             .field("genesis_hash", &self.genesis_hash)
             .field("metadata", &"<Metadata>")
+            .field("metadata_versions", &self.metadata_registry.spec_versions())
             .field("events_decoder", &"<EventsDecoder>")
             .field("properties", &self.properties)
-            .field("runtime_version", &self.runtime_version)
+            .field("runtime_version", &self.runtime_version())
             .field("iter_page_size", &self.iter_page_size)
+            .field("storage_prefetch", &self.storage_prefetch)
+            .field("submission_logging", &self.submission_logging)
+            .field("nonce_manager", &self.nonce_manager)
+            .field("transaction_timeout", &self.transaction_timeout)
+            .field("default_extra", &self.default_extra.is_some())
             .finish()
     }
 }
@@ -142,9 +718,191 @@ impl<T: Config> Client<T> {
         &self.genesis_hash
     }
 
-    /// Returns the chain metadata.
-    pub fn metadata(&self) -> &Metadata {
-        &self.metadata
+    /// Returns the metadata of the current runtime.
+    ///
+    /// This changes when a runtime upgrade is picked up; see [`Client::runtime_upgrades()`].
+    pub fn metadata(&self) -> Arc<Metadata> {
+        self.current_runtime().0
+    }
+
+    /// Check that the metadata of the current runtime is compatible with the metadata
+    /// that the given `RuntimeApi` was generated from: that each pallet it knows about
+    /// exists, and encodes its calls, events, errors, storage and constants the same way.
+    ///
+    /// The returned [`MetadataError::IncompatibleMetadata`] reports every difference.
+    pub fn validate_metadata<R: MetadataHashes>(&self) -> Result<(), MetadataError> {
+        self.metadata().check_pallet_hashes(R::pallet_hashes())
+    }
+
+    /// Decode the value of a pallet constant from the metadata of the current runtime.
+    ///
+    /// No request is made to the node; as with [`Client::metadata`], the value changes
+    /// when a runtime upgrade is picked up.
+    pub fn constant<V: Decode>(
+        &self,
+        pallet: &'static str,
+        constant: &'static str,
+    ) -> Result<V, BasicError> {
+        let metadata = self.metadata();
+        let constant = metadata.pallet(pallet)?.constant(constant)?;
+        let value = Decode::decode(&mut &constant.value[..])
+            .map_err(MetadataError::ConstantValueError)?;
+        Ok(value)
+    }
+
+    /// Returns the version of the current runtime.
+    pub fn runtime_version(&self) -> RuntimeVersion {
+        self.current_runtime().1
+    }
+
+    /// The metadata and version of the current runtime, which always belong together.
+    pub(crate) fn current_runtime(&self) -> (Arc<Metadata>, RuntimeVersion) {
+        let runtime = self.runtime.read().expect("runtime lock poisoned");
+        (runtime.metadata.clone(), runtime.runtime_version.clone())
+    }
+
+    /// Check the version of the runtime the node is running, and if it differs from the
+    /// one the client is using, switch to the metadata of the new runtime.
+    ///
+    /// Returns the details of the upgrade if there was one. Use
+    /// [`Client::runtime_upgrades()`] to do this whenever the node reports an upgrade.
+    pub async fn refresh_metadata(&self) -> Result<Option<UpgradeDetected>, BasicError> {
+        let failovers = self.rpc.failovers();
+        let runtime_version = self.rpc.runtime_version(None).await?;
+        let upgrade = self.apply_runtime_version(runtime_version).await?;
+        let mut runtime = self.runtime.write().expect("runtime lock poisoned");
+        runtime.failovers = runtime.failovers.max(failovers);
+        Ok(upgrade)
+    }
+
+    /// Refresh the runtime as with [`Client::refresh_metadata()`] if the connection has
+    /// failed over to another endpoint since it was last checked, since that node may
+    /// be running a different runtime version.
+    pub(crate) async fn refresh_after_failover(&self) -> Result<(), BasicError> {
+        let checked = self
+            .runtime
+            .read()
+            .expect("runtime lock poisoned")
+            .failovers;
+        if checked < self.rpc.failovers() {
+            self.refresh_metadata().await?;
+        }
+        Ok(())
+    }
+
+    /// Subscribe to runtime upgrades. Each time the node reports that the runtime has
+    /// been upgraded, the metadata of the new runtime is fetched and the client (and every
+    /// clone of it) switches to it, so that transactions keep being built correctly. The
+    /// stream then yields an [`UpgradeDetected`].
+    ///
+    /// Upgrades are only picked up while the stream is being polled, so a long-running
+    /// application will want to drive it in a task of its own.
+    pub async fn runtime_upgrades(
+        &self,
+    ) -> Result<
+        impl Stream<Item = Result<UpgradeDetected, BasicError>> + Send + 'static,
+        BasicError,
+    > {
+        let sub = self.rpc.subscribe_runtime_version().await?;
+        let client = self.clone();
+        Ok(sub.filter_map(move |runtime_version| {
+            let client = client.clone();
+            async move {
+                match runtime_version {
+                    Ok(runtime_version) => {
+                        client
+                            .apply_runtime_version(runtime_version)
+                            .await
+                            .transpose()
+                    }
+                    Err(e) => Some(Err(e.into())),
+                }
+            }
+        }))
+    }
+
+    /// Switch to the given runtime version if it isn't the current one.
+    async fn apply_runtime_version(
+        &self,
+        new_version: RuntimeVersion,
+    ) -> Result<Option<UpgradeDetected>, BasicError> {
+        if self.runtime_version().spec_version == new_version.spec_version {
+            return Ok(None)
+        }
+        let metadata = self
+            .metadata_for_version(new_version.spec_version, None)
+            .await?;
+
+        let mut runtime = self.runtime.write().expect("runtime lock poisoned");
+        let old_version =
+            std::mem::replace(&mut runtime.runtime_version, new_version.clone());
+        runtime.metadata = metadata;
+        log::info!(
+            "Runtime upgraded from spec version {} to {}",
+            old_version.spec_version,
+            new_version.spec_version
+        );
+        Ok(Some(UpgradeDetected {
+            old_version,
+            new_version,
+        }))
+    }
+
+    /// Returns the registry holding the metadata for every runtime version this
+    /// client knows about. It initially contains only the metadata obtained when
+    /// the client was built.
+    pub fn metadata_registry(&self) -> &MetadataRegistry {
+        &self.metadata_registry
+    }
+
+    /// Register the metadata for a runtime version ahead of time, so that it needn't
+    /// be downloaded when data from blocks produced by that runtime is handled.
+    pub fn register_metadata(&self, spec_version: u32, metadata: Metadata) {
+        self.metadata_registry.register(spec_version, metadata);
+    }
+
+    /// Returns the metadata for the runtime that was active at the given block.
+    ///
+    /// Metadata for runtime versions that haven't been seen before is fetched from the
+    /// node (or the metadata cache, if one was configured) and added to the registry.
+    pub async fn metadata_at(
+        &self,
+        block_hash: T::Hash,
+    ) -> Result<Arc<Metadata>, BasicError> {
+        let spec_version = self
+            .rpc
+            .runtime_version(Some(block_hash))
+            .await?
+            .spec_version;
+        self.metadata_for_version(spec_version, Some(block_hash))
+            .await
+    }
+
+    /// The metadata for the given runtime version, which is fetched at the given block
+    /// (or the best block) if it isn't already in the registry.
+    async fn metadata_for_version(
+        &self,
+        spec_version: u32,
+        at: Option<T::Hash>,
+    ) -> Result<Arc<Metadata>, BasicError> {
+        if let Some(metadata) = self.metadata_registry.get(spec_version) {
+            return Ok(metadata)
+        }
+
+        let metadata = match &self.metadata_cache {
+            Some(cache) => {
+                cache
+                    .fetch(&self.rpc, &self.genesis_hash, spec_version, at)
+                    .await?
+            }
+            None => {
+                match at {
+                    Some(block_hash) => self.rpc.metadata_at(block_hash).await?,
+                    None => self.rpc.metadata().await?,
+                }
+            }
+        };
+        Ok(self.metadata_registry.register(spec_version, metadata))
     }
 
     /// Returns the properties defined in the chain spec as a JSON object.
@@ -164,9 +922,238 @@ impl<T: Config> Client<T> {
         &self.rpc
     }
 
+    /// Shut down this client and every clone of it.
+    ///
+    /// Event subscriptions and transaction progress streams end, new requests fail with
+    /// [`BasicError::ShutDown`], and requests already in flight are given until `timeout`
+    /// to finish. See [`Rpc::shutdown`] for details.
+    pub async fn shutdown(&self, timeout: Duration) -> Result<(), BasicError> {
+        self.rpc.shutdown(timeout).await
+    }
+
+    /// Periodically ping the node, and report the connection as disconnected (see
+    /// [`Client::connection_events`]) if it stops responding, even though the connection
+    /// itself remains open. If the client can reconnect, the connection is then
+    /// re-established.
+    ///
+    /// The pings are sent by the returned [`KeepaliveDriver`], which must be spawned
+    /// onto whichever executor the application uses (or otherwise polled).
+    pub fn keepalive(&self, config: KeepaliveConfig) -> KeepaliveDriver {
+        KeepaliveDriver::new(self.rpc.clone(), config)
+    }
+
+    /// Returns a stream of changes to the state of the connection to the node, starting
+    /// with its current state. See [`Rpc::connection_events`].
+    pub fn connection_events(&self) -> ConnectionEvents {
+        self.rpc.connection_events()
+    }
+
+    /// Returns `true` if [`Client::shutdown`] has been called on this client or a clone of it.
+    pub fn is_shut_down(&self) -> bool {
+        self.rpc.is_shut_down()
+    }
+
+    /// The manager handing out nonces, if enabled with
+    /// [`ClientBuilder::set_nonce_manager()`].
+    pub fn nonce_manager(&self) -> Option<&NonceManager<T>> {
+        self.nonce_manager.as_deref()
+    }
+
+    /// How long to wait on a submitted transaction before giving up, if at all.
+    pub(crate) fn transaction_timeout(&self) -> Option<Duration> {
+        self.transaction_timeout
+    }
+
+    /// The parameters given to [`ClientBuilder::set_default_extra()`], or the default
+    /// parameters if none were given. Fails if the given parameters are not of the
+    /// type `P`.
+    pub(crate) fn default_extra_params<P: Default + 'static>(
+        &self,
+    ) -> Result<P, BasicError> {
+        match &self.default_extra {
+            None => Ok(P::default()),
+            Some(params) => {
+                params().downcast::<P>().map(|params| *params).map_err(|_| {
+                    BasicError::DefaultExtraMismatch {
+                        expected: std::any::type_name::<P>(),
+                    }
+                })
+            }
+        }
+    }
+
     /// Create a client for accessing runtime storage
     pub fn storage(&self) -> StorageClient<T> {
-        StorageClient::new(&self.rpc, &self.metadata, self.iter_page_size)
+        StorageClient::new(&self.rpc, self.metadata(), self.iter_page_size)
+            .set_prefetch(self.storage_prefetch)
+    }
+
+    /// Create a client for reading the given child trie.
+    pub fn child_storage(&self, child_info: &ChildInfo) -> ChildStorageClient<T> {
+        ChildStorageClient::new(&self.rpc, child_info, self.iter_page_size)
+    }
+
+    /// Create a client for fetching events, and subscribing to the events of new blocks.
+    pub fn events(&self) -> EventsClient<T> {
+        EventsClient::new(self)
+    }
+
+    /// Create a client for finding the data submitted to an Avail chain for an
+    /// application.
+    pub fn avail(&self) -> AvailClient<T>
+    where
+        T::Signature: Decode,
+    {
+        AvailClient::new(self)
+    }
+
+    /// Create a client for looking up blocks by their timestamp.
+    pub fn timestamps(&self) -> TimestampClient<T> {
+        TimestampClient::new(&self.rpc)
+    }
+
+    /// Create a client for looking up the nonce and balances of accounts.
+    pub fn accounts(&self) -> AccountsClient<T> {
+        AccountsClient::new(&self.rpc)
+    }
+
+    /// Create a client for querying the state of BABE consensus.
+    pub fn babe(&self) -> BabeClient<T> {
+        BabeClient::new(self.storage(), self.metadata())
+    }
+
+    /// Create a client for fetching blocks and subscribing to new ones, with their
+    /// extrinsics decoded.
+    pub fn blocks(&self) -> BlocksClient<T> {
+        BlocksClient::new(self)
+    }
+
+    /// Create a client for calling runtime APIs.
+    pub fn runtime_api(&self) -> RuntimeApiClient<T> {
+        RuntimeApiClient::new(&self.rpc)
+    }
+
+    /// Create a client for fetching and verifying GRANDPA finality proofs.
+    pub fn finality(&self) -> FinalityClient<T> {
+        FinalityClient::new(&self.rpc, self.storage())
+    }
+
+    /// Fetch the headers of a range of blocks, in order.
+    ///
+    /// Up to `concurrency` headers are fetched at once, and fetches which fail because
+    /// of a problem talking to the node are retried a couple of times before the error
+    /// is handed back. An error is also handed back for any block in the range which
+    /// doesn't exist.
+    pub fn headers_range(
+        &self,
+        blocks: RangeInclusive<u64>,
+        concurrency: usize,
+    ) -> impl Stream<Item = Result<T::Header, BasicError>> + Send + '_ {
+        crate::headers::range(&self.rpc, blocks, concurrency)
+    }
+
+    /// Returns a handle for working with the chain as it was at the given block.
+    ///
+    /// Data obtained through the handle is decoded using the metadata of the runtime
+    /// that was active at that block, which is looked up (or fetched) automatically, so
+    /// the handle can be used on blocks produced before a runtime upgrade.
+    pub async fn at(
+        &self,
+        block_hash: T::Hash,
+    ) -> Result<BlockClient<'_, T>, BasicError> {
+        let metadata = self.metadata_at(block_hash).await?;
+        Ok(BlockClient {
+            client: self,
+            block_hash,
+            metadata,
+        })
+    }
+
+    /// Submit an extrinsic which has already been signed (for instance by an
+    /// [`crate::OfflineClient`]), given as SCALE encoded bytes. Returns its hash.
+    pub async fn submit_signed(&self, extrinsic: Vec<u8>) -> Result<T::Hash, BasicError> {
+        self.rpc.submit_extrinsic(Encoded(extrinsic)).await
+    }
+
+    /// Like [`Client::submit_signed()`], but returns a [`TransactionProgress`] to follow
+    /// the status of the extrinsic with.
+    pub async fn submit_signed_then_watch<E, Evs>(
+        &self,
+        extrinsic: Vec<u8>,
+    ) -> Result<TransactionProgress<'_, T, E, Evs>, BasicError>
+    where
+        E: Decode + HasModuleError,
+        Evs: Decode,
+    {
+        let ext_hash = T::Hashing::hash(&extrinsic);
+        let sub = self.rpc.watch_extrinsic(Encoded(extrinsic)).await?;
+        Ok(TransactionProgress::new(sub, self, ext_hash))
+    }
+
+    /// Fetch the extrinsics which are currently in the transaction pool, along with
+    /// their hashes, decoded into their signers, nonces and calls.
+    pub async fn pending_extrinsics(
+        &self,
+    ) -> Result<Vec<(T::Hash, DecodedExtrinsic)>, BasicError> {
+        let metadata = self.metadata();
+        let pending = self.rpc.pending_extrinsics().await?;
+        pending
+            .iter()
+            .map(|xt| {
+                let decoded = extrinsic::decode_extrinsic(&metadata, &xt.0)?;
+                Ok((T::Hashing::hash(&xt.0), decoded))
+            })
+            .collect()
+    }
+
+    /// Submit a signed extrinsic, unless the very same extrinsic is already in the
+    /// transaction pool, or was included in one of the last `lookback` blocks.
+    ///
+    /// This makes it safe to retry submitting an extrinsic (for instance after a
+    /// connection error) without it being executed twice: the status of the earlier
+    /// submission is returned instead.
+    pub async fn submit_once<X: Encode>(
+        &self,
+        extrinsic: X,
+        lookback: u32,
+    ) -> Result<SubmissionStatus<T::Hash>, BasicError> {
+        let extrinsic = Encoded(extrinsic.encode());
+        let extrinsic_hash = T::Hashing::hash(&extrinsic.0);
+
+        let pending = self.rpc.pending_extrinsics().await?;
+        if pending
+            .iter()
+            .any(|xt| T::Hashing::hash(&xt.0) == extrinsic_hash)
+        {
+            return Ok(SubmissionStatus::InPool(extrinsic_hash))
+        }
+
+        let mut next_hash = self.rpc.block_hash(None).await?;
+        for _ in 0..lookback {
+            let block_hash = match next_hash {
+                Some(hash) => hash,
+                None => break,
+            };
+            let block = match self.rpc.block(Some(block_hash)).await? {
+                Some(block) => block.block,
+                None => break,
+            };
+            if block
+                .extrinsics
+                .iter()
+                .any(|xt| T::Hashing::hash_of(xt) == extrinsic_hash)
+            {
+                return Ok(SubmissionStatus::InBlock {
+                    extrinsic_hash,
+                    block_hash,
+                })
+            }
+            let number: u64 = (*block.header.number()).into();
+            next_hash = (number > 0).then(|| *block.header.parent_hash());
+        }
+
+        let hash = self.rpc.submit_extrinsic(extrinsic).await?;
+        Ok(SubmissionStatus::Submitted(hash))
     }
 
     /// Convert the client to a runtime api wrapper for custom runtime access.
@@ -178,6 +1165,155 @@ impl<T: Config> Client<T> {
     }
 }
 
+/// A handle to the chain state at a specific block, obtained from [`Client::at()`].
+pub struct BlockClient<'client, T: Config> {
+    client: &'client Client<T>,
+    block_hash: T::Hash,
+    metadata: Arc<Metadata>,
+}
+
+impl<'client, T: Config> BlockClient<'client, T> {
+    /// Returns the hash of the block this handle refers to.
+    pub fn block_hash(&self) -> T::Hash {
+        self.block_hash
+    }
+
+    /// Returns the metadata of the runtime that was active at this block.
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Create a client for accessing runtime storage, using the metadata of the
+    /// runtime that was active at this block.
+    ///
+    /// **Note:** the block hash must still be passed to the individual storage
+    /// queries; prefer [`BlockClient::fetch()`] and friends where possible.
+    pub fn storage(&self) -> StorageClient<'_, T> {
+        StorageClient::new(
+            &self.client.rpc,
+            self.metadata.clone(),
+            self.client.iter_page_size,
+        )
+        .set_prefetch(self.client.storage_prefetch)
+    }
+
+    /// Fetch a storage entry at this block.
+    pub async fn fetch<F: StorageEntry>(
+        &self,
+        store: &F,
+    ) -> Result<Option<F::Value>, BasicError> {
+        self.storage().fetch(store, Some(self.block_hash)).await
+    }
+
+    /// Fetch a storage entry at this block, falling back to the default value
+    /// given by the metadata of the runtime active at this block.
+    pub async fn fetch_or_default<F: StorageEntry>(
+        &self,
+        store: &F,
+    ) -> Result<F::Value, BasicError> {
+        self.storage()
+            .fetch_or_default(store, Some(self.block_hash))
+            .await
+    }
+
+    /// Fetch the raw encoded value under a storage key at this block.
+    pub async fn fetch_raw(
+        &self,
+        key: StorageKey,
+    ) -> Result<Option<StorageData>, BasicError> {
+        self.client.rpc.storage(&key, Some(self.block_hash)).await
+    }
+
+    /// Obtain the events emitted in this block. They are decoded using the
+    /// metadata of the runtime that was active at this block.
+    pub async fn events<Evs: Decode>(&self) -> Result<Events<'_, T, Evs>, BasicError> {
+        events::at_with_metadata(&self.client.rpc, self.metadata.clone(), self.block_hash)
+            .await
+    }
+
+    /// Fetch the header of this block.
+    pub async fn header(&self) -> Result<T::Header, BasicError> {
+        self.client
+            .rpc
+            .header(Some(self.block_hash))
+            .await?
+            .ok_or_else(|| {
+                BasicError::Other(format!("Block {:?} not found", self.block_hash))
+            })
+    }
+
+    /// Fetch the digest items in the header of this block, with the payloads of the
+    /// BABE and GRANDPA consensus engines decoded.
+    pub async fn digest(
+        &self,
+    ) -> Result<Vec<DecodedDigestItem<T::BlockNumber>>, BasicError> {
+        let header = self.header().await?;
+        Ok(digest::decode_digest(header.digest()))
+    }
+
+    /// Returns the account of the validator which authored this block.
+    ///
+    /// The author is found by decoding the BABE pre-runtime digest in the header of
+    /// the block, and looking up the authority index it contains in the validators of
+    /// the session as of the parent block, which the block was authored on top of.
+    ///
+    /// Returns `None` if the block doesn't carry a BABE pre-runtime digest, as is the
+    /// case for the genesis block.
+    pub async fn author(&self) -> Result<Option<T::AccountId>, BasicError> {
+        let header = self.header().await?;
+        let pre_digest = match BabePreDigest::find(header.digest()) {
+            Some(pre_digest) => pre_digest,
+            None => return Ok(None),
+        };
+
+        let mut validators_key = twox_128(b"Session").to_vec();
+        validators_key.extend(twox_128(b"Validators"));
+        let validators: Vec<T::AccountId> = self
+            .storage()
+            .fetch_unhashed(StorageKey(validators_key), Some(*header.parent_hash()))
+            .await?
+            .unwrap_or_default();
+        Ok(validators
+            .get(pre_digest.authority_index() as usize)
+            .cloned())
+    }
+
+    /// Fetch this block, including its header and (encoded) extrinsics.
+    pub async fn block(&self) -> Result<Option<ChainBlock<T>>, BasicError> {
+        self.client.rpc.block(Some(self.block_hash)).await
+    }
+}
+
+type TransactionStatusSubscription<T> =
+    RpcSubscription<SubstrateTransactionStatus<<T as Config>::Hash, <T as Config>::Hash>>;
+
+/// The fee that a call would pay, as estimated by [`SubmittableExtrinsic::estimate_fee()`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FeeEstimate {
+    /// The weight and class of the call, and the fee it would pay, excluding any tip.
+    pub info: RuntimeDispatchInfo,
+    /// A breakdown of the fee.
+    pub details: FeeDetails,
+}
+
+/// What would happen to a transaction if it were submitted, according to
+/// [`SubmittableExtrinsic::dry_run()`].
+#[derive(Clone, Debug)]
+pub enum DryRunOutcome {
+    /// The call would be dispatched successfully.
+    Success,
+    /// The transaction would be included in a block, and so pay its fees, but the call
+    /// would fail with the given error.
+    Failed(DispatchErrorDetails),
+}
+
+impl DryRunOutcome {
+    /// Would the call be dispatched successfully?
+    pub fn is_success(&self) -> bool {
+        matches!(self, DryRunOutcome::Success)
+    }
+}
+
 /// A constructed call ready to be signed and submitted.
 pub struct SubmittableExtrinsic<'client, T: Config, X, C, E: Decode, Evs: Decode> {
     client: &'client Client<T>,
@@ -202,6 +1338,14 @@ where
         }
     }
 
+    /// Check that the call hasn't changed in the current runtime since it was
+    /// generated, so that it will be encoded correctly. Other calls and pallets
+    /// changing doesn't matter. See [`crate::Metadata::validate_call()`].
+    pub fn validate(self) -> Result<Self, MetadataError> {
+        self.client.metadata().validate_call::<C>()?;
+        Ok(self)
+    }
+
     /// Creates and signs an extrinsic and submits it to the chain.
     ///
     /// Returns a [`TransactionProgress`], which can be used to track the status of the transaction
@@ -214,18 +1358,183 @@ where
         <<X as SignedExtra<T>>::Extra as SignedExtension>::AdditionalSigned:
             Send + Sync + 'static,
     {
-        // Sign the call data to create our extrinsic.
-        let extrinsic = self.create_signed(signer, Default::default()).await?;
+        self.sign_and_submit_then_watch_with_params(
+            signer,
+            self.client.default_extra_params()?,
+        )
+        .await
+    }
 
-        // Get a hash of the extrinsic (we'll need this later).
-        let ext_hash = T::Hashing::hash_of(&extrinsic);
+    /// Like [`SubmittableExtrinsic::sign_and_submit_then_watch()`], but with the given
+    /// additional parameters (such as the tip, or the asset to pay fees in) used to
+    /// construct the signed extra data.
+    pub async fn sign_and_submit_then_watch_with_params(
+        self,
+        signer: &(dyn Signer<T, X> + Send + Sync),
+        additional_params: X::Parameters,
+    ) -> Result<TransactionProgress<'client, T, E, Evs>, BasicError>
+    where
+        <<X as SignedExtra<T>>::Extra as SignedExtension>::AdditionalSigned:
+            Send + Sync + 'static,
+    {
+        let nonce = self.nonce(signer).await?;
+        self.sign_and_submit_then_watch_with_nonce(signer, nonce, additional_params)
+            .await
+    }
 
-        // Submit and watch for transaction progress.
-        let sub = self.client.rpc().watch_extrinsic(extrinsic).await?;
+    /// Like [`SubmittableExtrinsic::sign_and_submit_then_watch_with_params()`], but
+    /// signed with the given nonce, for submitting several extrinsics in a row.
+    pub(crate) async fn sign_and_submit_then_watch_with_nonce(
+        self,
+        signer: &(dyn Signer<T, X> + Send + Sync),
+        nonce: T::Index,
+        additional_params: X::Parameters,
+    ) -> Result<TransactionProgress<'client, T, E, Evs>, BasicError>
+    where
+        <<X as SignedExtra<T>>::Extra as SignedExtension>::AdditionalSigned:
+            Send + Sync + 'static,
+    {
+        // Sign the call data to create our extrinsic, then submit it and watch for
+        // transaction progress.
+        let (sub, ext_hash) = self
+            .submit_and_watch(signer, nonce, additional_params)
+            .await?;
 
         Ok(TransactionProgress::new(sub, self.client, ext_hash))
     }
 
+    /// Like [`SubmittableExtrinsic::sign_and_submit_then_watch()`], but the transaction is
+    /// signed and submitted again according to the given policy if it's `Dropped` or
+    /// `Usurped`, or stays in the `Future` queue for too long. The returned progress
+    /// carries on with the resubmitted transaction as if nothing happened.
+    ///
+    /// `params` provides the additional parameters used to construct the signed extra
+    /// data for each attempt, given the number of resubmissions so far. This allows for
+    /// the tip to be raised with each resubmission, which is needed for a resubmitted
+    /// transaction to replace the original in the pool.
+    pub async fn sign_and_submit_then_watch_with_resubmit<P>(
+        self,
+        signer: &'client (dyn Signer<T, X> + Send + Sync),
+        policy: ResubmitPolicy,
+        params: P,
+    ) -> Result<TransactionProgress<'client, T, E, Evs>, BasicError>
+    where
+        P: Fn(u32) -> X::Parameters + Send + Sync + 'client,
+        X: Send + Sync + 'client,
+        C: 'client,
+        E: Send + Sync + 'client,
+        Evs: Send + Sync + 'client,
+        <<X as SignedExtra<T>>::Extra as SignedExtension>::AdditionalSigned:
+            Send + Sync + 'static,
+    {
+        let nonce = self.nonce(signer).await?;
+        let (sub, ext_hash) = self.submit_and_watch(signer, nonce, params(0)).await?;
+        let client = self.client;
+
+        let this = Arc::new(self);
+        let params = Arc::new(params);
+        let nonce = Arc::new(std::sync::Mutex::new(nonce));
+        let resubmit = Box::new(move |resubmissions: u32, refresh_nonce: bool| {
+            let (this, params, nonce) = (this.clone(), params.clone(), nonce.clone());
+            async move {
+                let current = if refresh_nonce {
+                    let fresh = this.fresh_nonce(signer).await?;
+                    *nonce.lock().expect("nonce lock poisoned") = fresh;
+                    fresh
+                } else {
+                    *nonce.lock().expect("nonce lock poisoned")
+                };
+                this.submit_and_watch(signer, current, params(resubmissions))
+                    .await
+            }
+            .boxed()
+        });
+        Ok(TransactionProgress::new(sub, client, ext_hash)
+            .with_resubmit(policy, resubmit))
+    }
+
+    /// Replace a transaction of the signer which is stuck in the transaction pool with
+    /// this call, signed with the same nonce and the given additional parameters. For
+    /// the pool to accept the replacement, these have to carry a higher tip than the
+    /// original had (the call may be the same one, or a different one).
+    ///
+    /// Returns a [`TransactionProgress`] following the replacement. Anyone watching
+    /// the original transaction will see it `Usurped` by the replacement; if the
+    /// original makes it into a block first, the replacement becomes invalid instead
+    /// (it has a stale nonce), which the progress reports as usual. An error is
+    /// returned if the original is no longer in the pool of the node.
+    pub async fn replace_with_higher_tip(
+        self,
+        signer: &(dyn Signer<T, X> + Send + Sync),
+        replaced: T::Hash,
+        additional_params: X::Parameters,
+    ) -> Result<TransactionProgress<'client, T, E, Evs>, BasicError>
+    where
+        <<X as SignedExtra<T>>::Extra as SignedExtension>::AdditionalSigned:
+            Send + Sync + 'static,
+    {
+        let pending = self.client.rpc().pending_extrinsics().await?;
+        let original = pending
+            .iter()
+            .find(|ext| T::Hashing::hash(&ext.0) == replaced)
+            .ok_or_else(|| {
+                BasicError::Other(format!(
+                    "Transaction {:?} is not in the transaction pool",
+                    replaced
+                ))
+            })?;
+        let nonce = extrinsic::signed_nonce(&self.client.metadata(), &original.0)?
+            .ok_or_else(|| {
+                BasicError::Other(format!(
+                    "Transaction {:?} has no nonce to replace it with",
+                    replaced
+                ))
+            })?;
+        let nonce = T::Index::try_from(nonce)
+            .map_err(|_| BasicError::Other(format!("Nonce {} out of range", nonce)))?;
+
+        let (sub, ext_hash) = self
+            .submit_and_watch(signer, nonce, additional_params)
+            .await?;
+        Ok(TransactionProgress::new(sub, self.client, ext_hash))
+    }
+
+    /// Sign the call with the given nonce, submit it, and watch its progress.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "submit_and_watch",
+            skip_all,
+            fields(nonce = ?nonce, ext_hash = tracing::field::Empty)
+        )
+    )]
+    async fn submit_and_watch(
+        &self,
+        signer: &(dyn Signer<T, X> + Send + Sync),
+        nonce: T::Index,
+        additional_params: X::Parameters,
+    ) -> Result<(TransactionStatusSubscription<T>, T::Hash), BasicError>
+    where
+        <<X as SignedExtra<T>>::Extra as SignedExtension>::AdditionalSigned:
+            Send + Sync + 'static,
+    {
+        let extrinsic = self
+            .create_signed_with_nonce(signer, nonce, additional_params)
+            .await?;
+        let ext_hash = T::Hashing::hash_of(&extrinsic);
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("ext_hash", &tracing::field::debug(&ext_hash));
+        let sub = match self.client.rpc().watch_extrinsic(extrinsic).await {
+            Ok(sub) => sub,
+            Err(e) => {
+                self.resync_nonce(signer).await;
+                return Err(e)
+            }
+        };
+        self.log_submission(&ext_hash, &nonce);
+        Ok((sub, ext_hash))
+    }
+
     /// Creates and signs an extrinsic and submits to the chain for block inclusion.
     ///
     /// Returns `Ok` with the extrinsic hash if it is valid extrinsic.
@@ -242,10 +1551,204 @@ where
         <<X as SignedExtra<T>>::Extra as SignedExtension>::AdditionalSigned:
             Send + Sync + 'static,
     {
-        let extrinsic = self.create_signed(signer, Default::default()).await?;
+        self.sign_and_submit_with_params(signer, self.client.default_extra_params()?)
+            .await
+    }
+
+    /// Like [`SubmittableExtrinsic::sign_and_submit()`], but with the given additional
+    /// parameters (such as the tip, or the asset to pay fees in) used to construct the
+    /// signed extra data.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "sign_and_submit",
+            skip_all,
+            fields(nonce = tracing::field::Empty, ext_hash = tracing::field::Empty)
+        )
+    )]
+    pub async fn sign_and_submit_with_params(
+        self,
+        signer: &(dyn Signer<T, X> + Send + Sync),
+        additional_params: X::Parameters,
+    ) -> Result<T::Hash, BasicError>
+    where
+        <<X as SignedExtra<T>>::Extra as SignedExtension>::AdditionalSigned:
+            Send + Sync + 'static,
+    {
+        let nonce = self.nonce(signer).await?;
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("nonce", &tracing::field::debug(&nonce));
+        let extrinsic = self
+            .create_signed_with_nonce(signer, nonce, additional_params)
+            .await?;
+        let ext_hash = match self.client.rpc().submit_extrinsic(extrinsic).await {
+            Ok(ext_hash) => {
+                #[cfg(feature = "tracing")]
+                tracing::Span::current()
+                    .record("ext_hash", &tracing::field::debug(&ext_hash));
+                ext_hash
+            }
+            Err(e) => {
+                self.resync_nonce(signer).await;
+                return Err(e)
+            }
+        };
+        self.log_submission(&ext_hash, &nonce);
+        Ok(ext_hash)
+    }
+
+    /// Sign the call and dry run it against the state at the given block, or the best
+    /// block if `None`, without submitting it, to find out whether it would be valid and
+    /// dispatch successfully.
+    ///
+    /// Returns an `Invalid` error if the transaction would be rejected (for instance
+    /// because the signer can't pay the fees). Otherwise, the [`DryRunOutcome`] says
+    /// whether the call would be dispatched successfully, and if not, why it would fail.
+    ///
+    /// Unless the signer was given a nonce, the nonce of its account in that block's
+    /// state is used, ignoring any of its transactions in the transaction pool.
+    /// Nothing is submitted, so no fees are paid and the signer's nonce isn't used up.
+    ///
+    /// **Note:** The node doesn't reveal the events that the call would emit, only
+    /// whether it would succeed. This relies on the `system_dryRun` RPC method, which
+    /// is not available on nodes that only expose safe RPC methods.
+    pub async fn dry_run(
+        &self,
+        signer: &(dyn Signer<T, X> + Send + Sync),
+        at: Option<T::Hash>,
+    ) -> Result<DryRunOutcome, Error<E>>
+    where
+        <<X as SignedExtra<T>>::Extra as SignedExtension>::AdditionalSigned:
+            Send + Sync + 'static,
+    {
+        self.dry_run_with_params(signer, self.client.default_extra_params()?, at)
+            .await
+    }
+
+    /// Like [`SubmittableExtrinsic::dry_run()`], but with the given additional parameters
+    /// used to construct the signed extra data.
+    pub async fn dry_run_with_params(
+        &self,
+        signer: &(dyn Signer<T, X> + Send + Sync),
+        additional_params: X::Parameters,
+        at: Option<T::Hash>,
+    ) -> Result<DryRunOutcome, Error<E>>
+    where
+        <<X as SignedExtra<T>>::Extra as SignedExtension>::AdditionalSigned:
+            Send + Sync + 'static,
+    {
+        // The nonce of the signer in the state that the call is run against, rather than
+        // the next one counting transactions in the pool, which the dry run would reject
+        // as being in the future.
+        let nonce = match signer.nonce() {
+            Some(nonce) => nonce,
+            None => {
+                self.client
+                    .accounts()
+                    .nonce(signer.account_id(), at)
+                    .await?
+            }
+        };
+        let extrinsic = self
+            .create_signed_with_nonce(signer, nonce, additional_params)
+            .await?;
+        let result_bytes = self.client.rpc().dry_run(&extrinsic.encode(), at).await?;
+        // An `ApplyExtrinsicResult`, which is a `Result<Result<(), DispatchError>,
+        // TransactionValidityError>`. The `DispatchError` is decoded with the metadata,
+        // so that the details of any module error can be looked up.
+        let input = &mut &*result_bytes.0;
+        match u8::decode(input)? {
+            0 => {}
+            1 => return Err(Error::Invalid(TransactionValidityError::decode(input)?)),
+            _ => return Err(codec::Error::from("Invalid ApplyExtrinsicResult").into()),
+        }
+        match u8::decode(input)? {
+            0 => Ok(DryRunOutcome::Success),
+            1 => {
+                let details =
+                    DispatchErrorDetails::decode(&self.client.metadata(), input)?;
+                Ok(DryRunOutcome::Failed(details))
+            }
+            _ => Err(codec::Error::from("Invalid ApplyExtrinsicResult").into()),
+        }
+    }
+
+    /// Sign the call and ask the node what fee it would pay if it were submitted now,
+    /// without submitting it. The fee depends on the length of the signed extrinsic, and
+    /// so on the size of the call's arguments.
+    pub async fn estimate_fee(
+        &self,
+        signer: &(dyn Signer<T, X> + Send + Sync),
+    ) -> Result<FeeEstimate, BasicError>
+    where
+        <<X as SignedExtra<T>>::Extra as SignedExtension>::AdditionalSigned:
+            Send + Sync + 'static,
+    {
+        self.estimate_fee_with_params(signer, self.client.default_extra_params()?)
+            .await
+    }
+
+    /// Like [`SubmittableExtrinsic::estimate_fee()`], but with the given additional
+    /// parameters used to construct the signed extra data. Note that the node doesn't
+    /// report the tip as part of the fee.
+    pub async fn estimate_fee_with_params(
+        &self,
+        signer: &(dyn Signer<T, X> + Send + Sync),
+        additional_params: X::Parameters,
+    ) -> Result<FeeEstimate, BasicError>
+    where
+        <<X as SignedExtra<T>>::Extra as SignedExtension>::AdditionalSigned:
+            Send + Sync + 'static,
+    {
+        let nonce = self.peek_nonce(signer).await?;
+        let extrinsic = self
+            .create_signed_with_nonce(signer, nonce, additional_params)
+            .await?
+            .encode();
+        let rpc = self.client.rpc();
+        let (info, details) = future::try_join(
+            rpc.payment_query_info(&extrinsic, None),
+            rpc.payment_query_fee_details(&extrinsic, None),
+        )
+        .await?;
+        Ok(FeeEstimate { info, details })
+    }
+
+    /// Creates an unsigned extrinsic from the call.
+    ///
+    /// The runtime only accepts calls without a signature if the pallet validates them
+    /// itself (through `ValidateUnsigned`), such as `ImOnline::heartbeat`.
+    pub fn create_unsigned(&self) -> Result<UncheckedExtrinsic<T, X>, BasicError> {
+        Ok(extrinsic::create_unsigned::<T, X>(self.encoded()?))
+    }
+
+    /// The call data, prefixed with the pallet and call indices of the current runtime.
+    ///
+    /// This is what [`crate::BatchCall`] takes, so that calls of different types can be
+    /// dispatched together.
+    pub fn encoded(&self) -> Result<Encoded, BasicError> {
+        self.call.encode_with_metadata(&self.client.metadata())
+    }
+
+    /// Submits the call as an unsigned extrinsic; see
+    /// [`SubmittableExtrinsic::create_unsigned()`]. Returns the extrinsic hash once it
+    /// has been accepted into the transaction pool.
+    pub async fn submit_unsigned(self) -> Result<T::Hash, BasicError> {
+        let extrinsic = self.create_unsigned()?;
         self.client.rpc().submit_extrinsic(extrinsic).await
     }
 
+    /// Like [`SubmittableExtrinsic::submit_unsigned()`], but returns a
+    /// [`TransactionProgress`] to follow the status of the extrinsic with.
+    pub async fn submit_unsigned_then_watch(
+        self,
+    ) -> Result<TransactionProgress<'client, T, E, Evs>, BasicError> {
+        let extrinsic = self.create_unsigned()?;
+        let ext_hash = T::Hashing::hash_of(&extrinsic);
+        let sub = self.client.rpc().watch_extrinsic(extrinsic).await?;
+        Ok(TransactionProgress::new(sub, self.client, ext_hash))
+    }
+
     /// Creates a signed extrinsic.
     pub async fn create_signed(
         &self,
@@ -256,22 +1759,96 @@ where
         <<X as SignedExtra<T>>::Extra as SignedExtension>::AdditionalSigned:
             Send + Sync + 'static,
     {
-        let account_nonce = if let Some(nonce) = signer.nonce() {
-            nonce
-        } else {
-            self.client
-                .rpc()
-                .system_account_next_index(signer.account_id())
-                .await?
-        };
-        let call = self
-            .client
-            .metadata()
-            .pallet(C::PALLET)
-            .and_then(|pallet| pallet.encode_call(&self.call))?;
-
-        let signed = extrinsic::create_signed(
-            &self.client.runtime_version,
+        let account_nonce = self.nonce(signer).await?;
+        self.create_signed_with_nonce(signer, account_nonce, additional_params)
+            .await
+    }
+
+    /// The nonce to sign with: the one the signer was given, or else one handed out by
+    /// the nonce manager, or else the next one according to the node.
+    async fn nonce(
+        &self,
+        signer: &(dyn Signer<T, X> + Send + Sync),
+    ) -> Result<T::Index, BasicError> {
+        let rpc = self.client.rpc();
+        match (signer.nonce(), &self.client.nonce_manager) {
+            (Some(nonce), _) => Ok(nonce),
+            (None, Some(manager)) => manager.next(rpc, signer.account_id()).await,
+            (None, None) => rpc.system_account_next_index(signer.account_id()).await,
+        }
+    }
+
+    /// Like `nonce()`, but without using up a nonce from the nonce manager, for
+    /// extrinsics which won't be submitted.
+    async fn peek_nonce(
+        &self,
+        signer: &(dyn Signer<T, X> + Send + Sync),
+    ) -> Result<T::Index, BasicError> {
+        let rpc = self.client.rpc();
+        match (signer.nonce(), &self.client.nonce_manager) {
+            (Some(nonce), _) => Ok(nonce),
+            (None, Some(manager)) => manager.peek(rpc, signer.account_id()).await,
+            (None, None) => rpc.system_account_next_index(signer.account_id()).await,
+        }
+    }
+
+    /// The next nonce of the signer according to the node, ignoring any nonce that the
+    /// signer was given.
+    async fn fresh_nonce(
+        &self,
+        signer: &(dyn Signer<T, X> + Send + Sync),
+    ) -> Result<T::Index, BasicError> {
+        let rpc = self.client.rpc();
+        match &self.client.nonce_manager {
+            Some(manager) => {
+                manager.resync(signer.account_id()).await;
+                manager.next(rpc, signer.account_id()).await
+            }
+            None => rpc.system_account_next_index(signer.account_id()).await,
+        }
+    }
+
+    /// After a failed submission, the nonce handed out for it may or may not have been
+    /// used, so have the nonce manager fetch it from the node again.
+    async fn resync_nonce(&self, signer: &(dyn Signer<T, X> + Send + Sync)) {
+        if let (None, Some(manager)) = (signer.nonce(), &self.client.nonce_manager) {
+            manager.resync(signer.account_id()).await;
+        }
+    }
+
+    async fn create_signed_with_nonce(
+        &self,
+        signer: &(dyn Signer<T, X> + Send + Sync),
+        account_nonce: T::Index,
+        additional_params: X::Parameters,
+    ) -> Result<UncheckedExtrinsic<T, X>, BasicError>
+    where
+        <<X as SignedExtra<T>>::Extra as SignedExtension>::AdditionalSigned:
+            Send + Sync + 'static,
+    {
+        self.client.refresh_after_failover().await?;
+        let (metadata, runtime_version) = self.client.current_runtime();
+        let call = self.call.encode_with_metadata(&metadata)?;
+
+        if let Some(period) = X::mortal_period(&additional_params) {
+            let (era, checkpoint) = self.mortal_era(period).await?;
+            return extrinsic::create_signed_mortal(
+                &metadata,
+                &runtime_version,
+                self.client.genesis_hash,
+                era,
+                checkpoint,
+                account_nonce,
+                call,
+                signer,
+                additional_params,
+            )
+            .await
+        }
+
+        let signed = extrinsic::create_signed_with_metadata(
+            &metadata,
+            &runtime_version,
             self.client.genesis_hash,
             account_nonce,
             call,
@@ -281,4 +1858,354 @@ where
         .await?;
         Ok(signed)
     }
+
+    /// An era of roughly `period` blocks starting from the current best block, along
+    /// with the hash of the block that it starts at.
+    async fn mortal_era(&self, period: u64) -> Result<(Era, T::Hash), BasicError> {
+        let rpc = self.client.rpc();
+        let best = rpc
+            .header(None)
+            .await?
+            .ok_or_else(|| BasicError::Other("Best block header not found".into()))?;
+        let current = (*best.number()).into();
+        let era = Era::mortal(period, current);
+        let birth = era.birth(current);
+        let checkpoint = rpc
+            .block_hash(Some(birth.into()))
+            .await?
+            .ok_or_else(|| BasicError::Other(format!("Block {} not found", birth)))?;
+        Ok((era, checkpoint))
+    }
+
+    fn log_submission(&self, ext_hash: &T::Hash, nonce: &T::Index) {
+        if let Some(logging) = &self.client.submission_logging {
+            logging.log(&self.client.metadata(), &self.call, ext_hash, nonce);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        avail::AvailExtraParameters,
+        extrinsic::AssetTxPaymentParameters,
+        DefaultConfig,
+    };
+    use frame_metadata::v14::{
+        ExtrinsicMetadata,
+        RuntimeMetadataLastVersion,
+    };
+    use scale_info::meta_type;
+    use sp_core::H256;
+    use sp_runtime::{
+        AccountId32,
+        Digest,
+        DigestItem,
+    };
+    use std::time::Instant;
+
+    async fn mock_client() -> (MockRpcClient, Client<DefaultConfig>) {
+        let extrinsic = ExtrinsicMetadata {
+            ty: meta_type::<()>(),
+            version: 4,
+            signed_extensions: vec![],
+        };
+        // The runtime type is only there to have the `DispatchError` in the registry.
+        let v14 = RuntimeMetadataLastVersion::new(
+            vec![],
+            extrinsic,
+            meta_type::<sp_runtime::DispatchError>(),
+        );
+        let mock = MockRpcClient::new();
+        mock.set_metadata(&v14.into());
+        mock.set_genesis_hash(H256::zero());
+        mock.set_runtime_version(&RuntimeVersion {
+            spec_version: 1,
+            transaction_version: 1,
+            other: Default::default(),
+        });
+        let client = ClientBuilder::new()
+            .set_mock_client(mock.clone())
+            .build()
+            .await
+            .unwrap();
+        (mock, client)
+    }
+
+    #[async_std::test]
+    async fn connecting_is_retried_until_the_attempts_run_out() {
+        let mut calls = 0;
+        let started = Instant::now();
+        let result: Result<(), _> =
+            connect_with_retry(Some((3, Duration::from_millis(20))), || {
+                calls += 1;
+                future::ready(Err(BasicError::Other("not ready".into())))
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(calls, 3);
+        // 20ms after the first attempt, then 40ms after the second.
+        assert!(started.elapsed() >= Duration::from_millis(60));
+    }
+
+    #[test]
+    fn the_connect_backoff_doubles_up_to_a_cap() {
+        let backoff = Duration::from_secs(1);
+        let waits: Vec<_> = (1..=7).map(|n| connect_backoff(backoff, n)).collect();
+        assert_eq!(
+            waits,
+            [1, 2, 4, 8, 16, 30, 30].map(Duration::from_secs).to_vec()
+        );
+        assert_eq!(connect_backoff(backoff, 100), MAX_CONNECT_BACKOFF);
+
+        // A backoff longer than the cap is kept as it is.
+        let backoff = Duration::from_secs(60);
+        assert_eq!(connect_backoff(backoff, 1), backoff);
+        assert_eq!(connect_backoff(backoff, 3), backoff);
+    }
+
+    #[async_std::test]
+    async fn connecting_stops_once_it_succeeds() {
+        let mut calls = 0;
+        let result = connect_with_retry(Some((5, Duration::from_millis(1))), || {
+            calls += 1;
+            future::ready(
+                if calls < 2 {
+                    Err(BasicError::Other("not ready".into()))
+                } else {
+                    Ok(calls)
+                },
+            )
+        })
+        .await;
+        assert_eq!(result.unwrap(), 2);
+    }
+
+    #[async_std::test]
+    async fn the_author_is_found_in_the_validators_of_the_parent_block() {
+        let (mock, client) = mock_client().await;
+        let parent_hash = H256::repeat_byte(1);
+        let block_hash = H256::repeat_byte(2);
+        let pre_digest = BabePreDigest::SecondaryPlain {
+            authority_index: 1,
+            slot: 0,
+        };
+        let header = <DefaultConfig as Config>::Header::new(
+            2,
+            Default::default(),
+            Default::default(),
+            parent_hash,
+            Digest {
+                logs: vec![DigestItem::PreRuntime(
+                    digest::BABE_ENGINE_ID,
+                    pre_digest.encode(),
+                )],
+            },
+        );
+        mock.set_header(block_hash, &header);
+
+        // The block may itself start a new session, with a different set of validators.
+        let mut validators_key = twox_128(b"Session").to_vec();
+        validators_key.extend(twox_128(b"Validators"));
+        let validators_key = serde_json::to_value(StorageKey(validators_key)).unwrap();
+        let parent_validators =
+            vec![AccountId32::new([1; 32]), AccountId32::new([2; 32])];
+        let new_validators = vec![AccountId32::new([3; 32]), AccountId32::new([4; 32])];
+        mock.set_response_for(
+            "state_getStorage",
+            vec![
+                validators_key.clone(),
+                serde_json::to_value(parent_hash).unwrap(),
+            ],
+            StorageData(parent_validators.encode()),
+        );
+        mock.set_response_for(
+            "state_getStorage",
+            vec![validators_key, serde_json::to_value(block_hash).unwrap()],
+            StorageData(new_validators.encode()),
+        );
+
+        let author = client.at(block_hash).await.unwrap().author().await.unwrap();
+        assert_eq!(author, Some(AccountId32::new([2; 32])));
+    }
+
+    #[derive(Encode)]
+    struct Remark;
+
+    impl Call for Remark {
+        const PALLET: &'static str = "System";
+        const FUNCTION: &'static str = "remark";
+
+        fn encode_with_metadata(&self, _: &Metadata) -> Result<Encoded, BasicError> {
+            Ok(Encoded(vec![0, 1, 0]))
+        }
+    }
+
+    #[derive(Debug, Decode)]
+    struct TestError;
+
+    impl HasModuleError for TestError {
+        fn module_error_indices(&self) -> Option<(u8, u8)> {
+            None
+        }
+    }
+
+    #[async_std::test]
+    async fn dry_runs_decode_the_result() {
+        use sp_runtime::{
+            transaction_validity::InvalidTransaction,
+            ApplyExtrinsicResult,
+            DispatchError,
+        };
+
+        let (mock, client) = mock_client().await;
+        let mut signer = crate::PairSigner::<
+            DefaultConfig,
+            crate::DefaultExtra<DefaultConfig>,
+            _,
+        >::new(sp_keyring::AccountKeyring::Alice.pair());
+        signer.set_nonce(0);
+        let remark = SubmittableExtrinsic::<
+            DefaultConfig,
+            crate::DefaultExtra<DefaultConfig>,
+            _,
+            TestError,
+            (),
+        >::new(&client, Remark);
+
+        let results: Vec<ApplyExtrinsicResult> = vec![
+            Ok(Ok(())),
+            Ok(Err(DispatchError::BadOrigin)),
+            Err(InvalidTransaction::Payment.into()),
+        ];
+        for result in results {
+            mock.push_response("system_dryRun", sp_core::Bytes(result.encode()));
+        }
+
+        assert!(remark.dry_run(&signer, None).await.unwrap().is_success());
+        match remark.dry_run(&signer, None).await.unwrap() {
+            DryRunOutcome::Failed(DispatchErrorDetails::Other(names)) => {
+                assert_eq!(names, ["BadOrigin"])
+            }
+            outcome => panic!("Unexpected outcome: {:?}", outcome),
+        }
+        assert!(matches!(
+            remark.dry_run(&signer, None).await,
+            Err(Error::Invalid(TransactionValidityError::Invalid(
+                InvalidTransaction::Payment
+            )))
+        ));
+        assert!(mock
+            .requests()
+            .iter()
+            .all(|request| request.method != "author_submitExtrinsic"));
+    }
+
+    #[async_std::test]
+    async fn dry_runs_use_the_nonce_at_the_given_block() {
+        let (mock, client) = mock_client().await;
+        let signer = crate::PairSigner::<
+            DefaultConfig,
+            crate::DefaultExtra<DefaultConfig>,
+            _,
+        >::new(sp_keyring::AccountKeyring::Alice.pair());
+        let remark = SubmittableExtrinsic::<
+            DefaultConfig,
+            crate::DefaultExtra<DefaultConfig>,
+            _,
+            TestError,
+            (),
+        >::new(&client, Remark);
+        let old_block = H256::repeat_byte(1);
+
+        // The pool holds transactions of the signer which the old block hasn't seen.
+        mock.set_response("system_accountNextIndex", 7u32);
+        let account_id = signer.account_id().encode();
+        let mut account_key = twox_128(b"System").to_vec();
+        account_key.extend(twox_128(b"Account"));
+        account_key.extend(sp_core::blake2_128(&account_id));
+        account_key.extend(account_id);
+        let account_key = StorageKey(account_key);
+        mock.set_storage(&account_key, Some(3u32.encode()));
+        mock.push_response(
+            "system_dryRun",
+            sp_core::Bytes(sp_runtime::ApplyExtrinsicResult::Ok(Ok(())).encode()),
+        );
+
+        let outcome = remark.dry_run(&signer, Some(old_block)).await.unwrap();
+        assert!(outcome.is_success());
+        let requests = mock.requests();
+        assert!(requests
+            .iter()
+            .all(|request| request.method != "system_accountNextIndex"));
+        assert!(requests.iter().any(|request| {
+            request.method == "state_getStorage"
+                && request.params == serde_json::json!([account_key, old_block])
+        }));
+        assert!(requests.iter().any(|request| {
+            request.method == "system_dryRun"
+                && request.params[1] == serde_json::json!(old_block)
+        }));
+    }
+
+    #[async_std::test]
+    async fn default_extra_params_of_another_type_are_an_error() {
+        let (mock, client) = mock_client().await;
+        assert_eq!(
+            client
+                .default_extra_params::<AvailExtraParameters>()
+                .unwrap(),
+            AvailExtraParameters::new()
+        );
+
+        let params = AvailExtraParameters::new().set_app_id(7);
+        let client = ClientBuilder::new()
+            .set_mock_client(mock)
+            .set_default_extra(params.clone())
+            .build::<DefaultConfig>()
+            .await
+            .unwrap();
+        assert_eq!(
+            client
+                .default_extra_params::<AvailExtraParameters>()
+                .unwrap(),
+            params
+        );
+        assert!(matches!(
+            client.default_extra_params::<AssetTxPaymentParameters>(),
+            Err(BasicError::DefaultExtraMismatch { .. })
+        ));
+    }
+
+    #[async_std::test]
+    async fn the_runtime_is_refreshed_after_failing_over() {
+        let (mock, client) = mock_client().await;
+        let version_requests = || {
+            mock.requests()
+                .iter()
+                .filter(|request| request.method == "state_getRuntimeVersion")
+                .count()
+        };
+        let before = version_requests();
+
+        // Without a failover, the node isn't asked again.
+        client.refresh_after_failover().await.unwrap();
+        assert_eq!(version_requests(), before);
+
+        // The node failed over to runs a newer runtime.
+        mock.set_runtime_version(&RuntimeVersion {
+            spec_version: 2,
+            transaction_version: 1,
+            other: Default::default(),
+        });
+        client.rpc().failed_over("ws://127.0.0.1:9945");
+        client.refresh_after_failover().await.unwrap();
+        assert_eq!(client.runtime_version().spec_version, 2);
+
+        // Each failover is only acted on once.
+        let after = version_requests();
+        client.refresh_after_failover().await.unwrap();
+        assert_eq!(version_requests(), after);
+    }
 }
@@ -0,0 +1,361 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Working with several networks through one handle.
+
+use crate::{
+    avail::AvailExtraParameters,
+    error::BasicError,
+    Client,
+    ClientBuilder,
+    Config,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use sp_core::{
+    crypto::{
+        Ss58AddressFormat,
+        Ss58Codec,
+    },
+    Pair,
+};
+use std::{
+    collections::{
+        BTreeMap,
+        HashMap,
+    },
+    path::Path,
+    sync::Mutex,
+};
+
+/// Where the secret for a network's signer comes from.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignerSource {
+    /// A secret URI, such as `//Alice` or a mnemonic phrase.
+    Suri(String),
+    /// The name of an environment variable holding a secret URI.
+    Env(String),
+}
+
+/// The details needed to work with a single network.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkProfile {
+    /// The URL of the node to connect to.
+    pub url: String,
+    /// The SS58 address format used by the network; see
+    /// [`ClientManager::format_address`].
+    #[serde(default)]
+    pub ss58_prefix: Option<u16>,
+    /// The application ID to submit data under, if any. This is given to the network's
+    /// client as its default extra parameters (see
+    /// [`ClientBuilder::set_default_extra`]), and so the network's runtime must take
+    /// [`AvailExtraParameters`].
+    #[serde(default)]
+    pub app_id: Option<u32>,
+    /// Where to find the secret for signing transactions, if anywhere.
+    #[serde(default)]
+    pub signer: Option<SignerSource>,
+}
+
+/// A set of named network profiles, one of which is the default.
+///
+/// In a file, these look like:
+///
+/// ```json
+/// {
+///     "default": "local",
+///     "networks": {
+///         "local": { "url": "ws://127.0.0.1:9944", "signer": { "suri": "//Alice" } },
+///         "testnet": { "url": "wss://testnet.example", "ss58_prefix": 42, "app_id": 1 }
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkProfiles {
+    /// The name of the network to use by default.
+    pub default: String,
+    /// The profile of each network, by name.
+    pub networks: BTreeMap<String, NetworkProfile>,
+}
+
+impl NetworkProfiles {
+    /// Read profiles from a JSON file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, BasicError> {
+        let bytes = std::fs::read(path)?;
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+}
+
+/// Builds and caches a [`Client`] for each of a number of named networks, and keeps
+/// track of which one is currently in use.
+pub struct ClientManager<T: Config> {
+    profiles: NetworkProfiles,
+    current: Mutex<String>,
+    clients: futures::lock::Mutex<HashMap<String, Client<T>>>,
+}
+
+impl<T: Config> std::fmt::Debug for ClientManager<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClientManager")
+            .field("profiles", &self.profiles)
+            .field("current", &self.current_network())
+            .finish()
+    }
+}
+
+impl<T: Config> ClientManager<T> {
+    /// Create a manager for the given networks, starting with the default one.
+    pub fn new(profiles: NetworkProfiles) -> Result<Self, BasicError> {
+        if !profiles.networks.contains_key(&profiles.default) {
+            return Err(unknown_network(&profiles.default))
+        }
+        Ok(Self {
+            current: Mutex::new(profiles.default.clone()),
+            profiles,
+            clients: Default::default(),
+        })
+    }
+
+    /// Create a manager for the networks described in a JSON file.
+    /// See [`NetworkProfiles`] for the format.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, BasicError> {
+        Self::new(NetworkProfiles::from_file(path)?)
+    }
+
+    /// The names of the networks that this manager knows about.
+    pub fn networks(&self) -> impl Iterator<Item = &str> {
+        self.profiles.networks.keys().map(|name| name.as_str())
+    }
+
+    /// The profile of the given network.
+    pub fn profile(&self, network: &str) -> Result<&NetworkProfile, BasicError> {
+        self.profiles
+            .networks
+            .get(network)
+            .ok_or_else(|| unknown_network(network))
+    }
+
+    /// The name of the network currently in use.
+    pub fn current_network(&self) -> String {
+        self.current
+            .lock()
+            .expect("client manager lock poisoned")
+            .clone()
+    }
+
+    /// Use a different network from now on.
+    pub fn switch(&self, network: &str) -> Result<(), BasicError> {
+        self.profile(network)?;
+        *self.current.lock().expect("client manager lock poisoned") = network.to_owned();
+        Ok(())
+    }
+
+    /// A client for the given network. This connects to the network the first time
+    /// it's asked for, and hands back the same client after that.
+    pub async fn client(&self, network: &str) -> Result<Client<T>, BasicError> {
+        let profile = self.profile(network)?;
+        let mut clients = self.clients.lock().await;
+        if let Some(client) = clients.get(network) {
+            return Ok(client.clone())
+        }
+        let client: Client<T> = builder(profile).build().await?;
+        clients.insert(network.to_owned(), client.clone());
+        Ok(client)
+    }
+
+    /// A client for the network currently in use.
+    pub async fn current_client(&self) -> Result<Client<T>, BasicError> {
+        self.client(&self.current_network()).await
+    }
+
+    /// The key pair to sign transactions with on the given network, if its profile
+    /// says where to find one.
+    pub fn signer<P: Pair>(&self, network: &str) -> Result<Option<P>, BasicError> {
+        let suri = match &self.profile(network)?.signer {
+            None => return Ok(None),
+            Some(SignerSource::Suri(suri)) => suri.clone(),
+            Some(SignerSource::Env(var)) => {
+                std::env::var(var).map_err(|e| {
+                    BasicError::Other(format!(
+                        "Can't read the signer for {} from ${}: {}",
+                        network, var, e
+                    ))
+                })?
+            }
+        };
+        Ok(Some(P::from_string(&suri, None)?))
+    }
+
+    /// Format the account as an SS58 address in the format of the given network, or in
+    /// the default format if its profile doesn't give one.
+    pub fn format_address<A: Ss58Codec>(
+        &self,
+        network: &str,
+        account: &A,
+    ) -> Result<String, BasicError> {
+        Ok(match self.profile(network)?.ss58_prefix {
+            Some(prefix) => {
+                account.to_ss58check_with_version(Ss58AddressFormat::custom(prefix))
+            }
+            None => account.to_ss58check(),
+        })
+    }
+}
+
+/// A builder for the client of the network with the given profile.
+fn builder(profile: &NetworkProfile) -> ClientBuilder {
+    let builder = ClientBuilder::new().set_url(profile.url.clone());
+    match profile.app_id {
+        Some(app_id) => {
+            builder.set_default_extra(AvailExtraParameters::new().set_app_id(app_id))
+        }
+        None => builder,
+    }
+}
+
+fn unknown_network(network: &str) -> BasicError {
+    BasicError::Other(format!("No profile for network '{}'", network))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        rpc::{
+            MockRpcClient,
+            RuntimeVersion,
+        },
+        DefaultConfig,
+    };
+    use frame_metadata::v14::{
+        ExtrinsicMetadata,
+        RuntimeMetadataLastVersion,
+    };
+    use scale_info::meta_type;
+    use sp_core::{
+        sr25519,
+        H256,
+    };
+    use sp_keyring::AccountKeyring;
+
+    const PROFILES: &str = r#"{
+        "default": "local",
+        "networks": {
+            "local": { "url": "ws://127.0.0.1:9944", "signer": { "suri": "//Alice" } },
+            "testnet": { "url": "wss://testnet.example", "ss58_prefix": 42, "app_id": 1 }
+        }
+    }"#;
+
+    #[test]
+    fn profiles_are_loaded_and_switched_between() {
+        let profiles: NetworkProfiles = serde_json::from_str(PROFILES).unwrap();
+        let manager = ClientManager::<DefaultConfig>::new(profiles).unwrap();
+
+        assert_eq!(
+            manager.networks().collect::<Vec<_>>(),
+            vec!["local", "testnet"]
+        );
+        assert_eq!(manager.current_network(), "local");
+        assert_eq!(manager.profile("testnet").unwrap().app_id, Some(1));
+
+        manager.switch("testnet").unwrap();
+        assert_eq!(manager.current_network(), "testnet");
+        assert!(manager.switch("mainnet").is_err());
+        assert_eq!(manager.current_network(), "testnet");
+
+        let alice = sr25519::Pair::from_string("//Alice", None).unwrap();
+        let signer: sr25519::Pair = manager.signer("local").unwrap().unwrap();
+        assert_eq!(signer.public(), alice.public());
+        assert!(manager
+            .signer::<sr25519::Pair>("testnet")
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn default_network_must_exist() {
+        let mut profiles: NetworkProfiles = serde_json::from_str(PROFILES).unwrap();
+        profiles.default = "mainnet".into();
+        assert!(ClientManager::<DefaultConfig>::new(profiles).is_err());
+    }
+
+    /// The client the manager would build for the network, but answering requests
+    /// with canned responses rather than connecting to its URL.
+    async fn mock_client(profile: &NetworkProfile) -> Client<DefaultConfig> {
+        let extrinsic = ExtrinsicMetadata {
+            ty: meta_type::<()>(),
+            version: 4,
+            signed_extensions: vec![],
+        };
+        let v14 = RuntimeMetadataLastVersion::new(vec![], extrinsic, meta_type::<()>());
+        let mock = MockRpcClient::new();
+        mock.set_metadata(&v14.into());
+        mock.set_genesis_hash(H256::zero());
+        mock.set_runtime_version(&RuntimeVersion {
+            spec_version: 1,
+            transaction_version: 1,
+            other: Default::default(),
+        });
+        builder(profile)
+            .set_mock_client(mock)
+            .build()
+            .await
+            .unwrap()
+    }
+
+    #[async_std::test]
+    async fn clients_submit_under_the_app_id_of_their_network() {
+        let profiles: NetworkProfiles = serde_json::from_str(PROFILES).unwrap();
+
+        let testnet = mock_client(&profiles.networks["testnet"]).await;
+        assert_eq!(
+            testnet
+                .default_extra_params::<AvailExtraParameters>()
+                .unwrap(),
+            AvailExtraParameters::new().set_app_id(1)
+        );
+
+        let local = mock_client(&profiles.networks["local"]).await;
+        assert_eq!(
+            local
+                .default_extra_params::<AvailExtraParameters>()
+                .unwrap(),
+            AvailExtraParameters::new()
+        );
+    }
+
+    #[test]
+    fn addresses_are_formatted_for_the_network() {
+        let mut profiles: NetworkProfiles = serde_json::from_str(PROFILES).unwrap();
+        profiles.networks.get_mut("testnet").unwrap().ss58_prefix = Some(0);
+        let manager = ClientManager::<DefaultConfig>::new(profiles).unwrap();
+        let alice = AccountKeyring::Alice.to_account_id();
+
+        // Polkadot's format, whose addresses start with a 1.
+        assert_eq!(
+            manager.format_address("testnet", &alice).unwrap(),
+            "15oF4uVJwmo4TdGW7VfQxNLavjCXviqxT9S1MgbjMNHr6Sp5"
+        );
+        assert_eq!(
+            manager.format_address("local", &alice).unwrap(),
+            alice.to_ss58check()
+        );
+        assert!(manager.format_address("mainnet", &alice).is_err());
+    }
+}
@@ -0,0 +1,100 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Limits on the size and shape of the data decoded from a node's responses.
+
+use crate::error::BasicError;
+use codec::{
+    Decode,
+    DecodeLimit,
+};
+
+/// Limits applied to data received from a node, so that a malicious or buggy endpoint
+/// can't make the client allocate huge amounts of memory or recurse without bound.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DecodeLimits {
+    /// The largest message, in bytes, that will be accepted from the node. This only
+    /// applies to connections that the client opens itself (ie from a URL).
+    pub max_response_size: u32,
+    /// The largest SCALE encoded value, in bytes, that will be decoded. This applies to
+    /// storage values and to the events of a block.
+    pub max_value_size: usize,
+    /// How deeply nested a statically decoded value is allowed to be.
+    pub max_depth: u32,
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        Self {
+            max_response_size: 10 * 1024 * 1024,
+            max_value_size: 10 * 1024 * 1024,
+            max_depth: 256,
+        }
+    }
+}
+
+impl DecodeLimits {
+    /// Return an error if a value of `len` bytes is too large to be decoded.
+    pub(crate) fn check_size(&self, len: usize) -> Result<(), BasicError> {
+        if len > self.max_value_size {
+            Err(BasicError::ValueTooLarge {
+                len,
+                max: self.max_value_size,
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Decode a value from the given bytes, respecting these limits.
+    pub(crate) fn decode<V: Decode>(&self, bytes: &[u8]) -> Result<V, BasicError> {
+        self.check_size(bytes.len())?;
+        Ok(V::decode_with_depth_limit(self.max_depth, &mut &*bytes)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codec::Encode;
+
+    #[test]
+    fn limits_are_enforced() {
+        let limits = DecodeLimits {
+            max_value_size: 8,
+            max_depth: 1,
+            ..Default::default()
+        };
+
+        let value = vec![vec![1u8, 2, 3]];
+        assert_eq!(
+            limits.decode::<Vec<Vec<u8>>>(&value.encode()).unwrap(),
+            value
+        );
+
+        let too_large = vec![0u8; 16].encode();
+        assert!(matches!(
+            limits.decode::<Vec<u8>>(&too_large),
+            Err(BasicError::ValueTooLarge { len: 17, max: 8 })
+        ));
+
+        let too_deep = vec![vec![vec![1u8]]].encode();
+        assert!(matches!(
+            limits.decode::<Vec<Vec<Vec<u8>>>>(&too_deep),
+            Err(BasicError::Codec(_))
+        ));
+    }
+}
@@ -0,0 +1,363 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Decoding of the consensus data found in the digests of block headers.
+
+use codec::{
+    Decode,
+    DecodeAll,
+    Encode,
+};
+use sp_runtime::{
+    ConsensusEngineId,
+    Digest,
+    DigestItem,
+};
+
+/// The engine id of BABE consensus digests.
+pub const BABE_ENGINE_ID: ConsensusEngineId = *b"BABE";
+
+/// The engine id of GRANDPA consensus digests.
+pub const GRANDPA_ENGINE_ID: ConsensusEngineId = *b"FRNK";
+
+/// The public key of a BABE or GRANDPA authority.
+pub type AuthorityId = [u8; 32];
+
+/// The weight of an authority in a BABE or GRANDPA authority set.
+pub type AuthorityWeight = u64;
+
+/// A digest item, with the payloads of the consensus engines known to subxt decoded.
+///
+/// `N` is the block number type of the chain.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DecodedDigestItem<N> {
+    /// A pre-runtime digest, which is produced by the block author.
+    PreRuntime(PreRuntimeLog),
+    /// A message from the runtime to the consensus engine.
+    Consensus(ConsensusLog<N>),
+    /// A seal, which is the signature of the block author.
+    Seal(ConsensusEngineId, Vec<u8>),
+    /// Some other item, which is opaque to the consensus engines.
+    Other(Vec<u8>),
+    /// The runtime code or heap pages were updated.
+    RuntimeEnvironmentUpdated,
+}
+
+impl<N: Decode> DecodedDigestItem<N> {
+    /// Decode a single digest item. Payloads of unknown engines, or ones that fail to
+    /// decode, are kept as raw bytes.
+    pub fn decode_from(item: &DigestItem) -> Self {
+        match item {
+            DigestItem::PreRuntime(engine_id, data) => {
+                DecodedDigestItem::PreRuntime(PreRuntimeLog::decode_from(
+                    *engine_id, data,
+                ))
+            }
+            DigestItem::Consensus(engine_id, data) => {
+                DecodedDigestItem::Consensus(ConsensusLog::decode_from(*engine_id, data))
+            }
+            DigestItem::Seal(engine_id, data) => {
+                DecodedDigestItem::Seal(*engine_id, data.clone())
+            }
+            DigestItem::Other(data) => DecodedDigestItem::Other(data.clone()),
+            DigestItem::RuntimeEnvironmentUpdated => {
+                DecodedDigestItem::RuntimeEnvironmentUpdated
+            }
+        }
+    }
+}
+
+/// Decode all of the items in a digest.
+pub fn decode_digest<N: Decode>(digest: &Digest) -> Vec<DecodedDigestItem<N>> {
+    digest
+        .logs()
+        .iter()
+        .map(DecodedDigestItem::decode_from)
+        .collect()
+}
+
+/// The payload of a pre-runtime digest item.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PreRuntimeLog {
+    /// A BABE slot claim.
+    Babe(BabePreDigest),
+    /// The payload of some other engine.
+    Unknown(ConsensusEngineId, Vec<u8>),
+}
+
+impl PreRuntimeLog {
+    fn decode_from(engine_id: ConsensusEngineId, data: &[u8]) -> Self {
+        match engine_id {
+            BABE_ENGINE_ID => {
+                decode_all(data)
+                    .map(PreRuntimeLog::Babe)
+                    .unwrap_or_else(|| PreRuntimeLog::Unknown(engine_id, data.to_vec()))
+            }
+            _ => PreRuntimeLog::Unknown(engine_id, data.to_vec()),
+        }
+    }
+}
+
+/// The payload of a consensus digest item.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConsensusLog<N> {
+    /// A message to BABE.
+    Babe(BabeConsensusLog),
+    /// A message to GRANDPA.
+    Grandpa(GrandpaConsensusLog<N>),
+    /// The payload of some other engine.
+    Unknown(ConsensusEngineId, Vec<u8>),
+}
+
+impl<N: Decode> ConsensusLog<N> {
+    fn decode_from(engine_id: ConsensusEngineId, data: &[u8]) -> Self {
+        let decoded = match engine_id {
+            BABE_ENGINE_ID => decode_all(data).map(ConsensusLog::Babe),
+            GRANDPA_ENGINE_ID => decode_all(data).map(ConsensusLog::Grandpa),
+            _ => None,
+        };
+        decoded.unwrap_or_else(|| ConsensusLog::Unknown(engine_id, data.to_vec()))
+    }
+}
+
+/// A message from the runtime to BABE.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub enum BabeConsensusLog {
+    /// The epoch after the next one starts with the given authorities and randomness.
+    #[codec(index = 1)]
+    NextEpochData(NextEpochDescriptor),
+    /// The authority with the given index is disabled.
+    #[codec(index = 2)]
+    OnDisabled(u32),
+    /// The epoch after the next one uses the given configuration.
+    #[codec(index = 3)]
+    NextConfigData(NextConfigDescriptor),
+}
+
+/// The authorities and randomness of an upcoming BABE epoch.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct NextEpochDescriptor {
+    /// The authorities of the epoch.
+    pub authorities: Vec<(AuthorityId, AuthorityWeight)>,
+    /// The randomness of the epoch.
+    pub randomness: [u8; 32],
+}
+
+/// The configuration of an upcoming BABE epoch.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub enum NextConfigDescriptor {
+    /// Version 1.
+    #[codec(index = 1)]
+    V1 {
+        /// The probability of a slot being empty, as a fraction.
+        c: (u64, u64),
+        /// The types of slots that may be claimed.
+        allowed_slots: AllowedSlots,
+    },
+}
+
+/// The types of slot that may be claimed in a BABE epoch.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Encode, Decode)]
+pub enum AllowedSlots {
+    /// Only primary slots.
+    PrimarySlots,
+    /// Primary and secondary plain slots.
+    PrimaryAndSecondaryPlainSlots,
+    /// Primary and secondary VRF slots.
+    PrimaryAndSecondaryVRFSlots,
+}
+
+/// A message from the runtime to GRANDPA. `N` is the block number type of the chain.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub enum GrandpaConsensusLog<N> {
+    /// The authority set changes once the block carrying this log is finalized, and
+    /// `delay` more blocks have been finalized on top of it.
+    #[codec(index = 1)]
+    ScheduledChange(ScheduledChange<N>),
+    /// The authority set changes forcibly once the block carrying this log is
+    /// imported, and `delay` more blocks have been imported on top of it. The first
+    /// value is the median last finalized block when the change was signaled.
+    #[codec(index = 2)]
+    ForcedChange(N, ScheduledChange<N>),
+    /// The authority with the given index is disabled.
+    #[codec(index = 3)]
+    OnDisabled(u64),
+    /// Finality is paused after the given delay.
+    #[codec(index = 4)]
+    Pause(N),
+    /// Finality resumes after the given delay.
+    #[codec(index = 5)]
+    Resume(N),
+}
+
+/// A change of the GRANDPA authority set.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct ScheduledChange<N> {
+    /// The authorities after the change.
+    pub next_authorities: Vec<(AuthorityId, AuthorityWeight)>,
+    /// The number of blocks to delay the change by.
+    pub delay: N,
+}
+
+fn decode_all<T: Decode>(data: &[u8]) -> Option<T> {
+    T::decode_all(&mut &data[..]).ok()
+}
+
+/// The BABE pre-runtime digest, which every BABE block carries to claim its slot.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub enum BabePreDigest {
+    /// A primary VRF-based slot assignment.
+    #[codec(index = 1)]
+    Primary {
+        /// Index of the block author in the authority set of the epoch.
+        authority_index: u32,
+        /// The slot claimed by the block.
+        slot: u64,
+        /// The VRF output.
+        vrf_output: [u8; 32],
+        /// The VRF proof.
+        vrf_proof: [u8; 64],
+    },
+    /// A secondary deterministic slot assignment.
+    #[codec(index = 2)]
+    SecondaryPlain {
+        /// Index of the block author in the authority set of the epoch.
+        authority_index: u32,
+        /// The slot claimed by the block.
+        slot: u64,
+    },
+    /// A secondary deterministic slot assignment with VRF outputs.
+    #[codec(index = 3)]
+    SecondaryVRF {
+        /// Index of the block author in the authority set of the epoch.
+        authority_index: u32,
+        /// The slot claimed by the block.
+        slot: u64,
+        /// The VRF output.
+        vrf_output: [u8; 32],
+        /// The VRF proof.
+        vrf_proof: [u8; 64],
+    },
+}
+
+impl BabePreDigest {
+    /// Returns the index of the block author in the authority set of the epoch.
+    pub fn authority_index(&self) -> u32 {
+        match self {
+            BabePreDigest::Primary {
+                authority_index, ..
+            }
+            | BabePreDigest::SecondaryPlain {
+                authority_index, ..
+            }
+            | BabePreDigest::SecondaryVRF {
+                authority_index, ..
+            } => *authority_index,
+        }
+    }
+
+    /// Returns the slot claimed by the block.
+    pub fn slot(&self) -> u64 {
+        match self {
+            BabePreDigest::Primary { slot, .. }
+            | BabePreDigest::SecondaryPlain { slot, .. }
+            | BabePreDigest::SecondaryVRF { slot, .. } => *slot,
+        }
+    }
+
+    /// Find and decode the BABE pre-runtime digest among the given digest items.
+    pub fn find(digest: &Digest) -> Option<Self> {
+        digest.logs().iter().find_map(|item| {
+            match item {
+                DigestItem::PreRuntime(engine_id, data)
+                    if *engine_id == BABE_ENGINE_ID =>
+                {
+                    Self::decode(&mut &data[..]).ok()
+                }
+                _ => None,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn babe_pre_digest_is_found_among_logs() {
+        let pre_digest = BabePreDigest::SecondaryPlain {
+            authority_index: 3,
+            slot: 42,
+        };
+        let digest = Digest {
+            logs: vec![
+                DigestItem::Other(vec![1, 2, 3]),
+                DigestItem::PreRuntime(*b"aura", 7u64.encode()),
+                DigestItem::PreRuntime(BABE_ENGINE_ID, pre_digest.encode()),
+            ],
+        };
+
+        let found = BabePreDigest::find(&digest).unwrap();
+        assert_eq!(found, pre_digest);
+        assert_eq!(found.authority_index(), 3);
+        assert_eq!(found.slot(), 42);
+        assert_eq!(BabePreDigest::find(&Digest::default()), None);
+    }
+
+    #[test]
+    fn digest_items_are_decoded_by_engine() {
+        let pre_digest = BabePreDigest::SecondaryPlain {
+            authority_index: 1,
+            slot: 2,
+        };
+        let grandpa = GrandpaConsensusLog::ScheduledChange(ScheduledChange {
+            next_authorities: vec![([1; 32], 1)],
+            delay: 5u32,
+        });
+        let digest = Digest {
+            logs: vec![
+                DigestItem::PreRuntime(BABE_ENGINE_ID, pre_digest.encode()),
+                DigestItem::Consensus(GRANDPA_ENGINE_ID, grandpa.encode()),
+                DigestItem::Consensus(
+                    BABE_ENGINE_ID,
+                    BabeConsensusLog::OnDisabled(3).encode(),
+                ),
+                // Not a valid BABE consensus log:
+                DigestItem::Consensus(BABE_ENGINE_ID, vec![9]),
+                DigestItem::Seal(BABE_ENGINE_ID, vec![0; 64]),
+                DigestItem::Other(vec![1]),
+            ],
+        };
+
+        assert_eq!(
+            decode_digest::<u32>(&digest),
+            vec![
+                DecodedDigestItem::PreRuntime(PreRuntimeLog::Babe(pre_digest)),
+                DecodedDigestItem::Consensus(ConsensusLog::Grandpa(grandpa)),
+                DecodedDigestItem::Consensus(ConsensusLog::Babe(
+                    BabeConsensusLog::OnDisabled(3)
+                )),
+                DecodedDigestItem::Consensus(ConsensusLog::Unknown(
+                    BABE_ENGINE_ID,
+                    vec![9]
+                )),
+                DecodedDigestItem::Seal(BABE_ENGINE_ID, vec![0; 64]),
+                DecodedDigestItem::Other(vec![1]),
+            ]
+        );
+    }
+}
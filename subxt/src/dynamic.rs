@@ -0,0 +1,1059 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Building calls at runtime from pallet and call names, with the arguments given as
+//! [`Value`]s which are encoded according to the metadata of the connected node, and
+//! decoding data into [`Value`]s in the same way. This allows tools to target several
+//! runtimes (or chains) without generated code.
+
+use crate::{
+    error::BasicError,
+    Call,
+    Encoded,
+    Metadata,
+};
+use codec::{
+    Compact,
+    Decode,
+    Encode,
+};
+use scale_info::{
+    form::PortableForm,
+    Field,
+    PortableRegistry,
+    TypeDef,
+    TypeDefPrimitive,
+};
+use serde::ser::{
+    Serialize,
+    SerializeMap,
+    SerializeSeq,
+    Serializer,
+};
+
+/// A value of any type that can be described by the metadata.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    /// A boolean.
+    Bool(bool),
+    /// A character.
+    Char(char),
+    /// A string.
+    String(String),
+    /// An unsigned integer of any width.
+    UInt(u128),
+    /// A signed integer of any width.
+    Int(i128),
+    /// A struct, tuple, sequence or array.
+    Composite(Composite),
+    /// A variant of an enum.
+    Variant(Variant),
+}
+
+/// The fields of a struct or variant, or the items of a tuple, sequence or array.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Composite {
+    /// Named fields, which can be given in any order.
+    Named(Vec<(String, Value)>),
+    /// Fields (or items) in order.
+    Unnamed(Vec<Value>),
+}
+
+/// A variant of an enum, such as `MultiAddress::Id`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Variant {
+    /// The name of the variant.
+    pub name: String,
+    /// The fields of the variant.
+    pub fields: Composite,
+}
+
+impl Value {
+    /// A boolean value.
+    pub fn bool(value: bool) -> Self {
+        Value::Bool(value)
+    }
+
+    /// A string value.
+    pub fn string<S: Into<String>>(value: S) -> Self {
+        Value::String(value.into())
+    }
+
+    /// An unsigned integer value, which fits any unsigned integer type wide enough.
+    pub fn uint<N: Into<u128>>(value: N) -> Self {
+        Value::UInt(value.into())
+    }
+
+    /// A signed integer value, which fits any integer type wide enough.
+    pub fn int<N: Into<i128>>(value: N) -> Self {
+        Value::Int(value.into())
+    }
+
+    /// A sequence of bytes, for `Vec<u8>` and `[u8; N]` types (including newtypes
+    /// around them, such as `AccountId32`).
+    pub fn bytes<B: AsRef<[u8]>>(bytes: B) -> Self {
+        Self::unnamed_composite(bytes.as_ref().iter().map(|b| Value::UInt(*b as u128)))
+    }
+
+    /// A struct with the given fields.
+    pub fn named_composite<S, I>(fields: I) -> Self
+    where
+        S: Into<String>,
+        I: IntoIterator<Item = (S, Value)>,
+    {
+        Value::Composite(Composite::Named(
+            fields
+                .into_iter()
+                .map(|(name, value)| (name.into(), value))
+                .collect(),
+        ))
+    }
+
+    /// A tuple, sequence or array, or a struct whose fields are given in order.
+    pub fn unnamed_composite<I: IntoIterator<Item = Value>>(values: I) -> Self {
+        Value::Composite(Composite::Unnamed(values.into_iter().collect()))
+    }
+
+    /// A variant of an enum.
+    pub fn variant<S: Into<String>, C: Into<Composite>>(name: S, fields: C) -> Self {
+        Value::Variant(Variant {
+            name: name.into(),
+            fields: fields.into(),
+        })
+    }
+
+    /// The value as an unsigned integer, if it is a non-negative integer.
+    pub fn as_u128(&self) -> Option<u128> {
+        as_uint(self)
+    }
+
+    /// The value as a string, if it is one.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s.as_str()),
+            _ => None,
+        }
+    }
+
+    /// The value of the field with the given name, if this is a struct or a variant
+    /// with named fields.
+    pub fn field(&self, name: &str) -> Option<&Value> {
+        let fields = match self {
+            Value::Composite(Composite::Named(fields)) => fields,
+            Value::Variant(Variant {
+                fields: Composite::Named(fields),
+                ..
+            }) => fields,
+            _ => return None,
+        };
+        fields.iter().find(|(n, _)| n == name).map(|(_, v)| v)
+    }
+
+    /// A short description of the kind of value, for error messages.
+    fn kind(&self) -> &'static str {
+        match self {
+            Value::Bool(_) => "bool",
+            Value::Char(_) => "char",
+            Value::String(_) => "string",
+            Value::UInt(_) => "unsigned integer",
+            Value::Int(_) => "integer",
+            Value::Composite(_) => "composite",
+            Value::Variant(_) => "variant",
+        }
+    }
+}
+
+impl Composite {
+    /// The number of fields.
+    pub fn len(&self) -> usize {
+        match self {
+            Composite::Named(fields) => fields.len(),
+            Composite::Unnamed(values) => values.len(),
+        }
+    }
+
+    /// Whether there are no fields.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The values of the fields, in order.
+    pub fn values(&self) -> impl Iterator<Item = &Value> + '_ {
+        let (named, unnamed) = match self {
+            Composite::Named(fields) => (Some(fields.iter().map(|(_, v)| v)), None),
+            Composite::Unnamed(values) => (None, Some(values.iter())),
+        };
+        named
+            .into_iter()
+            .flatten()
+            .chain(unnamed.into_iter().flatten())
+    }
+}
+
+impl From<Vec<Value>> for Composite {
+    fn from(values: Vec<Value>) -> Self {
+        Composite::Unnamed(values)
+    }
+}
+
+impl From<Vec<(String, Value)>> for Composite {
+    fn from(fields: Vec<(String, Value)>) -> Self {
+        Composite::Named(fields)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl From<char> for Value {
+    fn from(value: char) -> Self {
+        Value::Char(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(value.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::String(value)
+    }
+}
+
+macro_rules! impl_from_int {
+    ($variant:ident: $($ty:ty),*) => {
+        $(
+            impl From<$ty> for Value {
+                fn from(value: $ty) -> Self {
+                    Value::$variant(value.into())
+                }
+            }
+        )*
+    };
+}
+
+impl_from_int!(UInt: u8, u16, u32, u64, u128);
+impl_from_int!(Int: i8, i16, i32, i64, i128);
+
+// Values serialize as the types they describe would with serde's defaults: structs
+// as maps, tuples and sequences as arrays and variants as `{ "Name": fields }`, or
+// just `"Name"` if they have no fields.
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::Bool(b) => serializer.serialize_bool(*b),
+            Value::Char(c) => serializer.serialize_char(*c),
+            Value::String(s) => serializer.serialize_str(s),
+            Value::UInt(n) => serializer.serialize_u128(*n),
+            Value::Int(n) => serializer.serialize_i128(*n),
+            Value::Composite(composite) => composite.serialize(serializer),
+            Value::Variant(variant) => variant.serialize(serializer),
+        }
+    }
+}
+
+impl Serialize for Composite {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Composite::Named(fields) => {
+                let mut map = serializer.serialize_map(Some(fields.len()))?;
+                for (name, value) in fields {
+                    map.serialize_entry(name, value)?;
+                }
+                map.end()
+            }
+            Composite::Unnamed(values) => {
+                let mut seq = serializer.serialize_seq(Some(values.len()))?;
+                for value in values {
+                    seq.serialize_element(value)?;
+                }
+                seq.end()
+            }
+        }
+    }
+}
+
+impl Serialize for Variant {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.fields.is_empty() {
+            return serializer.serialize_str(&self.name)
+        }
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry(&self.name, &self.fields)?;
+        map.end()
+    }
+}
+
+/// An error encoding a [`Value`] as a type described by the metadata.
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum DynamicError {
+    /// The type is not in the metadata.
+    #[error("Type {0} missing from type registry")]
+    TypeNotFound(u32),
+    /// The value is of the wrong kind for the type.
+    #[error("Cannot encode a {found} value as type {type_id}")]
+    Mismatch {
+        /// The type the value should have been encoded as.
+        type_id: u32,
+        /// The kind of value given.
+        found: &'static str,
+    },
+    /// A number doesn't fit the integer type.
+    #[error("{0} is out of range for {1:?}")]
+    OutOfRange(String, TypeDefPrimitive),
+    /// The number of fields or items doesn't match the type.
+    #[error("Expected {expected} values, found {found}")]
+    WrongLength {
+        /// The number of values the type has.
+        expected: usize,
+        /// The number of values given.
+        found: usize,
+    },
+    /// A named field of the type was not given.
+    #[error("Field {0} is missing")]
+    MissingField(String),
+    /// The enum type has no variant with the given name.
+    #[error("Variant {0} not found")]
+    VariantNotFound(String),
+    /// The runtime has no pallet with the given name.
+    #[error("Pallet {0} not found")]
+    PalletNotFound(String),
+    /// The pallet has no call with the given name.
+    #[error("Call {pallet}::{call} not found")]
+    CallNotFound {
+        /// The name of the pallet.
+        pallet: String,
+        /// The name of the call.
+        call: String,
+    },
+    /// The pallet has no storage entry with the given name.
+    #[error("Storage entry {pallet}::{entry} not found")]
+    StorageNotFound {
+        /// The name of the pallet.
+        pallet: String,
+        /// The name of the storage entry.
+        entry: String,
+    },
+    /// The type can't be encoded dynamically.
+    #[error("{0} are not supported")]
+    Unsupported(&'static str),
+}
+
+/// Encode a value as the type with the given ID.
+pub fn encode_value(
+    value: &Value,
+    type_id: u32,
+    types: &PortableRegistry,
+    out: &mut Vec<u8>,
+) -> Result<(), DynamicError> {
+    let ty = types
+        .resolve(type_id)
+        .ok_or(DynamicError::TypeNotFound(type_id))?;
+    let mismatch = || {
+        DynamicError::Mismatch {
+            type_id,
+            found: value.kind(),
+        }
+    };
+
+    match ty.type_def() {
+        TypeDef::Composite(composite) => {
+            match (composite.fields(), value) {
+                // Newtypes are transparent: their one field may be given directly, or
+                // wrapped in a composite. A composite of one value is tried as the field
+                // first, since it may be the field itself (such as a `BoundedVec<u8>`
+                // of one byte), and only unwrapped if that fails.
+                ([field], value) => {
+                    let mut encoded = Vec::new();
+                    match encode_value(value, field.ty().id(), types, &mut encoded) {
+                        Ok(()) => {
+                            out.extend(encoded);
+                            Ok(())
+                        }
+                        Err(e) => {
+                            match value {
+                                Value::Composite(fields) if fields.len() == 1 => {
+                                    let inner =
+                                        fields.values().next().expect("one field; qed");
+                                    encode_value(inner, field.ty().id(), types, out)
+                                        .map_err(|_| e)
+                                }
+                                _ => Err(e),
+                            }
+                        }
+                    }
+                }
+                (fields, Value::Composite(values)) => {
+                    encode_fields(values, fields, types, out)
+                }
+                _ => Err(mismatch()),
+            }
+        }
+        TypeDef::Variant(variants) => {
+            let variant = match value {
+                Value::Variant(variant) => variant,
+                _ => return Err(mismatch()),
+            };
+            let def = variants
+                .variants()
+                .iter()
+                .find(|v| v.name() == &variant.name)
+                .ok_or_else(|| DynamicError::VariantNotFound(variant.name.clone()))?;
+            out.push(def.index());
+            encode_fields(&variant.fields, def.fields(), types, out)
+        }
+        TypeDef::Sequence(seq) => {
+            let values = match value {
+                Value::Composite(values) => values,
+                _ => return Err(mismatch()),
+            };
+            Compact(values.len() as u32).encode_to(out);
+            for value in values.values() {
+                encode_value(value, seq.type_param().id(), types, out)?;
+            }
+            Ok(())
+        }
+        TypeDef::Array(array) => {
+            let values = match value {
+                Value::Composite(values) => values,
+                _ => return Err(mismatch()),
+            };
+            if values.len() != array.len() as usize {
+                return Err(DynamicError::WrongLength {
+                    expected: array.len() as usize,
+                    found: values.len(),
+                })
+            }
+            for value in values.values() {
+                encode_value(value, array.type_param().id(), types, out)?;
+            }
+            Ok(())
+        }
+        TypeDef::Tuple(tuple) => {
+            let values = match value {
+                Value::Composite(values) => values,
+                _ => return Err(mismatch()),
+            };
+            if values.len() != tuple.fields().len() {
+                return Err(DynamicError::WrongLength {
+                    expected: tuple.fields().len(),
+                    found: values.len(),
+                })
+            }
+            for (value, field) in values.values().zip(tuple.fields()) {
+                encode_value(value, field.id(), types, out)?;
+            }
+            Ok(())
+        }
+        TypeDef::Primitive(primitive) => {
+            encode_primitive(value, primitive, out).ok_or_else(mismatch)?
+        }
+        TypeDef::Compact(compact) => {
+            let primitive = compact_primitive(compact.type_param().id(), types)?;
+            let n = as_uint(value).ok_or_else(mismatch)?;
+            let max = match primitive {
+                TypeDefPrimitive::U8 => u8::MAX as u128,
+                TypeDefPrimitive::U16 => u16::MAX as u128,
+                TypeDefPrimitive::U32 => u32::MAX as u128,
+                TypeDefPrimitive::U64 => u64::MAX as u128,
+                TypeDefPrimitive::U128 => u128::MAX,
+                _ => return Err(mismatch()),
+            };
+            if n > max {
+                return Err(DynamicError::OutOfRange(n.to_string(), primitive.clone()))
+            }
+            // The compact encoding of a number doesn't depend on the width of its type.
+            Compact(n).encode_to(out);
+            Ok(())
+        }
+        TypeDef::BitSequence(_) => Err(DynamicError::Unsupported("Bit sequences")),
+    }
+}
+
+/// Decode a value of the type with the given ID from the input.
+pub fn decode_value(
+    type_id: u32,
+    types: &PortableRegistry,
+    input: &mut &[u8],
+) -> Result<Value, BasicError> {
+    let ty = types
+        .resolve(type_id)
+        .ok_or(DynamicError::TypeNotFound(type_id))?;
+
+    let value = match ty.type_def() {
+        TypeDef::Composite(composite) => {
+            Value::Composite(decode_fields(composite.fields(), types, input)?)
+        }
+        TypeDef::Variant(variants) => {
+            let index = u8::decode(input)?;
+            let variant = variants
+                .variants()
+                .iter()
+                .find(|v| v.index() == index)
+                .ok_or_else(|| DynamicError::VariantNotFound(index.to_string()))?;
+            Value::Variant(Variant {
+                name: variant.name().clone(),
+                fields: decode_fields(variant.fields(), types, input)?,
+            })
+        }
+        TypeDef::Sequence(seq) => {
+            let len = <Compact<u32>>::decode(input)?.0;
+            let values = (0..len)
+                .map(|_| decode_value(seq.type_param().id(), types, input))
+                .collect::<Result<_, _>>()?;
+            Value::Composite(Composite::Unnamed(values))
+        }
+        TypeDef::Array(array) => {
+            let values = (0..array.len())
+                .map(|_| decode_value(array.type_param().id(), types, input))
+                .collect::<Result<_, _>>()?;
+            Value::Composite(Composite::Unnamed(values))
+        }
+        TypeDef::Tuple(tuple) => {
+            let values = tuple
+                .fields()
+                .iter()
+                .map(|field| decode_value(field.id(), types, input))
+                .collect::<Result<_, _>>()?;
+            Value::Composite(Composite::Unnamed(values))
+        }
+        TypeDef::Primitive(primitive) => {
+            match primitive {
+                TypeDefPrimitive::Bool => Value::Bool(bool::decode(input)?),
+                TypeDefPrimitive::Char => {
+                    let c = u32::decode(input)?;
+                    Value::Char(
+                        char::from_u32(c)
+                            .ok_or_else(|| codec::Error::from("Invalid char"))?,
+                    )
+                }
+                TypeDefPrimitive::Str => Value::String(String::decode(input)?),
+                TypeDefPrimitive::U8 => Value::UInt(u8::decode(input)?.into()),
+                TypeDefPrimitive::U16 => Value::UInt(u16::decode(input)?.into()),
+                TypeDefPrimitive::U32 => Value::UInt(u32::decode(input)?.into()),
+                TypeDefPrimitive::U64 => Value::UInt(u64::decode(input)?.into()),
+                TypeDefPrimitive::U128 => Value::UInt(u128::decode(input)?),
+                TypeDefPrimitive::I8 => Value::Int(i8::decode(input)?.into()),
+                TypeDefPrimitive::I16 => Value::Int(i16::decode(input)?.into()),
+                TypeDefPrimitive::I32 => Value::Int(i32::decode(input)?.into()),
+                TypeDefPrimitive::I64 => Value::Int(i64::decode(input)?.into()),
+                TypeDefPrimitive::I128 => Value::Int(i128::decode(input)?),
+                TypeDefPrimitive::U256 | TypeDefPrimitive::I256 => {
+                    return Err(DynamicError::Unsupported("256 bit integers").into())
+                }
+            }
+        }
+        TypeDef::Compact(compact) => {
+            // Check that it's a compact integer, even though they all decode the same.
+            compact_primitive(compact.type_param().id(), types)?;
+            Value::UInt(<Compact<u128>>::decode(input)?.0)
+        }
+        TypeDef::BitSequence(_) => {
+            return Err(DynamicError::Unsupported("Bit sequences").into())
+        }
+    };
+    Ok(value)
+}
+
+/// Decode the fields of a struct or variant; named if they all have names.
+pub(crate) fn decode_fields(
+    fields: &[Field<PortableForm>],
+    types: &PortableRegistry,
+    input: &mut &[u8],
+) -> Result<Composite, BasicError> {
+    if !fields.is_empty() && fields.iter().all(|f| f.name().is_some()) {
+        let values = fields
+            .iter()
+            .map(|field| {
+                let name = field.name().expect("checked all fields are named; qed");
+                Ok((name.clone(), decode_value(field.ty().id(), types, input)?))
+            })
+            .collect::<Result<_, BasicError>>()?;
+        Ok(Composite::Named(values))
+    } else {
+        let values = fields
+            .iter()
+            .map(|field| decode_value(field.ty().id(), types, input))
+            .collect::<Result<_, _>>()?;
+        Ok(Composite::Unnamed(values))
+    }
+}
+
+/// Encode the fields of a struct or variant.
+fn encode_fields(
+    values: &Composite,
+    fields: &[Field<PortableForm>],
+    types: &PortableRegistry,
+    out: &mut Vec<u8>,
+) -> Result<(), DynamicError> {
+    if values.len() != fields.len() {
+        return Err(DynamicError::WrongLength {
+            expected: fields.len(),
+            found: values.len(),
+        })
+    }
+    match values {
+        Composite::Named(named) if fields.iter().all(|f| f.name().is_some()) => {
+            for field in fields {
+                let name = field.name().expect("checked all fields are named; qed");
+                let (_, value) = named
+                    .iter()
+                    .find(|(n, _)| n == name)
+                    .ok_or_else(|| DynamicError::MissingField(name.clone()))?;
+                encode_value(value, field.ty().id(), types, out)?;
+            }
+        }
+        values => {
+            for (value, field) in values.values().zip(fields) {
+                encode_value(value, field.ty().id(), types, out)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Encode a value as a primitive type, or return `None` if it's the wrong kind of value.
+fn encode_primitive(
+    value: &Value,
+    primitive: &TypeDefPrimitive,
+    out: &mut Vec<u8>,
+) -> Option<Result<(), DynamicError>> {
+    fn fits<N: TryFrom<V> + Encode, V: ToString + Copy>(
+        n: V,
+        primitive: &TypeDefPrimitive,
+        out: &mut Vec<u8>,
+    ) -> Result<(), DynamicError> {
+        let n = N::try_from(n)
+            .map_err(|_| DynamicError::OutOfRange(n.to_string(), primitive.clone()))?;
+        n.encode_to(out);
+        Ok(())
+    }
+
+    let result = match (primitive, value) {
+        (TypeDefPrimitive::Bool, Value::Bool(b)) => {
+            b.encode_to(out);
+            Ok(())
+        }
+        (TypeDefPrimitive::Char, Value::Char(c)) => {
+            (*c as u32).encode_to(out);
+            Ok(())
+        }
+        (TypeDefPrimitive::Str, Value::String(s)) => {
+            s.encode_to(out);
+            Ok(())
+        }
+        (TypeDefPrimitive::U8, _) => fits::<u8, _>(as_uint(value)?, primitive, out),
+        (TypeDefPrimitive::U16, _) => fits::<u16, _>(as_uint(value)?, primitive, out),
+        (TypeDefPrimitive::U32, _) => fits::<u32, _>(as_uint(value)?, primitive, out),
+        (TypeDefPrimitive::U64, _) => fits::<u64, _>(as_uint(value)?, primitive, out),
+        (TypeDefPrimitive::U128, _) => fits::<u128, _>(as_uint(value)?, primitive, out),
+        (TypeDefPrimitive::I8, _) => fits::<i8, _>(as_int(value)?, primitive, out),
+        (TypeDefPrimitive::I16, _) => fits::<i16, _>(as_int(value)?, primitive, out),
+        (TypeDefPrimitive::I32, _) => fits::<i32, _>(as_int(value)?, primitive, out),
+        (TypeDefPrimitive::I64, _) => fits::<i64, _>(as_int(value)?, primitive, out),
+        (TypeDefPrimitive::I128, _) => fits::<i128, _>(as_int(value)?, primitive, out),
+        (TypeDefPrimitive::U256 | TypeDefPrimitive::I256, _) => {
+            Err(DynamicError::Unsupported("256 bit integers"))
+        }
+        _ => return None,
+    };
+    Some(result)
+}
+
+/// The value as an unsigned integer, if it is a non-negative integer.
+fn as_uint(value: &Value) -> Option<u128> {
+    match value {
+        Value::UInt(n) => Some(*n),
+        Value::Int(n) => u128::try_from(*n).ok(),
+        _ => None,
+    }
+}
+
+/// The value as a signed integer, if it is an integer that fits.
+fn as_int(value: &Value) -> Option<i128> {
+    match value {
+        Value::Int(n) => Some(*n),
+        Value::UInt(n) => i128::try_from(*n).ok(),
+        _ => None,
+    }
+}
+
+/// The primitive type inside a compact type, looking through newtypes such as `Perbill`.
+fn compact_primitive(
+    type_id: u32,
+    types: &PortableRegistry,
+) -> Result<TypeDefPrimitive, DynamicError> {
+    let ty = types
+        .resolve(type_id)
+        .ok_or(DynamicError::TypeNotFound(type_id))?;
+    match ty.type_def() {
+        TypeDef::Primitive(primitive) => Ok(primitive.clone()),
+        TypeDef::Composite(composite) if composite.fields().len() == 1 => {
+            compact_primitive(composite.fields()[0].ty().id(), types)
+        }
+        _ => Err(DynamicError::Unsupported("Compact non-integer types")),
+    }
+}
+
+/// A call of a pallet, both given by name, with its arguments given as [`Value`]s.
+///
+/// This can be submitted like any other call, for instance with
+/// [`crate::SubmittableExtrinsic::new`]. The arguments are encoded when the call is
+/// created, and so it must be submitted to a runtime with the same metadata.
+#[derive(Clone, Debug)]
+pub struct DynamicCall {
+    pallet: String,
+    call: String,
+    pallet_index: u8,
+    call_index: u8,
+    args: Vec<u8>,
+}
+
+impl DynamicCall {
+    /// Look up the call in the metadata, and encode the arguments accordingly.
+    pub fn new<C: Into<Composite>>(
+        metadata: &Metadata,
+        pallet: &str,
+        call: &str,
+        args: C,
+    ) -> Result<Self, DynamicError> {
+        let call_not_found = || {
+            DynamicError::CallNotFound {
+                pallet: pallet.to_string(),
+                call: call.to_string(),
+            }
+        };
+        let pallet_metadata = metadata
+            .runtime_metadata()
+            .pallets
+            .iter()
+            .find(|p| p.name == pallet)
+            .ok_or_else(|| DynamicError::PalletNotFound(pallet.to_string()))?;
+        let calls_ty_id = pallet_metadata
+            .calls
+            .as_ref()
+            .ok_or_else(call_not_found)?
+            .ty
+            .id();
+        let types = &metadata.runtime_metadata().types;
+        let variant = match types
+            .resolve(calls_ty_id)
+            .ok_or(DynamicError::TypeNotFound(calls_ty_id))?
+            .type_def()
+        {
+            TypeDef::Variant(variants) => {
+                variants
+                    .variants()
+                    .iter()
+                    .find(|v| v.name() == call)
+                    .ok_or_else(call_not_found)?
+            }
+            _ => return Err(call_not_found()),
+        };
+
+        let mut encoded_args = Vec::new();
+        encode_fields(&args.into(), variant.fields(), types, &mut encoded_args)?;
+        Ok(Self {
+            pallet: pallet.to_string(),
+            call: call.to_string(),
+            pallet_index: pallet_metadata.index,
+            call_index: variant.index(),
+            args: encoded_args,
+        })
+    }
+}
+
+impl Encode for DynamicCall {
+    fn size_hint(&self) -> usize {
+        self.args.len()
+    }
+
+    fn encode_to<O: codec::Output + ?Sized>(&self, dest: &mut O) {
+        dest.write(&self.args)
+    }
+}
+
+impl Call for DynamicCall {
+    // The names are only known at runtime; see `pallet_name` and `function_name`.
+    const PALLET: &'static str = "";
+    const FUNCTION: &'static str = "";
+
+    fn pallet_name(&self) -> &str {
+        &self.pallet
+    }
+
+    fn function_name(&self) -> &str {
+        &self.call
+    }
+
+    fn encode_with_metadata(&self, _metadata: &Metadata) -> Result<Encoded, BasicError> {
+        let mut bytes = vec![self.pallet_index, self.call_index];
+        bytes.extend_from_slice(&self.args);
+        Ok(Encoded(bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use frame_metadata::{
+        v14::{
+            ExtrinsicMetadata,
+            PalletCallMetadata,
+            PalletMetadata,
+            RuntimeMetadataLastVersion,
+        },
+        RuntimeMetadataPrefixed,
+    };
+    use scale_info::{
+        meta_type,
+        Registry,
+        TypeInfo,
+    };
+    use std::convert::TryFrom;
+
+    #[derive(Encode, TypeInfo)]
+    struct AccountId([u8; 4]);
+
+    #[derive(Encode, TypeInfo)]
+    enum Address {
+        Id(AccountId),
+        Index(#[codec(compact)] u32),
+    }
+
+    #[derive(Encode, TypeInfo)]
+    struct Transfer {
+        dest: Address,
+        #[codec(compact)]
+        amount: u128,
+        memo: Vec<u8>,
+        flags: (bool, String),
+        delta: i16,
+    }
+
+    #[allow(dead_code)]
+    #[derive(TypeInfo)]
+    enum TestCall {
+        #[allow(non_camel_case_types)]
+        remark { remark: Vec<u8> },
+        #[allow(non_camel_case_types)]
+        transfer { dest: Address, amount: u64 },
+    }
+
+    fn encode_as<T: TypeInfo + 'static>(value: &Value) -> Result<Vec<u8>, DynamicError> {
+        let mut registry = Registry::new();
+        let type_id = registry.register_type(&meta_type::<T>()).id();
+        let types: PortableRegistry = registry.into();
+        let mut out = Vec::new();
+        encode_value(value, type_id, &types, &mut out)?;
+        Ok(out)
+    }
+
+    fn decode_as<T: TypeInfo + 'static>(bytes: &[u8]) -> Value {
+        let mut registry = Registry::new();
+        let type_id = registry.register_type(&meta_type::<T>()).id();
+        let types: PortableRegistry = registry.into();
+        let input = &mut &bytes[..];
+        let value = decode_value(type_id, &types, input).unwrap();
+        assert!(input.is_empty());
+        value
+    }
+
+    fn transfer_value(amount: u128) -> Value {
+        Value::named_composite(vec![
+            ("memo", Value::bytes(b"hi")),
+            ("amount", Value::uint(amount)),
+            (
+                "dest",
+                Value::variant("Id", vec![Value::bytes([1, 2, 3, 4])]),
+            ),
+            (
+                "flags",
+                Value::unnamed_composite(vec![true.into(), "yes".into()]),
+            ),
+            ("delta", Value::int(-5i16)),
+        ])
+    }
+
+    #[test]
+    fn values_encode_like_the_types_they_describe() {
+        let expected = Transfer {
+            dest: Address::Id(AccountId([1, 2, 3, 4])),
+            amount: 1_000_000,
+            memo: b"hi".to_vec(),
+            flags: (true, "yes".to_string()),
+            delta: -5,
+        };
+        assert_eq!(
+            encode_as::<Transfer>(&transfer_value(1_000_000)).unwrap(),
+            expected.encode()
+        );
+
+        let index = Value::variant("Index", vec![Value::uint(7u32)]);
+        assert_eq!(
+            encode_as::<Address>(&index).unwrap(),
+            Address::Index(7).encode()
+        );
+    }
+
+    #[test]
+    fn newtypes_of_one_value_are_unwrapped_only_when_needed() {
+        // Shaped like `frame_support::BoundedVec`.
+        #[derive(Encode, TypeInfo)]
+        struct BoundedVec<T>(Vec<T>);
+
+        // A single byte is the vector itself, rather than the newtype around it.
+        assert_eq!(
+            encode_as::<BoundedVec<u8>>(&Value::bytes([7])).unwrap(),
+            BoundedVec(vec![7u8]).encode()
+        );
+        assert_eq!(
+            encode_as::<BoundedVec<u8>>(&Value::bytes([7, 8])).unwrap(),
+            BoundedVec(vec![7u8, 8]).encode()
+        );
+        // The vector may still be wrapped in a composite standing for the newtype.
+        assert_eq!(
+            encode_as::<BoundedVec<u8>>(&Value::unnamed_composite(vec![Value::bytes([
+                7
+            ])]))
+            .unwrap(),
+            BoundedVec(vec![7u8]).encode()
+        );
+        assert_eq!(
+            encode_as::<AccountId>(&Value::unnamed_composite(vec![Value::bytes([
+                1, 2, 3, 4
+            ])]))
+            .unwrap(),
+            AccountId([1, 2, 3, 4]).encode()
+        );
+    }
+
+    #[test]
+    fn values_decode_into_what_they_were_encoded_from() {
+        let value = transfer_value(1_000_000);
+        let decoded = decode_as::<Transfer>(&encode_as::<Transfer>(&value).unwrap());
+
+        // Named fields come out in the order of the type.
+        assert_eq!(decoded.field("amount"), Some(&Value::uint(1_000_000u32)));
+        assert_eq!(decoded.field("delta"), Some(&Value::int(-5i8)));
+        assert_eq!(
+            decoded.field("memo"),
+            Some(&Value::unnamed_composite(vec![
+                Value::uint(b'h'),
+                Value::uint(b'i')
+            ]))
+        );
+        assert_eq!(
+            decoded.field("dest"),
+            Some(&Value::variant(
+                "Id",
+                vec![Value::unnamed_composite(vec![Value::bytes([1, 2, 3, 4])])]
+            ))
+        );
+        // Which re-encodes to the same thing.
+        assert_eq!(
+            encode_as::<Transfer>(&decoded).unwrap(),
+            encode_as::<Transfer>(&value).unwrap()
+        );
+    }
+
+    #[test]
+    fn mismatched_values_are_rejected() {
+        assert_eq!(
+            encode_as::<u8>(&Value::uint(256u16)),
+            Err(DynamicError::OutOfRange("256".into(), TypeDefPrimitive::U8))
+        );
+        assert!(matches!(
+            encode_as::<u32>(&Value::string("1")),
+            Err(DynamicError::Mismatch { .. })
+        ));
+        assert_eq!(
+            encode_as::<[u8; 4]>(&Value::bytes([1, 2, 3])),
+            Err(DynamicError::WrongLength {
+                expected: 4,
+                found: 3
+            })
+        );
+        assert_eq!(
+            encode_as::<Address>(&Value::variant("Raw", Vec::<Value>::new())),
+            Err(DynamicError::VariantNotFound("Raw".into()))
+        );
+        let mut missing_field = transfer_value(1);
+        if let Value::Composite(Composite::Named(fields)) = &mut missing_field {
+            fields[0].0 = "note".into();
+        }
+        assert_eq!(
+            encode_as::<Transfer>(&missing_field),
+            Err(DynamicError::MissingField("memo".into()))
+        );
+    }
+
+    #[test]
+    fn dynamic_calls_are_encoded_from_the_metadata() {
+        let pallets = vec![PalletMetadata {
+            name: "Test",
+            storage: None,
+            calls: Some(PalletCallMetadata {
+                ty: meta_type::<TestCall>(),
+            }),
+            event: None,
+            constants: vec![],
+            error: None,
+            index: 7,
+        }];
+        let extrinsic = ExtrinsicMetadata {
+            ty: meta_type::<()>(),
+            version: 0,
+            signed_extensions: vec![],
+        };
+        let v14 = RuntimeMetadataLastVersion::new(pallets, extrinsic, meta_type::<()>());
+        let runtime_metadata: RuntimeMetadataPrefixed = v14.into();
+        let metadata = Metadata::try_from(runtime_metadata).unwrap();
+
+        let call = DynamicCall::new(
+            &metadata,
+            "Test",
+            "transfer",
+            vec![
+                Value::variant("Index", vec![Value::uint(3u8)]),
+                500u64.into(),
+            ],
+        )
+        .unwrap();
+        assert_eq!(call.pallet_name(), "Test");
+        assert_eq!(call.function_name(), "transfer");
+
+        let mut expected = vec![7, 1];
+        Address::Index(3).encode_to(&mut expected);
+        500u64.encode_to(&mut expected);
+        assert_eq!(call.encode_with_metadata(&metadata).unwrap().0, expected);
+
+        assert!(matches!(
+            DynamicCall::new(&metadata, "Test", "burn", Vec::<Value>::new()),
+            Err(DynamicError::CallNotFound { .. })
+        ));
+        assert!(matches!(
+            DynamicCall::new(&metadata, "Balances", "transfer", Vec::<Value>::new()),
+            Err(DynamicError::PalletNotFound(_))
+        ));
+    }
+}
@@ -15,12 +15,20 @@
 // along with subxt.  If not, see <http://www.gnu.org/licenses/>.
 
 use crate::{
+    dynamic::{
+        decode_value,
+        Composite,
+        DynamicError,
+        Value,
+    },
     events::EventsDecodingError,
     metadata::{
         InvalidMetadataError,
         MetadataError,
     },
+    Metadata,
 };
+use codec::Encode;
 use core::fmt::Debug;
 use jsonrpsee::core::error::Error as RequestError;
 use sp_core::crypto::SecretStringError;
@@ -73,6 +81,39 @@ pub enum GenericError<E> {
     #[error("Module error: {0}")]
     /// An error from the `Module` variant of the generated `DispatchError`.
     Module(ModuleError),
+    /// The client has been shut down.
+    #[error("The client has been shut down")]
+    ShutDown,
+    /// The consumer of a buffered subscription fell behind, and this many items were dropped.
+    #[error("Subscription consumer fell behind; {0} items were dropped")]
+    SubscriptionLagged(usize),
+    /// Subscriptions were requested from a client which talks to the node over HTTP.
+    #[error("Subscriptions are not supported over HTTP; connect over WebSocket instead")]
+    SubscriptionsUnsupported,
+    /// A value received from the node is larger than the configured decode limit.
+    #[error("Value of {len} bytes exceeds the decode limit of {max} bytes")]
+    ValueTooLarge {
+        /// The size of the value, in bytes.
+        len: usize,
+        /// The largest size allowed, in bytes.
+        max: usize,
+    },
+    /// A storage proof received from the node doesn't match the state root it should
+    /// be checked against.
+    #[error("Invalid storage proof: {0}")]
+    InvalidStorageProof(String),
+    /// A dynamic value doesn't match the type it should be encoded as.
+    #[error("Dynamic value error: {0}")]
+    Dynamic(#[from] DynamicError),
+    /// The parameters given to [`crate::ClientBuilder::set_default_extra()`] are not
+    /// of the type taken by the `SignedExtra` of the transaction being signed.
+    #[error(
+        "Default extra parameters given to the client are not of the type `{expected}`"
+    )]
+    DefaultExtraMismatch {
+        /// The type of parameters taken by the `SignedExtra`.
+        expected: &'static str,
+    },
     /// Other error.
     #[error("Other error: {0}")]
     Other(String),
@@ -98,6 +139,19 @@ impl<E> GenericError<E> {
             GenericError::EventsDecoding(e) => GenericError::EventsDecoding(e),
             GenericError::Transaction(e) => GenericError::Transaction(e),
             GenericError::Module(e) => GenericError::Module(e),
+            GenericError::ShutDown => GenericError::ShutDown,
+            GenericError::SubscriptionLagged(n) => GenericError::SubscriptionLagged(n),
+            GenericError::SubscriptionsUnsupported => {
+                GenericError::SubscriptionsUnsupported
+            }
+            GenericError::ValueTooLarge { len, max } => {
+                GenericError::ValueTooLarge { len, max }
+            }
+            GenericError::InvalidStorageProof(e) => GenericError::InvalidStorageProof(e),
+            GenericError::Dynamic(e) => GenericError::Dynamic(e),
+            GenericError::DefaultExtraMismatch { expected } => {
+                GenericError::DefaultExtraMismatch { expected }
+            }
             GenericError::Other(e) => GenericError::Other(e),
             // This is the only branch we really care about:
             GenericError::Runtime(e) => GenericError::Runtime(f(e)),
@@ -113,6 +167,27 @@ impl BasicError {
     }
 }
 
+impl<E: HasModuleError> Error<E> {
+    /// Turn a runtime `DispatchError` into an error, using the metadata to look up the
+    /// details of module errors.
+    pub(crate) fn from_dispatch_error(metadata: &Metadata, dispatch_error: E) -> Self {
+        if let Some((pallet_idx, error_idx)) = dispatch_error.module_error_indices() {
+            match metadata.error(pallet_idx, error_idx) {
+                Ok(details) => {
+                    GenericError::Module(ModuleError {
+                        pallet: details.pallet().to_string(),
+                        error: details.error().to_string(),
+                        description: details.description().to_vec(),
+                    })
+                }
+                Err(e) => GenericError::Metadata(e),
+            }
+        } else {
+            GenericError::Runtime(RuntimeError(dispatch_error))
+        }
+    }
+}
+
 impl<E> From<BasicError> for Error<E> {
     fn from(err: BasicError) -> Self {
         err.into_error()
@@ -157,6 +232,15 @@ impl<E> RuntimeError<E> {
     pub fn inner(self) -> E {
         self.0
     }
+
+    /// Describe the runtime error using the metadata, for instance to learn that a
+    /// generated `DispatchError::Token(TokenError::NoFunds)` is `Token::NoFunds`.
+    pub fn details(&self, metadata: &Metadata) -> Result<DispatchErrorDetails, BasicError>
+    where
+        E: Encode,
+    {
+        DispatchErrorDetails::from_error(metadata, &self.0)
+    }
 }
 
 /// Transaction error.
@@ -170,6 +254,10 @@ pub enum TransactionError {
     /// This is probably because the block was retracted before being finalized.
     #[error("The block containing the transaction can no longer be found (perhaps it was on a non-finalized fork?)")]
     BlockHashNotFound,
+    /// No status update saying that the transaction had reached the state being
+    /// waited for arrived within the given time.
+    #[error("Timed out after {0:?} waiting for the transaction")]
+    TimedOut(std::time::Duration),
 }
 
 /// Details about a module error that has occurred.
@@ -184,6 +272,94 @@ pub struct ModuleError {
     pub description: Vec<String>,
 }
 
+/// A runtime `DispatchError`, decoded using the metadata rather than the generated
+/// types, with the details of module errors looked up in the metadata.
+#[derive(Clone, Debug, thiserror::Error)]
+pub enum DispatchErrorDetails {
+    /// An error from a pallet.
+    #[error("{0}")]
+    Module(ModuleError),
+    /// Any other error, given as the names of the nested variants it consists of, such
+    /// as `["Token", "NoFunds"]` or `["BadOrigin"]`.
+    #[error("{}", .0.join("::"))]
+    Other(Vec<String>),
+}
+
+impl DispatchErrorDetails {
+    /// Decode a SCALE encoded `DispatchError`, such as the one in a
+    /// `System::ExtrinsicFailed` event.
+    pub fn decode(metadata: &Metadata, bytes: &[u8]) -> Result<Self, BasicError> {
+        let types = &metadata.runtime_metadata().types;
+        let ty = types
+            .types()
+            .iter()
+            .find(|ty| ty.ty().path().segments() == ["sp_runtime", "DispatchError"])
+            .ok_or_else(|| {
+                BasicError::Other("sp_runtime::DispatchError type not found".into())
+            })?;
+        let value = decode_value(ty.id(), types, &mut &*bytes)?;
+        Self::from_value(metadata, &value)
+    }
+
+    /// Describe a `DispatchError`, such as the generated one, or one nested in an event
+    /// like `Utility::BatchInterrupted`.
+    pub fn from_error<E: Encode>(
+        metadata: &Metadata,
+        error: &E,
+    ) -> Result<Self, BasicError> {
+        Self::decode(metadata, &error.encode())
+    }
+
+    fn from_value(metadata: &Metadata, value: &Value) -> Result<Self, BasicError> {
+        let invalid = || BasicError::Other("Invalid DispatchError".into());
+        let variant = match value {
+            Value::Variant(variant) => variant,
+            _ => return Err(invalid()),
+        };
+        if variant.name != "Module" {
+            let mut names = vec![variant.name.clone()];
+            let mut fields = &variant.fields;
+            while let Some(Value::Variant(nested)) = single_value(fields) {
+                names.push(nested.name.clone());
+                fields = &nested.fields;
+            }
+            return Ok(DispatchErrorDetails::Other(names))
+        }
+
+        // Older runtimes have a `Module { index, error }` variant, and newer ones a
+        // `Module(ModuleError)` variant, whose error is a few bytes; the first is the
+        // index of the error, and the rest describe any error nested within it.
+        let module = match single_value(&variant.fields) {
+            Some(module @ Value::Composite(_)) => module,
+            _ => value,
+        };
+        let index = module
+            .field("index")
+            .and_then(Value::as_u128)
+            .ok_or_else(invalid)?;
+        let error = match module.field("error").ok_or_else(invalid)? {
+            Value::Composite(bytes) => bytes.values().next().and_then(Value::as_u128),
+            error => error.as_u128(),
+        }
+        .ok_or_else(invalid)?;
+        let details = metadata.error(index as u8, error as u8)?;
+        Ok(DispatchErrorDetails::Module(ModuleError {
+            pallet: details.pallet().to_string(),
+            error: details.error().to_string(),
+            description: details.description().to_vec(),
+        }))
+    }
+}
+
+/// The only value of a composite, if it has exactly one.
+fn single_value(fields: &Composite) -> Option<&Value> {
+    let mut values = fields.values();
+    match (values.next(), values.next()) {
+        (Some(value), None) => Some(value),
+        _ => None,
+    }
+}
+
 /// This trait is automatically implemented for the generated `DispatchError`,
 /// so that we can pluck out information about the `Module` error variant, if`
 /// it exists.
@@ -192,3 +368,98 @@ pub trait HasModuleError {
     /// pallet index and error index. Else, return `None`.
     fn module_error_indices(&self) -> Option<(u8, u8)>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use frame_metadata::{
+        v14::{
+            ExtrinsicMetadata,
+            PalletErrorMetadata,
+            PalletMetadata,
+            RuntimeMetadataLastVersion,
+        },
+        RuntimeMetadataPrefixed,
+    };
+    use scale_info::meta_type;
+    use sp_runtime::{
+        ArithmeticError,
+        DispatchError,
+        TokenError,
+    };
+    use std::convert::TryFrom;
+
+    #[allow(dead_code)]
+    #[derive(scale_info::TypeInfo)]
+    enum TestError {
+        Broken,
+        Missing,
+    }
+
+    fn metadata() -> Metadata {
+        let pallets = vec![PalletMetadata {
+            name: "Test",
+            storage: None,
+            calls: None,
+            event: None,
+            constants: vec![],
+            error: Some(PalletErrorMetadata {
+                ty: meta_type::<TestError>(),
+            }),
+            index: 12,
+        }];
+        let extrinsic = ExtrinsicMetadata {
+            ty: meta_type::<()>(),
+            version: 0,
+            signed_extensions: vec![],
+        };
+        let v14 = RuntimeMetadataLastVersion::new(
+            pallets,
+            extrinsic,
+            meta_type::<DispatchError>(),
+        );
+        let runtime_metadata: RuntimeMetadataPrefixed = v14.into();
+        Metadata::try_from(runtime_metadata).unwrap()
+    }
+
+    #[test]
+    fn module_errors_are_looked_up_in_the_metadata() {
+        let error = DispatchError::Module(sp_runtime::ModuleError {
+            index: 12,
+            error: [1, 0, 0, 0],
+            message: None,
+        });
+        match DispatchErrorDetails::from_error(&metadata(), &error).unwrap() {
+            DispatchErrorDetails::Module(module) => {
+                assert_eq!(module.pallet, "Test");
+                assert_eq!(module.error, "Missing");
+            }
+            other => panic!("expected a module error, got {:?}", other),
+        }
+
+        let unknown = DispatchError::Module(sp_runtime::ModuleError {
+            index: 12,
+            error: [3, 0, 0, 0],
+            message: None,
+        });
+        assert!(DispatchErrorDetails::from_error(&metadata(), &unknown).is_err());
+    }
+
+    #[test]
+    fn other_errors_are_described_by_their_variants() {
+        let describe = |error: DispatchError| {
+            DispatchErrorDetails::from_error(&metadata(), &error)
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(describe(DispatchError::BadOrigin), "BadOrigin");
+        assert_eq!(
+            describe(DispatchError::Token(TokenError::NoFunds)),
+            "Token::NoFunds"
+        );
+        assert_eq!(
+            describe(DispatchError::Arithmetic(ArithmeticError::Overflow)),
+            "Arithmetic::Overflow"
+        );
+    }
+}
@@ -18,6 +18,10 @@
 
 use crate::{
     error::BasicError,
+    rpc::{
+        RpcSubscription,
+        ShutdownSignal,
+    },
     Client,
     Config,
 };
@@ -34,7 +38,6 @@ use futures::{
     Stream,
     StreamExt,
 };
-use jsonrpsee::core::client::Subscription;
 use sp_runtime::traits::Header;
 use std::{
     marker::Unpin,
@@ -150,10 +153,10 @@ where
 #[doc(hidden)]
 pub type FinalizedEventSub<'a, Header> = BoxStream<'a, Result<Header, BasicError>>;
 
-/// A `jsonrpsee` Subscription. This forms a part of the `EventSubscription` type handed back
+/// A subscription to the node. This forms a part of the `EventSubscription` type handed back
 /// in codegen from `subscribe`, and is exposed to be used in codegen.
 #[doc(hidden)]
-pub type EventSub<Item> = Subscription<Item>;
+pub type EventSub<Item> = RpcSubscription<Item>;
 
 /// A subscription to events that implements [`Stream`], and returns [`Events`] objects for each block.
 #[derive(Derivative)]
@@ -163,6 +166,8 @@ pub struct EventSubscription<'a, Sub, T: Config, Evs: 'static> {
     client: &'a Client<T>,
     block_header_subscription: Sub,
     #[derivative(Debug = "ignore")]
+    shutdown: ShutdownSignal,
+    #[derivative(Debug = "ignore")]
     at: Option<
         std::pin::Pin<
             Box<dyn Future<Output = Result<Events<'a, T, Evs>, BasicError>> + Send + 'a>,
@@ -179,6 +184,7 @@ where
     fn new(client: &'a Client<T>, block_header_subscription: Sub) -> Self {
         EventSubscription {
             finished: false,
+            shutdown: client.rpc().shutdown_signal(),
             client,
             block_header_subscription,
             at: None,
@@ -233,6 +239,13 @@ where
             return Poll::Ready(None)
         }
 
+        // The client has been shut down; end the subscription.
+        if self.shutdown.poll_shut_down(cx) {
+            self.finished = true;
+            self.at = None;
+            return Poll::Ready(None)
+        }
+
         // If there isn't an `at` function yet that's busy resolving a block hash into
         // some event details, then poll the block header subscription to get one.
         if self.at.is_none() {
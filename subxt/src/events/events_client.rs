@@ -0,0 +1,86 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A client for fetching and subscribing to events.
+
+use super::{
+    event_subscription::{
+        subscribe,
+        subscribe_finalized,
+        EventSub,
+        EventSubscription,
+        FinalizedEventSub,
+    },
+    events_type::at_with_metadata,
+    Events,
+};
+use crate::{
+    error::BasicError,
+    Client,
+    Config,
+};
+use codec::Decode;
+
+/// Client for fetching the events of blocks, and subscribing to the events of new ones,
+/// without needing the code generated for the runtime.
+///
+/// Each method is given the type `Evs` that events are statically decoded into by
+/// [`Events::iter()`], which is usually the outermost `Event` enum of the generated code.
+/// The events can be dynamically decoded with [`Events::iter_raw()`] and
+/// [`Events::find()`] regardless.
+pub struct EventsClient<'a, T: Config> {
+    client: &'a Client<T>,
+}
+
+impl<'a, T: Config> EventsClient<'a, T> {
+    /// Create a new [`EventsClient`].
+    pub fn new(client: &'a Client<T>) -> Self {
+        Self { client }
+    }
+
+    /// Obtain the events of the block with the given hash, which may be any block in
+    /// the history of the chain. They're decoded using the metadata of the runtime that
+    /// was active at that block; see [`Client::metadata_at()`].
+    ///
+    /// The phase of each event says which extrinsic in the block emitted it, if any;
+    /// see also [`Events::iter_raw_for_extrinsic()`].
+    pub async fn at<Evs: Decode>(
+        &self,
+        block_hash: T::Hash,
+    ) -> Result<Events<'a, T, Evs>, BasicError> {
+        let metadata = self.client.metadata_at(block_hash).await?;
+        at_with_metadata(self.client.rpc(), metadata, block_hash).await
+    }
+
+    /// Subscribe to the events of each new block.
+    ///
+    /// **Note:** these blocks haven't necessarily been finalized yet; prefer
+    /// [`EventsClient::subscribe_finalized()`] if that is important.
+    pub async fn subscribe<Evs: Decode + 'static>(
+        &self,
+    ) -> Result<EventSubscription<'a, EventSub<T::Header>, T, Evs>, BasicError> {
+        subscribe::<T, Evs>(self.client).await
+    }
+
+    /// Subscribe to the events of each finalized block. No blocks are skipped, even if
+    /// the node reports that several have been finalized at once.
+    pub async fn subscribe_finalized<Evs: Decode + 'static>(
+        &self,
+    ) -> Result<EventSubscription<'a, FinalizedEventSub<'a, T::Header>, T, Evs>, BasicError>
+    {
+        subscribe_finalized::<T, Evs>(self.client).await
+    }
+}
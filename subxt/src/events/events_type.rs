@@ -18,7 +18,12 @@
 
 use super::decoding;
 use crate::{
+    dynamic::{
+        self,
+        Composite,
+    },
     error::BasicError,
+    rpc::Rpc,
     Client,
     Config,
     Event,
@@ -28,6 +33,7 @@ use crate::{
 use codec::{
     Compact,
     Decode,
+    DecodeLimit,
     Error as CodecError,
     Input,
 };
@@ -37,6 +43,7 @@ use sp_core::{
     twox_128,
     Bytes,
 };
+use std::sync::Arc;
 
 /// Obtain events at some block hash. The generic parameter is what we
 /// will attempt to decode each event into if using [`Events::iter()`],
@@ -51,12 +58,23 @@ pub async fn at<T: Config, Evs: Decode>(
     client: &'_ Client<T>,
     block_hash: T::Hash,
 ) -> Result<Events<'_, T, Evs>, BasicError> {
-    let mut event_bytes = client
-        .rpc()
+    at_with_metadata(client.rpc(), client.metadata(), block_hash).await
+}
+
+/// Obtain events at some block hash, decoding them with the metadata provided
+/// rather than that of the current runtime.
+pub(crate) async fn at_with_metadata<'a, T: Config, Evs: Decode>(
+    rpc: &Rpc<T>,
+    metadata: Arc<Metadata>,
+    block_hash: T::Hash,
+) -> Result<Events<'a, T, Evs>, BasicError> {
+    let mut event_bytes = rpc
         .storage(&system_events_key(), Some(block_hash))
         .await?
         .map(|s| s.0)
         .unwrap_or_else(Vec::new);
+    let decode_limits = rpc.decode_limits();
+    decode_limits.check_size(event_bytes.len())?;
 
     // event_bytes is a SCALE encoded vector of events. So, pluck the
     // compact encoded length from the front, leaving the remaining bytes
@@ -71,11 +89,13 @@ pub async fn at<T: Config, Evs: Decode>(
     event_bytes.drain(0..event_bytes_len - remaining_len);
 
     Ok(Events {
-        metadata: client.metadata(),
+        metadata,
         block_hash,
         event_bytes,
         num_events,
+        max_depth: decode_limits.max_depth,
         _event_type: std::marker::PhantomData,
+        _lifetime: std::marker::PhantomData,
     })
 }
 
@@ -91,14 +111,19 @@ fn system_events_key() -> StorageKey {
 #[derive(Derivative)]
 #[derivative(Debug(bound = ""))]
 pub struct Events<'a, T: Config, Evs> {
-    metadata: &'a Metadata,
+    // Held onto rather than borrowed from the client, since the client's metadata
+    // is replaced when the runtime is upgraded.
+    metadata: Arc<Metadata>,
     block_hash: T::Hash,
     // Note; raw event bytes are prefixed with a Compact<u32> containing
     // the number of events to be decoded. We should have stripped that off
     // before storing the bytes here.
     event_bytes: Vec<u8>,
     num_events: u32,
+    // How deeply nested statically decoded events are allowed to be.
+    max_depth: u32,
     _event_type: std::marker::PhantomData<Evs>,
+    _lifetime: std::marker::PhantomData<&'a ()>,
 }
 
 impl<'a, T: Config, Evs: Decode> Events<'a, T, Evs> {
@@ -142,7 +167,7 @@ impl<'a, T: Config, Evs: Decode> Events<'a, T, Evs> {
             } else {
                 let mut decode_one_event = || -> Result<_, BasicError> {
                     let phase = Phase::decode(cursor)?;
-                    let ev = Evs::decode(cursor)?;
+                    let ev = Evs::decode_with_depth_limit(self.max_depth, cursor)?;
                     let _topics = Vec::<T::Hash>::decode(cursor)?;
                     Ok((phase, ev))
                 };
@@ -192,7 +217,7 @@ impl<'a, T: Config, Evs: Decode> Events<'a, T, Evs> {
             if start_len == 0 || self.num_events == index {
                 None
             } else {
-                match decode_raw_event_details::<T>(self.metadata, index, cursor) {
+                match decode_raw_event_details::<T>(&self.metadata, index, cursor) {
                     Ok(raw_event) => {
                         // Skip over decoded bytes in next iteration:
                         pos += start_len - cursor.len();
@@ -235,7 +260,7 @@ impl<'a, T: Config, Evs: Decode> Events<'a, T, Evs> {
             if start_len == 0 || self.num_events == index {
                 None
             } else {
-                match decode_raw_event_details::<T>(self.metadata, index, cursor) {
+                match decode_raw_event_details::<T>(&self.metadata, index, cursor) {
                     Ok(raw_event) => {
                         // Skip over decoded bytes in next iteration:
                         pos += start_len - cursor.len();
@@ -256,6 +281,33 @@ impl<'a, T: Config, Evs: Decode> Events<'a, T, Evs> {
         })
     }
 
+    /// Iterate over all of the events, using metadata to decode each of them into
+    /// [`dynamic::Value`]s. If an error occurs, all subsequent iterations return `None`.
+    ///
+    /// Like [`Events::iter_raw()`], this is safe to use even if you do not statically
+    /// know about all of the possible events.
+    pub fn iter_dynamic(
+        &self,
+    ) -> impl Iterator<Item = Result<DynamicEventDetails, BasicError>> + '_ {
+        self.iter_raw()
+            .map(|ev| ev.and_then(|ev| ev.to_dynamic(&self.metadata)))
+    }
+
+    /// Iterate over the events emitted while applying the extrinsic at the given index
+    /// in the block, using metadata to dynamically decode them as we go. If an error
+    /// occurs, all subsequent iterations return `None`.
+    pub fn iter_raw_for_extrinsic(
+        &self,
+        extrinsic_index: u32,
+    ) -> impl Iterator<Item = Result<RawEventDetails, BasicError>> + '_ {
+        self.iter_raw().filter(move |ev| {
+            match ev {
+                Ok(ev) => ev.phase.extrinsic_index() == Some(extrinsic_index),
+                Err(_) => true,
+            }
+        })
+    }
+
     /// Iterate through the events using metadata to dynamically decode and skip
     /// them, and return only those which should decode to the provided `Ev` type.
     /// If an error occurs, all subsequent iterations return `None`.
@@ -327,6 +379,43 @@ impl RawEventDetails {
             Ok(None)
         }
     }
+
+    /// Decode the fields of this event into [`dynamic::Value`]s using the metadata.
+    pub fn to_dynamic(
+        &self,
+        metadata: &Metadata,
+    ) -> Result<DynamicEventDetails, BasicError> {
+        let event_metadata = metadata.event(self.pallet_index, self.variant_index)?;
+        let input = &mut &self.data[..];
+        let fields = dynamic::decode_fields(
+            event_metadata.variant().fields(),
+            &metadata.runtime_metadata().types,
+            input,
+        )?;
+        Ok(DynamicEventDetails {
+            phase: self.phase.clone(),
+            index: self.index,
+            pallet: self.pallet.clone(),
+            variant: self.variant.clone(),
+            fields,
+        })
+    }
+}
+
+/// An event decoded into [`dynamic::Value`]s using the metadata, with associated
+/// details. This serializes (for instance to JSON) with field and variant names.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct DynamicEventDetails {
+    /// When was the event produced?
+    pub phase: Phase,
+    /// What index is this event in the stored events for this block.
+    pub index: u32,
+    /// The name of the pallet from whence the Event originated.
+    pub pallet: String,
+    /// The name of the pallet Event variant.
+    pub variant: String,
+    /// The fields of the event.
+    pub fields: Composite,
 }
 
 // Attempt to dynamically decode a single event from our events input.
@@ -489,9 +578,11 @@ pub(crate) mod test_utils {
         Events {
             block_hash: <DefaultConfig as Config>::Hash::default(),
             event_bytes,
-            metadata,
+            metadata: Arc::new(metadata.clone()),
             num_events,
+            max_depth: crate::DecodeLimits::default().max_depth,
             _event_type: std::marker::PhantomData,
+            _lifetime: std::marker::PhantomData,
         }
     }
 }
@@ -587,6 +678,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn dynamically_decode_events_of_one_extrinsic() {
+        #[derive(Clone, Debug, PartialEq, Decode, Encode, TypeInfo)]
+        enum Event {
+            A(u8),
+        }
+
+        let metadata = metadata::<Event>();
+        let events = events::<Event>(
+            &metadata,
+            vec![
+                event_record(Phase::Initialization, Event::A(1)),
+                event_record(Phase::ApplyExtrinsic(0), Event::A(2)),
+                event_record(Phase::ApplyExtrinsic(1), Event::A(3)),
+                event_record(Phase::ApplyExtrinsic(1), Event::A(4)),
+                event_record(Phase::Finalization, Event::A(5)),
+            ],
+        );
+
+        let data: Vec<Vec<u8>> = events
+            .iter_raw_for_extrinsic(1)
+            .map(|ev| ev.unwrap().data.0)
+            .collect();
+        assert_eq!(data, vec![3u8.encode(), 4u8.encode()]);
+    }
+
     #[test]
     fn statically_decode_multiple_events_until_error() {
         #[derive(Clone, Debug, PartialEq, Decode, Encode, TypeInfo)]
@@ -986,4 +1103,58 @@ mod tests {
             }]
         );
     }
+
+    #[test]
+    fn dynamically_decode_events_to_json() {
+        #[derive(Clone, Debug, PartialEq, Decode, Encode, TypeInfo)]
+        enum Event {
+            Transfer { from: u8, to: u8, amount: u64 },
+            Tuple(bool, String),
+            Unit,
+        }
+
+        // Create fake metadata that knows about our single event, above:
+        let metadata = metadata::<Event>();
+
+        let events = events::<Event>(
+            &metadata,
+            vec![
+                event_record(
+                    Phase::ApplyExtrinsic(1),
+                    Event::Transfer {
+                        from: 1,
+                        to: 2,
+                        amount: 300,
+                    },
+                ),
+                event_record(Phase::Finalization, Event::Tuple(true, "hi".into())),
+                event_record(Phase::Finalization, Event::Unit),
+            ],
+        );
+
+        let event_details: Vec<DynamicEventDetails> =
+            events.iter_dynamic().collect::<Result<_, _>>().unwrap();
+        assert_eq!(event_details[0].variant, "Transfer");
+        assert_eq!(
+            event_details[0].fields,
+            Composite::Named(vec![
+                ("from".into(), dynamic::Value::uint(1u8)),
+                ("to".into(), dynamic::Value::uint(2u8)),
+                ("amount".into(), dynamic::Value::uint(300u64)),
+            ])
+        );
+
+        let json = event_details
+            .iter()
+            .map(|ev| serde_json::to_string(ev).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            json,
+            vec![
+                r#"{"phase":{"ApplyExtrinsic":1},"index":0,"pallet":"Test","variant":"Transfer","fields":{"from":1,"to":2,"amount":300}}"#,
+                r#"{"phase":"Finalization","index":1,"pallet":"Test","variant":"Tuple","fields":[true,"hi"]}"#,
+                r#"{"phase":"Finalization","index":2,"pallet":"Test","variant":"Unit","fields":[]}"#,
+            ]
+        );
+    }
 }
@@ -18,9 +18,11 @@
 
 mod decoding;
 mod event_subscription;
+mod events_client;
 mod events_type;
 mod filter_events;
 
+pub(crate) use decoding::decode_and_consume_type;
 pub use decoding::EventsDecodingError;
 pub use event_subscription::{
     subscribe,
@@ -30,8 +32,11 @@ pub use event_subscription::{
     EventSubscription,
     FinalizedEventSub,
 };
+pub use events_client::EventsClient;
+pub(crate) use events_type::at_with_metadata;
 pub use events_type::{
     at,
+    DynamicEventDetails,
     EventDetails,
     Events,
     RawEventDetails,
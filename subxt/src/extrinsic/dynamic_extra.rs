@@ -0,0 +1,693 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Signed extra data built from the list of signed extensions in the metadata.
+
+use super::{
+    extra::{
+        AssetTxPaymentParameters,
+        ChargeAssetTxPayment,
+        ChargeTransactionPayment,
+        CheckMortality,
+        CheckNonce,
+        PaymentExtension,
+        TxPaymentParameters,
+    },
+    SignedExtra,
+};
+use crate::{
+    error::BasicError,
+    events::decode_and_consume_type,
+    metadata::MetadataError,
+    Config,
+    Metadata,
+    PhantomDataSendSync,
+};
+use codec::{
+    Compact,
+    Decode,
+    Encode,
+    Input,
+};
+use derivative::Derivative;
+use scale_info::{
+    PortableRegistry,
+    TypeDef,
+    TypeInfo,
+};
+use sp_runtime::{
+    generic::Era,
+    traits::{
+        DispatchInfoOf,
+        SignedExtension,
+    },
+    transaction_validity::TransactionValidityError,
+};
+use std::collections::HashMap;
+
+/// The signed extensions assumed to be in use when no metadata is available,
+/// matching [`crate::DefaultExtra`].
+const DEFAULT_EXTENSIONS: &[&str] = &[
+    "CheckSpecVersion",
+    "CheckTxVersion",
+    "CheckGenesis",
+    "CheckMortality",
+    "CheckNonce",
+    "CheckWeight",
+    "ChargeTransactionPayment",
+];
+
+/// Parameters for [`DynamicExtra`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DynamicExtraParameters {
+    tip: u128,
+    asset_id: Option<u32>,
+    app_id: u32,
+    mortal_period: Option<u64>,
+    custom: HashMap<String, (Vec<u8>, Vec<u8>)>,
+}
+
+impl DynamicExtraParameters {
+    /// Creates new parameters, without a tip and with an `app_id` of 0.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the tip for the block author.
+    pub fn set_tip(mut self, tip: u128) -> Self {
+        self.tip = tip;
+        self
+    }
+
+    /// Pay the fees in the asset with the given id, if the chain uses the
+    /// `ChargeAssetTxPayment` extension.
+    pub fn set_asset_id(mut self, asset_id: u32) -> Self {
+        self.asset_id = Some(asset_id);
+        self
+    }
+
+    /// Set the application id, if the chain uses the `CheckAppId` extension.
+    pub fn set_app_id(mut self, app_id: u32) -> Self {
+        self.app_id = app_id;
+        self
+    }
+
+    /// Make the transaction mortal, so that it's only valid for roughly the given
+    /// number of blocks from the current best block. See [`Era::mortal()`] for how the
+    /// period is rounded.
+    pub fn set_mortal(mut self, period: u64) -> Self {
+        self.mortal_period = Some(period);
+        self
+    }
+
+    /// Provide the values for the signed extension with the given identifier. These
+    /// take precedence over the values that would otherwise be used for it, and are
+    /// required for any extension carrying data that isn't known to subxt.
+    pub fn set_extension<E: Encode, A: Encode>(
+        mut self,
+        identifier: impl Into<String>,
+        extra: E,
+        additional_signed: A,
+    ) -> Self {
+        self.custom.insert(
+            identifier.into(),
+            (extra.encode(), additional_signed.encode()),
+        );
+        self
+    }
+}
+
+/// SCALE encoded bytes, which are encoded as-is rather than as a length prefixed vector.
+#[derive(Clone, Debug, Default, PartialEq, Eq, TypeInfo)]
+pub struct RawBytes(pub Vec<u8>);
+
+impl Encode for RawBytes {
+    fn size_hint(&self) -> usize {
+        self.0.len()
+    }
+
+    fn encode_to<O: codec::Output + ?Sized>(&self, dest: &mut O) {
+        dest.write(&self.0)
+    }
+}
+
+impl Decode for RawBytes {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, codec::Error> {
+        let len = input
+            .remaining_len()?
+            .ok_or("Cannot decode raw bytes of unknown length")?;
+        let mut bytes = vec![0; len];
+        input.read(&mut bytes)?;
+        Ok(RawBytes(bytes))
+    }
+}
+
+/// The version of the extrinsic format which [`decode_extrinsic`] understands.
+const EXTRINSIC_FORMAT_VERSION: u8 = 4;
+
+/// The encoded extra and additional signed data of all of the signed extensions
+/// of a runtime, in the order given by its metadata.
+///
+/// Where the extra data ends can only be told from the types in the metadata, so
+/// this can't be decoded with [`Decode`]; use [`Self::decode_with_metadata`] or
+/// [`decode_extrinsic`] instead.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Encode, TypeInfo)]
+pub struct DynamicSignedExtensions {
+    /// The data included in the extrinsic.
+    pub extra: RawBytes,
+    /// The data included in the signed payload only.
+    #[codec(skip)]
+    pub additional_signed: RawBytes,
+}
+
+impl DynamicSignedExtensions {
+    /// Decode the extra data of each of the signed extensions in the metadata, in
+    /// order. The additional signed data isn't part of an extrinsic, so is left empty.
+    pub fn decode_with_metadata(
+        input: &mut &[u8],
+        metadata: &Metadata,
+    ) -> Result<Self, BasicError> {
+        let runtime_metadata = metadata.runtime_metadata();
+        let start = *input;
+        for extension in &runtime_metadata.extrinsic.signed_extensions {
+            decode_and_consume_type(extension.ty.id(), &runtime_metadata.types, input)?;
+        }
+        let len = start.len() - input.len();
+        Ok(DynamicSignedExtensions {
+            extra: RawBytes(start[..len].to_vec()),
+            additional_signed: RawBytes::default(),
+        })
+    }
+}
+
+impl Decode for DynamicSignedExtensions {
+    fn decode<I: Input>(_input: &mut I) -> Result<Self, codec::Error> {
+        Err("Dynamic signed extensions can only be decoded with the metadata".into())
+    }
+}
+
+/// Decode a SCALE encoded extrinsic whose extra data is that of the signed extensions
+/// in the metadata, such as one signed with [`DynamicExtra`].
+pub fn decode_extrinsic<T, Call>(
+    bytes: &[u8],
+    metadata: &Metadata,
+) -> Result<
+    sp_runtime::generic::UncheckedExtrinsic<
+        T::Address,
+        Call,
+        T::Signature,
+        DynamicSignedExtensions,
+    >,
+    BasicError,
+>
+where
+    T: Config,
+    T::Signature: Decode,
+    Call: Decode,
+{
+    let input = &mut &bytes[..];
+    // The extrinsic is prefixed with its length, which we have no need for.
+    let _len = Compact::<u32>::decode(input)?;
+    let version = u8::decode(input)?;
+    if version & 0b0111_1111 != EXTRINSIC_FORMAT_VERSION {
+        return Err(BasicError::Other(format!(
+            "Unsupported extrinsic version {}",
+            version & 0b0111_1111
+        )))
+    }
+    let signature = if version & 0b1000_0000 != 0 {
+        let address = T::Address::decode(input)?;
+        let signature = T::Signature::decode(input)?;
+        let extra = DynamicSignedExtensions::decode_with_metadata(input, metadata)?;
+        Some((address, signature, extra))
+    } else {
+        None
+    };
+    let function = Call::decode(input)?;
+    Ok(sp_runtime::generic::UncheckedExtrinsic {
+        signature,
+        function,
+    })
+}
+
+impl SignedExtension for DynamicSignedExtensions {
+    const IDENTIFIER: &'static str = "DynamicSignedExtensions";
+    type AccountId = ();
+    type Call = ();
+    type AdditionalSigned = RawBytes;
+    type Pre = ();
+
+    fn additional_signed(
+        &self,
+    ) -> Result<Self::AdditionalSigned, TransactionValidityError> {
+        Ok(self.additional_signed.clone())
+    }
+    fn pre_dispatch(
+        self,
+        _who: &Self::AccountId,
+        _call: &Self::Call,
+        _info: &DispatchInfoOf<Self::Call>,
+        _len: usize,
+    ) -> Result<Self::Pre, TransactionValidityError> {
+        Ok(())
+    }
+}
+
+/// A `SignedExtra` which is constructed from the list of signed extensions in the
+/// metadata, rather than from a fixed set of extensions.
+///
+/// Values for the extensions known to subxt are filled in automatically, or taken
+/// from the [`DynamicExtraParameters`]; this means that transactions stay valid when
+/// a runtime upgrade adds, removes or reorders extensions, so long as those have no
+/// data or their data is known.
+#[derive(Derivative, Encode, Decode, TypeInfo)]
+#[derivative(
+    Clone(bound = ""),
+    PartialEq(bound = ""),
+    Debug(bound = ""),
+    Eq(bound = "")
+)]
+#[scale_info(skip_type_params(T))]
+pub struct DynamicExtra<T: Config> {
+    extensions: DynamicSignedExtensions,
+    marker: PhantomDataSendSync<T>,
+}
+
+impl<T: Config> DynamicExtra<T> {
+    fn build<'a>(
+        identifiers: impl IntoIterator<Item = (&'a str, Option<(u32, u32)>)>,
+        types: Option<&PortableRegistry>,
+        values: ExtensionValues<T>,
+    ) -> Result<Self, BasicError> {
+        let mut extensions = DynamicSignedExtensions::default();
+        for (identifier, type_ids) in identifiers {
+            let extra = &mut extensions.extra.0;
+            let additional = &mut extensions.additional_signed.0;
+            if let Some((custom_extra, custom_additional)) =
+                values.params.custom.get(identifier)
+            {
+                extra.extend_from_slice(custom_extra);
+                additional.extend_from_slice(custom_additional);
+                continue
+            }
+            if values.encode_known(identifier, extra, additional) {
+                continue
+            }
+            // Extensions without any data can safely be skipped.
+            match (types, type_ids) {
+                (Some(types), Some((ty, additional_ty)))
+                    if is_empty_type(types, ty)
+                        && is_empty_type(types, additional_ty) => {}
+                _ => {
+                    return Err(MetadataError::UnsupportedSignedExtension(
+                        identifier.to_string(),
+                    )
+                    .into())
+                }
+            }
+        }
+        Ok(DynamicExtra {
+            extensions,
+            marker: PhantomDataSendSync::new(),
+        })
+    }
+
+    fn build_from_metadata(
+        values: ExtensionValues<T>,
+        metadata: &Metadata,
+    ) -> Result<Self, BasicError> {
+        let runtime_metadata = metadata.runtime_metadata();
+        let identifiers =
+            runtime_metadata
+                .extrinsic
+                .signed_extensions
+                .iter()
+                .map(|ext| {
+                    (
+                        ext.identifier.as_str(),
+                        Some((ext.ty.id(), ext.additional_signed.id())),
+                    )
+                });
+        Self::build(identifiers, Some(&runtime_metadata.types), values)
+    }
+}
+
+impl<T: Config> SignedExtra<T> for DynamicExtra<T> {
+    type Extra = DynamicSignedExtensions;
+    type Parameters = DynamicExtraParameters;
+
+    fn new(
+        spec_version: u32,
+        tx_version: u32,
+        nonce: T::Index,
+        genesis_hash: T::Hash,
+        params: Self::Parameters,
+    ) -> Self {
+        let values = ExtensionValues {
+            spec_version,
+            tx_version,
+            nonce,
+            genesis_hash,
+            era: Era::Immortal,
+            checkpoint: genesis_hash,
+            params,
+        };
+        let identifiers = DEFAULT_EXTENSIONS.iter().map(|id| (*id, None));
+        Self::build(identifiers, None, values)
+            .expect("default signed extensions are all known; qed")
+    }
+
+    fn new_with_metadata(
+        spec_version: u32,
+        tx_version: u32,
+        nonce: T::Index,
+        genesis_hash: T::Hash,
+        params: Self::Parameters,
+        metadata: &Metadata,
+    ) -> Result<Self, BasicError> {
+        let values = ExtensionValues {
+            spec_version,
+            tx_version,
+            nonce,
+            genesis_hash,
+            era: Era::Immortal,
+            checkpoint: genesis_hash,
+            params,
+        };
+        Self::build_from_metadata(values, metadata)
+    }
+
+    fn mortal_period(params: &Self::Parameters) -> Option<u64> {
+        params.mortal_period
+    }
+
+    fn new_mortal(
+        spec_version: u32,
+        tx_version: u32,
+        nonce: T::Index,
+        genesis_hash: T::Hash,
+        era: Era,
+        checkpoint: T::Hash,
+        params: Self::Parameters,
+        metadata: &Metadata,
+    ) -> Result<Self, BasicError> {
+        let values = ExtensionValues {
+            spec_version,
+            tx_version,
+            nonce,
+            genesis_hash,
+            era,
+            checkpoint,
+            params,
+        };
+        Self::build_from_metadata(values, metadata)
+    }
+
+    fn extra(&self) -> Self::Extra {
+        self.extensions.clone()
+    }
+}
+
+impl<T: Config> SignedExtension for DynamicExtra<T> {
+    const IDENTIFIER: &'static str = "DynamicExtra";
+    type AccountId = T::AccountId;
+    type Call = ();
+    type AdditionalSigned = RawBytes;
+    type Pre = ();
+
+    fn additional_signed(
+        &self,
+    ) -> Result<Self::AdditionalSigned, TransactionValidityError> {
+        self.extensions.additional_signed()
+    }
+    fn pre_dispatch(
+        self,
+        _who: &Self::AccountId,
+        _call: &Self::Call,
+        _info: &DispatchInfoOf<Self::Call>,
+        _len: usize,
+    ) -> Result<Self::Pre, TransactionValidityError> {
+        Ok(())
+    }
+}
+
+/// The values from which the data of the known signed extensions is encoded.
+struct ExtensionValues<T: Config> {
+    spec_version: u32,
+    tx_version: u32,
+    nonce: T::Index,
+    genesis_hash: T::Hash,
+    era: Era,
+    // The hash of the block the era starts at; the genesis hash if immortal.
+    checkpoint: T::Hash,
+    params: DynamicExtraParameters,
+}
+
+impl<T: Config> ExtensionValues<T> {
+    /// Encode the data of the extension with the given identifier, returning
+    /// `false` if the extension is not known.
+    fn encode_known(
+        &self,
+        identifier: &str,
+        extra: &mut Vec<u8>,
+        additional: &mut Vec<u8>,
+    ) -> bool {
+        match identifier {
+            "CheckSpecVersion" => self.spec_version.encode_to(additional),
+            "CheckTxVersion" => self.tx_version.encode_to(additional),
+            "CheckGenesis" => self.genesis_hash.encode_to(additional),
+            "CheckMortality" | "CheckEra" => {
+                CheckMortality::<T>(
+                    (self.era, PhantomDataSendSync::new()),
+                    self.checkpoint,
+                )
+                .encode_to(extra);
+                self.checkpoint.encode_to(additional);
+            }
+            "CheckNonce" => CheckNonce::<T>(self.nonce).encode_to(extra),
+            "CheckWeight" | "CheckNonZeroSender" => {}
+            "ChargeTransactionPayment" => {
+                let params = TxPaymentParameters::new().set_tip(self.params.tip);
+                ChargeTransactionPayment::<T>::new(params).encode_to(extra)
+            }
+            "ChargeAssetTxPayment" => {
+                let mut params = AssetTxPaymentParameters::new().set_tip(self.params.tip);
+                if let Some(asset_id) = self.params.asset_id {
+                    params = params.set_asset_id(asset_id);
+                }
+                ChargeAssetTxPayment::<T>::new(params).encode_to(extra)
+            }
+            "CheckAppId" => Compact(self.params.app_id).encode_to(extra),
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// Does the given type encode to zero bytes?
+fn is_empty_type(types: &PortableRegistry, id: u32) -> bool {
+    match types.resolve(id).map(|ty| ty.type_def()) {
+        Some(TypeDef::Composite(composite)) => {
+            composite
+                .fields()
+                .iter()
+                .all(|field| is_empty_type(types, field.ty().id()))
+        }
+        Some(TypeDef::Tuple(tuple)) => {
+            tuple
+                .fields()
+                .iter()
+                .all(|ty| is_empty_type(types, ty.id()))
+        }
+        Some(TypeDef::Array(array)) => {
+            array.len() == 0 || is_empty_type(types, array.type_param().id())
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        DefaultConfig,
+        DefaultExtra,
+    };
+    use frame_metadata::{
+        v14::{
+            ExtrinsicMetadata,
+            RuntimeMetadataLastVersion,
+            SignedExtensionMetadata,
+        },
+        RuntimeMetadataPrefixed,
+    };
+    use scale_info::meta_type;
+    use std::convert::TryFrom;
+
+    fn metadata(signed_extensions: Vec<SignedExtensionMetadata>) -> Metadata {
+        let extrinsic = ExtrinsicMetadata {
+            ty: meta_type::<()>(),
+            version: 4,
+            signed_extensions,
+        };
+        let v14 = RuntimeMetadataLastVersion::new(vec![], extrinsic, meta_type::<()>());
+        let runtime_metadata: RuntimeMetadataPrefixed = v14.into();
+        Metadata::try_from(runtime_metadata).unwrap()
+    }
+
+    fn extension<E: TypeInfo + 'static, A: TypeInfo + 'static>(
+        identifier: &'static str,
+    ) -> SignedExtensionMetadata {
+        SignedExtensionMetadata {
+            identifier,
+            ty: meta_type::<E>(),
+            additional_signed: meta_type::<A>(),
+        }
+    }
+
+    #[test]
+    fn default_extensions_match_default_extra() {
+        let genesis_hash = Default::default();
+        let dynamic = DynamicExtra::<DefaultConfig>::new(
+            1,
+            2,
+            3,
+            genesis_hash,
+            DynamicExtraParameters::new().set_tip(4),
+        );
+        let expected = DefaultExtra::<DefaultConfig>::new(
+            1,
+            2,
+            3,
+            genesis_hash,
+            TxPaymentParameters::new().set_tip(4),
+        )
+        .extra();
+
+        assert_eq!(dynamic.extra().encode(), expected.encode());
+        assert_eq!(
+            dynamic.additional_signed().unwrap().encode(),
+            expected.additional_signed().unwrap().encode()
+        );
+    }
+
+    #[test]
+    fn extensions_follow_the_metadata() {
+        let mut extensions = vec![
+            extension::<CheckNonce<DefaultConfig>, ()>("CheckNonce"),
+            extension::<(), ()>("SomethingNew"),
+            extension::<(), u32>("CheckSpecVersion"),
+            extension::<Compact<u32>, ()>("CheckAppId"),
+        ];
+        let params = DynamicExtraParameters::new().set_app_id(7);
+        let extra = DynamicExtra::<DefaultConfig>::new_with_metadata(
+            1,
+            2,
+            3,
+            Default::default(),
+            params.clone(),
+            &metadata(extensions.clone()),
+        )
+        .unwrap();
+
+        assert_eq!(extra.extra().encode(), vec![3 << 2, 7 << 2]);
+        assert_eq!(extra.additional_signed().unwrap().encode(), 1u32.encode());
+
+        // Extensions with data that isn't known must be provided explicitly.
+        extensions.push(extension::<u8, bool>("Unknown"));
+        let result = DynamicExtra::<DefaultConfig>::new_with_metadata(
+            1,
+            2,
+            3,
+            Default::default(),
+            params.clone(),
+            &metadata(extensions.clone()),
+        );
+        assert!(matches!(
+            result,
+            Err(BasicError::Metadata(MetadataError::UnsupportedSignedExtension(id)))
+                if id == "Unknown"
+        ));
+
+        let extra = DynamicExtra::<DefaultConfig>::new_with_metadata(
+            1,
+            2,
+            3,
+            Default::default(),
+            params.set_extension("Unknown", 9u8, true),
+            &metadata(extensions),
+        )
+        .unwrap();
+        assert_eq!(extra.extra().encode(), vec![3 << 2, 7 << 2, 9]);
+        assert_eq!(
+            extra.additional_signed().unwrap().encode(),
+            (1u32, true).encode()
+        );
+    }
+
+    #[test]
+    fn signed_extrinsics_round_trip() {
+        let metadata = metadata(vec![
+            extension::<(), u32>("CheckSpecVersion"),
+            extension::<CheckNonce<DefaultConfig>, ()>("CheckNonce"),
+            extension::<Compact<u32>, ()>("CheckAppId"),
+            extension::<u8, ()>("Custom"),
+        ]);
+        let extra = DynamicExtra::<DefaultConfig>::new_with_metadata(
+            1,
+            2,
+            300,
+            Default::default(),
+            DynamicExtraParameters::new()
+                .set_app_id(7)
+                .set_extension("Custom", 9u8, ()),
+            &metadata,
+        )
+        .unwrap()
+        .extra();
+        let address = sp_runtime::MultiAddress::Id(sp_runtime::AccountId32::new([1; 32]));
+        let signature =
+            sp_runtime::MultiSignature::Sr25519(sp_core::sr25519::Signature([2; 64]));
+        let call = RawBytes(vec![5, 0, 42]);
+        let extrinsic = sp_runtime::generic::UncheckedExtrinsic::new_signed(
+            call.clone(),
+            address.clone(),
+            signature.clone(),
+            extra.clone(),
+        );
+        let encoded = extrinsic.encode();
+
+        let decoded =
+            decode_extrinsic::<DefaultConfig, RawBytes>(&encoded, &metadata).unwrap();
+
+        let (decoded_address, decoded_signature, decoded_extra) =
+            decoded.signature.clone().unwrap();
+        assert_eq!(decoded_address, address);
+        assert_eq!(decoded_signature, signature);
+        assert_eq!(decoded_extra.extra, extra.extra);
+        assert_eq!(decoded.function, call);
+        assert_eq!(decoded.encode(), encoded);
+
+        // Without the metadata, the extra data can't be told apart from the call.
+        assert!(sp_runtime::generic::UncheckedExtrinsic::<
+            sp_runtime::MultiAddress<sp_runtime::AccountId32, u32>,
+            RawBytes,
+            sp_runtime::MultiSignature,
+            DynamicSignedExtensions,
+        >::decode(&mut &encoded[..])
+        .is_err());
+    }
+}
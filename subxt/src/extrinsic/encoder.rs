@@ -0,0 +1,183 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The encoding of extrinsics, exposed so that tools outside of subxt can build
+//! transactions that are byte-for-byte identical to the ones it submits.
+//!
+//! A signed extrinsic is encoded as a compact length prefix, followed by:
+//!
+//! 1. a version byte: [`EXTRINSIC_VERSION`] with the [`SIGNED_BIT`] set,
+//! 2. the address of the signer,
+//! 3. the signature of the [`signer_payload()`],
+//! 4. the signed extra data (see [`crate::extrinsic::SignedExtra::extra()`]),
+//! 5. the encoded call data.
+//!
+//! An unsigned extrinsic consists of just the version byte and the call data.
+//!
+//! These functions encode with the same [`sp_runtime::generic::UncheckedExtrinsic`] and
+//! [`sp_runtime::generic::SignedPayload`] types as [`crate::extrinsic::create_signed()`],
+//! so the two can't drift apart.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use subxt::{
+//!     extrinsic::encoder,
+//!     Encoded,
+//! };
+//!
+//! # fn sign(payload: &[u8]) -> sp_runtime::MultiSignature { unimplemented!() }
+//! # let address: sp_runtime::MultiAddress<sp_runtime::AccountId32, u32> = unimplemented!();
+//! # let (extra, additional_signed) = ((), ());
+//! let call = Encoded(vec![0, 1]);
+//! let payload = encoder::signer_payload(&call, &extra, &additional_signed);
+//! let signature = sign(&payload);
+//! let extrinsic = encoder::encode_signed(&address, &signature, &extra, &call);
+//! ```
+
+use crate::Encoded;
+use codec::Encode;
+use sp_runtime::{
+    generic::{
+        SignedPayload,
+        UncheckedExtrinsic,
+    },
+    traits::SignedExtension,
+};
+
+/// The version of the extrinsic format produced by this module.
+pub const EXTRINSIC_VERSION: u8 = 4;
+
+/// The bit which is set in the version byte of signed extrinsics.
+pub const SIGNED_BIT: u8 = 0b1000_0000;
+
+/// Signer payloads longer than this many bytes are hashed before being signed.
+pub const MAX_UNHASHED_PAYLOAD_LEN: usize = 256;
+
+/// Encode a signed extrinsic, ready to be submitted to a node.
+pub fn encode_signed<A, S, E>(
+    address: &A,
+    signature: &S,
+    extra: &E,
+    call: &Encoded,
+) -> Vec<u8>
+where
+    A: Encode + Clone,
+    S: Encode + Clone,
+    E: SignedExtension,
+{
+    UncheckedExtrinsic::new_signed(
+        call.clone(),
+        address.clone(),
+        signature.clone(),
+        extra.clone(),
+    )
+    .encode()
+}
+
+/// Encode an unsigned extrinsic, ready to be submitted to a node.
+pub fn encode_unsigned(call: &Encoded) -> Vec<u8> {
+    UncheckedExtrinsic::<(), _, (), ()>::new_unsigned(call.clone()).encode()
+}
+
+/// Return the bytes that must be signed in order to produce the signature of a
+/// signed extrinsic.
+///
+/// This is the call data, followed by the signed extra data and the additional
+/// signed data (see [`sp_runtime::traits::SignedExtension::additional_signed()`]).
+/// If that is longer than [`MAX_UNHASHED_PAYLOAD_LEN`] bytes, its blake2 256 hash
+/// is signed instead.
+pub fn signer_payload<E>(
+    call: &Encoded,
+    extra: &E,
+    additional_signed: &E::AdditionalSigned,
+) -> Vec<u8>
+where
+    E: SignedExtension,
+    E::AdditionalSigned: Clone,
+{
+    SignedPayload::from_raw(call.clone(), extra.clone(), additional_signed.clone())
+        .encode()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        extrinsic::{
+            DefaultExtra,
+            SignedExtra,
+            SignedPayload,
+            UncheckedExtrinsic,
+        },
+        Config,
+        DefaultConfig,
+    };
+    use sp_core::Pair;
+    use sp_keyring::AccountKeyring;
+    use sp_runtime::traits::SignedExtension;
+
+    type Extra = DefaultExtra<DefaultConfig>;
+
+    fn extra() -> <Extra as SignedExtra<DefaultConfig>>::Extra {
+        Extra::new(9100, 7, 3, Default::default(), Default::default()).extra()
+    }
+
+    #[test]
+    fn unsigned_extrinsic_matches_sp_runtime() {
+        let call = Encoded(vec![4, 0, 1, 2, 3]);
+        let expected =
+            UncheckedExtrinsic::<DefaultConfig, Extra>::new_unsigned(call.clone());
+        assert_eq!(encode_unsigned(&call), expected.encode());
+        // A one byte compact length prefix, then the version byte and the call data.
+        assert_eq!(
+            encode_unsigned(&call),
+            [&[6 << 2, EXTRINSIC_VERSION][..], &call.0].concat()
+        );
+    }
+
+    #[test]
+    fn signed_extrinsic_matches_sp_runtime() {
+        // Check payloads on both sides of the hashing threshold.
+        for call in [Encoded(vec![4, 0, 1, 2, 3]), Encoded(vec![7; 300])] {
+            let extra = extra();
+            let additional_signed = extra.additional_signed().unwrap();
+            let pair = AccountKeyring::Alice.pair();
+
+            let payload = signer_payload(&call, &extra, &additional_signed);
+            let expected_payload =
+                SignedPayload::<DefaultConfig, Extra>::new(call.clone(), extra.clone())
+                    .unwrap();
+            assert_eq!(payload, expected_payload.encode());
+
+            let address: <DefaultConfig as Config>::Address =
+                AccountKeyring::Alice.to_account_id().into();
+            let signature: <DefaultConfig as Config>::Signature =
+                pair.sign(&payload).into();
+
+            let expected = UncheckedExtrinsic::<DefaultConfig, Extra>::new_signed(
+                call.clone(),
+                address.clone(),
+                signature.clone(),
+                extra.clone(),
+            );
+            assert_eq!(
+                encode_signed(&address, &signature, &extra, &call),
+                expected.encode()
+            );
+        }
+    }
+}
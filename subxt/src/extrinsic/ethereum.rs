@@ -0,0 +1,252 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Accounts and signatures for chains which use Ethereum-style (secp256k1) keys, where
+//! an account is identified by the last 20 bytes of the keccak hash of its public key,
+//! and payloads are hashed with keccak before being signed.
+//!
+//! A [`crate::Config`] for such a chain would use [`AccountId20`] as its `AccountId`
+//! (and usually its `Address`) and [`EthereumSignature`] as its `Signature`, and sign
+//! extrinsics with an [`EthereumPairSigner`].
+
+use super::{
+    SignedExtra,
+    SignedPayload,
+    Signer,
+    UncheckedExtrinsic,
+};
+use crate::Config;
+use codec::{
+    Decode,
+    Encode,
+};
+use sp_core::{
+    ecdsa,
+    keccak_256,
+};
+use sp_runtime::traits::{
+    IdentifyAccount,
+    Lazy,
+    SignedExtension,
+    Verify,
+};
+
+/// An Ethereum-style account: the last 20 bytes of the keccak hash of the
+/// uncompressed public key.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Encode,
+    Decode,
+    scale_info::TypeInfo,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub struct AccountId20(pub [u8; 20]);
+
+impl AccountId20 {
+    /// The account of the given public key, or `None` if it isn't a valid
+    /// secp256k1 point.
+    pub fn from_public(public: &ecdsa::Public) -> Option<Self> {
+        let public = libsecp256k1::PublicKey::parse_compressed(&public.0).ok()?;
+        // Skip the prefix of the uncompressed serialization.
+        let hash = keccak_256(&public.serialize()[1..]);
+        let mut account = [0; 20];
+        account.copy_from_slice(&hash[12..]);
+        Some(Self(account))
+    }
+}
+
+impl std::fmt::Display for AccountId20 {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "0x{}", hex::encode(self.0))
+    }
+}
+
+impl From<[u8; 20]> for AccountId20 {
+    fn from(bytes: [u8; 20]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl AsRef<[u8]> for AccountId20 {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// The signer of an [`EthereumSignature`], identifying an [`AccountId20`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct EthereumSigner(AccountId20);
+
+impl IdentifyAccount for EthereumSigner {
+    type AccountId = AccountId20;
+
+    fn into_account(self) -> AccountId20 {
+        self.0
+    }
+}
+
+impl From<ecdsa::Public> for EthereumSigner {
+    fn from(public: ecdsa::Public) -> Self {
+        // Public keys obtained from a pair are always valid points.
+        Self(AccountId20::from_public(&public).expect("valid public key; qed"))
+    }
+}
+
+/// A secp256k1 signature over the keccak hash of a payload.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct EthereumSignature(pub ecdsa::Signature);
+
+impl From<ecdsa::Signature> for EthereumSignature {
+    fn from(signature: ecdsa::Signature) -> Self {
+        Self(signature)
+    }
+}
+
+impl Verify for EthereumSignature {
+    type Signer = EthereumSigner;
+
+    fn verify<L: Lazy<[u8]>>(&self, mut msg: L, signer: &AccountId20) -> bool {
+        self.0
+            .recover_prehashed(&keccak_256(msg.get()))
+            .and_then(|public| AccountId20::from_public(&public))
+            .map_or(false, |account| &account == signer)
+    }
+}
+
+/// Extrinsic signer using a secp256k1 key to produce [`EthereumSignature`]s.
+///
+/// Unlike a [`super::PairSigner`] over an [`ecdsa::Pair`], which signs the blake2
+/// hash of the payload, this signs its keccak hash as Ethereum-style chains expect.
+#[derive(Clone)]
+pub struct EthereumPairSigner<T: Config, E> {
+    account_id: T::AccountId,
+    nonce: Option<T::Index>,
+    signer: ecdsa::Pair,
+    marker: std::marker::PhantomData<E>,
+}
+
+impl<T, E> EthereumPairSigner<T, E>
+where
+    T: Config<AccountId = AccountId20>,
+    E: SignedExtra<T>,
+{
+    /// Creates a new `Signer` from an ecdsa `Pair`.
+    pub fn new(signer: ecdsa::Pair) -> Self {
+        use sp_core::Pair as _;
+        let account_id = EthereumSigner::from(signer.public()).into_account();
+        Self {
+            account_id,
+            nonce: None,
+            signer,
+            marker: Default::default(),
+        }
+    }
+
+    /// Sets the nonce to a new value.
+    pub fn set_nonce(&mut self, nonce: T::Index) {
+        self.nonce = Some(nonce);
+    }
+
+    /// Increment the nonce.
+    pub fn increment_nonce(&mut self) {
+        self.nonce = self.nonce.map(|nonce| nonce + 1u32.into());
+    }
+
+    /// Returns the signer.
+    pub fn signer(&self) -> &ecdsa::Pair {
+        &self.signer
+    }
+}
+
+#[async_trait::async_trait]
+impl<T, E> Signer<T, E> for EthereumPairSigner<T, E>
+where
+    T: Config<AccountId = AccountId20>,
+    E: SignedExtra<T>,
+    T::AccountId: Into<T::Address>,
+    T::Signature: From<EthereumSignature>,
+    <<E as SignedExtra<T>>::Extra as SignedExtension>::AdditionalSigned:
+        Send + Sync + 'static,
+{
+    fn account_id(&self) -> &T::AccountId {
+        &self.account_id
+    }
+
+    fn nonce(&self) -> Option<T::Index> {
+        self.nonce
+    }
+
+    async fn sign(
+        &self,
+        extrinsic: SignedPayload<T, E>,
+    ) -> Result<UncheckedExtrinsic<T, E>, String> {
+        let signature = extrinsic
+            .using_encoded(|payload| self.signer.sign_prehashed(&keccak_256(payload)));
+        let (call, extra, _) = extrinsic.deconstruct();
+        let extrinsic = UncheckedExtrinsic::<T, E>::new_signed(
+            call,
+            self.account_id.into(),
+            EthereumSignature(signature).into(),
+            extra,
+        );
+        Ok(extrinsic)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sp_core::Pair;
+
+    // A development account of Ethereum-compatible substrate chains.
+    const ALITH_SEED: &str =
+        "5fb92d6e98884f76de468fa3f6278f8807c48bebc13595d45af5bdc4da702133";
+    const ALITH: &str = "f24ff3a9cf04c71dbc94d0b566f7a27b94566cac";
+
+    #[test]
+    fn accounts_are_derived_like_ethereum_addresses() {
+        let seed = hex::decode(ALITH_SEED).unwrap();
+        let pair = ecdsa::Pair::from_seed_slice(&seed).unwrap();
+        let account = EthereumSigner::from(pair.public()).into_account();
+        assert_eq!(account.to_string(), format!("0x{}", ALITH));
+    }
+
+    #[test]
+    fn signatures_verify_against_the_signing_account() {
+        let seed = hex::decode(ALITH_SEED).unwrap();
+        let pair = ecdsa::Pair::from_seed_slice(&seed).unwrap();
+        let account = EthereumSigner::from(pair.public()).into_account();
+        let payload = b"payload";
+
+        let signature = EthereumSignature(pair.sign_prehashed(&keccak_256(payload)));
+        assert!(signature.verify(&payload[..], &account));
+        assert!(!signature.verify(&b"other payload"[..], &account));
+        assert!(!signature.verify(&payload[..], &AccountId20([0; 20])));
+
+        // A signature over the blake2 hash doesn't verify.
+        let signature = EthereumSignature(pair.sign(payload));
+        assert!(!signature.verify(&payload[..], &account));
+    }
+}
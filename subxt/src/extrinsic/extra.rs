@@ -30,7 +30,11 @@ use sp_runtime::{
     transaction_validity::TransactionValidityError,
 };
 
-use crate::Config;
+use crate::{
+    error::BasicError,
+    Config,
+    Metadata,
+};
 
 /// Extra type.
 // pub type Extra<T> = <<T as Config>::Extra as SignedExtra<T>>::Extra;
@@ -173,9 +177,9 @@ impl<T: Config> SignedExtension for CheckGenesis<T> {
 ///
 /// # Note
 ///
-/// This is modified from the substrate version to allow passing in of the genesis hash, which is
-/// returned via `additional_signed()`. It assumes therefore `Era::Immortal` (The transaction is
-/// valid forever)
+/// This is modified from the substrate version to allow passing in of the hash of the block
+/// that the era starts at (the genesis hash for `Era::Immortal`), which is returned via
+/// `additional_signed()`.
 #[derive(Derivative, Encode, Decode, TypeInfo)]
 #[derivative(
     Clone(bound = ""),
@@ -187,7 +191,7 @@ impl<T: Config> SignedExtension for CheckGenesis<T> {
 pub struct CheckMortality<T: Config>(
     /// The default structure for the Extra encoding
     pub (Era, PhantomDataSendSync<T>),
-    /// Local genesis hash to be used for `AdditionalSigned`
+    /// Hash of the block the era starts at, to be used for `AdditionalSigned`
     #[codec(skip)]
     pub T::Hash,
 );
@@ -361,12 +365,124 @@ impl<T: Config> SignedExtension for ChargeAssetTxPayment<T> {
     }
 }
 
+/// A signed extension through which the transactor pays the fees for a transaction.
+///
+/// Implementing this allows the payment extension used by [`DefaultExtraWithTxPayment`]
+/// to be configured for each transaction, via its [`PaymentExtension::Parameters`].
+pub trait PaymentExtension<T: Config>:
+    SignedExtension<AccountId = T::AccountId, Call = ()>
+{
+    /// The parameters needed to construct the extension, such as the tip.
+    type Parameters: Default + Send + Sync + 'static;
+
+    /// Creates the extension from the given parameters.
+    fn new(params: Self::Parameters) -> Self;
+
+    /// The number of blocks for which a transaction signed with the given parameters
+    /// should stay valid, or `None` if it should be immortal.
+    fn mortal_period(_params: &Self::Parameters) -> Option<u64> {
+        None
+    }
+}
+
+/// Parameters for [`ChargeTransactionPayment`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TxPaymentParameters {
+    tip: u128,
+    mortal_period: Option<u64>,
+}
+
+impl TxPaymentParameters {
+    /// Creates new parameters, without a tip.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the tip for the block author.
+    pub fn set_tip(mut self, tip: u128) -> Self {
+        self.tip = tip;
+        self
+    }
+
+    /// Make the transaction mortal, so that it's only valid for roughly the given
+    /// number of blocks from the current best block. See [`Era::mortal()`] for how the
+    /// period is rounded.
+    pub fn set_mortal(mut self, period: u64) -> Self {
+        self.mortal_period = Some(period);
+        self
+    }
+}
+
+impl<T: Config> PaymentExtension<T> for ChargeTransactionPayment<T> {
+    type Parameters = TxPaymentParameters;
+
+    fn new(params: Self::Parameters) -> Self {
+        ChargeTransactionPayment(params.tip, PhantomDataSendSync::new())
+    }
+
+    fn mortal_period(params: &Self::Parameters) -> Option<u64> {
+        params.mortal_period
+    }
+}
+
+/// Parameters for [`ChargeAssetTxPayment`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AssetTxPaymentParameters {
+    tip: u128,
+    asset_id: Option<u32>,
+    mortal_period: Option<u64>,
+}
+
+impl AssetTxPaymentParameters {
+    /// Creates new parameters, without a tip and paying fees in the native asset.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the tip for the block author.
+    pub fn set_tip(mut self, tip: u128) -> Self {
+        self.tip = tip;
+        self
+    }
+
+    /// Pay the fees (and tip) in the asset with the given id, rather than in the
+    /// native asset of the chain.
+    pub fn set_asset_id(mut self, asset_id: u32) -> Self {
+        self.asset_id = Some(asset_id);
+        self
+    }
+
+    /// Make the transaction mortal, so that it's only valid for roughly the given
+    /// number of blocks from the current best block. See [`Era::mortal()`] for how the
+    /// period is rounded.
+    pub fn set_mortal(mut self, period: u64) -> Self {
+        self.mortal_period = Some(period);
+        self
+    }
+}
+
+impl<T: Config> PaymentExtension<T> for ChargeAssetTxPayment<T> {
+    type Parameters = AssetTxPaymentParameters;
+
+    fn new(params: Self::Parameters) -> Self {
+        ChargeAssetTxPayment {
+            tip: params.tip,
+            asset_id: params.asset_id,
+            marker: PhantomDataSendSync::new(),
+        }
+    }
+
+    fn mortal_period(params: &Self::Parameters) -> Option<u64> {
+        params.mortal_period
+    }
+}
+
 /// Trait for implementing transaction extras for a runtime.
 pub trait SignedExtra<T: Config>: SignedExtension {
     /// The type the extras.
     type Extra: SignedExtension + Send + Sync;
     /// The additional config parameters.
-    type Parameters: Default + Send + Sync;
+    type Parameters: Default + Send + Sync + 'static;
 
     /// Creates a new `SignedExtra`.
     fn new(
@@ -377,6 +493,55 @@ pub trait SignedExtra<T: Config>: SignedExtension {
         additional_params: Self::Parameters,
     ) -> Self;
 
+    /// Creates a new `SignedExtra` for the runtime described by the given metadata.
+    ///
+    /// By default the metadata is ignored, and this is equivalent to [`SignedExtra::new()`].
+    /// Override this to construct the extra data from the signed extensions listed in
+    /// the metadata, as [`super::DynamicExtra`] does.
+    fn new_with_metadata(
+        spec_version: u32,
+        tx_version: u32,
+        nonce: T::Index,
+        genesis_hash: T::Hash,
+        additional_params: Self::Parameters,
+        _metadata: &Metadata,
+    ) -> Result<Self, BasicError> {
+        Ok(Self::new(
+            spec_version,
+            tx_version,
+            nonce,
+            genesis_hash,
+            additional_params,
+        ))
+    }
+
+    /// The number of blocks for which a transaction signed with the given parameters
+    /// should stay valid, or `None` if it should be immortal. When this returns a
+    /// period, the extra data is constructed with [`SignedExtra::new_mortal()`].
+    fn mortal_period(_params: &Self::Parameters) -> Option<u64> {
+        None
+    }
+
+    /// Creates a new `SignedExtra` for a transaction which is only valid during the
+    /// given era, which starts at the block with the hash `checkpoint`.
+    ///
+    /// By default this fails, since the extra data is assumed to be immortal.
+    #[allow(clippy::too_many_arguments)]
+    fn new_mortal(
+        _spec_version: u32,
+        _tx_version: u32,
+        _nonce: T::Index,
+        _genesis_hash: T::Hash,
+        _era: Era,
+        _checkpoint: T::Hash,
+        _additional_params: Self::Parameters,
+        _metadata: &Metadata,
+    ) -> Result<Self, BasicError> {
+        Err(BasicError::Other(
+            "Mortal transactions are not supported by this SignedExtra".into(),
+        ))
+    }
+
     /// Returns the transaction extra.
     fn extra(&self) -> Self::Extra;
 }
@@ -390,18 +555,21 @@ pub trait SignedExtra<T: Config>: SignedExtension {
     Eq(bound = "")
 )]
 #[scale_info(skip_type_params(T))]
-pub struct DefaultExtraWithTxPayment<T: Config, X> {
+pub struct DefaultExtraWithTxPayment<T: Config, X: PaymentExtension<T>> {
     spec_version: u32,
     tx_version: u32,
     nonce: T::Index,
     genesis_hash: T::Hash,
-    marker: PhantomDataSendSync<X>,
+    era: Era,
+    // The hash of the block the era starts at; the genesis hash if immortal.
+    checkpoint: T::Hash,
+    payment: X,
 }
 
 impl<T, X> SignedExtra<T> for DefaultExtraWithTxPayment<T, X>
 where
     T: Config,
-    X: SignedExtension<AccountId = T::AccountId, Call = ()> + Default,
+    X: PaymentExtension<T>,
 {
     type Extra = (
         CheckSpecVersion<T>,
@@ -412,45 +580,64 @@ where
         CheckWeight<T>,
         X,
     );
-    type Parameters = ();
+    type Parameters = X::Parameters;
 
     fn new(
         spec_version: u32,
         tx_version: u32,
         nonce: T::Index,
         genesis_hash: T::Hash,
-        _params: Self::Parameters,
+        params: Self::Parameters,
     ) -> Self {
         DefaultExtraWithTxPayment {
             spec_version,
             tx_version,
             nonce,
             genesis_hash,
-            marker: PhantomDataSendSync::new(),
+            era: Era::Immortal,
+            checkpoint: genesis_hash,
+            payment: X::new(params),
         }
     }
 
+    fn mortal_period(params: &Self::Parameters) -> Option<u64> {
+        X::mortal_period(params)
+    }
+
+    fn new_mortal(
+        spec_version: u32,
+        tx_version: u32,
+        nonce: T::Index,
+        genesis_hash: T::Hash,
+        era: Era,
+        checkpoint: T::Hash,
+        params: Self::Parameters,
+        _metadata: &Metadata,
+    ) -> Result<Self, BasicError> {
+        Ok(DefaultExtraWithTxPayment {
+            era,
+            checkpoint,
+            ..Self::new(spec_version, tx_version, nonce, genesis_hash, params)
+        })
+    }
+
     fn extra(&self) -> Self::Extra {
         (
             CheckSpecVersion(PhantomDataSendSync::new(), self.spec_version),
             CheckTxVersion(PhantomDataSendSync::new(), self.tx_version),
             CheckGenesis(PhantomDataSendSync::new(), self.genesis_hash),
-            CheckMortality(
-                (Era::Immortal, PhantomDataSendSync::new()),
-                self.genesis_hash,
-            ),
+            CheckMortality((self.era, PhantomDataSendSync::new()), self.checkpoint),
             CheckNonce(self.nonce),
             CheckWeight(PhantomDataSendSync::new()),
-            X::default(),
+            self.payment.clone(),
         )
     }
 }
 
-impl<T, X: SignedExtension<AccountId = T::AccountId, Call = ()> + Default> SignedExtension
-    for DefaultExtraWithTxPayment<T, X>
+impl<T, X> SignedExtension for DefaultExtraWithTxPayment<T, X>
 where
     T: Config,
-    X: SignedExtension,
+    X: PaymentExtension<T>,
 {
     const IDENTIFIER: &'static str = "DefaultExtra";
     type AccountId = T::AccountId;
@@ -479,3 +666,73 @@ where
 ///
 /// Note that this must match the `SignedExtra` type in the target runtime's extrinsic definition.
 pub type DefaultExtra<T> = DefaultExtraWithTxPayment<T, ChargeTransactionPayment<T>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DefaultConfig;
+    use frame_metadata::{
+        v14::{
+            ExtrinsicMetadata,
+            RuntimeMetadataLastVersion,
+        },
+        RuntimeMetadataPrefixed,
+    };
+    use scale_info::meta_type;
+    use std::convert::TryFrom;
+
+    fn empty_metadata() -> Metadata {
+        let extrinsic = ExtrinsicMetadata {
+            ty: meta_type::<()>(),
+            version: 4,
+            signed_extensions: vec![],
+        };
+        let v14 = RuntimeMetadataLastVersion::new(vec![], extrinsic, meta_type::<()>());
+        let runtime_metadata: RuntimeMetadataPrefixed = v14.into();
+        Metadata::try_from(runtime_metadata).unwrap()
+    }
+
+    #[test]
+    fn payment_parameters_are_used_in_the_extra() {
+        type Extra =
+            DefaultExtraWithTxPayment<DefaultConfig, ChargeAssetTxPayment<DefaultConfig>>;
+
+        let params = AssetTxPaymentParameters::new().set_tip(5).set_asset_id(1);
+        let extra = Extra::new(1, 1, 0, Default::default(), params).extra();
+        assert_eq!(extra.6.tip, 5);
+        assert_eq!(extra.6.asset_id, Some(1));
+
+        let extra = Extra::new(1, 1, 0, Default::default(), Default::default()).extra();
+        assert_eq!(extra.6, ChargeAssetTxPayment::default());
+    }
+
+    #[test]
+    fn mortal_extra_is_checkpointed() {
+        let params = TxPaymentParameters::new().set_mortal(64);
+        assert_eq!(
+            <DefaultExtra<DefaultConfig> as SignedExtra<_>>::mortal_period(&params),
+            Some(64)
+        );
+
+        let genesis_hash = sp_core::H256::repeat_byte(1);
+        let checkpoint = sp_core::H256::repeat_byte(2);
+        let era = Era::mortal(64, 100);
+        let metadata = empty_metadata();
+        let extra = DefaultExtra::<DefaultConfig>::new_mortal(
+            1,
+            1,
+            0,
+            genesis_hash,
+            era,
+            checkpoint,
+            params,
+            &metadata,
+        )
+        .unwrap();
+
+        assert_eq!(extra.extra().3 .0 .0, era);
+        let (_, _, genesis, mortality, ..) = extra.additional_signed().unwrap();
+        assert_eq!(genesis, genesis_hash);
+        assert_eq!(mortality, checkpoint);
+    }
+}
@@ -0,0 +1,394 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Signing extrinsics with a Ledger hardware wallet running a Substrate app (such as
+//! the Polkadot, Kusama or Avail apps), which shows the transaction on the device and
+//! only signs it once the user confirms.
+//!
+//! This is only available with the `signer-ledger` feature enabled.
+
+use super::{
+    SignedExtra,
+    SignedPayload,
+    Signer,
+    UncheckedExtrinsic,
+};
+use crate::Config;
+use codec::{
+    Decode,
+    Encode,
+};
+use futures::future::{
+    self,
+    Either,
+};
+use futures_timer::Delay;
+use ledger_transport::{
+    APDUCommand,
+    Exchange,
+};
+use sp_runtime::{
+    traits::SignedExtension,
+    MultiSignature,
+};
+use std::time::Duration;
+
+pub use ledger_transport_hid::TransportNativeHID;
+
+/// The instruction to get the address (and public key) of a derivation path.
+const INS_GET_ADDRESS: u8 = 0x01;
+/// The instruction to sign a payload, which is sent in several chunks.
+const INS_SIGN: u8 = 0x02;
+/// The first chunk of a payload to sign, which holds the derivation path.
+const CHUNK_INIT: u8 = 0x00;
+/// A chunk in the middle of a payload to sign.
+const CHUNK_ADD: u8 = 0x01;
+/// The last chunk of a payload to sign.
+const CHUNK_LAST: u8 = 0x02;
+/// The most data that can be sent to the device at once.
+const CHUNK_SIZE: usize = 250;
+/// The return code of a successful instruction.
+const RETURN_OK: u16 = 0x9000;
+/// BIP44 path components are hardened.
+const HARDENED: u32 = 0x8000_0000;
+
+/// The signature scheme of the key the device derives.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LedgerScheme {
+    /// ed25519 keys, supported by all Substrate apps.
+    Ed25519 = 0,
+    /// sr25519 keys, supported by some apps.
+    Sr25519 = 1,
+}
+
+/// Which app to talk to, and which key it should use.
+#[derive(Clone, Debug)]
+pub struct LedgerOptions {
+    cla: u8,
+    slip44: u32,
+    account: u32,
+    change: u32,
+    address_index: u32,
+    scheme: LedgerScheme,
+    timeout: Duration,
+}
+
+impl Default for LedgerOptions {
+    /// The first ed25519 key of the Polkadot app, allowing a minute to confirm.
+    fn default() -> Self {
+        Self {
+            cla: 0x90,
+            slip44: 354,
+            account: 0,
+            change: 0,
+            address_index: 0,
+            scheme: LedgerScheme::Ed25519,
+            timeout: Duration::from_secs(60),
+        }
+    }
+}
+
+impl LedgerOptions {
+    /// Set the app to talk to, by the class byte of its instructions and the SLIP-0044
+    /// coin type used in its derivation paths; these are given in the documentation
+    /// of each app.
+    pub fn set_app(mut self, cla: u8, slip44: u32) -> Self {
+        self.cla = cla;
+        self.slip44 = slip44;
+        self
+    }
+
+    /// Set the account, change and address index of the derivation path
+    /// `m/44'/<slip44>'/<account>'/<change>'/<address_index>'`.
+    pub fn set_path(mut self, account: u32, change: u32, address_index: u32) -> Self {
+        self.account = account;
+        self.change = change;
+        self.address_index = address_index;
+        self
+    }
+
+    /// Set the signature scheme of the key.
+    pub fn set_scheme(mut self, scheme: LedgerScheme) -> Self {
+        self.scheme = scheme;
+        self
+    }
+
+    /// Set how long to wait for the user to confirm on the device.
+    pub fn set_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// The derivation path, as the device expects it.
+    fn path(&self) -> Vec<u8> {
+        [
+            44,
+            self.slip44,
+            self.account,
+            self.change,
+            self.address_index,
+        ]
+        .iter()
+        .flat_map(|component| (component | HARDENED).to_le_bytes())
+        .collect()
+    }
+}
+
+/// An error talking to a Ledger device.
+#[derive(Debug, thiserror::Error)]
+pub enum LedgerError {
+    /// The device couldn't be reached.
+    #[error("Ledger transport error: {0}")]
+    Transport(String),
+    /// The device refused the instruction, for instance because the app isn't open
+    /// or the user rejected the transaction.
+    #[error("Ledger device returned error code {0:#06x}")]
+    Device(u16),
+    /// The user didn't confirm on the device in time.
+    #[error("Timed out waiting for confirmation on the Ledger device")]
+    TimedOut,
+    /// The device responded with something unexpected.
+    #[error("Invalid response from the Ledger device")]
+    InvalidResponse,
+}
+
+/// Extrinsic signer using a key held on a Ledger device.
+///
+/// The payload is sent to the device in full, so the app must be able to parse the
+/// calls being signed, and the user has to confirm each transaction on the device.
+pub struct LedgerSigner<T: Config, E, X = TransportNativeHID> {
+    transport: X,
+    options: LedgerOptions,
+    account_id: T::AccountId,
+    nonce: Option<T::Index>,
+    marker: std::marker::PhantomData<E>,
+}
+
+impl<T, E> LedgerSigner<T, E, TransportNativeHID>
+where
+    T: Config,
+    T::AccountId: From<[u8; 32]>,
+    E: SignedExtra<T>,
+{
+    /// Connect to the first Ledger device plugged in over USB.
+    pub async fn connect(options: LedgerOptions) -> Result<Self, LedgerError> {
+        let hid = ledger_transport_hid::hidapi::HidApi::new()
+            .map_err(|e| LedgerError::Transport(e.to_string()))?;
+        let transport = TransportNativeHID::new(&hid)
+            .map_err(|e| LedgerError::Transport(e.to_string()))?;
+        Self::with_transport(transport, options).await
+    }
+}
+
+impl<T, E, X> LedgerSigner<T, E, X>
+where
+    T: Config,
+    T::AccountId: From<[u8; 32]>,
+    E: SignedExtra<T>,
+    X: Exchange + Send + Sync,
+    X::Error: std::fmt::Display,
+{
+    /// Creates a new `Signer` talking to the device over the given transport, and
+    /// fetches the public key of the configured derivation path from it.
+    pub async fn with_transport(
+        transport: X,
+        options: LedgerOptions,
+    ) -> Result<Self, LedgerError> {
+        let command = APDUCommand {
+            cla: options.cla,
+            ins: INS_GET_ADDRESS,
+            p1: 0,
+            p2: options.scheme as u8,
+            data: options.path(),
+        };
+        let response = exchange(&transport, &command).await?;
+        let public: [u8; 32] = response
+            .get(..32)
+            .and_then(|public| public.try_into().ok())
+            .ok_or(LedgerError::InvalidResponse)?;
+        Ok(Self {
+            transport,
+            options,
+            account_id: public.into(),
+            nonce: None,
+            marker: Default::default(),
+        })
+    }
+
+    /// Sets the nonce to a new value.
+    pub fn set_nonce(&mut self, nonce: T::Index) {
+        self.nonce = Some(nonce);
+    }
+
+    /// Increment the nonce.
+    pub fn increment_nonce(&mut self) {
+        self.nonce = self.nonce.map(|nonce| nonce + 1u32.into());
+    }
+
+    /// Ask the user to check the address of the key on the device.
+    pub async fn show_address(&self) -> Result<(), LedgerError> {
+        let command = APDUCommand {
+            cla: self.options.cla,
+            ins: INS_GET_ADDRESS,
+            p1: 1,
+            p2: self.options.scheme as u8,
+            data: self.options.path(),
+        };
+        self.exchange_with_timeout(&command).await.map(|_| ())
+    }
+
+    /// Sign a payload, waiting for the user to confirm it on the device.
+    pub async fn sign_payload(
+        &self,
+        payload: &[u8],
+    ) -> Result<MultiSignature, LedgerError> {
+        let mut response = Vec::new();
+        for (p1, data) in sign_chunks(self.options.path(), payload) {
+            let command = APDUCommand {
+                cla: self.options.cla,
+                ins: INS_SIGN,
+                p1,
+                p2: self.options.scheme as u8,
+                data,
+            };
+            response = self.exchange_with_timeout(&command).await?;
+        }
+        // The signature is prefixed with its scheme, like a `MultiSignature`.
+        MultiSignature::decode(&mut &response[..])
+            .map_err(|_| LedgerError::InvalidResponse)
+    }
+
+    async fn exchange_with_timeout(
+        &self,
+        command: &APDUCommand<Vec<u8>>,
+    ) -> Result<Vec<u8>, LedgerError> {
+        let response = exchange(&self.transport, command);
+        futures::pin_mut!(response);
+        match future::select(response, Delay::new(self.options.timeout)).await {
+            Either::Left((response, _)) => response,
+            Either::Right(_) => Err(LedgerError::TimedOut),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<T, E, X> Signer<T, E> for LedgerSigner<T, E, X>
+where
+    T: Config,
+    T::AccountId: From<[u8; 32]> + Into<T::Address> + 'static,
+    T::Signature: From<MultiSignature>,
+    E: SignedExtra<T>,
+    <<E as SignedExtra<T>>::Extra as SignedExtension>::AdditionalSigned:
+        Send + Sync + 'static,
+    X: Exchange + Send + Sync,
+    X::Error: std::fmt::Display,
+{
+    fn account_id(&self) -> &T::AccountId {
+        &self.account_id
+    }
+
+    fn nonce(&self) -> Option<T::Index> {
+        self.nonce
+    }
+
+    async fn sign(
+        &self,
+        extrinsic: SignedPayload<T, E>,
+    ) -> Result<UncheckedExtrinsic<T, E>, String> {
+        let (call, extra, additional) = extrinsic.deconstruct();
+        // The device hashes long payloads itself, after showing them to the user.
+        let payload = (&call, &extra, &additional).encode();
+        let signature = self
+            .sign_payload(&payload)
+            .await
+            .map_err(|e| e.to_string())?;
+        let extrinsic = UncheckedExtrinsic::<T, E>::new_signed(
+            call,
+            self.account_id.clone().into(),
+            signature.into(),
+            extra,
+        );
+        Ok(extrinsic)
+    }
+}
+
+/// Send a command to the device, returning the data of a successful response.
+async fn exchange<X>(
+    transport: &X,
+    command: &APDUCommand<Vec<u8>>,
+) -> Result<Vec<u8>, LedgerError>
+where
+    X: Exchange + Send + Sync,
+    X::Error: std::fmt::Display,
+{
+    let answer = transport
+        .exchange(command)
+        .await
+        .map_err(|e| LedgerError::Transport(e.to_string()))?;
+    match answer.retcode() {
+        RETURN_OK => Ok(answer.data().to_vec()),
+        code => Err(LedgerError::Device(code)),
+    }
+}
+
+/// The chunks a payload is sent to the device in: the derivation path first, then
+/// the payload, with the kind of each chunk.
+fn sign_chunks(path: Vec<u8>, payload: &[u8]) -> Vec<(u8, Vec<u8>)> {
+    let mut chunks = vec![(CHUNK_INIT, path)];
+    let mut payload_chunks = payload.chunks(CHUNK_SIZE).peekable();
+    while let Some(chunk) = payload_chunks.next() {
+        let kind = if payload_chunks.peek().is_some() {
+            CHUNK_ADD
+        } else {
+            CHUNK_LAST
+        };
+        chunks.push((kind, chunk.to_vec()));
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_are_hardened_little_endian() {
+        let path = LedgerOptions::default().set_path(1, 0, 2).path();
+        assert_eq!(
+            path,
+            [
+                0x2c, 0, 0, 0x80, 0x62, 0x01, 0, 0x80, 1, 0, 0, 0x80, 0, 0, 0, 0x80, 2,
+                0, 0, 0x80
+            ]
+        );
+    }
+
+    #[test]
+    fn payloads_are_sent_in_chunks_after_the_path() {
+        let payload = vec![7; CHUNK_SIZE * 2 + 1];
+        let chunks = sign_chunks(vec![1], &payload);
+        let kinds: Vec<_> = chunks.iter().map(|(kind, _)| *kind).collect();
+        assert_eq!(kinds, [CHUNK_INIT, CHUNK_ADD, CHUNK_ADD, CHUNK_LAST]);
+        assert_eq!(chunks[0].1, vec![1]);
+        assert_eq!(chunks[3].1, vec![7]);
+
+        let chunks = sign_chunks(vec![1], &[7; 10]);
+        assert_eq!(
+            chunks,
+            vec![(CHUNK_INIT, vec![1]), (CHUNK_LAST, vec![7; 10])]
+        );
+    }
+}
@@ -16,11 +16,25 @@
 
 //! Create signed or unsigned extrinsics.
 
+mod dynamic_extra;
+pub mod encoder;
+pub mod ethereum;
 mod extra;
+pub mod keystore;
+#[cfg(feature = "signer-ledger")]
+pub mod ledger;
 mod signer;
 
 pub use self::{
+    dynamic_extra::{
+        decode_extrinsic,
+        DynamicExtra,
+        DynamicExtraParameters,
+        DynamicSignedExtensions,
+        RawBytes,
+    },
     extra::{
+        AssetTxPaymentParameters,
         ChargeAssetTxPayment,
         ChargeTransactionPayment,
         CheckGenesis,
@@ -31,22 +45,45 @@ pub use self::{
         CheckWeight,
         DefaultExtra,
         DefaultExtraWithTxPayment,
+        PaymentExtension,
         SignedExtra,
+        TxPaymentParameters,
     },
     signer::{
+        EcdsaSigner,
+        Ed25519Signer,
         PairSigner,
+        PayloadSigner,
+        RemoteSigner,
         Signer,
+        Sr25519Signer,
     },
 };
 
-use sp_runtime::traits::SignedExtension;
+use sp_runtime::{
+    generic::Era,
+    traits::SignedExtension,
+};
 
 use crate::{
+    dynamic::{
+        decode_fields,
+        decode_value,
+        Composite,
+        DynamicError,
+        Value,
+    },
     error::BasicError,
     rpc::RuntimeVersion,
     Config,
     Encoded,
+    Metadata,
 };
+use codec::{
+    Compact,
+    Decode,
+};
+use scale_info::TypeDef;
 
 /// UncheckedExtrinsic type.
 pub type UncheckedExtrinsic<T, X> = sp_runtime::generic::UncheckedExtrinsic<
@@ -60,6 +97,15 @@ pub type UncheckedExtrinsic<T, X> = sp_runtime::generic::UncheckedExtrinsic<
 pub type SignedPayload<T, X> =
     sp_runtime::generic::SignedPayload<Encoded, <X as SignedExtra<T>>::Extra>;
 
+/// Creates an unsigned extrinsic from an encoded call.
+pub fn create_unsigned<T, X>(call: Encoded) -> UncheckedExtrinsic<T, X>
+where
+    T: Config,
+    X: SignedExtra<T>,
+{
+    UncheckedExtrinsic::<T, X>::new_unsigned(call)
+}
+
 /// Creates a signed extrinsic
 pub async fn create_signed<T, X>(
     runtime_version: &RuntimeVersion,
@@ -83,7 +129,400 @@ where
         genesis_hash,
         additional_params,
     );
+    sign(call, extra, signer).await
+}
+
+/// Creates a signed extrinsic, constructing the signed extra data for the runtime
+/// described by the given metadata (see [`SignedExtra::new_with_metadata()`]).
+pub async fn create_signed_with_metadata<T, X>(
+    metadata: &Metadata,
+    runtime_version: &RuntimeVersion,
+    genesis_hash: T::Hash,
+    nonce: T::Index,
+    call: Encoded,
+    signer: &(dyn Signer<T, X> + Send + Sync),
+    additional_params: X::Parameters,
+) -> Result<UncheckedExtrinsic<T, X>, BasicError>
+where
+    T: Config,
+    X: SignedExtra<T>,
+    <X::Extra as SignedExtension>::AdditionalSigned: Send + Sync,
+{
+    let extra = X::new_with_metadata(
+        runtime_version.spec_version,
+        runtime_version.transaction_version,
+        nonce,
+        genesis_hash,
+        additional_params,
+        metadata,
+    )?;
+    sign(call, extra, signer).await
+}
+
+/// Creates a signed extrinsic which is only valid during the given era, which starts
+/// at the block with the hash `checkpoint` (see [`SignedExtra::new_mortal()`]).
+#[allow(clippy::too_many_arguments)]
+pub async fn create_signed_mortal<T, X>(
+    metadata: &Metadata,
+    runtime_version: &RuntimeVersion,
+    genesis_hash: T::Hash,
+    era: Era,
+    checkpoint: T::Hash,
+    nonce: T::Index,
+    call: Encoded,
+    signer: &(dyn Signer<T, X> + Send + Sync),
+    additional_params: X::Parameters,
+) -> Result<UncheckedExtrinsic<T, X>, BasicError>
+where
+    T: Config,
+    X: SignedExtra<T>,
+    <X::Extra as SignedExtension>::AdditionalSigned: Send + Sync,
+{
+    let extra = X::new_mortal(
+        runtime_version.spec_version,
+        runtime_version.transaction_version,
+        nonce,
+        genesis_hash,
+        era,
+        checkpoint,
+        additional_params,
+        metadata,
+    )?;
+    sign(call, extra, signer).await
+}
+
+async fn sign<T, X>(
+    call: Encoded,
+    extra: X,
+    signer: &(dyn Signer<T, X> + Send + Sync),
+) -> Result<UncheckedExtrinsic<T, X>, BasicError>
+where
+    T: Config,
+    X: SignedExtra<T>,
+    <X::Extra as SignedExtension>::AdditionalSigned: Send + Sync,
+{
     let payload = SignedPayload::<T, X>::new(call, extra.extra())?;
     let signed = signer.sign(payload).await?;
     Ok(signed)
 }
+
+/// An extrinsic, such as one from the transaction pool or a block body, decoded using
+/// the metadata.
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct DecodedExtrinsic {
+    /// The address of the signer, or `None` if the extrinsic is unsigned.
+    pub address: Option<Value>,
+    /// The signature, or `None` if the extrinsic is unsigned.
+    pub signature: Option<Value>,
+    /// The era the extrinsic is valid in, if it is signed and the runtime has a
+    /// `CheckMortality` extension.
+    pub era: Option<Era>,
+    /// The nonce, if the extrinsic is signed and the runtime has a `CheckNonce`
+    /// extension.
+    pub nonce: Option<u64>,
+    /// The tip, if the extrinsic is signed and the runtime has a
+    /// `ChargeTransactionPayment` or `ChargeAssetTxPayment` extension.
+    pub tip: Option<u128>,
+    /// The application the extrinsic belongs to, if it is signed and the runtime has
+    /// the Avail `CheckAppId` extension.
+    pub app_id: Option<u32>,
+    /// The values of all of the signed extensions, by identifier, in the order the
+    /// metadata lists them. Empty if the extrinsic is unsigned.
+    pub extensions: Vec<(String, Value)>,
+    /// The name of the pallet the call belongs to.
+    pub pallet: String,
+    /// The name of the call.
+    pub call: String,
+    /// The arguments of the call.
+    pub args: Composite,
+}
+
+/// Decode an encoded (and length prefixed) extrinsic into its signature, signed
+/// extensions and call.
+pub fn decode_extrinsic(
+    metadata: &Metadata,
+    extrinsic: &[u8],
+) -> Result<DecodedExtrinsic, BasicError> {
+    let input = &mut &extrinsic[..];
+    let signed = decode_signed(metadata, input)?.unwrap_or_default();
+
+    let runtime_metadata = metadata.runtime_metadata();
+    let types = &runtime_metadata.types;
+    let pallet_index = u8::decode(input)?;
+    let pallet = runtime_metadata
+        .pallets
+        .iter()
+        .find(|pallet| pallet.index == pallet_index)
+        .ok_or_else(|| DynamicError::PalletNotFound(pallet_index.to_string()))?;
+    let call_index = u8::decode(input)?;
+    let call_not_found = || {
+        DynamicError::CallNotFound {
+            pallet: pallet.name.clone(),
+            call: call_index.to_string(),
+        }
+    };
+    let calls_ty = pallet.calls.as_ref().ok_or_else(call_not_found)?.ty.id();
+    let call = match types.resolve(calls_ty).map(|ty| ty.type_def()) {
+        Some(TypeDef::Variant(variants)) => {
+            variants
+                .variants()
+                .iter()
+                .find(|variant| variant.index() == call_index)
+                .ok_or_else(call_not_found)?
+        }
+        _ => return Err(DynamicError::TypeNotFound(calls_ty).into()),
+    };
+    let args = decode_fields(call.fields(), types, input)?;
+
+    Ok(DecodedExtrinsic {
+        address: signed.address,
+        signature: signed.signature,
+        era: signed.era,
+        nonce: signed.nonce,
+        tip: signed.tip,
+        app_id: signed.app_id,
+        extensions: signed.extensions,
+        pallet: pallet.name.clone(),
+        call: call.name().clone(),
+        args,
+    })
+}
+
+/// The nonce of an encoded (and length prefixed) extrinsic, such as one from the
+/// transaction pool, or `None` if it's unsigned or the runtime has no `CheckNonce`
+/// extension.
+pub(crate) fn signed_nonce(
+    metadata: &Metadata,
+    extrinsic: &[u8],
+) -> Result<Option<u64>, BasicError> {
+    let input = &mut &extrinsic[..];
+    Ok(decode_signed(metadata, input)?.and_then(|signed| signed.nonce))
+}
+
+/// The signed part of an extrinsic.
+#[derive(Default)]
+struct Signed {
+    address: Option<Value>,
+    signature: Option<Value>,
+    era: Option<Era>,
+    nonce: Option<u64>,
+    tip: Option<u128>,
+    app_id: Option<u32>,
+    extensions: Vec<(String, Value)>,
+}
+
+/// Decode the signed part of an extrinsic, or return `None` if it is unsigned, leaving
+/// the input at its call. The well known signed extensions are decoded into their
+/// values as well.
+fn decode_signed(
+    metadata: &Metadata,
+    input: &mut &[u8],
+) -> Result<Option<Signed>, BasicError> {
+    let _len = <Compact<u32>>::decode(input)?;
+    let version = u8::decode(input)?;
+    if version & 0b1000_0000 == 0 {
+        return Ok(None)
+    }
+
+    let runtime_metadata = metadata.runtime_metadata();
+    let types = &runtime_metadata.types;
+    let extrinsic_ty = types
+        .resolve(runtime_metadata.extrinsic.ty.id())
+        .ok_or_else(|| BasicError::Other("Extrinsic type not found".into()))?;
+    let type_param = |name: &str| {
+        extrinsic_ty
+            .type_params()
+            .iter()
+            .find(|param| param.name() == name)
+            .and_then(|param| param.ty())
+            .map(|ty| ty.id())
+            .ok_or_else(|| {
+                BasicError::Other(format!("Extrinsic type has no {} parameter", name))
+            })
+    };
+    let mut signed = Signed {
+        address: Some(decode_value(type_param("Address")?, types, input)?),
+        signature: Some(decode_value(type_param("Signature")?, types, input)?),
+        ..Default::default()
+    };
+
+    for extension in &runtime_metadata.extrinsic.signed_extensions {
+        let start = *input;
+        let value = decode_value(extension.ty.id(), types, input)?;
+        let bytes = &mut &start[..start.len() - input.len()];
+        match extension.identifier.as_str() {
+            "CheckMortality" | "CheckEra" => signed.era = Some(Era::decode(bytes)?),
+            "CheckNonce" => signed.nonce = Some(<Compact<u64>>::decode(bytes)?.0),
+            // The tip comes first in both payment extensions.
+            "ChargeTransactionPayment" | "ChargeAssetTxPayment" => {
+                signed.tip = Some(<Compact<u128>>::decode(bytes)?.0)
+            }
+            "CheckAppId" => signed.app_id = Some(<Compact<u32>>::decode(bytes)?.0),
+            _ => {}
+        }
+        signed
+            .extensions
+            .push((extension.identifier.clone(), value));
+    }
+    Ok(Some(signed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codec::Encode;
+    use frame_metadata::{
+        v14::{
+            ExtrinsicMetadata,
+            PalletCallMetadata,
+            PalletMetadata,
+            RuntimeMetadataLastVersion,
+            SignedExtensionMetadata,
+        },
+        RuntimeMetadataPrefixed,
+    };
+    use scale_info::meta_type;
+    use sp_core::sr25519;
+    use sp_runtime::{
+        AccountId32,
+        MultiAddress,
+        MultiSignature,
+    };
+    use std::convert::TryFrom;
+
+    type Address = MultiAddress<AccountId32, u32>;
+
+    #[allow(non_camel_case_types)]
+    #[derive(Encode, scale_info::TypeInfo)]
+    enum TestCall {
+        remark { value: u32 },
+    }
+
+    fn metadata(signed_extensions: Vec<SignedExtensionMetadata>) -> Metadata {
+        let extrinsic = ExtrinsicMetadata {
+            ty: meta_type::<
+                sp_runtime::generic::UncheckedExtrinsic<Address, (), MultiSignature, ()>,
+            >(),
+            version: 4,
+            signed_extensions,
+        };
+        let pallets = vec![PalletMetadata {
+            name: "Test",
+            storage: None,
+            calls: Some(PalletCallMetadata {
+                ty: meta_type::<TestCall>(),
+            }),
+            event: None,
+            constants: vec![],
+            error: None,
+            index: 5,
+        }];
+        let v14 = RuntimeMetadataLastVersion::new(pallets, extrinsic, meta_type::<()>());
+        let runtime_metadata: RuntimeMetadataPrefixed = v14.into();
+        Metadata::try_from(runtime_metadata).unwrap()
+    }
+
+    fn extension<E: scale_info::TypeInfo + 'static>(
+        identifier: &'static str,
+    ) -> SignedExtensionMetadata {
+        SignedExtensionMetadata {
+            identifier,
+            ty: meta_type::<E>(),
+            additional_signed: meta_type::<()>(),
+        }
+    }
+
+    #[test]
+    fn nonces_are_found_after_the_extensions_before_them() {
+        let metadata = metadata(vec![
+            extension::<CheckMortality<crate::DefaultConfig>>("CheckMortality"),
+            extension::<CheckNonce<crate::DefaultConfig>>("CheckNonce"),
+            extension::<Compact<u128>>("ChargeTransactionPayment"),
+        ]);
+        let signed = (
+            0b1000_0100u8,
+            Address::Id(AccountId32::new([1; 32])),
+            MultiSignature::Sr25519(sr25519::Signature::from_raw([2; 64])),
+            (Era::mortal(64, 100), Compact(7u32), Compact(0u128)),
+            vec![5u8, 0],
+        )
+            .encode()
+            .encode();
+        assert_eq!(signed_nonce(&metadata, &signed).unwrap(), Some(7));
+
+        let unsigned = (0b0000_0100u8, vec![5u8, 0]).encode().encode();
+        assert_eq!(signed_nonce(&metadata, &unsigned).unwrap(), None);
+    }
+
+    #[test]
+    fn extrinsics_are_decoded_into_signers_and_calls() {
+        let metadata = metadata(vec![
+            extension::<CheckMortality<crate::DefaultConfig>>("CheckMortality"),
+            extension::<CheckNonce<crate::DefaultConfig>>("CheckNonce"),
+            extension::<Compact<u128>>("ChargeTransactionPayment"),
+            extension::<Compact<u32>>("CheckAppId"),
+        ]);
+        let call = (5u8, TestCall::remark { value: 9 });
+        let signed = (
+            0b1000_0100u8,
+            Address::Id(AccountId32::new([1; 32])),
+            MultiSignature::Sr25519(sr25519::Signature::from_raw([2; 64])),
+            (
+                Era::mortal(64, 100),
+                Compact(3u32),
+                Compact(50u128),
+                Compact(2u32),
+            ),
+            &call,
+        )
+            .encode()
+            .encode();
+        let decoded = decode_extrinsic(&metadata, &signed).unwrap();
+        let identifiers: Vec<_> = decoded
+            .extensions
+            .iter()
+            .map(|(id, _)| id.as_str())
+            .collect();
+        assert_eq!(
+            identifiers,
+            [
+                "CheckMortality",
+                "CheckNonce",
+                "ChargeTransactionPayment",
+                "CheckAppId"
+            ]
+        );
+        assert_eq!(decoded.extensions[3].1, Value::uint(2u32));
+        assert_eq!(
+            decoded,
+            DecodedExtrinsic {
+                address: Some(Value::variant(
+                    "Id",
+                    vec![Value::unnamed_composite(vec![Value::bytes([1; 32])])]
+                )),
+                signature: Some(Value::variant(
+                    "Sr25519",
+                    vec![Value::unnamed_composite(vec![Value::bytes([2; 64])])]
+                )),
+                era: Some(Era::mortal(64, 100)),
+                nonce: Some(3),
+                tip: Some(50),
+                app_id: Some(2),
+                extensions: decoded.extensions.clone(),
+                pallet: "Test".into(),
+                call: "remark".into(),
+                args: vec![("value".to_string(), Value::uint(9u32))].into(),
+            }
+        );
+
+        let unsigned = (0b0000_0100u8, &call).encode().encode();
+        let decoded = decode_extrinsic(&metadata, &unsigned).unwrap();
+        assert_eq!(decoded.address, None);
+        assert_eq!(decoded.nonce, None);
+        assert!(decoded.extensions.is_empty());
+        assert_eq!(decoded.call, "remark");
+
+        let unknown = (0b0000_0100u8, 6u8, 0u8).encode().encode();
+        assert!(decode_extrinsic(&metadata, &unknown).is_err());
+    }
+}
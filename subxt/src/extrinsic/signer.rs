@@ -18,6 +18,11 @@
 //! [substrate](https://github.com/paritytech/substrate) node via RPC.
 
 use super::{
+    keystore::{
+        self,
+        KeystoreError,
+        KeystorePair,
+    },
     SignedExtra,
     SignedPayload,
     UncheckedExtrinsic,
@@ -30,6 +35,7 @@ use sp_runtime::traits::{
     SignedExtension,
     Verify,
 };
+use std::future::Future;
 
 /// Extrinsic signer.
 #[async_trait::async_trait]
@@ -50,6 +56,118 @@ pub trait Signer<T: Config, E: SignedExtra<T>> {
     ) -> Result<UncheckedExtrinsic<T, E>, String>;
 }
 
+/// Signs payloads on behalf of a [`RemoteSigner`], for instance by forwarding them to
+/// a remote key management service, a hardware wallet or a browser extension.
+///
+/// This is implemented for async closures taking the payload and returning its
+/// signature.
+#[async_trait::async_trait]
+pub trait PayloadSigner<T: Config>: Send + Sync {
+    /// Sign the SCALE encoded payload of an extrinsic (which is already hashed if it
+    /// was longer than 256 bytes).
+    async fn sign_payload(&self, payload: Vec<u8>) -> Result<T::Signature, String>;
+}
+
+#[async_trait::async_trait]
+impl<T, F, Fut> PayloadSigner<T> for F
+where
+    T: Config,
+    F: Fn(Vec<u8>) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<T::Signature, String>> + Send,
+{
+    async fn sign_payload(&self, payload: Vec<u8>) -> Result<T::Signature, String> {
+        self(payload).await
+    }
+}
+
+/// Extrinsic signer for keys which aren't held locally, delegating the signing of
+/// payloads to a [`PayloadSigner`] and awaiting the signature.
+pub struct RemoteSigner<T: Config, E, S> {
+    account_id: T::AccountId,
+    nonce: Option<T::Index>,
+    signer: S,
+    marker: std::marker::PhantomData<E>,
+}
+
+impl<T, E, S> RemoteSigner<T, E, S>
+where
+    T: Config,
+    E: SignedExtra<T>,
+    S: PayloadSigner<T>,
+{
+    /// Creates a new `Signer` for the given account, whose payloads are signed by
+    /// `signer`.
+    pub fn new(account_id: T::AccountId, signer: S) -> Self {
+        Self {
+            account_id,
+            nonce: None,
+            signer,
+            marker: Default::default(),
+        }
+    }
+
+    /// Sets the nonce to a new value.
+    pub fn set_nonce(&mut self, nonce: T::Index) {
+        self.nonce = Some(nonce);
+    }
+
+    /// Increment the nonce.
+    pub fn increment_nonce(&mut self) {
+        self.nonce = self.nonce.map(|nonce| nonce + 1u32.into());
+    }
+
+    /// Returns the payload signer.
+    pub fn signer(&self) -> &S {
+        &self.signer
+    }
+}
+
+#[async_trait::async_trait]
+impl<T, E, S> Signer<T, E> for RemoteSigner<T, E, S>
+where
+    T: Config,
+    E: SignedExtra<T>,
+    T::AccountId: Into<T::Address> + 'static,
+    <<E as SignedExtra<T>>::Extra as SignedExtension>::AdditionalSigned:
+        Send + Sync + 'static,
+    S: PayloadSigner<T>,
+{
+    fn account_id(&self) -> &T::AccountId {
+        &self.account_id
+    }
+
+    fn nonce(&self) -> Option<T::Index> {
+        self.nonce
+    }
+
+    async fn sign(
+        &self,
+        extrinsic: SignedPayload<T, E>,
+    ) -> Result<UncheckedExtrinsic<T, E>, String> {
+        let payload = extrinsic.using_encoded(|payload| payload.to_vec());
+        let signature = self.signer.sign_payload(payload).await?;
+        let (call, extra, _) = extrinsic.deconstruct();
+        let extrinsic = UncheckedExtrinsic::<T, E>::new_signed(
+            call,
+            self.account_id.clone().into(),
+            signature,
+            extra,
+        );
+        Ok(extrinsic)
+    }
+}
+
+/// A [`PairSigner`] using an sr25519 key.
+pub type Sr25519Signer<T, E> = PairSigner<T, E, sp_core::sr25519::Pair>;
+
+/// A [`PairSigner`] using an ed25519 key.
+pub type Ed25519Signer<T, E> = PairSigner<T, E, sp_core::ed25519::Pair>;
+
+/// A [`PairSigner`] using a secp256k1 key, whose account is the blake2 hash of the
+/// public key as with [`sp_runtime::MultiSigner`]. For chains with Ethereum-style
+/// accounts, see [`super::ethereum::EthereumPairSigner`] instead.
+pub type EcdsaSigner<T, E> = PairSigner<T, E, sp_core::ecdsa::Pair>;
+
 /// Extrinsic signer using a private key.
 #[derive(Clone, Debug)]
 pub struct PairSigner<T: Config, E, P: Pair> {
@@ -94,6 +212,32 @@ where
     pub fn signer(&self) -> &P {
         &self.signer
     }
+
+    /// Creates a new `Signer` from a key file in the encrypted JSON format of
+    /// polkadot-js.
+    pub fn from_json<Path: AsRef<std::path::Path>>(
+        path: Path,
+        password: &str,
+    ) -> Result<Self, KeystoreError>
+    where
+        P: KeystorePair,
+    {
+        let json = std::fs::read_to_string(path)?;
+        Ok(Self::new(keystore::decrypt_json(&json, password)?))
+    }
+
+    /// Export the key in the encrypted JSON format of polkadot-js, naming it `name`
+    /// if given.
+    pub fn to_json(
+        &self,
+        password: &str,
+        name: Option<&str>,
+    ) -> Result<String, KeystoreError>
+    where
+        P: KeystorePair,
+    {
+        keystore::encrypt_json(&self.signer, password, name)
+    }
 }
 
 #[async_trait::async_trait]
@@ -130,3 +274,61 @@ where
         Ok(extrinsic)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        DefaultConfig,
+        DefaultExtra,
+        Encoded,
+    };
+    use futures::channel::oneshot;
+    use sp_core::sr25519;
+    use sp_runtime::MultiSignature;
+
+    #[async_std::test]
+    async fn remote_signer_awaits_signatures() {
+        let pair = sr25519::Pair::from_string("//Alice", None).unwrap();
+        let account_id =
+            PairSigner::<DefaultConfig, DefaultExtra<DefaultConfig>, _>::new(
+                pair.clone(),
+            )
+            .account_id()
+            .clone();
+
+        // Sign on another thread, as a remote signer would.
+        let signer = RemoteSigner::<DefaultConfig, DefaultExtra<DefaultConfig>, _>::new(
+            account_id.clone(),
+            move |payload: Vec<u8>| {
+                let pair = pair.clone();
+                let (tx, rx) = oneshot::channel();
+                std::thread::spawn(move || tx.send(pair.sign(&payload)));
+                async move {
+                    rx.await
+                        .map(MultiSignature::from)
+                        .map_err(|e| e.to_string())
+                }
+            },
+        );
+
+        let extra = DefaultExtra::<DefaultConfig>::new(
+            1,
+            1,
+            0,
+            Default::default(),
+            Default::default(),
+        );
+        let payload = || {
+            SignedPayload::<DefaultConfig, DefaultExtra<DefaultConfig>>::new(
+                Encoded(vec![0, 1]),
+                extra.extra(),
+            )
+            .unwrap()
+        };
+        let extrinsic = signer.sign(payload()).await.unwrap();
+
+        let (_, signature, _) = extrinsic.signature.expect("extrinsic is signed");
+        assert!(payload().using_encoded(|p| signature.verify(p, &account_id)));
+    }
+}
@@ -0,0 +1,695 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Fetching and verifying GRANDPA finality proofs, and tracking the GRANDPA authority
+//! set as blocks are finalized. This is what a light client or a bridge needs to
+//! check for itself that a block is final, without trusting the node it talks to.
+
+use crate::{
+    digest::{
+        AuthorityId,
+        AuthorityWeight,
+        ConsensusLog,
+        DecodedDigestItem,
+        GrandpaConsensusLog,
+    },
+    error::BasicError,
+    rpc::Rpc,
+    storage::StorageClient,
+    Config,
+};
+use codec::{
+    Decode,
+    Encode,
+};
+use sp_core::{
+    ed25519,
+    storage::StorageKey,
+    twox_128,
+    Pair,
+};
+use sp_runtime::traits::Header;
+use std::collections::{
+    HashMap,
+    HashSet,
+};
+
+/// The well known storage key under which the GRANDPA authorities are kept.
+const GRANDPA_AUTHORITIES_KEY: &[u8] = b":grandpa_authorities";
+
+/// The version of the authority list kept under [`GRANDPA_AUTHORITIES_KEY`].
+const AUTHORITIES_VERSION: u8 = 1;
+
+/// The index of the precommit variant of GRANDPA messages, which is what the
+/// precommits of a justification sign.
+const PRECOMMIT_MESSAGE_INDEX: u8 = 1;
+
+/// A proof that a block is final, as returned by the `grandpa_proveFinality` RPC method.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct FinalityProof<H: Header> {
+    /// The hash of the block that the justification finalizes. This is the last
+    /// block of the authority set that finalized the requested block.
+    pub block: H::Hash,
+    /// The encoded [`GrandpaJustification`] of `block`.
+    pub justification: Vec<u8>,
+    /// The headers between the requested block and `block`.
+    pub unknown_headers: Vec<H>,
+}
+
+impl<H: Header> FinalityProof<H> {
+    /// Decode the justification of the proof.
+    pub fn decode_justification(&self) -> Result<GrandpaJustification<H>, FinalityError> {
+        Ok(GrandpaJustification::decode(&mut &self.justification[..])?)
+    }
+}
+
+/// A GRANDPA justification: the precommits of more than two thirds of the authority
+/// set for a block, or for descendants of it.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct GrandpaJustification<H: Header> {
+    /// The round in which the block was finalized.
+    pub round: u64,
+    /// The precommits which finalized the block.
+    pub commit: Commit<H::Hash, H::Number>,
+    /// The headers between the block being finalized and the blocks that the
+    /// precommits actually vote for.
+    pub votes_ancestries: Vec<H>,
+}
+
+/// The precommits for a block.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct Commit<Hash, N> {
+    /// The hash of the block being finalized.
+    pub target_hash: Hash,
+    /// The number of the block being finalized.
+    pub target_number: N,
+    /// The signed precommits.
+    pub precommits: Vec<SignedPrecommit<Hash, N>>,
+}
+
+/// A vote of an authority to finalize a block.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct Precommit<Hash, N> {
+    /// The hash of the block voted for.
+    pub target_hash: Hash,
+    /// The number of the block voted for.
+    pub target_number: N,
+}
+
+/// A precommit along with the authority that made it and its signature.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct SignedPrecommit<Hash, N> {
+    /// The precommit.
+    pub precommit: Precommit<Hash, N>,
+    /// The signature of the authority over the precommit, the round and the set id.
+    pub signature: ed25519::Signature,
+    /// The authority that made the precommit.
+    pub id: AuthorityId,
+}
+
+impl<Hash: Encode, N: Encode> SignedPrecommit<Hash, N> {
+    /// The message that the authority signed: the precommit, along with the round and
+    /// the id of the authority set it was made in.
+    pub fn signed_message(&self, round: u64, set_id: u64) -> Vec<u8> {
+        (PRECOMMIT_MESSAGE_INDEX, &self.precommit, round, set_id).encode()
+    }
+
+    fn has_valid_signature(&self, round: u64, set_id: u64) -> bool {
+        ed25519::Pair::verify(
+            &self.signature,
+            self.signed_message(round, set_id),
+            &ed25519::Public(self.id),
+        )
+    }
+}
+
+/// A justification or finality proof could not be verified.
+#[derive(Debug, thiserror::Error)]
+pub enum FinalityError {
+    /// The justification could not be decoded.
+    #[error("Could not decode the justification: {0}")]
+    Codec(#[from] codec::Error),
+    /// The justification finalizes a different block than the one expected.
+    #[error("The justification is for block {found}, but {expected} was expected")]
+    TargetMismatch {
+        /// The block the justification was expected to finalize.
+        expected: String,
+        /// The block that the justification finalizes.
+        found: String,
+    },
+    /// A precommit was made by an authority outside of the authority set.
+    #[error("Precommit from 0x{} which is not in authority set {set_id}", hex::encode(.id))]
+    UnknownAuthority {
+        /// The authority that made the precommit.
+        id: AuthorityId,
+        /// The id of the authority set the justification was checked against.
+        set_id: u64,
+    },
+    /// A precommit has an invalid signature.
+    #[error("Precommit from 0x{} has an invalid signature", hex::encode(.0))]
+    BadSignature(AuthorityId),
+    /// A precommit votes for a block which is not a descendant of the block being
+    /// finalized, as far as the ancestries in the justification show.
+    #[error("Precommit for {0} is not a descendant of the justification target")]
+    InvalidAncestry(String),
+    /// The precommits do not carry enough weight to finalize the block.
+    #[error("Precommits have a weight of {signed}, but {threshold} is needed")]
+    NotEnoughWeight {
+        /// The total weight of the distinct authorities which signed a precommit.
+        signed: u64,
+        /// The weight needed to finalize a block.
+        threshold: u64,
+    },
+}
+
+/// The GRANDPA authority set, along with any change to it that has been scheduled but
+/// not yet enacted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuthoritySet {
+    /// The id of the set, which increases by one with every change.
+    pub set_id: u64,
+    /// The authorities in the set, with their weights.
+    pub authorities: Vec<(AuthorityId, AuthorityWeight)>,
+    pending: Option<PendingChange>,
+}
+
+/// A new GRANDPA authority set, which took over once the change to it was enacted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuthoritySetChange {
+    /// The id of the new set.
+    pub set_id: u64,
+    /// The authorities in the new set, with their weights.
+    pub authorities: Vec<(AuthorityId, AuthorityWeight)>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct PendingChange {
+    next_authorities: Vec<(AuthorityId, AuthorityWeight)>,
+    effective_number: u64,
+}
+
+impl AuthoritySet {
+    /// Create an authority set with no pending change.
+    pub fn new(set_id: u64, authorities: Vec<(AuthorityId, AuthorityWeight)>) -> Self {
+        Self {
+            set_id,
+            authorities,
+            pending: None,
+        }
+    }
+
+    /// The total weight of the authorities in the set.
+    pub fn total_weight(&self) -> u64 {
+        self.authorities
+            .iter()
+            .fold(0u64, |total, (_, weight)| total.saturating_add(*weight))
+    }
+
+    /// The weight of precommits needed to finalize a block: more than two thirds of
+    /// the total weight.
+    pub fn threshold(&self) -> u64 {
+        let total = self.total_weight();
+        let faulty = total.saturating_sub(1) / 3;
+        total - faulty
+    }
+
+    /// The block number at which the pending change of authorities, if any, takes
+    /// effect.
+    pub fn pending_change_at(&self) -> Option<u64> {
+        self.pending.as_ref().map(|change| change.effective_number)
+    }
+
+    /// Verify a justification for the block `hash` against this authority set.
+    ///
+    /// Every precommit must be signed by a member of the set and vote for `hash` or a
+    /// descendant of it, and the distinct authorities which signed must carry at least
+    /// [`AuthoritySet::threshold()`] of weight.
+    pub fn verify_justification<H>(
+        &self,
+        hash: H::Hash,
+        justification: &GrandpaJustification<H>,
+    ) -> Result<(), FinalityError>
+    where
+        H: Header,
+    {
+        let commit = &justification.commit;
+        if commit.target_hash != hash {
+            return Err(FinalityError::TargetMismatch {
+                expected: format!("{:?}", hash),
+                found: format!("{:?}", commit.target_hash),
+            })
+        }
+
+        let weights: HashMap<_, _> = self.authorities.iter().cloned().collect();
+        let ancestries: HashMap<_, _> = justification
+            .votes_ancestries
+            .iter()
+            .map(|header| (header.hash(), *header.parent_hash()))
+            .collect();
+
+        let mut signers = HashSet::new();
+        let mut signed = 0u64;
+        for precommit in &commit.precommits {
+            let weight =
+                weights
+                    .get(&precommit.id)
+                    .ok_or(FinalityError::UnknownAuthority {
+                        id: precommit.id,
+                        set_id: self.set_id,
+                    })?;
+            if !precommit.has_valid_signature(justification.round, self.set_id) {
+                return Err(FinalityError::BadSignature(precommit.id))
+            }
+            if !descends_from(
+                &ancestries,
+                &precommit.precommit.target_hash,
+                &commit.target_hash,
+            ) {
+                return Err(FinalityError::InvalidAncestry(format!(
+                    "{:?}",
+                    precommit.precommit.target_hash
+                )))
+            }
+            // An authority which equivocated still only counts once.
+            if signers.insert(precommit.id) {
+                signed = signed.saturating_add(*weight);
+            }
+        }
+
+        let threshold = self.threshold();
+        if signed < threshold {
+            return Err(FinalityError::NotEnoughWeight { signed, threshold })
+        }
+        Ok(())
+    }
+
+    /// Decode the justification in a finality proof and verify it against this
+    /// authority set, returning it if it is valid.
+    pub fn verify_finality_proof<H>(
+        &self,
+        proof: &FinalityProof<H>,
+    ) -> Result<GrandpaJustification<H>, FinalityError>
+    where
+        H: Header,
+    {
+        let justification = proof.decode_justification()?;
+        self.verify_justification(proof.block, &justification)?;
+        Ok(justification)
+    }
+
+    /// Track the authority set across a newly finalized block. Headers must be passed
+    /// in order, without gaps, and only once their finality has been verified.
+    ///
+    /// A change scheduled in the header is recorded, and the pending change is enacted
+    /// once the block it takes effect at is finalized, in which case `true` is returned.
+    pub fn apply_finalized<H>(&mut self, header: &H) -> bool
+    where
+        H: Header,
+        H::Number: Into<u64>,
+    {
+        if self.pending.is_none() {
+            self.pending = scheduled_change(header);
+        }
+        match &self.pending {
+            Some(change) if change.effective_number <= (*header.number()).into() => {
+                let change = self.pending.take().expect("pending change checked above");
+                self.set_id += 1;
+                self.authorities = change.next_authorities;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Find the GRANDPA change scheduled in a header, if there is one. A forced change
+/// takes effect at the same block as a standard one with the same delay would, which
+/// is all that matters once the blocks in between have been finalized.
+fn scheduled_change<H>(header: &H) -> Option<PendingChange>
+where
+    H: Header,
+    H::Number: Into<u64>,
+{
+    header.digest().logs().iter().find_map(|item| {
+        let change = match DecodedDigestItem::<H::Number>::decode_from(item) {
+            DecodedDigestItem::Consensus(ConsensusLog::Grandpa(
+                GrandpaConsensusLog::ScheduledChange(change),
+            )) => change,
+            DecodedDigestItem::Consensus(ConsensusLog::Grandpa(
+                GrandpaConsensusLog::ForcedChange(_, change),
+            )) => change,
+            _ => return None,
+        };
+        Some(PendingChange {
+            next_authorities: change.next_authorities,
+            effective_number: (*header.number())
+                .into()
+                .saturating_add(change.delay.into()),
+        })
+    })
+}
+
+/// A change of the authority set that has been scheduled, as kept in the storage of
+/// the GRANDPA pallet.
+#[derive(Decode)]
+struct StoredPendingChange<N> {
+    scheduled_at: N,
+    delay: N,
+    next_authorities: Vec<(AuthorityId, AuthorityWeight)>,
+    _forced: Option<N>,
+}
+
+/// Whether `hash` is `ancestor`, or reaches it by following the parents recorded in
+/// `ancestries`.
+fn descends_from<Hash: Eq + std::hash::Hash>(
+    ancestries: &HashMap<Hash, Hash>,
+    hash: &Hash,
+    ancestor: &Hash,
+) -> bool {
+    let mut current = hash;
+    // Each step must use up a header, which rules out going round in circles.
+    for _ in 0..=ancestries.len() {
+        if current == ancestor {
+            return true
+        }
+        match ancestries.get(current) {
+            Some(parent) => current = parent,
+            None => return false,
+        }
+    }
+    false
+}
+
+/// Client for fetching GRANDPA finality proofs and authority sets.
+pub struct FinalityClient<'a, T: Config> {
+    rpc: &'a Rpc<T>,
+    storage: StorageClient<'a, T>,
+}
+
+impl<'a, T: Config> FinalityClient<'a, T> {
+    /// Create a new [`FinalityClient`].
+    pub fn new(rpc: &'a Rpc<T>, storage: StorageClient<'a, T>) -> Self {
+        Self { rpc, storage }
+    }
+
+    /// The GRANDPA authority set at the given block, or at the latest block if `None`,
+    /// along with any change to it that is scheduled but not yet enacted.
+    pub async fn authority_set(
+        &self,
+        hash: Option<T::Hash>,
+    ) -> Result<AuthoritySet, BasicError> {
+        let mut set_id = self
+            .storage
+            .fetch_unhashed::<u64>(grandpa_key("CurrentSetId"), hash)
+            .await?
+            .unwrap_or_default();
+        let pending = self
+            .storage
+            .fetch_unhashed::<StoredPendingChange<T::BlockNumber>>(
+                grandpa_key("PendingChange"),
+                hash,
+            )
+            .await?
+            .map(|change| {
+                // The set id is bumped as soon as a change is scheduled, but the
+                // authorities only once it is enacted.
+                set_id = set_id.saturating_sub(1);
+                PendingChange {
+                    next_authorities: change.next_authorities,
+                    effective_number: change
+                        .scheduled_at
+                        .into()
+                        .saturating_add(change.delay.into()),
+                }
+            });
+
+        let key = StorageKey(GRANDPA_AUTHORITIES_KEY.to_vec());
+        let (version, authorities) = self
+            .storage
+            .fetch_unhashed::<(u8, Vec<(AuthorityId, AuthorityWeight)>)>(key, hash)
+            .await?
+            .ok_or_else(|| {
+                BasicError::Other("No GRANDPA authorities found in storage".into())
+            })?;
+        if version != AUTHORITIES_VERSION {
+            return Err(BasicError::Other(format!(
+                "Unsupported version {} of the GRANDPA authority list",
+                version
+            )))
+        }
+        Ok(AuthoritySet {
+            set_id,
+            authorities,
+            pending,
+        })
+    }
+
+    /// Fetch a proof that the given block is final. `None` is returned if the block has
+    /// not been finalized yet.
+    pub async fn prove_finality(
+        &self,
+        block_number: T::BlockNumber,
+    ) -> Result<Option<FinalityProof<T::Header>>, BasicError> {
+        self.rpc.grandpa_prove_finality(block_number).await
+    }
+}
+
+fn grandpa_key(storage: &str) -> StorageKey {
+    let mut key = twox_128(b"Grandpa").to_vec();
+    key.extend(twox_128(storage.as_bytes()));
+    StorageKey(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::digest::{
+        ScheduledChange,
+        GRANDPA_ENGINE_ID,
+    };
+    use assert_matches::assert_matches;
+    use sp_core::H256;
+    use sp_runtime::{
+        generic,
+        traits::BlakeTwo256,
+        Digest,
+        DigestItem,
+    };
+
+    type TestHeader = generic::Header<u32, BlakeTwo256>;
+
+    fn header(number: u32, parent_hash: H256, logs: Vec<DigestItem>) -> TestHeader {
+        TestHeader::new(
+            number,
+            H256::zero(),
+            H256::zero(),
+            parent_hash,
+            Digest { logs },
+        )
+    }
+
+    fn pairs() -> Vec<ed25519::Pair> {
+        (1u8..=4)
+            .map(|n| ed25519::Pair::from_seed(&[n; 32]))
+            .collect()
+    }
+
+    fn authority_set() -> AuthoritySet {
+        AuthoritySet::new(7, pairs().iter().map(|pair| (pair.public().0, 1)).collect())
+    }
+
+    fn justification(
+        signers: &[ed25519::Pair],
+        target: &TestHeader,
+        vote: &TestHeader,
+        set_id: u64,
+    ) -> GrandpaJustification<TestHeader> {
+        let round = 3;
+        let precommits = signers
+            .iter()
+            .map(|pair| {
+                let precommit = Precommit {
+                    target_hash: vote.hash(),
+                    target_number: vote.number,
+                };
+                let message =
+                    (PRECOMMIT_MESSAGE_INDEX, &precommit, round, set_id).encode();
+                SignedPrecommit {
+                    precommit,
+                    signature: pair.sign(&message),
+                    id: pair.public().0,
+                }
+            })
+            .collect();
+        GrandpaJustification {
+            round,
+            commit: Commit {
+                target_hash: target.hash(),
+                target_number: target.number,
+                precommits,
+            },
+            votes_ancestries: if vote == target {
+                Vec::new()
+            } else {
+                vec![vote.clone()]
+            },
+        }
+    }
+
+    #[test]
+    fn justification_needs_two_thirds_of_the_weight() {
+        let set = authority_set();
+        assert_eq!(set.threshold(), 3);
+        let pairs = pairs();
+        let target = header(10, H256::zero(), Vec::new());
+
+        let enough = justification(&pairs[..3], &target, &target, set.set_id);
+        assert!(set.verify_justification(target.hash(), &enough).is_ok());
+
+        let too_few = justification(&pairs[..2], &target, &target, set.set_id);
+        assert_matches!(
+            set.verify_justification(target.hash(), &too_few),
+            Err(FinalityError::NotEnoughWeight {
+                signed: 2,
+                threshold: 3
+            })
+        );
+
+        // The same authority precommitting twice still only counts once.
+        let mut repeated = too_few.clone();
+        repeated
+            .commit
+            .precommits
+            .push(too_few.commit.precommits[0].clone());
+        assert_matches!(
+            set.verify_justification(target.hash(), &repeated),
+            Err(FinalityError::NotEnoughWeight { signed: 2, .. })
+        );
+    }
+
+    #[test]
+    fn precommits_may_vote_for_descendants_of_the_target() {
+        let set = authority_set();
+        let target = header(10, H256::zero(), Vec::new());
+        let child = header(11, target.hash(), Vec::new());
+
+        let valid = justification(&pairs(), &target, &child, set.set_id);
+        assert!(set.verify_justification(target.hash(), &valid).is_ok());
+
+        let mut missing_ancestry = valid;
+        missing_ancestry.votes_ancestries.clear();
+        assert_matches!(
+            set.verify_justification(target.hash(), &missing_ancestry),
+            Err(FinalityError::InvalidAncestry(_))
+        );
+    }
+
+    #[test]
+    fn invalid_justifications_are_rejected() {
+        let set = authority_set();
+        let target = header(10, H256::zero(), Vec::new());
+        let other = header(10, H256::repeat_byte(1), Vec::new());
+
+        let valid = justification(&pairs(), &target, &target, set.set_id);
+        assert_matches!(
+            set.verify_justification(other.hash(), &valid),
+            Err(FinalityError::TargetMismatch { .. })
+        );
+
+        let wrong_set = justification(&pairs(), &target, &target, set.set_id + 1);
+        assert_matches!(
+            set.verify_justification(target.hash(), &wrong_set),
+            Err(FinalityError::BadSignature(_))
+        );
+
+        let outsider = ed25519::Pair::from_seed(&[9; 32]);
+        let unknown = justification(&[outsider], &target, &target, set.set_id);
+        assert_matches!(
+            set.verify_justification(target.hash(), &unknown),
+            Err(FinalityError::UnknownAuthority { set_id: 7, .. })
+        );
+    }
+
+    #[test]
+    fn finality_proofs_are_decoded_and_verified() {
+        let set = authority_set();
+        let target = header(10, H256::zero(), Vec::new());
+        let justification = justification(&pairs(), &target, &target, set.set_id);
+        let proof = FinalityProof {
+            block: target.hash(),
+            justification: justification.encode(),
+            unknown_headers: vec![target.clone()],
+        };
+        let encoded = proof.encode();
+        let decoded = FinalityProof::<TestHeader>::decode(&mut &encoded[..]).unwrap();
+
+        assert_eq!(set.verify_finality_proof(&decoded).unwrap(), justification);
+
+        let garbage = FinalityProof {
+            justification: vec![1, 2, 3],
+            ..decoded
+        };
+        assert_matches!(
+            set.verify_finality_proof(&garbage),
+            Err(FinalityError::Codec(_))
+        );
+    }
+
+    #[test]
+    fn forced_changes_are_tracked_too() {
+        let mut set = authority_set();
+        let change = GrandpaConsensusLog::ForcedChange(
+            4u32,
+            ScheduledChange {
+                next_authorities: vec![([5; 32], 1)],
+                delay: 0,
+            },
+        );
+        let signal = header(
+            10,
+            H256::zero(),
+            vec![DigestItem::Consensus(GRANDPA_ENGINE_ID, change.encode())],
+        );
+
+        assert!(set.apply_finalized(&signal));
+        assert_eq!(set.set_id, 8);
+    }
+
+    #[test]
+    fn scheduled_changes_are_enacted_after_their_delay() {
+        let mut set = authority_set();
+        let next_authorities = vec![([5; 32], 1)];
+        let change = GrandpaConsensusLog::ScheduledChange(ScheduledChange {
+            next_authorities: next_authorities.clone(),
+            delay: 2u32,
+        });
+        let signal = header(
+            10,
+            H256::zero(),
+            vec![DigestItem::Consensus(GRANDPA_ENGINE_ID, change.encode())],
+        );
+
+        assert!(!set.apply_finalized(&signal));
+        assert_eq!(set.pending_change_at(), Some(12));
+        assert!(!set.apply_finalized(&header(11, signal.hash(), Vec::new())));
+        assert_eq!(set.set_id, 7);
+
+        assert!(set.apply_finalized(&header(12, H256::zero(), Vec::new())));
+        assert_eq!(set.set_id, 8);
+        assert_eq!(set.authorities, next_authorities);
+        assert_eq!(set.pending_change_at(), None);
+    }
+}
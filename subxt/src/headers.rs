@@ -0,0 +1,88 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Fetching the headers of a range of blocks.
+
+use crate::{
+    error::BasicError,
+    rpc::Rpc,
+    Config,
+};
+use futures::{
+    stream,
+    Stream,
+    StreamExt,
+};
+use futures_timer::Delay;
+use std::{
+    ops::RangeInclusive,
+    time::Duration,
+};
+
+/// How many times fetching a header is attempted before giving up.
+const FETCH_ATTEMPTS: u32 = 3;
+/// How long to wait before retrying a failed fetch. Doubles with each retry.
+const RETRY_DELAY: Duration = Duration::from_millis(200);
+
+/// Fetch the headers of the given blocks, in order, with up to `concurrency` blocks
+/// being fetched at once. Each failed fetch is retried a couple of times before the
+/// error is handed back.
+pub(crate) fn range<T: Config>(
+    rpc: &Rpc<T>,
+    blocks: RangeInclusive<u64>,
+    concurrency: usize,
+) -> impl Stream<Item = Result<T::Header, BasicError>> + Send + '_ {
+    stream::iter(blocks)
+        .map(move |number| fetch_with_retry(rpc, number))
+        .buffered(concurrency.max(1))
+}
+
+async fn fetch_with_retry<T: Config>(
+    rpc: &Rpc<T>,
+    number: u64,
+) -> Result<T::Header, BasicError> {
+    let mut attempt = 1;
+    loop {
+        match fetch(rpc, number).await {
+            Err(e) if attempt < FETCH_ATTEMPTS && is_retryable(&e) => {
+                log::debug!(
+                    "Fetching the header of block {} failed (attempt {}): {}",
+                    number,
+                    attempt,
+                    e
+                );
+                Delay::new(RETRY_DELAY * 2u32.pow(attempt - 1)).await;
+                attempt += 1;
+            }
+            res => return res,
+        }
+    }
+}
+
+async fn fetch<T: Config>(rpc: &Rpc<T>, number: u64) -> Result<T::Header, BasicError> {
+    let hash = rpc
+        .block_hash(Some(number.into()))
+        .await?
+        .ok_or_else(|| BasicError::Other(format!("Block {} not found", number)))?;
+    rpc.header(Some(hash))
+        .await?
+        .ok_or_else(|| BasicError::Other(format!("Header of block {} not found", number)))
+}
+
+/// Errors from talking to the node might go away on a second try; others won't.
+fn is_retryable(error: &BasicError) -> bool {
+    matches!(error, BasicError::Rpc(_))
+}
@@ -0,0 +1,200 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Detecting a node which has stopped responding, even though the connection to it is open.
+
+use crate::{
+    rpc::{
+        ConnectionEvent,
+        Rpc,
+    },
+    Config,
+};
+use futures::{
+    future::{
+        self,
+        BoxFuture,
+        Either,
+    },
+    Future,
+    FutureExt,
+};
+use futures_timer::Delay;
+use std::{
+    pin::Pin,
+    task::{
+        Context,
+        Poll,
+    },
+    time::Duration,
+};
+
+/// How often the node is pinged, and how long it has to respond before the connection
+/// to it is considered dead.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeepaliveConfig {
+    /// How long to wait between pings.
+    pub ping_interval: Duration,
+    /// How long the node has to respond to a ping.
+    pub pong_timeout: Duration,
+    /// How long the node may go without successfully responding to a ping (for
+    /// instance because every ping fails with an error) before the connection is
+    /// considered dead.
+    pub inactivity_timeout: Duration,
+}
+
+impl Default for KeepaliveConfig {
+    fn default() -> Self {
+        Self {
+            ping_interval: Duration::from_secs(30),
+            pong_timeout: Duration::from_secs(10),
+            inactivity_timeout: Duration::from_secs(90),
+        }
+    }
+}
+
+/// Pings the node, as configured with [`crate::Client::keepalive`]. Nothing is sent
+/// unless this is spawned (or otherwise polled); it resolves once the client is shut
+/// down, or once every other handle to the client has been dropped.
+#[must_use = "no pings are sent unless the driver is spawned or polled"]
+pub struct KeepaliveDriver {
+    config: KeepaliveConfig,
+    run: BoxFuture<'static, ()>,
+}
+
+impl KeepaliveDriver {
+    pub(crate) fn new<T: Config>(rpc: Rpc<T>, config: KeepaliveConfig) -> Self {
+        Self {
+            config,
+            run: run(rpc, config).boxed(),
+        }
+    }
+}
+
+impl std::fmt::Debug for KeepaliveDriver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeepaliveDriver")
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+impl Future for KeepaliveDriver {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.run.poll_unpin(cx)
+    }
+}
+
+/// Ping the node until the client is shut down or dropped, reporting the connection as
+/// [`ConnectionEvent::Disconnected`] when the node stops responding, and as
+/// [`ConnectionEvent::Connected`] if it starts responding again. If the client can
+/// reconnect, a dead connection is re-established.
+async fn run<T: Config>(rpc: Rpc<T>, config: KeepaliveConfig) {
+    let mut shutdown = rpc.shutdown_signal();
+    // Fires once the node has gone for the inactivity timeout without responding.
+    let mut inactivity = Delay::new(config.inactivity_timeout);
+
+    loop {
+        let interval = Delay::new(config.ping_interval);
+        let shut_down = future::poll_fn(|cx| {
+            if shutdown.poll_shut_down(cx) {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        });
+        futures::pin_mut!(shut_down);
+        if let Either::Left(_) = future::select(shut_down, interval).await {
+            return
+        }
+        // Nobody but us is using the connection any more.
+        if !rpc.has_other_handles() {
+            return
+        }
+
+        let generation = rpc.connection_generation();
+        let ping = rpc.ping();
+        futures::pin_mut!(ping);
+        let reason = match future::select(ping, Delay::new(config.pong_timeout)).await {
+            Either::Left((Ok(()), _)) => {
+                inactivity.reset(config.inactivity_timeout);
+                if rpc.is_disconnected() {
+                    rpc.notify_connection_event(ConnectionEvent::Connected);
+                }
+                continue
+            }
+            Either::Left((Err(e), _)) => {
+                if (&mut inactivity).now_or_never().is_none() {
+                    log::debug!("Keepalive ping failed: {}", e);
+                    continue
+                }
+                format!(
+                    "no response from the node for over {:?} (last error: {})",
+                    config.inactivity_timeout, e
+                )
+            }
+            Either::Right(_) => {
+                format!(
+                    "the node did not respond to a ping within {:?}",
+                    config.pong_timeout
+                )
+            }
+        };
+        log::warn!("Marking the connection as dead: {}", reason);
+        rpc.notify_connection_event(ConnectionEvent::Disconnected { reason });
+        if rpc.can_reconnect() {
+            match rpc.reconnect(generation).await {
+                Ok(()) => inactivity.reset(config.inactivity_timeout),
+                Err(e) => log::warn!("Failed to reconnect: {}", e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        rpc::MockRpcClient,
+        DefaultConfig,
+    };
+
+    #[async_std::test]
+    async fn the_node_is_pinged_until_the_client_is_shut_down() {
+        let mock = MockRpcClient::new();
+        mock.set_response("system_health", serde_json::json!({}));
+        let rpc = Rpc::<DefaultConfig>::new_mock(mock.clone());
+        let config = KeepaliveConfig {
+            ping_interval: Duration::from_millis(5),
+            ..Default::default()
+        };
+
+        // Driven by whichever executor polls it, rather than a runtime of its own.
+        let driver = KeepaliveDriver::new(rpc.clone(), config);
+        let shutdown = async {
+            Delay::new(Duration::from_millis(50)).await;
+            rpc.shutdown(Duration::from_secs(1)).await.unwrap();
+        };
+        future::join(driver, shutdown).await;
+
+        assert!(mock
+            .requests()
+            .iter()
+            .any(|request| request.method == "system_health"));
+    }
+}
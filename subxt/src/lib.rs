@@ -40,6 +40,9 @@
 )]
 #![allow(clippy::type_complexity)]
 
+#[cfg(not(any(feature = "native", feature = "web")))]
+compile_error!("either the `native` or the `web` feature must be enabled");
+
 pub use frame_metadata::StorageHasher;
 pub use subxt_macro::subxt;
 
@@ -56,66 +59,173 @@ use codec::{
 use core::fmt::Debug;
 use derivative::Derivative;
 
+pub mod accounts;
+pub mod avail;
+pub mod babe;
+mod backpressure;
+mod batch;
+pub mod blocks;
 mod client;
+mod client_manager;
 mod config;
+mod decode_limits;
+pub mod digest;
+pub mod dynamic;
 mod error;
 pub mod events;
 pub mod extrinsic;
+pub mod finality;
+mod headers;
+mod keepalive;
 mod metadata;
+mod metadata_cache;
+pub mod metadata_diff;
+mod metadata_registry;
+pub mod metrics;
+pub mod multisig;
+mod nonce_manager;
+mod offline;
+pub mod proxy;
+mod rate_limit;
+mod reconnect;
 pub mod rpc;
+mod rpc_batch;
+mod rpc_middleware;
+mod rpc_mock;
+mod rpc_recording;
+pub mod runtime_api;
 pub mod storage;
+mod submission_log;
+#[cfg(feature = "native")]
+pub mod testing;
+pub mod timestamp;
 mod transaction;
+pub mod weights;
 
 pub use crate::{
+    avail::AvailConfig,
+    backpressure::{
+        with_backpressure,
+        BackpressurePolicy,
+        BufferDriver,
+        Buffered,
+    },
+    batch::{
+        BatchAllCall,
+        BatchCall,
+        BatchCompleted,
+        BatchInterrupted,
+        BatchOutcome,
+        ItemCompleted,
+    },
     client::{
+        BlockClient,
         Client,
         ClientBuilder,
+        DryRunOutcome,
+        FeeEstimate,
         SubmittableExtrinsic,
+        UpgradeDetected,
+    },
+    client_manager::{
+        ClientManager,
+        NetworkProfile,
+        NetworkProfiles,
+        SignerSource,
     },
     config::{
         Config,
         DefaultConfig,
     },
+    decode_limits::DecodeLimits,
     error::{
         BasicError,
+        DispatchErrorDetails,
         Error,
         GenericError,
         HasModuleError,
+        ModuleError,
         RuntimeError,
         TransactionError,
     },
     events::{
+        DynamicEventDetails,
         EventDetails,
         Events,
         RawEventDetails,
     },
     extrinsic::{
+        DecodedExtrinsic,
         DefaultExtra,
         DefaultExtraWithTxPayment,
+        EcdsaSigner,
+        Ed25519Signer,
         PairSigner,
+        PayloadSigner,
+        RemoteSigner,
         SignedExtra,
         Signer,
+        Sr25519Signer,
         UncheckedExtrinsic,
     },
+    keepalive::{
+        KeepaliveConfig,
+        KeepaliveDriver,
+    },
     metadata::{
         ErrorMetadata,
+        IncompatibleMetadata,
         Metadata,
         MetadataError,
+        MetadataHashes,
+        PalletHashes,
         PalletMetadata,
     },
+    metadata_registry::MetadataRegistry,
+    nonce_manager::NonceManager,
+    offline::OfflineClient,
+    reconnect::ReconnectPolicy,
     rpc::{
         BlockNumber,
+        ConnectionEvent,
+        ConnectionEvents,
+        ExtrinsicOrHash,
+        MockRpcClient,
         ReadProof,
         RpcClient,
         SystemProperties,
     },
+    rpc_batch::{
+        Batch,
+        BatchItem,
+        BatchResponse,
+    },
+    rpc_middleware::{
+        CacheMiddleware,
+        Next,
+        RetryMiddleware,
+        RetryPolicy,
+        RpcMiddleware,
+        RpcRequest,
+    },
+    rpc_recording::RpcRecorder,
     storage::{
+        verify_read_proof,
+        ChildStorageClient,
+        DecodedKeyIter,
         KeyIter,
+        StorageChanges,
         StorageEntry,
         StorageEntryKey,
         StorageMapKey,
     },
+    submission_log::{
+        SubmissionLogging,
+        SUBMISSION_LOG_TARGET,
+    },
     transaction::{
+        ResubmitPolicy,
+        SubmissionStatus,
         TransactionEvents,
         TransactionInBlock,
         TransactionProgress,
@@ -129,11 +239,60 @@ pub trait Call: Encode {
     const PALLET: &'static str;
     /// Function name.
     const FUNCTION: &'static str;
+    /// The hash of the call in the metadata that it was generated from, for checking
+    /// that it's unchanged with [`Metadata::validate_call()`]. `None` for calls which
+    /// weren't generated by the [`subxt`] macro.
+    const METADATA_HASH: Option<[u8; 32]> = None;
 
     /// Returns true if the given pallet and function names match this call.
     fn is_call(pallet: &str, function: &str) -> bool {
         Self::PALLET == pallet && Self::FUNCTION == function
     }
+
+    /// The name of the pallet of this call. This is [`Call::PALLET`], unless the call
+    /// is only known at runtime, such as a [`dynamic::DynamicCall`].
+    fn pallet_name(&self) -> &str {
+        Self::PALLET
+    }
+
+    /// The name of this call. This is [`Call::FUNCTION`], unless the call is only
+    /// known at runtime, such as a [`dynamic::DynamicCall`].
+    fn function_name(&self) -> &str {
+        Self::FUNCTION
+    }
+
+    /// Encode the call data, prefixed with the pallet and call indices found in the
+    /// given metadata.
+    fn encode_with_metadata(&self, metadata: &Metadata) -> Result<Encoded, BasicError>
+    where
+        Self: Sized,
+    {
+        let call = metadata
+            .pallet(Self::PALLET)
+            .and_then(|pallet| pallet.encode_call(self))?;
+        Ok(call)
+    }
+}
+
+/// A [`Call`] whose pallet and call indices were taken from the metadata at codegen time.
+///
+/// This allows the call data to be encoded without a connected [`Client`], for instance
+/// when building transactions on a machine that has no access to a node.
+pub trait StaticCall: Call {
+    /// Index of the pallet that the call belongs to.
+    const PALLET_INDEX: u8;
+    /// Index of the call within the pallet.
+    const CALL_INDEX: u8;
+
+    /// Encode the call data, prefixed with the pallet and call indices.
+    ///
+    /// **Note:** the indices are those of the metadata the code was generated from, and so
+    /// the result is only valid for runtimes that are compatible with that metadata.
+    fn encode_call_data(&self) -> Encoded {
+        let mut bytes = vec![Self::PALLET_INDEX, Self::CALL_INDEX];
+        self.encode_to(&mut bytes);
+        Encoded(bytes)
+    }
 }
 
 /// Event trait.
@@ -161,7 +320,7 @@ impl codec::Encode for Encoded {
 }
 
 /// A phase of a block's execution.
-#[derive(Clone, Debug, Eq, PartialEq, Decode, Encode)]
+#[derive(Clone, Debug, Eq, PartialEq, Decode, Encode, serde::Serialize)]
 pub enum Phase {
     /// Applying an extrinsic.
     ApplyExtrinsic(u32),
@@ -171,6 +330,16 @@ pub enum Phase {
     Initialization,
 }
 
+impl Phase {
+    /// The index in the block of the extrinsic being applied, if any.
+    pub fn extrinsic_index(&self) -> Option<u32> {
+        match self {
+            Phase::ApplyExtrinsic(index) => Some(*index),
+            _ => None,
+        }
+    }
+}
+
 /// A wrapper for any type `T` which implement encode/decode in a way compatible with `Vec<u8>`.
 ///
 /// [`WrapperKeepOpaque`] stores the type only in its opaque format, aka as a `Vec<u8>`. To
@@ -33,7 +33,15 @@ use frame_metadata::{
 use crate::{
     Call,
     Encoded,
+    StorageEntry,
 };
+pub use subxt_metadata::PalletHashes;
+use subxt_metadata::{
+    get_call_hash,
+    get_pallet_hashes,
+    get_storage_hash,
+};
+
 use scale_info::{
     form::PortableForm,
     Type,
@@ -53,10 +61,10 @@ pub enum MetadataError {
     #[error("Call {0} not found")]
     CallNotFound(&'static str),
     /// Event is not in metadata.
-    #[error("Pallet {0}, Event {0} not found")]
+    #[error("Pallet {0}, Event {1} not found")]
     EventNotFound(u8, u8),
     /// Event is not in metadata.
-    #[error("Pallet {0}, Error {0} not found")]
+    #[error("Pallet {0}, Error {1} not found")]
     ErrorNotFound(u8, u8),
     /// Storage is not in metadata.
     #[error("Storage {0} not found")]
@@ -76,6 +84,50 @@ pub enum MetadataError {
     /// Type is not in metadata.
     #[error("Type {0} missing from type registry")]
     TypeNotFound(u32),
+    /// Signed extension carries data that can't be constructed.
+    #[error("Signed extension {0} is not supported")]
+    UnsupportedSignedExtension(String),
+    /// The metadata differs from the metadata which code was generated from.
+    #[error("Metadata is incompatible with the generated code: {0}")]
+    IncompatibleMetadata(IncompatibleMetadata),
+    /// A call differs from the call which code was generated for.
+    #[error("Call {0}::{1} has changed since the code was generated")]
+    IncompatibleCall(&'static str, &'static str),
+    /// A storage entry differs from the entry which code was generated for.
+    #[error("Storage {0}::{1} has changed since the code was generated")]
+    IncompatibleStorage(&'static str, &'static str),
+}
+
+/// Implemented by the `RuntimeApi` generated by the [`crate::subxt`] macro: the hashes
+/// of the pallets in the metadata it was generated from.
+pub trait MetadataHashes {
+    /// The hashes of each pallet, by name.
+    fn pallet_hashes() -> &'static [(&'static str, PalletHashes)];
+}
+
+/// How the metadata of a node differs from the metadata that code was generated from.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct IncompatibleMetadata {
+    /// The pallets which the node doesn't have.
+    pub missing_pallets: Vec<String>,
+    /// The pallets which differ, along with which of their parts ("calls", "events",
+    /// "errors", "storage" or "constants") do.
+    pub changed_pallets: Vec<(String, Vec<&'static str>)>,
+}
+
+impl std::fmt::Display for IncompatibleMetadata {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let missing = self
+            .missing_pallets
+            .iter()
+            .map(|pallet| format!("{} is missing", pallet));
+        let changed = self
+            .changed_pallets
+            .iter()
+            .map(|(pallet, parts)| format!("{} {} changed", pallet, parts.join(", ")));
+        let report = missing.chain(changed).collect::<Vec<_>>();
+        write!(f, "{}", report.join("; "))
+    }
 }
 
 /// Runtime metadata.
@@ -130,6 +182,86 @@ impl Metadata {
     pub fn runtime_metadata(&self) -> &RuntimeMetadataLastVersion {
         &self.metadata
     }
+
+    /// Check that the call is the same in this metadata as in the metadata it was
+    /// generated from, so that it will be encoded correctly. Calls without a
+    /// [`Call::METADATA_HASH`] aren't checked.
+    pub fn validate_call<C: Call>(&self) -> Result<(), MetadataError> {
+        let expected = match C::METADATA_HASH {
+            Some(hash) => hash,
+            None => return Ok(()),
+        };
+        let hash = get_call_hash(
+            &self.metadata.types,
+            self.raw_pallet(C::PALLET)?,
+            C::FUNCTION,
+        )
+        .ok_or(MetadataError::CallNotFound(C::FUNCTION))?;
+        if hash != expected {
+            return Err(MetadataError::IncompatibleCall(C::PALLET, C::FUNCTION))
+        }
+        Ok(())
+    }
+
+    /// Check that the storage entry is the same in this metadata as in the metadata it
+    /// was generated from, so that its keys and value will be encoded correctly.
+    /// Entries without a [`StorageEntry::METADATA_HASH`] aren't checked.
+    pub fn validate_storage<F: StorageEntry>(&self) -> Result<(), MetadataError> {
+        let expected = match F::METADATA_HASH {
+            Some(hash) => hash,
+            None => return Ok(()),
+        };
+        let hash = get_storage_hash(
+            &self.metadata.types,
+            self.raw_pallet(F::PALLET)?,
+            F::STORAGE,
+        )
+        .ok_or(MetadataError::StorageNotFound(F::STORAGE))?;
+        if hash != expected {
+            return Err(MetadataError::IncompatibleStorage(F::PALLET, F::STORAGE))
+        }
+        Ok(())
+    }
+
+    fn raw_pallet(
+        &self,
+        name: &'static str,
+    ) -> Result<&frame_metadata::PalletMetadata<PortableForm>, MetadataError> {
+        self.metadata
+            .pallets
+            .iter()
+            .find(|pallet| pallet.name == name)
+            .ok_or_else(|| MetadataError::PalletNotFound(name.to_string()))
+    }
+
+    /// Check that the given pallets, with the hashes that code was generated with, are
+    /// the same in this metadata. Pallets which aren't given are ignored.
+    pub fn check_pallet_hashes(
+        &self,
+        expected: &[(&str, PalletHashes)],
+    ) -> Result<(), MetadataError> {
+        let mut incompatible = IncompatibleMetadata::default();
+        for (name, expected) in expected {
+            let pallet = self.metadata.pallets.iter().find(|p| p.name == *name);
+            match pallet {
+                Some(pallet) => {
+                    let hashes = get_pallet_hashes(&self.metadata.types, pallet);
+                    let changed = expected.differences(&hashes);
+                    if !changed.is_empty() {
+                        incompatible
+                            .changed_pallets
+                            .push((name.to_string(), changed));
+                    }
+                }
+                None => incompatible.missing_pallets.push(name.to_string()),
+            }
+        }
+        if incompatible == IncompatibleMetadata::default() {
+            Ok(())
+        } else {
+            Err(MetadataError::IncompatibleMetadata(incompatible))
+        }
+    }
 }
 
 /// Metadata for a specific pallet.
@@ -148,6 +280,19 @@ impl PalletMetadata {
         &self.name
     }
 
+    /// Get the index of the pallet.
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+
+    /// Get the index of the call with the given name.
+    pub fn call_index(&self, name: &'static str) -> Result<u8, MetadataError> {
+        self.calls
+            .get(name)
+            .copied()
+            .ok_or(MetadataError::CallNotFound(name))
+    }
+
     /// Encode a call based on this pallet metadata.
     pub fn encode_call<C>(&self, call: &C) -> Result<Encoded, MetadataError>
     where
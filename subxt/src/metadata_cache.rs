@@ -0,0 +1,163 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! An on-disk cache of runtime metadata, keyed by genesis hash and spec version.
+
+use crate::{
+    error::BasicError,
+    rpc::Rpc,
+    Config,
+    Metadata,
+};
+use codec::Decode;
+use frame_metadata::RuntimeMetadataPrefixed;
+use std::{
+    fs,
+    io,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+/// Stores the SCALE encoded metadata of each runtime version we connect to in a directory,
+/// so that it doesn't need to be downloaded from the node again on the next startup.
+///
+/// A runtime upgrade always bumps the `spec_version`, so a new entry is created (and the old
+/// one simply no longer used) whenever the node reports a version we haven't seen before.
+#[derive(Clone, Debug)]
+pub(crate) struct MetadataCache {
+    dir: PathBuf,
+}
+
+impl MetadataCache {
+    /// Create a new cache that stores metadata in the given directory.
+    pub(crate) fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Return the metadata for the given runtime version from the cache if it's there,
+    /// otherwise download it from the node and store it in the cache for next time.
+    pub(crate) async fn fetch<T: Config>(
+        &self,
+        rpc: &Rpc<T>,
+        genesis_hash: &T::Hash,
+        spec_version: u32,
+        at: Option<T::Hash>,
+    ) -> Result<Metadata, BasicError> {
+        let path = self.path(genesis_hash.as_ref(), spec_version);
+
+        match self.load(&path) {
+            Ok(Some(metadata)) => return Ok(metadata),
+            Ok(None) => {}
+            Err(e) => {
+                log::warn!(
+                    "Ignoring unreadable cached metadata at {}: {}",
+                    path.display(),
+                    e
+                );
+            }
+        }
+
+        let bytes = rpc.metadata_bytes(at).await?;
+        let metadata = decode_metadata(&bytes)?;
+        if let Err(e) = self.store(&path, &bytes) {
+            log::warn!("Failed to cache metadata at {}: {}", path.display(), e);
+        }
+        Ok(metadata)
+    }
+
+    fn path(&self, genesis_hash: &[u8], spec_version: u32) -> PathBuf {
+        self.dir.join(format!(
+            "{}-{}.scale",
+            hex::encode(genesis_hash),
+            spec_version
+        ))
+    }
+
+    fn load(&self, path: &Path) -> Result<Option<Metadata>, BasicError> {
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        decode_metadata(&bytes).map(Some)
+    }
+
+    fn store(&self, path: &Path, bytes: &[u8]) -> Result<(), BasicError> {
+        fs::create_dir_all(&self.dir)?;
+        // Write to a temporary file first and then move it into place, so that another
+        // process starting up at the same time never sees a partially written file.
+        let tmp_path = path.with_extension("scale.tmp");
+        fs::write(&tmp_path, bytes)?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+}
+
+/// Decode SCALE encoded metadata, as returned by `state_getMetadata`.
+pub(crate) fn decode_metadata(bytes: &[u8]) -> Result<Metadata, BasicError> {
+    let meta: RuntimeMetadataPrefixed = Decode::decode(&mut &bytes[..])?;
+    Ok(meta.try_into()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codec::Encode;
+    use frame_metadata::v14::{
+        ExtrinsicMetadata,
+        RuntimeMetadataLastVersion,
+    };
+    use scale_info::meta_type;
+
+    fn metadata_bytes() -> Vec<u8> {
+        let extrinsic = ExtrinsicMetadata {
+            ty: meta_type::<()>(),
+            version: 0,
+            signed_extensions: vec![],
+        };
+        let v14 = RuntimeMetadataLastVersion::new(vec![], extrinsic, meta_type::<()>());
+        let runtime_metadata: RuntimeMetadataPrefixed = v14.into();
+        runtime_metadata.encode()
+    }
+
+    #[test]
+    fn cached_metadata_is_keyed_by_genesis_and_spec_version() {
+        let dir = tempdir::TempDir::new("subxt-metadata-cache").unwrap();
+        let cache = MetadataCache::new(dir.path().to_path_buf());
+
+        let path = cache.path(&[1, 2], 10);
+        assert!(cache.load(&path).unwrap().is_none());
+
+        cache.store(&path, &metadata_bytes()).unwrap();
+        assert!(cache.load(&path).unwrap().is_some());
+
+        // A different spec version or chain doesn't hit the stored entry.
+        assert!(cache.load(&cache.path(&[1, 2], 11)).unwrap().is_none());
+        assert!(cache.load(&cache.path(&[3, 4], 10)).unwrap().is_none());
+    }
+
+    #[test]
+    fn corrupt_cache_entry_is_an_error() {
+        let dir = tempdir::TempDir::new("subxt-metadata-cache").unwrap();
+        let cache = MetadataCache::new(dir.path().to_path_buf());
+
+        let path = cache.path(&[1, 2], 10);
+        cache.store(&path, &[1, 2, 3]).unwrap();
+        assert!(cache.load(&path).is_err());
+    }
+}
@@ -0,0 +1,42 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Comparison of two versions of the metadata of a runtime, to review what a runtime
+//! upgrade changes before updating the code that talks to it. The same comparison is
+//! available from the command line as `subxt diff`.
+//!
+//! ```no_run
+//! # async fn run(client: subxt::Client<subxt::DefaultConfig>, old: subxt::Metadata) {
+//! let diff = subxt::metadata_diff::diff(&old, &client.metadata());
+//! if !diff.is_empty() {
+//!     println!("{}", diff);
+//! }
+//! # }
+//! ```
+
+use crate::Metadata;
+pub use subxt_metadata::{
+    Change,
+    ItemDiff,
+    ItemKind,
+    MetadataDiff,
+};
+
+/// Compare the calls, storage entries, events and constants of the pallets in `old`
+/// with those in `new`; see [`subxt_metadata::diff`].
+pub fn diff(old: &Metadata, new: &Metadata) -> MetadataDiff {
+    subxt_metadata::diff(old.runtime_metadata(), new.runtime_metadata())
+}
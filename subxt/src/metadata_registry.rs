@@ -0,0 +1,129 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A registry of metadata for the different runtime versions of a chain.
+
+use crate::Metadata;
+use std::{
+    collections::BTreeMap,
+    sync::{
+        Arc,
+        RwLock,
+    },
+};
+
+/// Holds the metadata for each runtime version (identified by its `spec_version`) that
+/// a [`crate::Client`] knows about, so that data from blocks produced by older runtimes
+/// can be encoded and decoded with the type information that was valid at the time.
+///
+/// Cloning the registry is cheap, and clones share the same underlying entries.
+#[derive(Clone, Debug, Default)]
+pub struct MetadataRegistry {
+    entries: Arc<RwLock<BTreeMap<u32, Arc<Metadata>>>>,
+}
+
+impl MetadataRegistry {
+    /// Create a new, empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register the metadata for the given runtime `spec_version`, replacing any
+    /// metadata that was previously registered for it.
+    pub fn register(&self, spec_version: u32, metadata: Metadata) -> Arc<Metadata> {
+        let metadata = Arc::new(metadata);
+        self.insert(spec_version, metadata.clone());
+        metadata
+    }
+
+    /// Return the metadata registered for the given runtime `spec_version`, if any.
+    pub fn get(&self, spec_version: u32) -> Option<Arc<Metadata>> {
+        self.entries
+            .read()
+            .expect("metadata registry lock poisoned")
+            .get(&spec_version)
+            .cloned()
+    }
+
+    /// Is there metadata registered for the given runtime `spec_version`?
+    pub fn contains(&self, spec_version: u32) -> bool {
+        self.entries
+            .read()
+            .expect("metadata registry lock poisoned")
+            .contains_key(&spec_version)
+    }
+
+    /// The runtime versions for which metadata is registered, in ascending order.
+    pub fn spec_versions(&self) -> Vec<u32> {
+        self.entries
+            .read()
+            .expect("metadata registry lock poisoned")
+            .keys()
+            .copied()
+            .collect()
+    }
+
+    pub(crate) fn insert(&self, spec_version: u32, metadata: Arc<Metadata>) {
+        self.entries
+            .write()
+            .expect("metadata registry lock poisoned")
+            .insert(spec_version, metadata);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use frame_metadata::{
+        v14::{
+            ExtrinsicMetadata,
+            RuntimeMetadataLastVersion,
+        },
+        RuntimeMetadataPrefixed,
+    };
+    use scale_info::meta_type;
+    use std::convert::TryFrom;
+
+    fn metadata() -> Metadata {
+        let extrinsic = ExtrinsicMetadata {
+            ty: meta_type::<()>(),
+            version: 0,
+            signed_extensions: vec![],
+        };
+        let v14 = RuntimeMetadataLastVersion::new(vec![], extrinsic, meta_type::<()>());
+        let runtime_metadata: RuntimeMetadataPrefixed = v14.into();
+        Metadata::try_from(runtime_metadata).unwrap()
+    }
+
+    #[test]
+    fn metadata_is_looked_up_by_spec_version() {
+        let registry = MetadataRegistry::new();
+        assert!(registry.get(1).is_none());
+
+        registry.register(2, metadata());
+        registry.register(1, metadata());
+
+        assert!(registry.contains(1));
+        assert!(registry.get(2).is_some());
+        assert!(registry.get(3).is_none());
+        assert_eq!(registry.spec_versions(), vec![1, 2]);
+
+        // Clones share their entries.
+        let cloned = registry.clone();
+        cloned.register(3, metadata());
+        assert!(registry.contains(3));
+    }
+}
@@ -0,0 +1,176 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A facade for metrics about what the client is up to, to be bridged to a metrics
+//! system such as Prometheus. See [`ClientMetrics`].
+
+use std::{
+    sync::Arc,
+    time::Duration,
+};
+
+/// Receives metrics from a client, given to it with
+/// [`crate::ClientBuilder::set_metrics`]. Every method does nothing by default, so
+/// implementations only need to provide the ones they're interested in.
+///
+/// The methods are called inline with the work being measured, so they should be
+/// cheap, such as incrementing a counter or observing a histogram.
+///
+/// ```
+/// use std::sync::atomic::{AtomicU64, Ordering};
+/// use subxt::metrics::ClientMetrics;
+///
+/// #[derive(Default)]
+/// struct Counters {
+///     calls: AtomicU64,
+///     errors: AtomicU64,
+/// }
+///
+/// impl ClientMetrics for Counters {
+///     fn rpc_call(&self, _method: &str) {
+///         self.calls.fetch_add(1, Ordering::Relaxed);
+///     }
+///     fn rpc_error(&self, _method: &str) {
+///         self.errors.fetch_add(1, Ordering::Relaxed);
+///     }
+/// }
+/// ```
+///
+/// **Note:** durations can't be measured on `wasm32`, so the histogram methods are
+/// never called there.
+pub trait ClientMetrics: Send + Sync + 'static {
+    /// An RPC method call (or subscription) was made.
+    fn rpc_call(&self, _method: &str) {}
+
+    /// An RPC method call (or subscription) failed.
+    fn rpc_error(&self, _method: &str) {}
+
+    /// The connection to the node was re-established after it was lost.
+    fn reconnect(&self) {}
+
+    /// How long an RPC method call took to be answered, whether it succeeded or not.
+    /// Calls sent as part of a batch aren't measured.
+    fn request_latency(&self, _method: &str, _latency: Duration) {}
+
+    /// How long a transaction watched with a [`crate::TransactionProgress`] took to be
+    /// finalized, counting from when it was submitted.
+    fn time_to_finality(&self, _elapsed: Duration) {}
+}
+
+impl<M: ClientMetrics + ?Sized> ClientMetrics for Arc<M> {
+    fn rpc_call(&self, method: &str) {
+        (**self).rpc_call(method)
+    }
+
+    fn rpc_error(&self, method: &str) {
+        (**self).rpc_error(method)
+    }
+
+    fn reconnect(&self) {
+        (**self).reconnect()
+    }
+
+    fn request_latency(&self, method: &str, latency: Duration) {
+        (**self).request_latency(method, latency)
+    }
+
+    fn time_to_finality(&self, elapsed: Duration) {
+        (**self).time_to_finality(elapsed)
+    }
+}
+
+/// Measures how long something takes, where that's possible.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Timer {
+    #[cfg(not(target_arch = "wasm32"))]
+    started: std::time::Instant,
+}
+
+impl Timer {
+    /// Start measuring from now.
+    pub(crate) fn start() -> Self {
+        Self {
+            #[cfg(not(target_arch = "wasm32"))]
+            started: std::time::Instant::now(),
+        }
+    }
+
+    /// How long it has been since the timer was started, or `None` if that can't
+    /// be measured.
+    pub(crate) fn elapsed(&self) -> Option<Duration> {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            Some(self.started.elapsed())
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        rpc::{
+            MockRpcClient,
+            Rpc,
+        },
+        DefaultConfig,
+    };
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Recorded {
+        calls: Mutex<Vec<String>>,
+        errors: Mutex<Vec<String>>,
+        latencies: Mutex<Vec<String>>,
+    }
+
+    impl ClientMetrics for Recorded {
+        fn rpc_call(&self, method: &str) {
+            self.calls.lock().unwrap().push(method.into());
+        }
+        fn rpc_error(&self, method: &str) {
+            self.errors.lock().unwrap().push(method.into());
+        }
+        fn request_latency(&self, method: &str, _latency: Duration) {
+            self.latencies.lock().unwrap().push(method.into());
+        }
+    }
+
+    #[async_std::test]
+    async fn rpc_calls_and_errors_are_counted() {
+        let mock = MockRpcClient::new();
+        mock.set_response("system_chain", "Avail");
+        let recorded = Arc::new(Recorded::default());
+        let rpc = Rpc::<DefaultConfig>::new_mock(mock).with_metrics(recorded.clone());
+
+        rpc.system_chain().await.unwrap();
+        assert!(rpc.system_name().await.is_err());
+
+        assert_eq!(
+            *recorded.calls.lock().unwrap(),
+            vec!["system_chain", "system_name"]
+        );
+        assert_eq!(*recorded.errors.lock().unwrap(), vec!["system_name"]);
+        assert_eq!(
+            *recorded.latencies.lock().unwrap(),
+            vec!["system_chain", "system_name"]
+        );
+    }
+}
@@ -0,0 +1,448 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Working with accounts controlled by several signatories through the `Multisig`
+//! pallet: deriving the account of a set of signatories, building the `as_multi`,
+//! `approve_as_multi` and `cancel_as_multi` calls with the [`Timepoint`] of the
+//! pending operation looked up from storage, and following approvals by their events.
+//!
+//! The calls are built from [`Encoded`] call data, for instance from
+//! [`crate::SubmittableExtrinsic::encoded()`], so any call can be dispatched from a
+//! multisig account.
+
+use crate::{
+    error::BasicError,
+    storage::{
+        StorageEntry,
+        StorageEntryKey,
+        StorageMapKey,
+    },
+    Call,
+    Client,
+    Config,
+    Encoded,
+    Event,
+    StorageHasher,
+};
+use codec::{
+    Decode,
+    Encode,
+};
+use derivative::Derivative;
+use sp_runtime::traits::TrailingZeroInput;
+use std::marker::PhantomData;
+
+/// The hash identifying a call in the `Multisig` pallet.
+pub type CallHash = [u8; 32];
+
+/// The hash of a call, as used by `approve_as_multi` and stored with pending
+/// operations.
+pub fn call_hash(call: &Encoded) -> CallHash {
+    sp_core::blake2_256(&call.0)
+}
+
+/// The block and extrinsic at which a multisig operation was first approved, which
+/// the later approvals of it have to give.
+#[derive(Clone, Copy, Debug, Encode, Decode, PartialEq, Eq)]
+pub struct Timepoint<BlockNumber> {
+    /// The height of the block.
+    pub height: BlockNumber,
+    /// The index of the extrinsic in the block.
+    pub index: u32,
+}
+
+/// An operation which has been approved by some, but not yet enough, signatories.
+#[derive(Clone, Debug, Decode, PartialEq, Eq)]
+pub struct PendingMultisig<BlockNumber, Balance, AccountId> {
+    /// When the operation was first approved.
+    pub when: Timepoint<BlockNumber>,
+    /// The deposit reserved from the depositor.
+    pub deposit: Balance,
+    /// The signatory which first approved the operation, and paid the deposit.
+    pub depositor: AccountId,
+    /// The signatories which have approved the operation so far.
+    pub approvals: Vec<AccountId>,
+}
+
+/// The `Multisig::Multisigs` storage entry: the pending operations of each multisig
+/// account, by call hash. `B` is the `Balance` type of the runtime.
+pub struct Multisigs<'a, T: Config, B> {
+    /// The multisig account.
+    pub account: &'a T::AccountId,
+    /// The hash of the call.
+    pub call_hash: CallHash,
+    marker: PhantomData<B>,
+}
+
+impl<'a, T: Config, B> Multisigs<'a, T, B> {
+    /// The entry of the given operation of the given multisig account.
+    pub fn new(account: &'a T::AccountId, call_hash: CallHash) -> Self {
+        Self {
+            account,
+            call_hash,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T: Config, B: Decode> StorageEntry for Multisigs<'_, T, B> {
+    const PALLET: &'static str = "Multisig";
+    const STORAGE: &'static str = "Multisigs";
+    type Value = PendingMultisig<T::BlockNumber, B, T::AccountId>;
+
+    fn key(&self) -> StorageEntryKey {
+        StorageEntryKey::Map(vec![
+            StorageMapKey::new(self.account, StorageHasher::Twox64Concat),
+            StorageMapKey::new(&self.call_hash, StorageHasher::Blake2_128Concat),
+        ])
+    }
+}
+
+/// The `Multisig::as_multi` call: approve an operation, dispatching it if this is the
+/// last approval it needs.
+#[derive(Clone, Debug, Encode)]
+pub struct AsMulti<AccountId, BlockNumber> {
+    /// The number of approvals needed.
+    pub threshold: u16,
+    /// The signatories other than the sender, sorted.
+    pub other_signatories: Vec<AccountId>,
+    /// The timepoint of the operation; `None` for the first approval.
+    pub maybe_timepoint: Option<Timepoint<BlockNumber>>,
+    /// The call to dispatch, which is encoded as opaque bytes.
+    pub call: Vec<u8>,
+    /// Whether to store the call, if it isn't dispatched yet.
+    pub store_call: bool,
+    /// The most weight the call may use, if it's dispatched.
+    pub max_weight: u64,
+}
+
+impl<AccountId: Encode, BlockNumber: Encode> Call for AsMulti<AccountId, BlockNumber> {
+    const PALLET: &'static str = "Multisig";
+    const FUNCTION: &'static str = "as_multi";
+}
+
+/// The `Multisig::approve_as_multi` call: approve an operation by the hash of its
+/// call, without dispatching it.
+#[derive(Clone, Debug, Encode)]
+pub struct ApproveAsMulti<AccountId, BlockNumber> {
+    /// The number of approvals needed.
+    pub threshold: u16,
+    /// The signatories other than the sender, sorted.
+    pub other_signatories: Vec<AccountId>,
+    /// The timepoint of the operation; `None` for the first approval.
+    pub maybe_timepoint: Option<Timepoint<BlockNumber>>,
+    /// The hash of the call.
+    pub call_hash: CallHash,
+    /// The most weight the call may use, if it's dispatched.
+    pub max_weight: u64,
+}
+
+impl<AccountId: Encode, BlockNumber: Encode> Call
+    for ApproveAsMulti<AccountId, BlockNumber>
+{
+    const PALLET: &'static str = "Multisig";
+    const FUNCTION: &'static str = "approve_as_multi";
+}
+
+/// The `Multisig::cancel_as_multi` call: cancel an operation, which only the signatory
+/// who first approved it may do.
+#[derive(Clone, Debug, Encode)]
+pub struct CancelAsMulti<AccountId, BlockNumber> {
+    /// The number of approvals needed.
+    pub threshold: u16,
+    /// The signatories other than the sender, sorted.
+    pub other_signatories: Vec<AccountId>,
+    /// The timepoint of the operation.
+    pub timepoint: Timepoint<BlockNumber>,
+    /// The hash of the call.
+    pub call_hash: CallHash,
+}
+
+impl<AccountId: Encode, BlockNumber: Encode> Call
+    for CancelAsMulti<AccountId, BlockNumber>
+{
+    const PALLET: &'static str = "Multisig";
+    const FUNCTION: &'static str = "cancel_as_multi";
+}
+
+/// A new multisig operation has begun.
+#[derive(Clone, Debug, Decode, PartialEq, Eq)]
+pub struct NewMultisig<AccountId> {
+    /// The signatory which approved it first.
+    pub approving: AccountId,
+    /// The multisig account.
+    pub multisig: AccountId,
+    /// The hash of the call.
+    pub call_hash: CallHash,
+}
+
+impl<AccountId: Decode> Event for NewMultisig<AccountId> {
+    const PALLET: &'static str = "Multisig";
+    const EVENT: &'static str = "NewMultisig";
+}
+
+/// A multisig operation has been approved by a signatory.
+#[derive(Clone, Debug, Decode, PartialEq, Eq)]
+pub struct MultisigApproval<AccountId, BlockNumber> {
+    /// The signatory which approved it.
+    pub approving: AccountId,
+    /// The timepoint of the operation.
+    pub timepoint: Timepoint<BlockNumber>,
+    /// The multisig account.
+    pub multisig: AccountId,
+    /// The hash of the call.
+    pub call_hash: CallHash,
+}
+
+impl<AccountId: Decode, BlockNumber: Decode> Event
+    for MultisigApproval<AccountId, BlockNumber>
+{
+    const PALLET: &'static str = "Multisig";
+    const EVENT: &'static str = "MultisigApproval";
+}
+
+/// A multisig operation has been approved by enough signatories and dispatched. `E` is
+/// the `DispatchError` type of the runtime.
+#[derive(Clone, Debug, Decode, PartialEq, Eq)]
+pub struct MultisigExecuted<AccountId, BlockNumber, E> {
+    /// The signatory which gave the last approval.
+    pub approving: AccountId,
+    /// The timepoint of the operation.
+    pub timepoint: Timepoint<BlockNumber>,
+    /// The multisig account.
+    pub multisig: AccountId,
+    /// The hash of the call.
+    pub call_hash: CallHash,
+    /// The result of dispatching the call.
+    pub result: Result<(), E>,
+}
+
+impl<AccountId: Decode, BlockNumber: Decode, E: Decode> Event
+    for MultisigExecuted<AccountId, BlockNumber, E>
+{
+    const PALLET: &'static str = "Multisig";
+    const EVENT: &'static str = "MultisigExecuted";
+}
+
+/// A multisig operation has been cancelled.
+#[derive(Clone, Debug, Decode, PartialEq, Eq)]
+pub struct MultisigCancelled<AccountId, BlockNumber> {
+    /// The signatory which cancelled it.
+    pub cancelling: AccountId,
+    /// The timepoint of the operation.
+    pub timepoint: Timepoint<BlockNumber>,
+    /// The multisig account.
+    pub multisig: AccountId,
+    /// The hash of the call.
+    pub call_hash: CallHash,
+}
+
+impl<AccountId: Decode, BlockNumber: Decode> Event
+    for MultisigCancelled<AccountId, BlockNumber>
+{
+    const PALLET: &'static str = "Multisig";
+    const EVENT: &'static str = "MultisigCancelled";
+}
+
+/// A set of signatories, and the number of them which have to approve operations
+/// of their multisig account.
+#[derive(Derivative)]
+#[derivative(
+    Clone(bound = ""),
+    Debug(bound = ""),
+    PartialEq(bound = ""),
+    Eq(bound = "")
+)]
+pub struct MultisigAccount<T: Config> {
+    signatories: Vec<T::AccountId>,
+    threshold: u16,
+}
+
+impl<T: Config> MultisigAccount<T>
+where
+    T::AccountId: Ord,
+{
+    /// The multisig account of the given signatories, in any order.
+    pub fn new(mut signatories: Vec<T::AccountId>, threshold: u16) -> Self {
+        signatories.sort();
+        signatories.dedup();
+        Self {
+            signatories,
+            threshold,
+        }
+    }
+
+    /// The signatories, sorted.
+    pub fn signatories(&self) -> &[T::AccountId] {
+        &self.signatories
+    }
+
+    /// The number of approvals operations need.
+    pub fn threshold(&self) -> u16 {
+        self.threshold
+    }
+
+    /// The account controlled by the signatories, as derived by the pallet.
+    pub fn account_id(&self) -> T::AccountId {
+        let entropy = (b"modlpy/utilisuba", &self.signatories, self.threshold)
+            .using_encoded(sp_core::blake2_256);
+        T::AccountId::decode(&mut TrailingZeroInput::new(&entropy[..]))
+            .expect("infinite length input; no invalid inputs for type; qed")
+    }
+
+    /// The signatories other than the given one, as the calls take them.
+    pub fn other_signatories(&self, signatory: &T::AccountId) -> Vec<T::AccountId> {
+        self.signatories
+            .iter()
+            .filter(|s| *s != signatory)
+            .cloned()
+            .collect()
+    }
+
+    /// Look up the pending operation with the given call hash, if any. `B` is the
+    /// `Balance` type of the runtime.
+    pub async fn pending<B: Decode>(
+        &self,
+        client: &Client<T>,
+        call_hash: CallHash,
+        hash: Option<T::Hash>,
+    ) -> Result<Option<PendingMultisig<T::BlockNumber, B, T::AccountId>>, BasicError>
+    {
+        let account = self.account_id();
+        client
+            .storage()
+            .fetch(&Multisigs::<T, B>::new(&account, call_hash), hash)
+            .await
+    }
+
+    /// The timepoint of the pending operation with the given call hash, or `None` if
+    /// it hasn't been approved by anyone yet.
+    pub async fn timepoint(
+        &self,
+        client: &Client<T>,
+        call_hash: CallHash,
+        hash: Option<T::Hash>,
+    ) -> Result<Option<Timepoint<T::BlockNumber>>, BasicError> {
+        let account = self.account_id();
+        let entry = PendingTimepoint::<T>(Multisigs::new(&account, call_hash));
+        client.storage().fetch(&entry, hash).await
+    }
+
+    /// Build the `as_multi` call with which `signatory` approves the given call,
+    /// dispatching it if theirs is the last approval needed.
+    pub async fn as_multi(
+        &self,
+        client: &Client<T>,
+        signatory: &T::AccountId,
+        call: Encoded,
+        max_weight: u64,
+    ) -> Result<AsMulti<T::AccountId, T::BlockNumber>, BasicError> {
+        let maybe_timepoint = self.timepoint(client, call_hash(&call), None).await?;
+        Ok(AsMulti {
+            threshold: self.threshold,
+            other_signatories: self.other_signatories(signatory),
+            maybe_timepoint,
+            call: call.0,
+            store_call: false,
+            max_weight,
+        })
+    }
+
+    /// Build the `approve_as_multi` call with which `signatory` approves the call with
+    /// the given hash.
+    pub async fn approve_as_multi(
+        &self,
+        client: &Client<T>,
+        signatory: &T::AccountId,
+        call_hash: CallHash,
+        max_weight: u64,
+    ) -> Result<ApproveAsMulti<T::AccountId, T::BlockNumber>, BasicError> {
+        let maybe_timepoint = self.timepoint(client, call_hash, None).await?;
+        Ok(ApproveAsMulti {
+            threshold: self.threshold,
+            other_signatories: self.other_signatories(signatory),
+            maybe_timepoint,
+            call_hash,
+            max_weight,
+        })
+    }
+
+    /// Build the `cancel_as_multi` call with which `signatory` cancels the pending
+    /// operation with the given call hash.
+    pub async fn cancel_as_multi(
+        &self,
+        client: &Client<T>,
+        signatory: &T::AccountId,
+        call_hash: CallHash,
+    ) -> Result<CancelAsMulti<T::AccountId, T::BlockNumber>, BasicError> {
+        let timepoint =
+            self.timepoint(client, call_hash, None)
+                .await?
+                .ok_or_else(|| {
+                    BasicError::Other(format!(
+                        "No pending multisig operation with call hash 0x{}",
+                        hex::encode(call_hash)
+                    ))
+                })?;
+        Ok(CancelAsMulti {
+            threshold: self.threshold,
+            other_signatories: self.other_signatories(signatory),
+            timepoint,
+            call_hash,
+        })
+    }
+}
+
+/// Only the timepoint of a [`PendingMultisig`], which comes before the fields whose
+/// types depend on the runtime.
+struct PendingTimepoint<'a, T: Config>(Multisigs<'a, T, ()>);
+
+impl<T: Config> StorageEntry for PendingTimepoint<'_, T> {
+    const PALLET: &'static str = "Multisig";
+    const STORAGE: &'static str = "Multisigs";
+    type Value = Timepoint<T::BlockNumber>;
+
+    fn key(&self) -> StorageEntryKey {
+        self.0.key()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DefaultConfig;
+    use sp_runtime::AccountId32;
+
+    #[test]
+    fn multisig_accounts_are_derived_from_sorted_signatories() {
+        let alice = AccountId32::new([1; 32]);
+        let bob = AccountId32::new([2; 32]);
+        let multisig =
+            MultisigAccount::<DefaultConfig>::new(vec![bob.clone(), alice.clone()], 2);
+        assert_eq!(multisig.signatories(), &[alice.clone(), bob.clone()]);
+        assert_eq!(multisig.other_signatories(&alice), vec![bob.clone()]);
+
+        // As derived by `pallet_multisig::Pallet::multi_account_id`.
+        let entropy =
+            (b"modlpy/utilisuba", vec![alice.clone(), bob.clone()], 2u16).encode();
+        let expected = AccountId32::new(sp_core::blake2_256(&entropy));
+        assert_eq!(multisig.account_id(), expected);
+        assert_ne!(
+            MultisigAccount::<DefaultConfig>::new(vec![alice, bob], 3).account_id(),
+            expected
+        );
+    }
+}
@@ -0,0 +1,223 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Handing out nonces locally, so that many transactions can be submitted at once.
+
+use crate::{
+    error::BasicError,
+    rpc::Rpc,
+    Config,
+};
+use codec::Encode;
+use std::{
+    collections::{
+        BTreeSet,
+        HashMap,
+    },
+    ops::Range,
+};
+
+/// Keeps track of the next nonce of each account that transactions are signed for, so
+/// that concurrent submissions from one account each get a different nonce, rather
+/// than all being given the nonce the node reports.
+///
+/// The nonce of an account is fetched from the node the first time it's needed, and
+/// again after [`NonceManager::resync()`], which is done automatically when a
+/// submission fails. Enable it with [`crate::ClientBuilder::set_nonce_manager()`].
+pub struct NonceManager<T: Config> {
+    // Accounts are keyed by their encoding, since account IDs needn't be hashable.
+    accounts: futures::lock::Mutex<HashMap<Vec<u8>, AccountNonces<T::Index>>>,
+}
+
+struct AccountNonces<Index> {
+    // The nonce to hand out next.
+    next: Index,
+    // Nonces handed out whose transactions are known to have been dropped, or to have
+    // timed out, and which may be handed out again.
+    dropped: BTreeSet<Index>,
+}
+
+impl<Index> AccountNonces<Index> {
+    fn new(next: Index) -> Self {
+        Self {
+            next,
+            dropped: BTreeSet::new(),
+        }
+    }
+}
+
+impl<T: Config> Default for NonceManager<T> {
+    fn default() -> Self {
+        Self {
+            accounts: Default::default(),
+        }
+    }
+}
+
+impl<T: Config> std::fmt::Debug for NonceManager<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NonceManager").finish()
+    }
+}
+
+impl<T: Config> NonceManager<T> {
+    /// Create a manager which doesn't know about any accounts yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Hand out the next nonce for the account, fetching it from the node if it isn't
+    /// known yet. Each call returns a different nonce.
+    pub async fn next(
+        &self,
+        rpc: &Rpc<T>,
+        account: &T::AccountId,
+    ) -> Result<T::Index, BasicError> {
+        let mut accounts = self.accounts.lock().await;
+        let key = account.encode();
+        if !accounts.contains_key(&key) {
+            let next = rpc.system_account_next_index(account).await?;
+            accounts.insert(key.clone(), AccountNonces::new(next));
+        }
+        let nonces = accounts.get_mut(&key).expect("inserted above; qed");
+        let nonce = nonces.next;
+        nonces.next = nonce + 1u32.into();
+        Ok(nonce)
+    }
+
+    /// The nonce that will be handed out next for the account, without handing it out.
+    pub async fn peek(
+        &self,
+        rpc: &Rpc<T>,
+        account: &T::AccountId,
+    ) -> Result<T::Index, BasicError> {
+        let accounts = self.accounts.lock().await;
+        match accounts.get(&account.encode()) {
+            Some(nonces) => Ok(nonces.next),
+            None => rpc.system_account_next_index(account).await,
+        }
+    }
+
+    /// Forget the nonce of the account, so that it's fetched from the node again
+    /// the next time it's needed.
+    pub async fn resync(&self, account: &T::AccountId) {
+        self.accounts.lock().await.remove(&account.encode());
+    }
+
+    /// Take note that the transaction signed for the account with the given nonce was
+    /// dropped (or gave up waiting for it, for instance with
+    /// [`crate::TransactionError::TimedOut`]), so that [`NonceManager::detect_gap()`]
+    /// can tell it apart from a transaction which is still on its way to the node.
+    pub async fn report_dropped(&self, account: &T::AccountId, nonce: T::Index) {
+        let mut accounts = self.accounts.lock().await;
+        if let Some(nonces) = accounts.get_mut(&account.encode()) {
+            if nonce < nonces.next {
+                nonces.dropped.insert(nonce);
+            }
+        }
+    }
+
+    /// Check whether any of the nonces handed out for the account were never used,
+    /// because their transactions were dropped. If so, the missing nonces are
+    /// returned, and they will be handed out again.
+    ///
+    /// The nonces from the next one according to the node's pool up to the next one to
+    /// hand out are missing from the pool; they're only treated as a gap once the first
+    /// of them has been reported with [`NonceManager::report_dropped()`], since until
+    /// then its transaction may simply not have reached the node yet.
+    pub async fn detect_gap(
+        &self,
+        rpc: &Rpc<T>,
+        account: &T::AccountId,
+    ) -> Result<Option<Range<T::Index>>, BasicError> {
+        let mut accounts = self.accounts.lock().await;
+        let nonces = match accounts.get_mut(&account.encode()) {
+            Some(nonces) => nonces,
+            None => return Ok(None),
+        };
+        let in_pool = rpc.system_account_next_index(account).await?;
+        // Drops reported for nonces which have since been used are stale.
+        nonces.dropped = nonces.dropped.split_off(&in_pool);
+        if in_pool >= nonces.next || !nonces.dropped.contains(&in_pool) {
+            return Ok(None)
+        }
+        let missing = in_pool..nonces.next;
+        nonces.next = in_pool;
+        nonces.dropped.clear();
+        Ok(Some(missing))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        rpc::MockRpcClient,
+        DefaultConfig,
+    };
+    use sp_runtime::AccountId32;
+
+    fn account() -> AccountId32 {
+        AccountId32::new([1; 32])
+    }
+
+    #[async_std::test]
+    async fn transactions_in_flight_are_not_a_gap() {
+        let mock = MockRpcClient::new();
+        let rpc = Rpc::<DefaultConfig>::new_mock(mock.clone());
+        let manager = NonceManager::<DefaultConfig>::new();
+        mock.push_response("system_accountNextIndex", 5u32);
+        for expected in 5..8 {
+            assert_eq!(manager.next(&rpc, &account()).await.unwrap(), expected);
+        }
+
+        // Only the first transaction has reached the pool so far.
+        mock.push_response("system_accountNextIndex", 6u32);
+        assert_eq!(manager.detect_gap(&rpc, &account()).await.unwrap(), None);
+        assert_eq!(manager.peek(&rpc, &account()).await.unwrap(), 8);
+
+        // A later transaction being dropped leaves the earlier ones in flight.
+        manager.report_dropped(&account(), 7).await;
+        mock.push_response("system_accountNextIndex", 6u32);
+        assert_eq!(manager.detect_gap(&rpc, &account()).await.unwrap(), None);
+        assert_eq!(manager.peek(&rpc, &account()).await.unwrap(), 8);
+    }
+
+    #[async_std::test]
+    async fn dropped_transactions_leave_a_gap() {
+        let mock = MockRpcClient::new();
+        let rpc = Rpc::<DefaultConfig>::new_mock(mock.clone());
+        let manager = NonceManager::<DefaultConfig>::new();
+        mock.push_response("system_accountNextIndex", 5u32);
+        for expected in 5..8 {
+            assert_eq!(manager.next(&rpc, &account()).await.unwrap(), expected);
+        }
+
+        manager.report_dropped(&account(), 6).await;
+        mock.push_response("system_accountNextIndex", 6u32);
+        assert_eq!(
+            manager.detect_gap(&rpc, &account()).await.unwrap(),
+            Some(6..8)
+        );
+        // The missing nonces are handed out again, without asking the node.
+        assert_eq!(manager.next(&rpc, &account()).await.unwrap(), 6);
+        assert_eq!(manager.next(&rpc, &account()).await.unwrap(), 7);
+
+        // Once every transaction is in the pool, there's no gap.
+        mock.push_response("system_accountNextIndex", 8u32);
+        assert_eq!(manager.detect_gap(&rpc, &account()).await.unwrap(), None);
+    }
+}
@@ -0,0 +1,173 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Signing extrinsics without a connection to a node.
+
+use crate::{
+    error::BasicError,
+    extrinsic::{
+        self,
+        SignedExtra,
+        Signer,
+        UncheckedExtrinsic,
+    },
+    rpc::RuntimeVersion,
+    Call,
+    Client,
+    Config,
+    Metadata,
+};
+use sp_runtime::{
+    generic::Era,
+    traits::SignedExtension,
+};
+use std::sync::Arc;
+
+/// Builds signed extrinsics from chain data supplied up front, rather than fetched
+/// from a node, so that transactions can be signed on a machine which isn't online.
+///
+/// The resulting [`UncheckedExtrinsic`] can be SCALE encoded, carried over to a
+/// machine which is online, and submitted from there with
+/// [`Client::submit_signed()`] or [`Client::submit_signed_then_watch()`].
+#[derive(Debug)]
+pub struct OfflineClient<T: Config> {
+    metadata: Arc<Metadata>,
+    genesis_hash: T::Hash,
+    runtime_version: RuntimeVersion,
+}
+
+impl<T: Config> Clone for OfflineClient<T> {
+    fn clone(&self) -> Self {
+        Self {
+            metadata: self.metadata.clone(),
+            genesis_hash: self.genesis_hash,
+            runtime_version: self.runtime_version.clone(),
+        }
+    }
+}
+
+impl<T: Config> OfflineClient<T> {
+    /// Create a client for the chain with the given genesis hash, whose runtime has the
+    /// given metadata and version.
+    pub fn new(
+        metadata: Metadata,
+        genesis_hash: T::Hash,
+        runtime_version: RuntimeVersion,
+    ) -> Self {
+        Self {
+            metadata: Arc::new(metadata),
+            genesis_hash,
+            runtime_version,
+        }
+    }
+
+    /// Create a client using the chain data that an online client has already fetched.
+    pub fn from_client(client: &Client<T>) -> Self {
+        let (metadata, runtime_version) = client.current_runtime();
+        Self {
+            metadata,
+            genesis_hash: *client.genesis(),
+            runtime_version,
+        }
+    }
+
+    /// Returns the genesis hash.
+    pub fn genesis(&self) -> &T::Hash {
+        &self.genesis_hash
+    }
+
+    /// Returns the chain metadata.
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Returns the runtime version.
+    pub fn runtime_version(&self) -> &RuntimeVersion {
+        &self.runtime_version
+    }
+
+    /// Create an immortal signed extrinsic for the given call. The account nonce can't be
+    /// looked up offline, so it must be given.
+    ///
+    /// Mortal transactions need a recent block hash to be signed; if the parameters ask
+    /// for one, use [`OfflineClient::create_signed_mortal()`] instead.
+    pub async fn create_signed<C, X>(
+        &self,
+        call: &C,
+        nonce: T::Index,
+        signer: &(dyn Signer<T, X> + Send + Sync),
+        additional_params: X::Parameters,
+    ) -> Result<UncheckedExtrinsic<T, X>, BasicError>
+    where
+        C: Call,
+        X: SignedExtra<T>,
+        <X::Extra as SignedExtension>::AdditionalSigned: Send + Sync,
+    {
+        if X::mortal_period(&additional_params).is_some() {
+            return Err(BasicError::Other(
+                "Mortal transactions can't be signed offline without a checkpoint block; \
+                 use OfflineClient::create_signed_mortal"
+                    .into(),
+            ))
+        }
+        let call = self.encode_call(call)?;
+        extrinsic::create_signed_with_metadata(
+            &self.metadata,
+            &self.runtime_version,
+            self.genesis_hash,
+            nonce,
+            call,
+            signer,
+            additional_params,
+        )
+        .await
+    }
+
+    /// Create a signed extrinsic for the given call which is only valid during `era`,
+    /// which starts at the block with the hash `checkpoint`.
+    pub async fn create_signed_mortal<C, X>(
+        &self,
+        call: &C,
+        nonce: T::Index,
+        era: Era,
+        checkpoint: T::Hash,
+        signer: &(dyn Signer<T, X> + Send + Sync),
+        additional_params: X::Parameters,
+    ) -> Result<UncheckedExtrinsic<T, X>, BasicError>
+    where
+        C: Call,
+        X: SignedExtra<T>,
+        <X::Extra as SignedExtension>::AdditionalSigned: Send + Sync,
+    {
+        let call = self.encode_call(call)?;
+        extrinsic::create_signed_mortal(
+            &self.metadata,
+            &self.runtime_version,
+            self.genesis_hash,
+            era,
+            checkpoint,
+            nonce,
+            call,
+            signer,
+            additional_params,
+        )
+        .await
+    }
+
+    fn encode_call<C: Call>(&self, call: &C) -> Result<crate::Encoded, BasicError> {
+        call.encode_with_metadata(&self.metadata)
+    }
+}
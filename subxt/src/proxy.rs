@@ -0,0 +1,195 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Dispatching calls on behalf of another account through the `Proxy` pallet, and
+//! looking up which proxies accounts have.
+//!
+//! The calls are given as [`Encoded`] call data, for instance from
+//! [`crate::SubmittableExtrinsic::encoded()`], so that any call can be proxied. With
+//! the generated API, `api.tx().proxied(real, force_proxy_type, call)` builds the
+//! `Proxy::proxy` call using the runtime's own `ProxyType`.
+
+use crate::{
+    error::BasicError,
+    storage::{
+        StorageEntry,
+        StorageEntryKey,
+        StorageMapKey,
+    },
+    Call,
+    Client,
+    Config,
+    Encoded,
+    Event,
+    StorageHasher,
+};
+use codec::{
+    Decode,
+    Encode,
+};
+use std::marker::PhantomData;
+
+/// The `Proxy::proxy` call: dispatch `call` as `real`, which the sender must be a
+/// proxy of. `P` is the `ProxyType` of the runtime; if `force_proxy_type` is given,
+/// only a proxy of that type is used.
+#[derive(Clone, Debug, Encode)]
+pub struct ProxyCall<AccountId, P> {
+    /// The account to dispatch the call as.
+    pub real: AccountId,
+    /// The type of proxy relationship to use.
+    pub force_proxy_type: Option<P>,
+    /// The call to dispatch.
+    pub call: Encoded,
+}
+
+impl<AccountId: Encode, P: Encode> Call for ProxyCall<AccountId, P> {
+    const PALLET: &'static str = "Proxy";
+    const FUNCTION: &'static str = "proxy";
+}
+
+/// A proxy of an account: the delegate may dispatch calls of the kind allowed by the
+/// proxy type on the account's behalf.
+#[derive(Clone, Debug, Decode, PartialEq, Eq)]
+pub struct ProxyDefinition<AccountId, P, BlockNumber> {
+    /// The account which may act on behalf of the other.
+    pub delegate: AccountId,
+    /// The calls it may dispatch.
+    pub proxy_type: P,
+    /// The number of blocks its calls have to be announced in advance for.
+    pub delay: BlockNumber,
+}
+
+/// The `Proxy::Proxies` storage entry: the proxies of an account. Only the proxies
+/// are decoded, and not the deposit which follows them.
+pub struct Proxies<T: Config, P> {
+    /// The account whose proxies these are.
+    pub real: T::AccountId,
+    marker: PhantomData<P>,
+}
+
+impl<T: Config, P> Proxies<T, P> {
+    /// The entry of the proxies of the given account.
+    pub fn new(real: T::AccountId) -> Self {
+        Self {
+            real,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T: Config, P: Decode> StorageEntry for Proxies<T, P> {
+    const PALLET: &'static str = "Proxy";
+    const STORAGE: &'static str = "Proxies";
+    type Value = Vec<ProxyDefinition<T::AccountId, P, T::BlockNumber>>;
+
+    fn key(&self) -> StorageEntryKey {
+        StorageEntryKey::Map(vec![StorageMapKey::new(
+            &self.real,
+            StorageHasher::Twox64Concat,
+        )])
+    }
+}
+
+/// A proxied call was dispatched. `E` is the `DispatchError` type of the runtime.
+#[derive(Clone, Debug, Decode, PartialEq, Eq)]
+pub struct ProxyExecuted<E> {
+    /// The result of dispatching the call.
+    pub result: Result<(), E>,
+}
+
+impl<E: Decode> Event for ProxyExecuted<E> {
+    const PALLET: &'static str = "Proxy";
+    const EVENT: &'static str = "ProxyExecuted";
+}
+
+/// A proxy was added to an account.
+#[derive(Clone, Debug, Decode, PartialEq, Eq)]
+pub struct ProxyAdded<AccountId, P, BlockNumber> {
+    /// The account which may now be proxied.
+    pub delegator: AccountId,
+    /// The proxy.
+    pub delegatee: AccountId,
+    /// The calls it may dispatch.
+    pub proxy_type: P,
+    /// The number of blocks its calls have to be announced in advance for.
+    pub delay: BlockNumber,
+}
+
+impl<AccountId: Decode, P: Decode, BlockNumber: Decode> Event
+    for ProxyAdded<AccountId, P, BlockNumber>
+{
+    const PALLET: &'static str = "Proxy";
+    const EVENT: &'static str = "ProxyAdded";
+}
+
+/// The proxies of `real`. `P` is the `ProxyType` of the runtime.
+pub async fn proxies<T: Config, P: Decode>(
+    client: &Client<T>,
+    real: T::AccountId,
+    hash: Option<T::Hash>,
+) -> Result<Vec<ProxyDefinition<T::AccountId, P, T::BlockNumber>>, BasicError> {
+    client
+        .storage()
+        .fetch_or_default(&Proxies::<T, P>::new(real), hash)
+        .await
+}
+
+/// The accounts which `delegate` is a proxy of, with the proxy definitions that
+/// allow it. This iterates over the proxies of every account, so can be slow on
+/// large chains.
+pub async fn proxied_accounts<T: Config, P: Decode>(
+    client: &Client<T>,
+    delegate: &T::AccountId,
+    hash: Option<T::Hash>,
+) -> Result<
+    Vec<(
+        T::AccountId,
+        ProxyDefinition<T::AccountId, P, T::BlockNumber>,
+    )>,
+    BasicError,
+> {
+    let mut iter = client
+        .storage()
+        .iter::<Proxies<T, P>>(hash)
+        .await?
+        .with_keys::<T::AccountId>();
+    let mut accounts = Vec::new();
+    while let Some((real, proxies)) = iter.next().await? {
+        accounts.extend(
+            proxies
+                .into_iter()
+                .filter(|proxy| &proxy.delegate == delegate)
+                .map(|proxy| (real.clone(), proxy)),
+        );
+    }
+    Ok(accounts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn proxy_calls_encode_the_call_inline() {
+        let call = ProxyCall {
+            real: [1u8; 4],
+            force_proxy_type: Some(3u8),
+            call: Encoded(vec![5, 0, 7]),
+        };
+        // The call isn't length prefixed, as with the `Box<RuntimeCall>` in the pallet.
+        assert_eq!(call.encode(), vec![1, 1, 1, 1, 1, 3, 5, 0, 7]);
+    }
+}
@@ -0,0 +1,151 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Limiting how fast, and how many at once, requests are sent to the node.
+
+use futures::{
+    channel::mpsc,
+    lock::Mutex,
+    StreamExt,
+};
+use futures_timer::Delay;
+use std::{
+    sync::Arc,
+    time::Duration,
+};
+
+/// Holds back requests to the node according to a rate limit and a limit on how many
+/// may be in flight at once. See [`crate::ClientBuilder::set_rate_limit`] and
+/// [`crate::ClientBuilder::set_max_concurrent_requests`].
+#[derive(Clone, Default)]
+pub(crate) struct RequestLimiter {
+    rate: Option<Arc<RateLimit>>,
+    concurrency: Option<Arc<ConcurrencyLimit>>,
+}
+
+impl RequestLimiter {
+    /// Send at most `requests_per_second` requests a second, evenly spaced.
+    pub(crate) fn set_rate_limit(&mut self, requests_per_second: u32) {
+        let interval = Duration::from_secs(1) / requests_per_second.max(1);
+        self.rate = Some(Arc::new(RateLimit {
+            interval,
+            next_slot: Mutex::new(None),
+        }));
+    }
+
+    /// Have at most `max` requests in flight at once.
+    pub(crate) fn set_max_concurrent(&mut self, max: usize) {
+        let (release, permits) = mpsc::unbounded();
+        for _ in 0..max.max(1) {
+            release.unbounded_send(()).expect("receiver is alive; qed");
+        }
+        self.concurrency = Some(Arc::new(ConcurrencyLimit {
+            release,
+            permits: Mutex::new(permits),
+        }));
+    }
+
+    /// Wait until a request may be sent. The returned permit counts towards the
+    /// requests in flight until it's dropped.
+    pub(crate) async fn acquire(&self) -> RequestPermit {
+        // Wait for a free spot before taking a rate limit slot, so as not to waste
+        // the slot while waiting.
+        let permit = match &self.concurrency {
+            Some(concurrency) => {
+                concurrency
+                    .permits
+                    .lock()
+                    .await
+                    .next()
+                    .await
+                    .expect("a sender is kept alongside the receiver; qed");
+                RequestPermit(Some(concurrency.release.clone()))
+            }
+            None => RequestPermit(None),
+        };
+        if let Some(rate) = &self.rate {
+            let mut next_slot = rate.next_slot.lock().await;
+            if let Some(slot) = next_slot.as_mut() {
+                slot.await;
+            }
+            *next_slot = Some(Delay::new(rate.interval));
+        }
+        permit
+    }
+}
+
+struct RateLimit {
+    interval: Duration,
+    // Elapses once the next request may be sent.
+    next_slot: Mutex<Option<Delay>>,
+}
+
+struct ConcurrencyLimit {
+    release: mpsc::UnboundedSender<()>,
+    // Holds a message for every request which may be sent right now.
+    permits: Mutex<mpsc::UnboundedReceiver<()>>,
+}
+
+/// Allows a request to be in flight; see [`RequestLimiter::acquire`].
+pub(crate) struct RequestPermit(Option<mpsc::UnboundedSender<()>>);
+
+impl Drop for RequestPermit {
+    fn drop(&mut self) {
+        if let Some(release) = &self.0 {
+            let _ = release.unbounded_send(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::FutureExt;
+    use std::time::Instant;
+
+    #[async_std::test]
+    async fn requests_wait_for_a_free_spot() {
+        let mut limiter = RequestLimiter::default();
+        limiter.set_max_concurrent(2);
+
+        let first = limiter.acquire().await;
+        let _second = limiter.acquire().await;
+        assert!(limiter.acquire().now_or_never().is_none());
+        drop(first);
+        assert!(limiter.acquire().now_or_never().is_some());
+    }
+
+    #[async_std::test]
+    async fn requests_are_spaced_out_by_the_rate_limit() {
+        let mut limiter = RequestLimiter::default();
+        limiter.set_rate_limit(20);
+
+        let started = Instant::now();
+        for _ in 0..4 {
+            limiter.acquire().await;
+        }
+        // The first request goes straight away, and the others 50ms apart.
+        assert!(started.elapsed() >= Duration::from_millis(150));
+    }
+
+    #[async_std::test]
+    async fn no_limits_never_wait() {
+        let limiter = RequestLimiter::default();
+        for _ in 0..100 {
+            assert!(limiter.acquire().now_or_never().is_some());
+        }
+    }
+}
@@ -0,0 +1,432 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Re-establishing the connection to a node, and restoring subscriptions afterwards.
+
+use crate::{
+    error::BasicError,
+    rpc::{
+        JsonValue,
+        RpcError,
+        Transport,
+    },
+    rpc_middleware::RpcRequest,
+    rpc_recording::RpcRecorder,
+};
+use futures::{
+    future::BoxFuture,
+    stream::BoxStream,
+    FutureExt,
+    Stream,
+    StreamExt,
+};
+use serde::de::DeserializeOwned;
+use std::{
+    marker::PhantomData,
+    pin::Pin,
+    sync::{
+        atomic::{
+            AtomicUsize,
+            Ordering,
+        },
+        Arc,
+        Mutex,
+    },
+    task::{
+        Context,
+        Poll,
+        Waker,
+    },
+    time::Duration,
+};
+
+/// How to go about re-establishing a lost connection to the node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    /// Give up after this many failed attempts, or never give up if `None`.
+    pub max_attempts: Option<u32>,
+    /// How long to wait after the first failed attempt. The wait doubles after each
+    /// subsequent failure.
+    pub initial_delay: Duration,
+    /// The longest to wait between attempts.
+    pub max_delay: Duration,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: None,
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// How long to wait after the given number of failed attempts.
+    pub(crate) fn delay(&self, failures: u32) -> Duration {
+        let factor = 1u32
+            .checked_shl(failures.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        self.initial_delay
+            .checked_mul(factor)
+            .map_or(self.max_delay, |delay| delay.min(self.max_delay))
+    }
+}
+
+/// Everything needed to open a new connection to the node.
+pub(crate) struct Reconnect {
+    /// The endpoints to connect to. When a connection is lost, the ones after the
+    /// current one are tried in turn.
+    pub(crate) urls: Vec<String>,
+    /// The index into `urls` of the endpoint currently connected to.
+    pub(crate) current: AtomicUsize,
+    /// The genesis hash every endpoint must report, so that we never fail over to a
+    /// node of a different chain.
+    pub(crate) genesis: Mutex<Option<JsonValue>>,
+    pub(crate) policy: ReconnectPolicy,
+    /// Held while reconnecting, so that only one reconnection happens at a time.
+    pub(crate) lock: futures::lock::Mutex<()>,
+}
+
+impl Reconnect {
+    pub(crate) fn new(
+        urls: Vec<String>,
+        current: usize,
+        policy: ReconnectPolicy,
+    ) -> Self {
+        Self {
+            urls,
+            current: AtomicUsize::new(current),
+            genesis: Mutex::new(None),
+            policy,
+            lock: Default::default(),
+        }
+    }
+
+    /// The endpoint to use for the given attempt to reconnect, counting from 0, along
+    /// with its index. Each attempt moves on to the next endpoint, starting with the
+    /// one after the endpoint whose connection was lost.
+    pub(crate) fn endpoint(&self, attempt: u32) -> (usize, &str) {
+        let current = self.current.load(Ordering::SeqCst);
+        let index = (current + 1 + attempt as usize) % self.urls.len();
+        (index, &self.urls[index])
+    }
+
+    /// How long to wait before the given attempt to reconnect, counting from 0. There
+    /// is no wait until every endpoint has been tried once, and the wait then grows
+    /// with each round of attempts.
+    pub(crate) fn delay_before(&self, attempt: u32) -> Option<Duration> {
+        let endpoints = self.urls.len() as u32;
+        if attempt == 0 || attempt % endpoints != 0 {
+            return None
+        }
+        Some(self.policy.delay(attempt / endpoints))
+    }
+
+    /// The genesis hash that endpoints must report, if it's known.
+    pub(crate) fn genesis(&self) -> Option<JsonValue> {
+        self.genesis
+            .lock()
+            .expect("reconnect lock poisoned")
+            .clone()
+    }
+}
+
+/// The request behind a subscription, along with the underlying subscription, which
+/// is replaced each time the connection is re-established.
+pub(crate) struct SubscriptionSlot {
+    request: RpcRequest,
+    unsubscribe_method: String,
+    recorder: Option<Arc<RpcRecorder>>,
+    state: Mutex<SlotState>,
+}
+
+struct SlotState {
+    current: Option<Notifications>,
+    // The generation of the connection that `current` belongs to.
+    generation: u64,
+    // Why the subscription couldn't be restored, if it couldn't.
+    failed: Option<String>,
+    // Whether the client has been shut down, which ends the subscription for good.
+    terminated: bool,
+    waker: Option<Waker>,
+}
+
+impl SubscriptionSlot {
+    pub(crate) fn new(
+        request: RpcRequest,
+        unsubscribe_method: &str,
+        recorder: Option<Arc<RpcRecorder>>,
+    ) -> Self {
+        Self {
+            request,
+            unsubscribe_method: unsubscribe_method.to_owned(),
+            recorder,
+            state: Mutex::new(SlotState {
+                current: None,
+                generation: 0,
+                failed: None,
+                terminated: false,
+                waker: None,
+            }),
+        }
+    }
+
+    /// Make the subscription request on the given client.
+    pub(crate) async fn subscribe(
+        &self,
+        client: &Transport,
+    ) -> Result<Notifications, RpcError> {
+        let notifications = client
+            .subscribe(&self.request, &self.unsubscribe_method)
+            .await?;
+        Ok(match &self.recorder {
+            Some(recorder) => recorder.record_subscription(&self.request, notifications),
+            None => notifications,
+        })
+    }
+
+    /// The generation of the connection that the subscription currently belongs to.
+    pub(crate) fn generation(&self) -> u64 {
+        self.lock().generation
+    }
+
+    /// Replace the underlying subscription with one made on the connection of the
+    /// given generation, and let the consumer know.
+    pub(crate) fn replace(
+        &self,
+        subscription: Result<Notifications, RpcError>,
+        generation: u64,
+    ) {
+        let mut state = self.lock();
+        if state.terminated {
+            return
+        }
+        match subscription {
+            Ok(subscription) => state.current = Some(subscription),
+            Err(e) => state.failed = Some(e.to_string()),
+        }
+        state.generation = generation;
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+
+    /// End the subscription for good, which unsubscribes from the node if the
+    /// subscription is still open.
+    pub(crate) fn terminate(&self) {
+        let mut state = self.lock();
+        state.terminated = true;
+        state.current = None;
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, SlotState> {
+        self.state.lock().expect("subscription lock poisoned")
+    }
+}
+
+/// The notifications of a subscription, as they arrive from the client.
+pub(crate) type Notifications = BoxStream<'static, Result<JsonValue, RpcError>>;
+
+/// Restores a subscription once its connection has been lost. The flag says whether
+/// the connection is known to be lost; if not, it's checked first. Resolves to `false`
+/// if the subscription was instead ended by the node.
+pub(crate) type Restore = Arc<
+    dyn Fn(
+            Arc<SubscriptionSlot>,
+            u64,
+            bool,
+        ) -> BoxFuture<'static, Result<bool, BasicError>>
+        + Send
+        + Sync,
+>;
+
+/// A subscription to the node. If the client was configured to reconnect (see
+/// [`crate::ClientBuilder::set_reconnect_policy`]), the subscription is made again
+/// whenever the connection is re-established, and carries on as if nothing happened.
+pub struct RpcSubscription<N> {
+    slot: Arc<SubscriptionSlot>,
+    restore: Option<Restore>,
+    restoring: Option<BoxFuture<'static, Result<bool, BasicError>>>,
+    finished: bool,
+    _marker: PhantomData<fn() -> N>,
+}
+
+impl<N> std::fmt::Debug for RpcSubscription<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RpcSubscription")
+            .field("method", &self.slot.request.method)
+            .field("finished", &self.finished)
+            .finish()
+    }
+}
+
+impl<N: DeserializeOwned> RpcSubscription<N> {
+    pub(crate) fn new(slot: Arc<SubscriptionSlot>, restore: Option<Restore>) -> Self {
+        Self {
+            slot,
+            restore,
+            restoring: None,
+            finished: false,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the next notification from the subscription, or `None` once it has ended.
+    pub async fn next(&mut self) -> Option<Result<N, RpcError>> {
+        StreamExt::next(self).await
+    }
+}
+
+impl<N: DeserializeOwned> Stream for RpcSubscription<N> {
+    type Item = Result<N, RpcError>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        loop {
+            if self.finished {
+                return Poll::Ready(None)
+            }
+
+            if let Some(restoring) = self.restoring.as_mut() {
+                let res = futures::ready!(restoring.poll_unpin(cx));
+                self.restoring = None;
+                match res {
+                    Ok(true) => continue,
+                    Ok(false) => {
+                        self.finished = true;
+                        return Poll::Ready(None)
+                    }
+                    Err(e) => {
+                        self.finished = true;
+                        return Poll::Ready(Some(Err(RpcError::RestartNeeded(
+                            e.to_string(),
+                        ))))
+                    }
+                }
+            }
+
+            let slot = self.slot.clone();
+            let mut state = slot.lock();
+            if state.terminated {
+                self.finished = true;
+                return Poll::Ready(None)
+            }
+            if let Some(reason) = state.failed.take() {
+                self.finished = true;
+                return Poll::Ready(Some(Err(RpcError::RestartNeeded(reason))))
+            }
+            let generation = state.generation;
+            // Woken once the subscription is restored elsewhere, or terminated.
+            state.waker = Some(cx.waker().clone());
+            let subscription = match state.current.as_mut() {
+                Some(subscription) => subscription,
+                None => return Poll::Pending,
+            };
+
+            let connection_lost = match futures::ready!(subscription.poll_next_unpin(cx))
+            {
+                Some(Ok(value)) => {
+                    return Poll::Ready(Some(
+                        serde_json::from_value(value).map_err(RpcError::ParseError),
+                    ))
+                }
+                Some(Err(RpcError::RestartNeeded(reason))) if self.restore.is_none() => {
+                    return Poll::Ready(Some(Err(RpcError::RestartNeeded(reason))))
+                }
+                Some(Err(RpcError::RestartNeeded(_))) => true,
+                Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+                None => false,
+            };
+
+            let restore = match &self.restore {
+                Some(restore) => restore.clone(),
+                None => {
+                    self.finished = true;
+                    return Poll::Ready(None)
+                }
+            };
+            state.current = None;
+            drop(state);
+            self.restoring = Some(restore(slot, generation, connection_lost));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delay_doubles_up_to_the_maximum() {
+        let policy = ReconnectPolicy {
+            max_attempts: None,
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(1),
+        };
+        let delays: Vec<_> = (1..=6).map(|failures| policy.delay(failures)).collect();
+        assert_eq!(
+            delays,
+            [100, 200, 400, 800, 1000, 1000].map(Duration::from_millis)
+        );
+        assert_eq!(policy.delay(u32::MAX), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn endpoints_are_tried_in_turn_before_waiting() {
+        let policy = ReconnectPolicy {
+            max_attempts: None,
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(1),
+        };
+        let urls = vec!["ws://a".to_string(), "ws://b".into(), "ws://c".into()];
+        let reconnect = Reconnect::new(urls, 1, policy);
+
+        let endpoints: Vec<_> =
+            (0..4).map(|attempt| reconnect.endpoint(attempt)).collect();
+        assert_eq!(
+            endpoints,
+            [(2, "ws://c"), (0, "ws://a"), (1, "ws://b"), (2, "ws://c")]
+        );
+        let delays: Vec<_> = (0..7)
+            .map(|attempt| reconnect.delay_before(attempt))
+            .collect();
+        assert_eq!(
+            delays,
+            [
+                None,
+                None,
+                None,
+                Some(Duration::from_millis(100)),
+                None,
+                None,
+                Some(Duration::from_millis(200))
+            ]
+        );
+
+        // With a single endpoint, every attempt is made on it, and waits as before.
+        let single = Reconnect::new(vec!["ws://a".into()], 0, policy);
+        assert_eq!(single.endpoint(3), (0, "ws://a"));
+        assert_eq!(single.delay_before(0), None);
+        assert_eq!(single.delay_before(2), Some(policy.delay(2)));
+    }
+}
@@ -23,13 +23,49 @@
 
 use std::{
     collections::HashMap,
-    sync::Arc,
+    sync::{
+        atomic::{
+            AtomicU64,
+            AtomicUsize,
+            Ordering,
+        },
+        Arc,
+        Mutex,
+        RwLock,
+        Weak,
+    },
+    time::Duration,
 };
 
 use crate::{
     error::BasicError,
+    finality::FinalityProof,
+    metrics::{
+        ClientMetrics,
+        Timer,
+    },
+    rate_limit::RequestLimiter,
+    reconnect::{
+        Notifications,
+        Reconnect,
+        ReconnectPolicy,
+        Restore,
+        SubscriptionSlot,
+    },
+    rpc_batch::Batch,
+    rpc_middleware::{
+        Next,
+        RpcMiddleware,
+        RpcRequest,
+    },
+    rpc_recording::RpcRecorder,
     storage::StorageKeyPrefix,
+    weights::{
+        DispatchClass,
+        Weight,
+    },
     Config,
+    DecodeLimits,
     Metadata,
     PhantomDataSendSync,
 };
@@ -38,6 +74,21 @@ use codec::{
     Encode,
 };
 use frame_metadata::RuntimeMetadataPrefixed;
+use futures::{
+    channel::{
+        mpsc,
+        oneshot,
+    },
+    future::Shared,
+    FutureExt,
+    Stream,
+    StreamExt,
+};
+use futures_timer::Delay;
+#[cfg(all(feature = "web", not(feature = "native")))]
+use jsonrpsee::client_transport::web;
+use jsonrpsee::types::ParamsSer;
+#[cfg(feature = "native")]
 pub use jsonrpsee::{
     client_transport::ws::{
         InvalidUri,
@@ -46,6 +97,12 @@ pub use jsonrpsee::{
         Uri,
         WsTransportClientBuilder,
     },
+    http_client::{
+        HttpClient,
+        HttpClientBuilder,
+    },
+};
+pub use jsonrpsee::{
     core::{
         client::{
             Client as RpcClient,
@@ -66,12 +123,15 @@ use serde::{
     Serialize,
 };
 use sp_core::{
+    keccak_256,
     storage::{
+        PrefixedStorageKey,
         StorageChangeSet,
         StorageData,
         StorageKey,
     },
     Bytes,
+    H256,
     U256,
 };
 use sp_runtime::generic::{
@@ -79,6 +139,17 @@ use sp_runtime::generic::{
     SignedBlock,
 };
 
+pub use crate::{
+    reconnect::RpcSubscription,
+    rpc_mock::MockRpcClient,
+};
+
+/// How often [`Rpc::shutdown()`] checks whether all requests have completed.
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How long the node has to respond when checking whether the connection still works.
+const LIVENESS_TIMEOUT: Duration = Duration::from_secs(10);
+
 /// A number type that can be serialized both as a number or a string that encodes a number in a
 /// string.
 ///
@@ -160,9 +231,24 @@ pub enum SubstrateTransactionStatus<Hash, BlockHash> {
     Invalid,
 }
 
+/// An extrinsic to remove from the transaction pool, given either by its hash or in full.
+///
+/// # Note
+///
+/// This is copied from `sc-rpc-api` to avoid a dependency on that crate. Therefore it
+/// must be kept compatible with that type from the target substrate version.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ExtrinsicOrHash<Hash> {
+    /// The hash of the extrinsic.
+    Hash(Hash),
+    /// The SCALE encoded extrinsic.
+    Extrinsic(Bytes),
+}
+
 /// This contains the runtime version information necessary to make transactions, as obtained from
 /// the RPC call `state_getRuntimeVersion`,
-#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RuntimeVersion {
     /// Version of the runtime specification. A full-node will not attempt to use its native
@@ -224,29 +310,1173 @@ pub struct BlockStats {
 
 /// Client for substrate rpc interfaces
 pub struct Rpc<T: Config> {
-    /// Rpc client for sending requests.
-    pub client: Arc<RpcClient>,
+    middleware: Arc<[Arc<dyn RpcMiddleware>]>,
+    decode_limits: DecodeLimits,
+    reconnect: Option<Arc<Reconnect>>,
+    recorder: Option<Arc<RpcRecorder>>,
+    metrics: Option<Arc<dyn ClientMetrics>>,
+    limiter: RequestLimiter,
+    state: Arc<RpcState>,
     _marker: PhantomDataSendSync<T>,
 }
 
 impl<T: Config> Clone for Rpc<T> {
     fn clone(&self) -> Self {
         Self {
-            client: self.client.clone(),
+            middleware: self.middleware.clone(),
+            decode_limits: self.decode_limits,
+            reconnect: self.reconnect.clone(),
+            recorder: self.recorder.clone(),
+            metrics: self.metrics.clone(),
+            limiter: self.limiter.clone(),
+            state: self.state.clone(),
             _marker: PhantomDataSendSync::new(),
         }
     }
 }
 
+/// The position of a cell in the extended data matrix of a block, as used by the Avail
+/// `kate_*` RPC methods.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Cell {
+    /// The row of the cell.
+    pub row: u32,
+    /// The column of the cell.
+    pub col: u32,
+}
+
+impl Cell {
+    /// The cell at the given row and column.
+    pub fn new(row: u32, col: u32) -> Self {
+        Self { row, col }
+    }
+}
+
+/// The length in bytes of the KZG proof of a cell.
+pub const CELL_PROOF_LEN: usize = 48;
+/// The length in bytes of the data in a cell.
+pub const CELL_DATA_LEN: usize = 32;
+
+/// The proof of a single cell returned by `kate_queryProof`: a KZG proof that the cell's
+/// data is committed to by the block header.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Proof {
+    /// The proof that `data` belongs at its position in the matrix.
+    pub proof: [u8; CELL_PROOF_LEN],
+    /// The data in the cell.
+    pub data: [u8; CELL_DATA_LEN],
+}
+
+impl Proof {
+    /// Split the concatenated proofs returned by `kate_queryProof` into one per cell.
+    fn split(bytes: &[u8], cells: usize) -> Result<Vec<Proof>, BasicError> {
+        let len = CELL_PROOF_LEN + CELL_DATA_LEN;
+        if bytes.len() != cells * len {
+            return Err(BasicError::Other(format!(
+                "Expected {} bytes of proof for {} cells, but got {}",
+                cells * len,
+                cells,
+                bytes.len()
+            )))
+        }
+        Ok(bytes
+            .chunks_exact(len)
+            .map(|chunk| {
+                let mut proof = Proof {
+                    proof: [0; CELL_PROOF_LEN],
+                    data: [0; CELL_DATA_LEN],
+                };
+                proof.proof.copy_from_slice(&chunk[..CELL_PROOF_LEN]);
+                proof.data.copy_from_slice(&chunk[CELL_PROOF_LEN..]);
+                proof
+            })
+            .collect())
+    }
+}
+
+/// A value for each class of dispatch.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PerDispatchClass<V> {
+    /// The value for normal dispatches.
+    pub normal: V,
+    /// The value for operational dispatches.
+    pub operational: V,
+    /// The value for mandatory dispatches.
+    pub mandatory: V,
+}
+
+/// The length limits of a block, and the dimensions of its data matrix, as returned by
+/// the Avail `kate_blockLength` RPC method.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockLength {
+    /// The maximum length in bytes of the extrinsics of each dispatch class.
+    pub max: PerDispatchClass<u32>,
+    /// The number of columns in the data matrix.
+    pub cols: u32,
+    /// The number of rows in the data matrix.
+    pub rows: u32,
+    /// The size in bytes of each chunk of data in the matrix.
+    pub chunk_size: u32,
+}
+
+/// A Merkle proof that a data submission was included in a block, as returned by the
+/// Avail `kate_queryDataProof` RPC method.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DataProof {
+    /// The root of the Merkle tree of data submissions.
+    pub root: H256,
+    /// The sibling nodes on the branch from the leaf up to the root.
+    pub proof: Vec<H256>,
+    /// The number of leaves in the tree.
+    pub number_of_leaves: u32,
+    /// The position of the leaf in the tree.
+    pub leaf_index: u32,
+    /// The leaf being proven: the hash of the submitted data.
+    pub leaf: H256,
+}
+
+impl DataProof {
+    /// Verify this proof against the `data_root` taken from a block header, returning
+    /// the proven leaf (the hash of the submitted data) if the Merkle branch leads to it.
+    ///
+    /// Nodes are combined with Keccak-256 in sorted order, so the position of the leaf
+    /// is not needed to recompute the root.
+    pub fn verify(&self, data_root: &H256) -> Result<H256, BasicError> {
+        if &self.root != data_root {
+            return Err(BasicError::Other(format!(
+                "Proof is for data root {:?}, but the block has {:?}",
+                self.root, data_root
+            )))
+        }
+        if self.leaf_index >= self.number_of_leaves {
+            return Err(BasicError::Other(format!(
+                "Leaf index {} is out of range for {} leaves",
+                self.leaf_index, self.number_of_leaves
+            )))
+        }
+        let leaf_hash = H256(keccak_256(self.leaf.as_bytes()));
+        let computed = self.proof.iter().fold(leaf_hash, |node, sibling| {
+            let (left, right) = if node < *sibling {
+                (node, *sibling)
+            } else {
+                (*sibling, node)
+            };
+            let mut combined = [0u8; 64];
+            combined[..32].copy_from_slice(left.as_bytes());
+            combined[32..].copy_from_slice(right.as_bytes());
+            H256(keccak_256(&combined))
+        });
+        if &computed != data_root {
+            return Err(BasicError::Other(format!(
+                "Merkle branch leads to {:?}, not the data root {:?}",
+                computed, data_root
+            )))
+        }
+        Ok(self.leaf)
+    }
+}
+
+/// Information about the dispatch of an extrinsic, and the fee it would pay, as returned
+/// by the `payment_queryInfo` RPC method.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuntimeDispatchInfo {
+    /// The weight of the extrinsic, as either a single number or a `refTime` and
+    /// `proofSize`, depending on the runtime.
+    pub weight: Weight,
+    /// The class of the extrinsic.
+    pub class: DispatchClass,
+    /// The fee that would be paid for the extrinsic, not including any tip.
+    #[serde(deserialize_with = "deserialize_balance")]
+    pub partial_fee: u128,
+}
+
+/// The parts of the fee for including an extrinsic in a block.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InclusionFee {
+    /// The minimum fee for any extrinsic.
+    #[serde(deserialize_with = "deserialize_balance")]
+    pub base_fee: u128,
+    /// The fee for the length of the extrinsic in bytes.
+    #[serde(deserialize_with = "deserialize_balance")]
+    pub len_fee: u128,
+    /// The fee for the weight of the extrinsic, adjusted according to how full
+    /// recent blocks have been.
+    #[serde(deserialize_with = "deserialize_balance")]
+    pub adjusted_weight_fee: u128,
+}
+
+/// A breakdown of the fee that an extrinsic would pay, as returned by the
+/// `payment_queryFeeDetails` RPC method.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeDetails {
+    /// The fee for inclusion in a block, or `None` for unsigned extrinsics, which
+    /// pay no fee.
+    pub inclusion_fee: Option<InclusionFee>,
+    /// The tip. The node doesn't report this, and so it's 0 unless set otherwise.
+    #[serde(default, deserialize_with = "deserialize_balance")]
+    pub tip: u128,
+}
+
+impl FeeDetails {
+    /// The total fee: the inclusion fee plus the tip.
+    pub fn final_fee(&self) -> u128 {
+        let inclusion_fee = self.inclusion_fee.as_ref().map_or(0, |fee| {
+            fee.base_fee
+                .saturating_add(fee.len_fee)
+                .saturating_add(fee.adjusted_weight_fee)
+        });
+        inclusion_fee.saturating_add(self.tip)
+    }
+}
+
+/// Balances are returned as numbers, or as decimal or hex encoded strings when they
+/// could be too large for a JSON number.
+fn deserialize_balance<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<u128, D::Error> {
+    use serde::de::Error;
+    match JsonValue::deserialize(deserializer)? {
+        JsonValue::Number(number) => {
+            number
+                .as_u64()
+                .map(Into::into)
+                .ok_or_else(|| D::Error::custom(format!("invalid balance: {}", number)))
+        }
+        JsonValue::String(string) => {
+            let parsed = match string.strip_prefix("0x") {
+                Some(hex) => u128::from_str_radix(hex, 16),
+                None => string.parse(),
+            };
+            parsed.map_err(|e| {
+                D::Error::custom(format!("invalid balance {}: {}", string, e))
+            })
+        }
+        other => Err(D::Error::custom(format!("invalid balance: {}", other))),
+    }
+}
+
+/// A change in the state of the connection to the node.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConnectionEvent {
+    /// The connection to the node is established.
+    Connected,
+    /// The connection to the node has been lost.
+    Disconnected {
+        /// Why the connection was lost.
+        reason: String,
+    },
+    /// An attempt is being made to re-establish the connection.
+    Reconnecting {
+        /// The number of the attempt, starting at 1.
+        attempt: u32,
+    },
+    /// The connection was re-established to a different endpoint than before. Since
+    /// that node may be running a different runtime version, a [`crate::Client`]
+    /// fetches the runtime version (and the metadata, if the version differs) again
+    /// before signing its next transaction. Call [`crate::Client::refresh_metadata()`]
+    /// to have it do so straight away.
+    FailedOver {
+        /// The endpoint now connected to.
+        url: String,
+    },
+    /// The connection was re-established and active subscriptions have been restored.
+    Resubscribed,
+}
+
+/// A stream of [`ConnectionEvent`]s, obtained from [`Rpc::connection_events()`].
+///
+/// The first item is the state of the connection at the time the stream was created.
+#[derive(Debug)]
+pub struct ConnectionEvents(mpsc::UnboundedReceiver<ConnectionEvent>);
+
+impl Stream for ConnectionEvents {
+    type Item = ConnectionEvent;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        self.0.poll_next_unpin(cx)
+    }
+}
+
+/// The last known connection state, and everybody listening for changes to it.
+struct ConnectionState {
+    last: ConnectionEvent,
+    listeners: Vec<mpsc::UnboundedSender<ConnectionEvent>>,
+}
+
+impl ConnectionState {
+    fn new() -> Self {
+        Self {
+            last: ConnectionEvent::Connected,
+            listeners: Vec::new(),
+        }
+    }
+
+    fn listen(&mut self) -> ConnectionEvents {
+        let (tx, rx) = mpsc::unbounded();
+        // Can't fail; we hold the receiver.
+        let _ = tx.unbounded_send(self.last.clone());
+        self.listeners.push(tx);
+        ConnectionEvents(rx)
+    }
+
+    /// Tell every listener about a connection event. Repeated disconnections are
+    /// only reported once, so that every failing request doesn't produce an event.
+    fn notify(&mut self, event: ConnectionEvent) {
+        if matches!(
+            (&self.last, &event),
+            (
+                ConnectionEvent::Disconnected { .. },
+                ConnectionEvent::Disconnected { .. }
+            )
+        ) {
+            return
+        }
+        self.listeners
+            .retain(|listener| listener.unbounded_send(event.clone()).is_ok());
+        self.last = event;
+    }
+}
+
+/// The client that method calls are sent with.
+#[derive(Clone)]
+pub(crate) enum Transport {
+    /// A WebSocket connection, which supports subscriptions.
+    Ws(Arc<RpcClient>),
+    /// HTTP requests, which don't support subscriptions.
+    #[cfg(feature = "native")]
+    Http(Arc<HttpClient>),
+    /// Canned responses, rather than a node.
+    Mock(MockRpcClient),
+    /// The client has been shut down, and the connection closed.
+    Closed,
+}
+
+impl Transport {
+    pub(crate) async fn request<'a, R: DeserializeOwned>(
+        &self,
+        method: &'a str,
+        params: Option<ParamsSer<'a>>,
+    ) -> Result<R, RpcError> {
+        match self {
+            Transport::Ws(client) => client.request(method, params).await,
+            #[cfg(feature = "native")]
+            Transport::Http(client) => client.request(method, params).await,
+            Transport::Mock(client) => {
+                let response = client.request(RpcRequest::from_params(method, params))?;
+                serde_json::from_value(response).map_err(RpcError::ParseError)
+            }
+            Transport::Closed => Err(closed()),
+        }
+    }
+
+    async fn batch_request(
+        &self,
+        calls: &[RpcRequest],
+    ) -> Result<Vec<JsonValue>, RpcError> {
+        let batch = calls
+            .iter()
+            .map(|call| (call.method.as_str(), call.to_params()))
+            .collect();
+        match self {
+            Transport::Ws(client) => client.batch_request(batch).await,
+            #[cfg(feature = "native")]
+            Transport::Http(client) => client.batch_request(batch).await,
+            Transport::Mock(client) => {
+                calls
+                    .iter()
+                    .map(|call| client.request(call.clone()))
+                    .collect()
+            }
+            Transport::Closed => Err(closed()),
+        }
+    }
+
+    /// Make the given subscription request. Fails for clients which don't support
+    /// subscriptions; see [`Transport::supports_subscriptions`].
+    pub(crate) async fn subscribe(
+        &self,
+        request: &RpcRequest,
+        unsubscribe_method: &str,
+    ) -> Result<Notifications, RpcError> {
+        match self {
+            Transport::Ws(client) => {
+                let subscription: Subscription<JsonValue> = client
+                    .subscribe(&request.method, request.to_params(), unsubscribe_method)
+                    .await?;
+                Ok(subscription.boxed())
+            }
+            #[cfg(feature = "native")]
+            Transport::Http(_) => {
+                Err(RpcError::Custom(
+                    "Subscriptions are not supported over HTTP".into(),
+                ))
+            }
+            Transport::Mock(client) => client.subscribe(request),
+            Transport::Closed => Err(closed()),
+        }
+    }
+
+    /// Can subscriptions be made with this client?
+    fn supports_subscriptions(&self) -> bool {
+        match self {
+            Transport::Ws(_) | Transport::Mock(_) => true,
+            #[cfg(feature = "native")]
+            Transport::Http(_) => false,
+            Transport::Closed => false,
+        }
+    }
+
+    /// The WebSocket client, if this is a WebSocket connection.
+    fn ws(&self) -> Option<&Arc<RpcClient>> {
+        match self {
+            Transport::Ws(client) => Some(client),
+            #[cfg(feature = "native")]
+            Transport::Http(_) => None,
+            Transport::Mock(_) => None,
+            Transport::Closed => None,
+        }
+    }
+}
+
+fn closed() -> RpcError {
+    RpcError::Custom("The client has been shut down".into())
+}
+
+/// State shared by all clones of an [`Rpc`]: the underlying client, the number of
+/// requests in flight, whether it has been shut down, the state of the connection,
+/// and the subscriptions to restore should it need to be re-established.
+struct RpcState {
+    client: RwLock<Transport>,
+    // Incremented each time the client is replaced.
+    generation: AtomicU64,
+    // Incremented each time the connection moves to a different endpoint.
+    failovers: AtomicU64,
+    subscriptions: Mutex<Vec<Weak<SubscriptionSlot>>>,
+    in_flight: AtomicUsize,
+    shutdown_tx: Mutex<Option<oneshot::Sender<()>>>,
+    shutdown_rx: Shared<oneshot::Receiver<()>>,
+    connection: Mutex<ConnectionState>,
+}
+
+impl RpcState {
+    fn new(client: Transport) -> Self {
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        Self {
+            client: RwLock::new(client),
+            generation: AtomicU64::new(0),
+            failovers: AtomicU64::new(0),
+            subscriptions: Mutex::new(Vec::new()),
+            in_flight: AtomicUsize::new(0),
+            shutdown_tx: Mutex::new(Some(shutdown_tx)),
+            shutdown_rx: shutdown_rx.shared(),
+            connection: Mutex::new(ConnectionState::new()),
+        }
+    }
+
+    /// The current client, and the generation it belongs to.
+    fn client(&self) -> (Transport, u64) {
+        let client = self.client.read().expect("rpc state lock poisoned");
+        (client.clone(), self.generation.load(Ordering::SeqCst))
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation.load(Ordering::SeqCst)
+    }
+
+    /// Drop the client, closing the connection once no request is using it anymore.
+    fn close(&self) {
+        *self.client.write().expect("rpc state lock poisoned") = Transport::Closed;
+    }
+
+    /// Replace the client, returning the generation of the new one.
+    fn set_client(&self, client: Arc<RpcClient>) -> u64 {
+        let mut current = self.client.write().expect("rpc state lock poisoned");
+        *current = Transport::Ws(client);
+        self.generation.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    /// Keep track of a subscription, so that it can be restored after reconnecting.
+    fn register(&self, slot: &Arc<SubscriptionSlot>) {
+        let mut subscriptions =
+            self.subscriptions.lock().expect("rpc state lock poisoned");
+        subscriptions.retain(|slot| slot.strong_count() > 0);
+        subscriptions.push(Arc::downgrade(slot));
+    }
+
+    fn live_subscriptions(&self) -> Vec<Arc<SubscriptionSlot>> {
+        self.subscriptions
+            .lock()
+            .expect("rpc state lock poisoned")
+            .iter()
+            .filter_map(Weak::upgrade)
+            .collect()
+    }
+
+    fn connection_events(&self) -> ConnectionEvents {
+        self.connection
+            .lock()
+            .expect("rpc state lock poisoned")
+            .listen()
+    }
+
+    fn notify(&self, event: ConnectionEvent) {
+        self.connection
+            .lock()
+            .expect("rpc state lock poisoned")
+            .notify(event)
+    }
+
+    fn is_disconnected(&self) -> bool {
+        matches!(
+            self.connection
+                .lock()
+                .expect("rpc state lock poisoned")
+                .last,
+            ConnectionEvent::Disconnected { .. }
+        )
+    }
+
+    /// Take note of the connection having been lost if the error says so.
+    fn observe_error(&self, err: &RpcError) {
+        if let RpcError::RestartNeeded(reason) = err {
+            self.notify(ConnectionEvent::Disconnected {
+                reason: reason.clone(),
+            })
+        }
+    }
+
+    fn is_shut_down(&self) -> bool {
+        self.shutdown_tx
+            .lock()
+            .expect("rpc state lock poisoned")
+            .is_none()
+    }
+
+    fn begin_request(&self) -> Result<InFlightGuard<'_>, BasicError> {
+        if self.is_shut_down() {
+            return Err(BasicError::ShutDown)
+        }
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        Ok(InFlightGuard(self))
+    }
+}
+
+/// Marks a request as being in flight until dropped.
+struct InFlightGuard<'a>(&'a RpcState);
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Resolves once the [`Rpc`] it was obtained from has been shut down.
+#[derive(Clone)]
+pub(crate) struct ShutdownSignal(Shared<oneshot::Receiver<()>>);
+
+impl ShutdownSignal {
+    /// Returns `true` if the client has been shut down, and otherwise arranges for
+    /// the current task to be woken once it is.
+    pub(crate) fn poll_shut_down(&mut self, cx: &mut std::task::Context<'_>) -> bool {
+        self.0.poll_unpin(cx).is_ready()
+    }
+}
+
 impl<T: Config> Rpc<T> {
     /// Create a new [`Rpc`]
     pub fn new(client: RpcClient) -> Self {
+        Self::with_transport(Transport::Ws(Arc::new(client)))
+    }
+
+    /// Create a new [`Rpc`] which sends method calls over HTTP.
+    ///
+    /// Subscriptions need a WebSocket connection, and so they fail with
+    /// [`BasicError::SubscriptionsUnsupported`]. This includes watching the progress of
+    /// a transaction and subscribing to events; submit transactions with
+    /// [`crate::SubmittableExtrinsic::submit()`] instead.
+    #[cfg(feature = "native")]
+    pub fn new_http(client: HttpClient) -> Self {
+        Self::with_transport(Transport::Http(Arc::new(client)))
+    }
+
+    /// Create a new [`Rpc`] which answers requests with the canned responses of the
+    /// given [`MockRpcClient`], rather than talking to a node.
+    pub fn new_mock(client: MockRpcClient) -> Self {
+        Self::with_transport(Transport::Mock(client))
+    }
+
+    fn with_transport(client: Transport) -> Self {
         Self {
-            client: Arc::new(client),
+            middleware: Arc::from(Vec::new()),
+            decode_limits: DecodeLimits::default(),
+            reconnect: None,
+            recorder: None,
+            metrics: None,
+            limiter: RequestLimiter::default(),
+            state: Arc::new(RpcState::new(client)),
             _marker: PhantomDataSendSync::new(),
         }
     }
 
+    /// The underlying client for sending requests, or `None` if they are sent over
+    /// HTTP (or answered by a [`MockRpcClient`]). This changes if the connection is re-established (see
+    /// [`Rpc::with_reconnect`]).
+    pub fn client(&self) -> Option<Arc<RpcClient>> {
+        self.state.client().0.ws().cloned()
+    }
+
+    /// Are method calls sent over HTTP, in which case subscriptions are unsupported?
+    pub fn is_http(&self) -> bool {
+        match self.state.client().0 {
+            Transport::Ws(_) | Transport::Mock(_) | Transport::Closed => false,
+            #[cfg(feature = "native")]
+            Transport::Http(_) => true,
+        }
+    }
+
+    /// Re-establish the connection to the node at `url` according to `policy` whenever
+    /// it's lost, restoring any active subscriptions. Requests which fail because the
+    /// connection was lost are retried once it has been re-established.
+    ///
+    /// [`ConnectionEvent`]s are emitted as this happens; see [`Rpc::connection_events`].
+    pub fn with_reconnect<U: Into<String>>(
+        self,
+        url: U,
+        policy: ReconnectPolicy,
+    ) -> Self {
+        self.with_failover(vec![url], policy)
+    }
+
+    /// Like [`Rpc::with_reconnect`], but with several endpoints to choose from, the
+    /// first of which should be the one currently connected to. Whenever the
+    /// connection is lost, the next endpoint is connected to; only once all of them
+    /// have been tried does `policy` make us wait between attempts.
+    ///
+    /// Endpoints which report a different genesis hash from the one the client was
+    /// built with are skipped. A [`ConnectionEvent::FailedOver`] is emitted whenever
+    /// the connection moves to a different endpoint.
+    pub fn with_failover<U: Into<String>>(
+        self,
+        urls: Vec<U>,
+        policy: ReconnectPolicy,
+    ) -> Self {
+        self.with_failover_from(urls.into_iter().map(Into::into).collect(), 0, policy)
+    }
+
+    pub(crate) fn with_failover_from(
+        mut self,
+        urls: Vec<String>,
+        current: usize,
+        policy: ReconnectPolicy,
+    ) -> Self {
+        if !urls.is_empty() {
+            self.reconnect = Some(Arc::new(Reconnect::new(urls, current, policy)));
+        }
+        self
+    }
+
+    /// Only reconnect to endpoints which report this genesis hash.
+    pub(crate) fn pin_genesis(&self, genesis_hash: &T::Hash) {
+        if let Some(reconnect) = &self.reconnect {
+            *reconnect.genesis.lock().expect("reconnect lock poisoned") =
+                to_json_value(genesis_hash).ok();
+        }
+    }
+
+    /// Set the limits applied when decoding values received from the node.
+    pub fn with_decode_limits(mut self, limits: DecodeLimits) -> Self {
+        self.decode_limits = limits;
+        self
+    }
+
+    /// The limits applied when decoding values received from the node.
+    pub fn decode_limits(&self) -> &DecodeLimits {
+        &self.decode_limits
+    }
+
+    /// Record the method calls made by this client and the notifications received by
+    /// its subscriptions, so that they can be replayed by a [`MockRpcClient`].
+    ///
+    /// Method calls are recorded as seen by the caller, after any middleware.
+    pub fn with_recorder(mut self, recorder: RpcRecorder) -> Self {
+        self.recorder = Some(Arc::new(recorder));
+        self
+    }
+
+    /// Report the method calls made by this client, their latency and errors, and
+    /// reconnections to the given [`ClientMetrics`].
+    pub fn with_metrics<M: ClientMetrics>(mut self, metrics: M) -> Self {
+        self.metrics = Some(Arc::new(metrics));
+        self
+    }
+
+    /// Send at most this many requests (method calls, batches of them, and
+    /// subscriptions) a second, holding the rest back until it's their turn.
+    pub fn with_rate_limit(mut self, requests_per_second: u32) -> Self {
+        self.limiter.set_rate_limit(requests_per_second);
+        self
+    }
+
+    /// Have at most this many requests (method calls, batches of them, and
+    /// subscriptions being made) waiting on the node at once, holding the rest back
+    /// until one of them is answered.
+    pub fn with_max_concurrent_requests(mut self, max: usize) -> Self {
+        self.limiter.set_max_concurrent(max);
+        self
+    }
+
+    /// The metrics this client reports to, if any.
+    pub(crate) fn metrics(&self) -> Option<&dyn ClientMetrics> {
+        self.metrics.as_deref()
+    }
+
+    /// Wrap the method calls made by this client in another layer of middleware.
+    ///
+    /// Layers see requests in the order in which they were added, and responses in
+    /// the reverse order, so the first layer added is the outermost one.
+    pub fn with_middleware<M: RpcMiddleware>(self, middleware: M) -> Self {
+        self.with_middleware_layers(vec![Arc::new(middleware)])
+    }
+
+    pub(crate) fn with_middleware_layers(
+        mut self,
+        middleware: Vec<Arc<dyn RpcMiddleware>>,
+    ) -> Self {
+        if !middleware.is_empty() {
+            let mut layers = self.middleware.to_vec();
+            layers.extend(middleware);
+            self.middleware = layers.into();
+        }
+        self
+    }
+
+    /// Shut down this client and all of its clones.
+    ///
+    /// New requests and subscriptions are refused with [`BasicError::ShutDown`], and
+    /// subscriptions obtained via the higher level APIs (such as event subscriptions
+    /// and transaction progress) end. Requests already in flight are given until the
+    /// `timeout` to complete; an error is returned if some are still pending after it.
+    ///
+    /// Subscriptions made with [`Rpc`] itself end straight away, and the connection to
+    /// the node is closed once the requests in flight are done (or the timeout elapses).
+    pub async fn shutdown(&self, timeout: Duration) -> Result<(), BasicError> {
+        self.state
+            .shutdown_tx
+            .lock()
+            .expect("rpc state lock poisoned")
+            .take();
+        self.state.notify(ConnectionEvent::Disconnected {
+            reason: "the client has been shut down".into(),
+        });
+        for slot in self.state.live_subscriptions() {
+            slot.terminate();
+        }
+
+        let mut deadline = Delay::new(timeout);
+        let result = loop {
+            let in_flight = self.state.in_flight.load(Ordering::SeqCst);
+            if in_flight == 0 {
+                break Ok(())
+            }
+            if (&mut deadline).now_or_never().is_some() {
+                break Err(BasicError::Other(format!(
+                    "{} requests still in flight after shutdown timeout",
+                    in_flight
+                )))
+            }
+            Delay::new(SHUTDOWN_POLL_INTERVAL).await;
+        };
+        self.state.close();
+        result
+    }
+
+    /// Has this client been shut down?
+    pub fn is_shut_down(&self) -> bool {
+        self.state.is_shut_down()
+    }
+
+    /// Returns a stream of changes to the state of the connection to the node, starting
+    /// with its current state. This can be used to report on the health of the
+    /// connection, or to hold off submitting transactions while it's down.
+    pub fn connection_events(&self) -> ConnectionEvents {
+        self.state.connection_events()
+    }
+
+    /// Report a change in the state of the connection to the node to every listener.
+    pub(crate) fn notify_connection_event(&self, event: ConnectionEvent) {
+        self.state.notify(event)
+    }
+
+    /// Record that the connection has moved to the endpoint at `url`, and report it.
+    pub(crate) fn failed_over(&self, url: &str) {
+        self.state.failovers.fetch_add(1, Ordering::SeqCst);
+        self.state
+            .notify(ConnectionEvent::FailedOver { url: url.into() });
+    }
+
+    /// How many times the connection has moved to a different endpoint.
+    pub(crate) fn failovers(&self) -> u64 {
+        self.state.failovers.load(Ordering::SeqCst)
+    }
+
+    /// Was the last reported state of the connection [`ConnectionEvent::Disconnected`]?
+    pub(crate) fn is_disconnected(&self) -> bool {
+        self.state.is_disconnected()
+    }
+
+    /// Is anything other than this handle still using the connection?
+    pub(crate) fn has_other_handles(&self) -> bool {
+        Arc::strong_count(&self.state) > 1
+    }
+
+    /// The generation of the current connection, which changes each time the
+    /// connection is re-established.
+    pub(crate) fn connection_generation(&self) -> u64 {
+        self.state.generation()
+    }
+
+    /// Can this client re-establish its connection?
+    pub(crate) fn can_reconnect(&self) -> bool {
+        self.reconnect.is_some()
+    }
+
+    /// Re-establish the connection to the node and restore active subscriptions, unless
+    /// the connection of the given generation has already been replaced.
+    pub(crate) async fn reconnect(&self, seen_generation: u64) -> Result<(), BasicError> {
+        let reconnect = match &self.reconnect {
+            Some(reconnect) => reconnect,
+            None => {
+                return Err(BasicError::Other(
+                    "The connection to the node was lost".into(),
+                ))
+            }
+        };
+        let _lock = reconnect.lock.lock().await;
+        if self.state.generation() != seen_generation {
+            return Ok(())
+        }
+
+        let mut attempt = 0;
+        loop {
+            if self.is_shut_down() {
+                return Err(BasicError::ShutDown)
+            }
+            if reconnect
+                .policy
+                .max_attempts
+                .map_or(false, |max| attempt >= max)
+            {
+                return Err(BasicError::Other(format!(
+                    "Gave up reconnecting to {} after {} attempts",
+                    reconnect.urls.join(", "),
+                    attempt
+                )))
+            }
+            if let Some(delay) = reconnect.delay_before(attempt) {
+                Delay::new(delay).await;
+            }
+            let (index, url) = reconnect.endpoint(attempt);
+            attempt += 1;
+            self.state.notify(ConnectionEvent::Reconnecting { attempt });
+
+            let client = match ws_client_with_limits(url, &self.decode_limits).await {
+                Ok(client) => Arc::new(client),
+                Err(e) => {
+                    log::warn!(
+                        "Reconnection attempt {} to {} failed: {}",
+                        attempt,
+                        url,
+                        e
+                    );
+                    continue
+                }
+            };
+            if let Some(expected) = reconnect.genesis() {
+                match client
+                    .request::<JsonValue>("chain_getBlockHash", rpc_params![0u32])
+                    .await
+                {
+                    Ok(genesis) if genesis == expected => {}
+                    Ok(genesis) => {
+                        log::warn!(
+                            "Not failing over to {}, which is on a different chain (genesis {})",
+                            url,
+                            genesis
+                        );
+                        continue
+                    }
+                    Err(e) => {
+                        log::warn!(
+                            "Reconnection attempt {} to {} failed: {}",
+                            attempt,
+                            url,
+                            e
+                        );
+                        continue
+                    }
+                }
+            }
+            if self.is_shut_down() {
+                return Err(BasicError::ShutDown)
+            }
+            let previous = reconnect.current.swap(index, Ordering::SeqCst);
+            let generation = self.state.set_client(client.clone());
+            self.state.notify(ConnectionEvent::Connected);
+            if let Some(metrics) = &self.metrics {
+                metrics.reconnect();
+            }
+            if previous != index {
+                log::info!("Failed over to {}", url);
+                self.failed_over(url);
+            }
+
+            let client = Transport::Ws(client);
+            for slot in self.state.live_subscriptions() {
+                slot.replace(slot.subscribe(&client).await, generation);
+            }
+            self.state.notify(ConnectionEvent::Resubscribed);
+            return Ok(())
+        }
+    }
+
+    /// Restore a subscription whose underlying subscription has ended. Unless the
+    /// connection is known to have been lost, we check that it has been first, since
+    /// otherwise it was the node that ended the subscription, and `false` is returned.
+    async fn restore_subscription(
+        &self,
+        slot: Arc<SubscriptionSlot>,
+        seen_generation: u64,
+        connection_lost: bool,
+    ) -> Result<bool, BasicError> {
+        if !connection_lost && self.is_alive(seen_generation).await {
+            return Ok(false)
+        }
+        self.reconnect(seen_generation).await?;
+        // It may have been restored along with the others while reconnecting.
+        if slot.generation() != self.state.generation() {
+            let (client, generation) = self.state.client();
+            if client.supports_subscriptions() {
+                slot.replace(slot.subscribe(&client).await, generation);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Does the connection of the given generation still work?
+    async fn is_alive(&self, generation: u64) -> bool {
+        let (client, current_generation) = self.state.client();
+        if current_generation != generation {
+            return false
+        }
+        let ping = client.request::<JsonValue>("system_health", None);
+        futures::pin_mut!(ping);
+        matches!(
+            futures::future::select(ping, Delay::new(LIVENESS_TIMEOUT)).await,
+            futures::future::Either::Left((Ok(_), _))
+        )
+    }
+
+    fn restorer(&self) -> Option<Restore> {
+        self.reconnect.as_ref()?;
+        let rpc = self.clone();
+        Some(Arc::new(move |slot, generation, connection_lost| {
+            let rpc = rpc.clone();
+            async move {
+                rpc.restore_subscription(slot, generation, connection_lost)
+                    .await
+            }
+            .boxed()
+        }))
+    }
+
+    /// Send a cheap request to the node, to check that it's still responding.
+    pub(crate) async fn ping(&self) -> Result<(), BasicError> {
+        let _: JsonValue = self.request("system_health", None).await?;
+        Ok(())
+    }
+
+    pub(crate) fn shutdown_signal(&self) -> ShutdownSignal {
+        ShutdownSignal(self.state.shutdown_rx.clone())
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "rpc_request", level = "debug", skip_all, fields(method = method))
+    )]
+    async fn request<'a, R: DeserializeOwned>(
+        &self,
+        method: &'a str,
+        params: Option<ParamsSer<'a>>,
+    ) -> Result<R, BasicError> {
+        let _permit = self.limiter.acquire().await;
+        let _guard = self.state.begin_request()?;
+        let (client, generation) = self.state.client();
+        match self.request_on(&client, method, params.clone()).await {
+            Err(RpcError::RestartNeeded(_)) if self.can_reconnect() => {
+                self.reconnect(generation).await?;
+                let (client, _) = self.state.client();
+                Ok(self.request_on(&client, method, params).await?)
+            }
+            res => Ok(res?),
+        }
+    }
+
+    async fn request_on<'a, R: DeserializeOwned>(
+        &self,
+        client: &Transport,
+        method: &'a str,
+        params: Option<ParamsSer<'a>>,
+    ) -> Result<R, RpcError> {
+        let timer = Timer::start();
+        let res = if self.middleware.is_empty() && self.recorder.is_none() {
+            client.request(method, params).await
+        } else {
+            let request = RpcRequest::from_params(method, params);
+            let res = Next::new(client, &self.middleware)
+                .run(request.clone())
+                .await;
+            if let Some(recorder) = &self.recorder {
+                recorder.record_call(&request, &res);
+            }
+            res.and_then(|value| {
+                serde_json::from_value(value).map_err(RpcError::ParseError)
+            })
+        };
+        if let Some(metrics) = &self.metrics {
+            metrics.rpc_call(method);
+            if let Some(latency) = timer.elapsed() {
+                metrics.request_latency(method, latency);
+            }
+            if res.is_err() {
+                metrics.rpc_error(method);
+            }
+        }
+        if let Err(err) = &res {
+            self.state.observe_error(err);
+        }
+        res
+    }
+
+    /// Send the given calls as a single batch, handing back their results in order.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "rpc_batch_request",
+            level = "debug",
+            skip_all,
+            fields(calls = calls.len())
+        )
+    )]
+    pub(crate) async fn batch_request(
+        &self,
+        calls: &[RpcRequest],
+    ) -> Result<Vec<JsonValue>, BasicError> {
+        let _permit = self.limiter.acquire().await;
+        let _guard = self.state.begin_request()?;
+        let (client, generation) = self.state.client();
+        let res = match client.batch_request(calls).await {
+            Err(RpcError::RestartNeeded(reason)) if self.can_reconnect() => {
+                self.state.observe_error(&RpcError::RestartNeeded(reason));
+                self.reconnect(generation).await?;
+                let (client, _) = self.state.client();
+                client.batch_request(calls).await
+            }
+            res => res,
+        };
+        if let Some(recorder) = &self.recorder {
+            match &res {
+                Ok(results) => {
+                    for (call, result) in calls.iter().zip(results) {
+                        recorder.record_call(call, &Ok(result.clone()))
+                    }
+                }
+                Err(err) => {
+                    for call in calls {
+                        recorder
+                            .record_call(call, &Err(RpcError::Custom(err.to_string())))
+                    }
+                }
+            }
+        }
+        if let Some(metrics) = &self.metrics {
+            for call in calls {
+                metrics.rpc_call(&call.method);
+                if res.is_err() {
+                    metrics.rpc_error(&call.method);
+                }
+            }
+        }
+        if let Err(err) = &res {
+            self.state.observe_error(err);
+        }
+        Ok(res?)
+    }
+
+    /// Start a batch of method calls, to be sent to the node all at once. See [`Batch`].
+    pub fn batch(&self) -> Batch<'_, T> {
+        Batch::new(self)
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "rpc_subscribe",
+            level = "debug",
+            skip_all,
+            fields(method = subscribe_method)
+        )
+    )]
+    async fn subscribe<'a, N: DeserializeOwned>(
+        &self,
+        subscribe_method: &'a str,
+        params: Option<ParamsSer<'a>>,
+        unsubscribe_method: &'a str,
+    ) -> Result<RpcSubscription<N>, BasicError> {
+        let _permit = self.limiter.acquire().await;
+        let _guard = self.state.begin_request()?;
+        let slot = Arc::new(SubscriptionSlot::new(
+            RpcRequest::from_params(subscribe_method, params),
+            unsubscribe_method,
+            self.recorder.clone(),
+        ));
+        let (client, generation) = self.state.client();
+        if !client.supports_subscriptions() {
+            return Err(BasicError::SubscriptionsUnsupported)
+        }
+        let (res, generation) = match slot.subscribe(&client).await {
+            Err(RpcError::RestartNeeded(reason)) if self.can_reconnect() => {
+                self.state.observe_error(&RpcError::RestartNeeded(reason));
+                self.reconnect(generation).await?;
+                let (client, generation) = self.state.client();
+                if !client.supports_subscriptions() {
+                    return Err(BasicError::SubscriptionsUnsupported)
+                }
+                (slot.subscribe(&client).await, generation)
+            }
+            res => (res, generation),
+        };
+        if let Some(metrics) = &self.metrics {
+            metrics.rpc_call(subscribe_method);
+            if res.is_err() {
+                metrics.rpc_error(subscribe_method);
+            }
+        }
+        if let Err(err) = &res {
+            self.state.observe_error(err);
+        }
+        slot.replace(Ok(res?), generation);
+        self.state.register(&slot);
+        Ok(RpcSubscription::new(slot, self.restorer()))
+    }
+
     /// Fetch a storage key
     pub async fn storage(
         &self,
@@ -254,7 +1484,7 @@ impl<T: Config> Rpc<T> {
         hash: Option<T::Hash>,
     ) -> Result<Option<StorageData>, BasicError> {
         let params = rpc_params![key, hash];
-        let data = self.client.request("state_getStorage", params).await?;
+        let data = self.request("state_getStorage", params).await?;
         Ok(data)
     }
 
@@ -270,7 +1500,22 @@ impl<T: Config> Rpc<T> {
     ) -> Result<Vec<StorageKey>, BasicError> {
         let prefix = prefix.map(|p| p.to_storage_key());
         let params = rpc_params![prefix, count, start_key, hash];
-        let data = self.client.request("state_getKeysPaged", params).await?;
+        let data = self.request("state_getKeysPaged", params).await?;
+        Ok(data)
+    }
+
+    /// Fetch all of the key-value pairs whose keys start with the given prefix.
+    ///
+    /// # Note
+    ///
+    /// This is an unsafe RPC method, which nodes only serve when configured to.
+    pub async fn storage_pairs(
+        &self,
+        prefix: &StorageKey,
+        hash: Option<T::Hash>,
+    ) -> Result<Vec<(StorageKey, StorageData)>, BasicError> {
+        let params = rpc_params![prefix, hash];
+        let data = self.request("state_getPairs", params).await?;
         Ok(data)
     }
 
@@ -282,10 +1527,48 @@ impl<T: Config> Rpc<T> {
         to: Option<T::Hash>,
     ) -> Result<Vec<StorageChangeSet<T::Hash>>, BasicError> {
         let params = rpc_params![keys, from, to];
-        self.client
-            .request("state_queryStorage", params)
-            .await
-            .map_err(Into::into)
+        self.request("state_queryStorage", params).await
+    }
+
+    /// Fetch a key from the child trie under the given (prefixed) child storage key.
+    pub async fn child_storage(
+        &self,
+        child_key: &PrefixedStorageKey,
+        key: &StorageKey,
+        hash: Option<T::Hash>,
+    ) -> Result<Option<StorageData>, BasicError> {
+        let params = rpc_params![child_key, key, hash];
+        let data = self.request("childstate_getStorage", params).await?;
+        Ok(data)
+    }
+
+    /// Returns the keys with prefix from the child trie under the given (prefixed) child
+    /// storage key, with pagination support; see [`Rpc::storage_keys_paged`].
+    pub async fn child_storage_keys_paged(
+        &self,
+        child_key: &PrefixedStorageKey,
+        prefix: Option<StorageKey>,
+        count: u32,
+        start_key: Option<StorageKey>,
+        hash: Option<T::Hash>,
+    ) -> Result<Vec<StorageKey>, BasicError> {
+        let params = rpc_params![child_key, prefix, count, start_key, hash];
+        let data = self.request("childstate_getKeysPaged", params).await?;
+        Ok(data)
+    }
+
+    /// Call a runtime API method with the given SCALE encoded arguments, returning its
+    /// SCALE encoded result.
+    pub async fn state_call(
+        &self,
+        method: &str,
+        call_parameters: &[u8],
+        hash: Option<T::Hash>,
+    ) -> Result<Bytes, BasicError> {
+        let call_parameters = Bytes(call_parameters.to_vec());
+        let params = rpc_params![method, call_parameters, hash];
+        let data = self.request("state_call", params).await?;
+        Ok(data)
     }
 
     /// Query historical storage entries
@@ -295,10 +1578,7 @@ impl<T: Config> Rpc<T> {
         at: Option<T::Hash>,
     ) -> Result<Vec<StorageChangeSet<T::Hash>>, BasicError> {
         let params = rpc_params![keys, at];
-        self.client
-            .request("state_queryStorageAt", params)
-            .await
-            .map_err(Into::into)
+        self.request("state_queryStorageAt", params).await
     }
 
     /// Fetch the genesis hash
@@ -306,16 +1586,28 @@ impl<T: Config> Rpc<T> {
         let block_zero = 0u32;
         let params = rpc_params![block_zero];
         let genesis_hash: Option<T::Hash> =
-            self.client.request("chain_getBlockHash", params).await?;
+            self.request("chain_getBlockHash", params).await?;
         genesis_hash.ok_or_else(|| "Genesis hash not found".into())
     }
 
+    /// Fetch the SCALE encoded metadata, optionally at the given block
+    pub async fn metadata_bytes(&self, at: Option<T::Hash>) -> Result<Bytes, BasicError> {
+        let bytes = self.request("state_getMetadata", rpc_params![at]).await?;
+        Ok(bytes)
+    }
+
     /// Fetch the metadata
     pub async fn metadata(&self) -> Result<Metadata, BasicError> {
-        let bytes: Bytes = self
-            .client
-            .request("state_getMetadata", rpc_params![])
-            .await?;
+        self.fetch_metadata(None).await
+    }
+
+    /// Fetch the metadata of the runtime that was active at the given block
+    pub async fn metadata_at(&self, at: T::Hash) -> Result<Metadata, BasicError> {
+        self.fetch_metadata(Some(at)).await
+    }
+
+    async fn fetch_metadata(&self, at: Option<T::Hash>) -> Result<Metadata, BasicError> {
+        let bytes = self.metadata_bytes(at).await?;
         let meta: RuntimeMetadataPrefixed = Decode::decode(&mut &bytes[..])?;
         let metadata: Metadata = meta.try_into()?;
         Ok(metadata)
@@ -323,25 +1615,22 @@ impl<T: Config> Rpc<T> {
 
     /// Fetch system properties
     pub async fn system_properties(&self) -> Result<SystemProperties, BasicError> {
-        Ok(self
-            .client
-            .request("system_properties", rpc_params![])
-            .await?)
+        Ok(self.request("system_properties", rpc_params![]).await?)
     }
 
     /// Fetch system chain
     pub async fn system_chain(&self) -> Result<String, BasicError> {
-        Ok(self.client.request("system_chain", rpc_params![]).await?)
+        Ok(self.request("system_chain", rpc_params![]).await?)
     }
 
     /// Fetch system name
     pub async fn system_name(&self) -> Result<String, BasicError> {
-        Ok(self.client.request("system_name", rpc_params![]).await?)
+        Ok(self.request("system_name", rpc_params![]).await?)
     }
 
     /// Fetch system version
     pub async fn system_version(&self) -> Result<String, BasicError> {
-        Ok(self.client.request("system_version", rpc_params![]).await?)
+        Ok(self.request("system_version", rpc_params![]).await?)
     }
 
     /// Fetch the current nonce for the given account ID.
@@ -350,7 +1639,6 @@ impl<T: Config> Rpc<T> {
         account: &T::AccountId,
     ) -> Result<T::Index, BasicError> {
         Ok(self
-            .client
             .request("system_accountNextIndex", rpc_params![account])
             .await?)
     }
@@ -361,7 +1649,7 @@ impl<T: Config> Rpc<T> {
         hash: Option<T::Hash>,
     ) -> Result<Option<T::Header>, BasicError> {
         let params = rpc_params![hash];
-        let header = self.client.request("chain_getHeader", params).await?;
+        let header = self.request("chain_getHeader", params).await?;
         Ok(header)
     }
 
@@ -371,14 +1659,13 @@ impl<T: Config> Rpc<T> {
         block_number: Option<BlockNumber>,
     ) -> Result<Option<T::Hash>, BasicError> {
         let params = rpc_params![block_number];
-        let block_hash = self.client.request("chain_getBlockHash", params).await?;
+        let block_hash = self.request("chain_getBlockHash", params).await?;
         Ok(block_hash)
     }
 
     /// Get a block hash of the latest finalized block
     pub async fn finalized_head(&self) -> Result<T::Hash, BasicError> {
         let hash = self
-            .client
             .request("chain_getFinalizedHead", rpc_params![])
             .await?;
         Ok(hash)
@@ -390,7 +1677,7 @@ impl<T: Config> Rpc<T> {
         hash: Option<T::Hash>,
     ) -> Result<Option<ChainBlock<T>>, BasicError> {
         let params = rpc_params![hash];
-        let block = self.client.request("chain_getBlock", params).await?;
+        let block = self.request("chain_getBlock", params).await?;
         Ok(block)
     }
 
@@ -404,7 +1691,7 @@ impl<T: Config> Rpc<T> {
         block_hash: T::Hash,
     ) -> Result<Option<BlockStats>, BasicError> {
         let params = rpc_params![block_hash];
-        let stats = self.client.request("dev_getBlockStats", params).await?;
+        let stats = self.request("dev_getBlockStats", params).await?;
         Ok(stats)
     }
 
@@ -415,27 +1702,104 @@ impl<T: Config> Rpc<T> {
         hash: Option<T::Hash>,
     ) -> Result<ReadProof<T::Hash>, BasicError> {
         let params = rpc_params![keys, hash];
-        let proof = self.client.request("state_getReadProof", params).await?;
+        let proof = self.request("state_getReadProof", params).await?;
         Ok(proof)
     }
 
+    /// Get the proofs of the given cells of a block's data matrix, in the same order as
+    /// the cells. This is an Avail specific RPC method.
+    pub async fn kate_query_proof(
+        &self,
+        cells: &[Cell],
+        block_hash: Option<T::Hash>,
+    ) -> Result<Vec<Proof>, BasicError> {
+        let params = rpc_params![cells, block_hash];
+        let bytes: Vec<u8> = self.request("kate_queryProof", params).await?;
+        Proof::split(&bytes, cells.len())
+    }
+
+    /// Get the length limits of a block, and the dimensions of its data matrix. This is
+    /// an Avail specific RPC method.
+    pub async fn kate_block_length(
+        &self,
+        block_hash: Option<T::Hash>,
+    ) -> Result<BlockLength, BasicError> {
+        let params = rpc_params![block_hash];
+        Ok(self.request("kate_blockLength", params).await?)
+    }
+
+    /// Get a proof that the data submitted by the extrinsic at `transaction_index` in
+    /// the block was included in it. This is an Avail specific RPC method.
+    pub async fn kate_query_data_proof(
+        &self,
+        transaction_index: u32,
+        block_hash: Option<T::Hash>,
+    ) -> Result<DataProof, BasicError> {
+        let params = rpc_params![transaction_index, block_hash];
+        Ok(self.request("kate_queryDataProof", params).await?)
+    }
+
+    /// Fetch a proof that the block with the given number is final, or `None` if it
+    /// has not been finalized yet.
+    pub async fn grandpa_prove_finality(
+        &self,
+        block_number: T::BlockNumber,
+    ) -> Result<Option<FinalityProof<T::Header>>, BasicError> {
+        let params = rpc_params![block_number];
+        let proof: Option<Bytes> = self.request("grandpa_proveFinality", params).await?;
+        proof
+            .map(|bytes| self.decode_limits.decode(&bytes.0))
+            .transpose()
+    }
+
     /// Fetch the runtime version
     pub async fn runtime_version(
         &self,
         at: Option<T::Hash>,
     ) -> Result<RuntimeVersion, BasicError> {
         let params = rpc_params![at];
-        let version = self
-            .client
-            .request("state_getRuntimeVersion", params)
-            .await?;
+        let version = self.request("state_getRuntimeVersion", params).await?;
         Ok(version)
     }
 
+    /// Subscribe to runtime version changes. The current version is sent first, and then
+    /// a new one each time the runtime is upgraded.
+    pub async fn subscribe_runtime_version(
+        &self,
+    ) -> Result<RpcSubscription<RuntimeVersion>, BasicError> {
+        let subscription = self
+            .subscribe(
+                "state_subscribeRuntimeVersion",
+                rpc_params![],
+                "state_unsubscribeRuntimeVersion",
+            )
+            .await?;
+
+        Ok(subscription)
+    }
+
+    /// Subscribe to changes of the given storage keys, or of all storage if `None`. The
+    /// current values of the keys are sent first.
+    pub async fn subscribe_storage(
+        &self,
+        keys: Option<Vec<StorageKey>>,
+    ) -> Result<RpcSubscription<StorageChangeSet<T::Hash>>, BasicError> {
+        let subscription = self
+            .subscribe(
+                "state_subscribeStorage",
+                rpc_params![keys],
+                "state_unsubscribeStorage",
+            )
+            .await?;
+
+        Ok(subscription)
+    }
+
     /// Subscribe to blocks.
-    pub async fn subscribe_blocks(&self) -> Result<Subscription<T::Header>, BasicError> {
+    pub async fn subscribe_blocks(
+        &self,
+    ) -> Result<RpcSubscription<T::Header>, BasicError> {
         let subscription = self
-            .client
             .subscribe(
                 "chain_subscribeNewHeads",
                 rpc_params![],
@@ -449,9 +1813,8 @@ impl<T: Config> Rpc<T> {
     /// Subscribe to finalized blocks.
     pub async fn subscribe_finalized_blocks(
         &self,
-    ) -> Result<Subscription<T::Header>, BasicError> {
+    ) -> Result<RpcSubscription<T::Header>, BasicError> {
         let subscription = self
-            .client
             .subscribe(
                 "chain_subscribeFinalizedHeads",
                 rpc_params![],
@@ -468,23 +1831,79 @@ impl<T: Config> Rpc<T> {
     ) -> Result<T::Hash, BasicError> {
         let bytes: Bytes = extrinsic.encode().into();
         let params = rpc_params![bytes];
-        let xt_hash = self
-            .client
-            .request("author_submitExtrinsic", params)
-            .await?;
+        let xt_hash = self.request("author_submitExtrinsic", params).await?;
         Ok(xt_hash)
     }
 
+    /// Dry run an extrinsic against the state at the given block (or the best block),
+    /// returning the SCALE encoded `ApplyExtrinsicResult`.
+    ///
+    /// This is an unsafe RPC method, and so may not be available on public nodes.
+    pub async fn dry_run(
+        &self,
+        encoded_signed: &[u8],
+        at: Option<T::Hash>,
+    ) -> Result<Bytes, BasicError> {
+        let bytes: Bytes = encoded_signed.to_vec().into();
+        let params = rpc_params![bytes, at];
+        let result_bytes: Bytes = self.request("system_dryRun", params).await?;
+        Ok(result_bytes)
+    }
+
+    /// Get information about the dispatch of an encoded extrinsic, including the fee it
+    /// would pay, according to the state at the given block (or the best block).
+    pub async fn payment_query_info(
+        &self,
+        encoded_signed: &[u8],
+        at: Option<T::Hash>,
+    ) -> Result<RuntimeDispatchInfo, BasicError> {
+        let bytes: Bytes = encoded_signed.to_vec().into();
+        let params = rpc_params![bytes, at];
+        Ok(self.request("payment_queryInfo", params).await?)
+    }
+
+    /// Get a breakdown of the fee that an encoded extrinsic would pay, according to
+    /// the state at the given block (or the best block).
+    pub async fn payment_query_fee_details(
+        &self,
+        encoded_signed: &[u8],
+        at: Option<T::Hash>,
+    ) -> Result<FeeDetails, BasicError> {
+        let bytes: Bytes = encoded_signed.to_vec().into();
+        let params = rpc_params![bytes, at];
+        Ok(self.request("payment_queryFeeDetails", params).await?)
+    }
+
+    /// Fetch the encoded extrinsics which are currently in the transaction pool.
+    pub async fn pending_extrinsics(&self) -> Result<Vec<Bytes>, BasicError> {
+        let extrinsics = self
+            .request("author_pendingExtrinsics", rpc_params![])
+            .await?;
+        Ok(extrinsics)
+    }
+
+    /// Remove the given extrinsics from the transaction pool, along with any that
+    /// depend on them, returning the hashes of all of the removed extrinsics.
+    ///
+    /// This is an unsafe RPC method, and so may not be available on public nodes.
+    pub async fn remove_extrinsic(
+        &self,
+        extrinsics: Vec<ExtrinsicOrHash<T::Hash>>,
+    ) -> Result<Vec<T::Hash>, BasicError> {
+        let params = rpc_params![extrinsics];
+        let removed = self.request("author_removeExtrinsic", params).await?;
+        Ok(removed)
+    }
+
     /// Create and submit an extrinsic and return a subscription to the events triggered.
     pub async fn watch_extrinsic<X: Encode>(
         &self,
         extrinsic: X,
-    ) -> Result<Subscription<SubstrateTransactionStatus<T::Hash, T::Hash>>, BasicError>
+    ) -> Result<RpcSubscription<SubstrateTransactionStatus<T::Hash, T::Hash>>, BasicError>
     {
         let bytes: Bytes = extrinsic.encode().into();
         let params = rpc_params![bytes];
         let subscription = self
-            .client
             .subscribe(
                 "author_submitAndWatchExtrinsic",
                 params,
@@ -502,16 +1921,13 @@ impl<T: Config> Rpc<T> {
         public: Bytes,
     ) -> Result<(), BasicError> {
         let params = rpc_params![key_type, suri, public];
-        self.client.request("author_insertKey", params).await?;
+        self.request("author_insertKey", params).await?;
         Ok(())
     }
 
     /// Generate new session keys and returns the corresponding public keys.
     pub async fn rotate_keys(&self) -> Result<Bytes, BasicError> {
-        Ok(self
-            .client
-            .request("author_rotateKeys", rpc_params![])
-            .await?)
+        Ok(self.request("author_rotateKeys", rpc_params![]).await?)
     }
 
     /// Checks if the keystore has private keys for the given session public keys.
@@ -524,7 +1940,7 @@ impl<T: Config> Rpc<T> {
         session_keys: Bytes,
     ) -> Result<bool, BasicError> {
         let params = rpc_params![session_keys];
-        Ok(self.client.request("author_hasSessionKeys", params).await?)
+        Ok(self.request("author_hasSessionKeys", params).await?)
     }
 
     /// Checks if the keystore has private keys for the given public key and key type.
@@ -536,23 +1952,64 @@ impl<T: Config> Rpc<T> {
         key_type: String,
     ) -> Result<bool, BasicError> {
         let params = rpc_params![public_key, key_type];
-        Ok(self.client.request("author_hasKey", params).await?)
+        Ok(self.request("author_hasKey", params).await?)
     }
 }
 
 /// Build WS RPC client from URL
 pub async fn ws_client(url: &str) -> Result<RpcClient, RpcError> {
-    let (sender, receiver) = ws_transport(url).await?;
+    ws_client_with_limits(url, &DecodeLimits::default()).await
+}
+
+/// Build WS RPC client from URL, refusing messages larger than the given limits allow.
+#[cfg(feature = "native")]
+pub(crate) async fn ws_client_with_limits(
+    url: &str,
+    limits: &DecodeLimits,
+) -> Result<RpcClient, RpcError> {
+    let (sender, receiver) = ws_transport(url, limits.max_response_size).await?;
+    Ok(RpcClientBuilder::default()
+        .max_notifs_per_subscription(4096)
+        .build_with_tokio(sender, receiver))
+}
+
+/// Build WS RPC client from URL with the browser's WebSocket, which can't limit the
+/// size of the messages it receives, so `max_response_size` is not enforced.
+#[cfg(all(feature = "web", not(feature = "native")))]
+pub(crate) async fn ws_client_with_limits(
+    url: &str,
+    _limits: &DecodeLimits,
+) -> Result<RpcClient, RpcError> {
+    let (sender, receiver) = web::connect(url)
+        .await
+        .map_err(|e| RpcError::Transport(e.into()))?;
     Ok(RpcClientBuilder::default()
         .max_notifs_per_subscription(4096)
-        .build(sender, receiver))
+        .build_with_wasm(sender, receiver))
+}
+
+/// Build HTTP RPC client from URL, refusing responses larger than the given limits
+/// allow.
+#[cfg(feature = "native")]
+pub(crate) fn http_client_with_limits(
+    url: &str,
+    limits: &DecodeLimits,
+) -> Result<HttpClient, RpcError> {
+    HttpClientBuilder::default()
+        .max_request_body_size(limits.max_response_size)
+        .build(url)
 }
 
-async fn ws_transport(url: &str) -> Result<(WsSender, WsReceiver), RpcError> {
+#[cfg(feature = "native")]
+async fn ws_transport(
+    url: &str,
+    max_response_size: u32,
+) -> Result<(WsSender, WsReceiver), RpcError> {
     let url: Uri = url
         .parse()
         .map_err(|e: InvalidUri| RpcError::Transport(e.into()))?;
     WsTransportClientBuilder::default()
+        .max_request_body_size(max_response_size)
         .build(url)
         .await
         .map_err(|e| RpcError::Transport(e.into()))
@@ -561,6 +2018,29 @@ async fn ws_transport(url: &str) -> Result<(WsSender, WsReceiver), RpcError> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::DefaultConfig;
+
+    #[async_std::test]
+    async fn shutting_down_ends_subscriptions_and_closes_the_client() {
+        let mock = MockRpcClient::new();
+        mock.push_subscription(
+            "chain_subscribeNewHeads",
+            vec![serde_json::json!({}), serde_json::json!({})],
+        );
+        let rpc = Rpc::<DefaultConfig>::new_mock(mock);
+        let mut blocks = rpc.subscribe_blocks().await.unwrap();
+
+        rpc.shutdown(Duration::from_secs(1)).await.unwrap();
+
+        // The notifications not yet received are never delivered.
+        assert!(blocks.next().await.is_none());
+        assert!(rpc.is_shut_down());
+        assert!(matches!(rpc.state.client().0, Transport::Closed));
+        assert!(matches!(
+            rpc.subscribe_blocks().await,
+            Err(BasicError::ShutDown)
+        ));
+    }
 
     #[test]
     fn test_deser_runtime_version() {
@@ -587,4 +2067,198 @@ mod test {
             }
         );
     }
+
+    #[test]
+    fn fee_responses_deserialize() {
+        let info: RuntimeDispatchInfo = serde_json::from_str(
+            r#"{ "weight": 100, "class": "normal", "partialFee": "123456789012345678901" }"#,
+        )
+        .expect("deserializing failed");
+        assert_eq!(
+            info,
+            RuntimeDispatchInfo {
+                weight: Weight::from_ref_time(100),
+                class: DispatchClass::Normal,
+                partial_fee: 123456789012345678901,
+            }
+        );
+
+        // Runtimes with two-dimensional weights return both parts.
+        let info: RuntimeDispatchInfo = serde_json::from_str(
+            r#"{ "weight": { "ref_time": 100, "proof_size": 20 }, "class": "operational", "partialFee": 7 }"#,
+        )
+        .expect("deserializing failed");
+        assert_eq!(
+            info,
+            RuntimeDispatchInfo {
+                weight: Weight::from_parts(100, 20),
+                class: DispatchClass::Operational,
+                partial_fee: 7,
+            }
+        );
+
+        let details: FeeDetails = serde_json::from_str(
+            r#"{ "inclusionFee": { "baseFee": 1, "lenFee": "0x10", "adjustedWeightFee": "3" } }"#,
+        )
+        .expect("deserializing failed");
+        assert_eq!(details.tip, 0);
+        assert_eq!(details.final_fee(), 20);
+
+        let unsigned: FeeDetails =
+            serde_json::from_str(r#"{ "inclusionFee": null }"#).unwrap();
+        assert_eq!(unsigned.final_fee(), 0);
+    }
+
+    #[test]
+    fn kate_responses_deserialize() {
+        let length: BlockLength = serde_json::from_str(
+            r#"{
+            "max": { "normal": 1, "operational": 2, "mandatory": 3 },
+            "cols": 256,
+            "rows": 128,
+            "chunkSize": 32
+        }"#,
+        )
+        .expect("deserializing failed");
+        assert_eq!(length.max.operational, 2);
+        assert_eq!(
+            (length.cols, length.rows, length.chunk_size),
+            (256, 128, 32)
+        );
+
+        let hash = |n: u8| format!("\"0x{}\"", hex::encode([n; 32]));
+        let proof: DataProof = serde_json::from_str(&format!(
+            r#"{{
+            "root": {},
+            "proof": [{}, {}],
+            "numberOfLeaves": 4,
+            "leafIndex": 1,
+            "leaf": {}
+        }}"#,
+            hash(1),
+            hash(2),
+            hash(3),
+            hash(4)
+        ))
+        .expect("deserializing failed");
+        assert_eq!(proof.root, H256::repeat_byte(1));
+        assert_eq!(
+            proof.proof,
+            vec![H256::repeat_byte(2), H256::repeat_byte(3)]
+        );
+        assert_eq!((proof.number_of_leaves, proof.leaf_index), (4, 1));
+        assert_eq!(proof.leaf, H256::repeat_byte(4));
+    }
+
+    #[test]
+    fn kate_proofs_are_split_per_cell() {
+        let bytes: Vec<u8> = (0..160).map(|n| n as u8).collect();
+        let proofs = Proof::split(&bytes, 2).unwrap();
+        assert_eq!(proofs.len(), 2);
+        assert_eq!(proofs[0].proof[..], bytes[..48]);
+        assert_eq!(proofs[0].data[..], bytes[48..80]);
+        assert_eq!(proofs[1].proof[..], bytes[80..128]);
+        assert_eq!(proofs[1].data[..], bytes[128..]);
+
+        assert!(Proof::split(&bytes, 3).is_err());
+    }
+
+    fn combine(a: H256, b: H256) -> H256 {
+        let (left, right) = if a < b { (a, b) } else { (b, a) };
+        H256(keccak_256(&[left.as_bytes(), right.as_bytes()].concat()))
+    }
+
+    #[test]
+    fn data_proofs_are_verified_against_the_data_root() {
+        let leaves: Vec<H256> = (0u8..3).map(|n| H256(keccak_256(&[n]))).collect();
+        let hashed: Vec<H256> = leaves
+            .iter()
+            .map(|l| H256(keccak_256(l.as_bytes())))
+            .collect();
+        // The odd leaf out is promoted to the next level unchanged.
+        let node = combine(hashed[0], hashed[1]);
+        let root = combine(node, hashed[2]);
+
+        let proof = DataProof {
+            root,
+            proof: vec![hashed[1], hashed[2]],
+            number_of_leaves: 3,
+            leaf_index: 0,
+            leaf: leaves[0],
+        };
+        assert_eq!(proof.verify(&root).unwrap(), leaves[0]);
+
+        let last = DataProof {
+            proof: vec![node],
+            leaf_index: 2,
+            leaf: leaves[2],
+            ..proof.clone()
+        };
+        assert_eq!(last.verify(&root).unwrap(), leaves[2]);
+
+        // A different leaf, root or index must all be rejected.
+        let wrong_leaf = DataProof {
+            leaf: leaves[1],
+            ..proof.clone()
+        };
+        assert!(wrong_leaf.verify(&root).is_err());
+        assert!(proof.verify(&H256::repeat_byte(1)).is_err());
+        let wrong_index = DataProof {
+            leaf_index: 3,
+            ..proof
+        };
+        assert!(wrong_index.verify(&root).is_err());
+    }
+
+    #[async_std::test]
+    async fn subscriptions_are_refused_over_http() {
+        // Nothing is sent until a request is made, so nothing needs to be listening.
+        let client =
+            http_client_with_limits("http://127.0.0.1:1", &DecodeLimits::default())
+                .unwrap();
+        let rpc = Rpc::<crate::DefaultConfig>::new_http(client);
+        assert!(rpc.is_http());
+        assert!(rpc.client().is_none());
+        assert!(matches!(
+            rpc.subscribe_finalized_blocks().await,
+            Err(BasicError::SubscriptionsUnsupported)
+        ));
+    }
+
+    #[test]
+    fn connection_events_start_with_current_state_and_skip_repeats() {
+        let mut state = ConnectionState::new();
+        let mut events = state.listen();
+
+        let disconnected = |reason: &str| {
+            ConnectionEvent::Disconnected {
+                reason: reason.into(),
+            }
+        };
+        state.notify(disconnected("first"));
+        state.notify(disconnected("second"));
+        state.notify(ConnectionEvent::Reconnecting { attempt: 1 });
+        state.notify(ConnectionEvent::Resubscribed);
+
+        let mut received = Vec::new();
+        while let Ok(Some(event)) = events.0.try_next() {
+            received.push(event);
+        }
+        assert_eq!(
+            received,
+            vec![
+                ConnectionEvent::Connected,
+                disconnected("first"),
+                ConnectionEvent::Reconnecting { attempt: 1 },
+                ConnectionEvent::Resubscribed,
+            ]
+        );
+
+        // Late subscribers learn about the current state first.
+        let mut late = state.listen();
+        assert_eq!(
+            late.0.try_next().unwrap(),
+            Some(ConnectionEvent::Resubscribed)
+        );
+    }
 }
@@ -0,0 +1,225 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Sending several RPC method calls to the node at once, as a single JSON-RPC batch.
+
+use crate::{
+    error::BasicError,
+    rpc::{
+        to_json_value,
+        BlockNumber,
+        DeserializeOwned,
+        JsonValue,
+        Rpc,
+    },
+    rpc_middleware::RpcRequest,
+    Config,
+};
+use serde::Serialize;
+use sp_core::storage::{
+    StorageData,
+    StorageKey,
+};
+use std::marker::PhantomData;
+
+/// Collects method calls to send to the node as a single JSON-RPC batch, saving a
+/// round trip per call. Obtained from [`Rpc::batch()`].
+///
+/// Each call returns a [`BatchItem`], which picks its result out of the
+/// [`BatchResponse`] once the batch has been sent. Batches are sent straight to the
+/// node, bypassing any [`crate::RpcMiddleware`].
+///
+/// ```no_run
+/// # #[async_std::main]
+/// # async fn main() -> Result<(), subxt::BasicError> {
+/// # let client = subxt::ClientBuilder::new()
+/// #     .build::<subxt::DefaultConfig>()
+/// #     .await?;
+/// let mut batch = client.rpc().batch();
+/// let hashes: Vec<_> = (0u32..100).map(|n| batch.block_hash(Some(n.into()))).collect();
+/// let response = batch.send().await?;
+/// for hash in hashes {
+///     println!("{:?}", response.get(hash)?);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct Batch<'a, T: Config> {
+    rpc: &'a Rpc<T>,
+    calls: Vec<RpcRequest>,
+}
+
+impl<'a, T: Config> Batch<'a, T> {
+    pub(crate) fn new(rpc: &'a Rpc<T>) -> Self {
+        Self {
+            rpc,
+            calls: Vec::new(),
+        }
+    }
+
+    /// Add a call to `chain_getBlockHash`; see [`Rpc::block_hash()`].
+    pub fn block_hash(
+        &mut self,
+        block_number: Option<BlockNumber>,
+    ) -> BatchItem<Option<T::Hash>> {
+        self.call("chain_getBlockHash", vec![to_json(&block_number)])
+    }
+
+    /// Add a call to `chain_getHeader`; see [`Rpc::header()`].
+    pub fn header(&mut self, hash: Option<T::Hash>) -> BatchItem<Option<T::Header>> {
+        self.call("chain_getHeader", vec![to_json(&hash)])
+    }
+
+    /// Add a call to `state_getStorage`; see [`Rpc::storage()`].
+    pub fn storage(
+        &mut self,
+        key: &StorageKey,
+        hash: Option<T::Hash>,
+    ) -> BatchItem<Option<StorageData>> {
+        self.call("state_getStorage", vec![to_json(key), to_json(&hash)])
+    }
+
+    /// Add a call to any method, whose result will be deserialized as `R`.
+    pub fn call<R: DeserializeOwned>(
+        &mut self,
+        method: &str,
+        params: Vec<JsonValue>,
+    ) -> BatchItem<R> {
+        self.calls.push(RpcRequest::new(method, params));
+        BatchItem {
+            index: self.calls.len() - 1,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The number of calls in the batch.
+    pub fn len(&self) -> usize {
+        self.calls.len()
+    }
+
+    /// Are there no calls in the batch?
+    pub fn is_empty(&self) -> bool {
+        self.calls.is_empty()
+    }
+
+    /// Send the calls to the node. An error is returned if any of them fails.
+    pub async fn send(self) -> Result<BatchResponse, BasicError> {
+        if self.calls.is_empty() {
+            return Ok(BatchResponse { values: Vec::new() })
+        }
+        let values = self.rpc.batch_request(&self.calls).await?;
+        if values.len() != self.calls.len() {
+            return Err(BasicError::Other(format!(
+                "Sent a batch of {} calls, but got {} responses",
+                self.calls.len(),
+                values.len()
+            )))
+        }
+        Ok(BatchResponse { values })
+    }
+}
+
+/// Our parameters are all plain data, which always serializes.
+fn to_json<V: Serialize>(value: &V) -> JsonValue {
+    to_json_value(value).expect("RPC parameters serialize to JSON")
+}
+
+/// A call in a [`Batch`], whose result of type `R` can be taken from the
+/// [`BatchResponse`].
+pub struct BatchItem<R> {
+    index: usize,
+    _marker: PhantomData<fn() -> R>,
+}
+
+impl<R> Clone for BatchItem<R> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<R> Copy for BatchItem<R> {}
+
+impl<R> std::fmt::Debug for BatchItem<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BatchItem")
+            .field("index", &self.index)
+            .finish()
+    }
+}
+
+/// The results of the calls in a [`Batch`], in the order they were added.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BatchResponse {
+    values: Vec<JsonValue>,
+}
+
+impl BatchResponse {
+    /// The result of the given call.
+    pub fn get<R: DeserializeOwned>(&self, item: BatchItem<R>) -> Result<R, BasicError> {
+        let value = self.values.get(item.index).ok_or_else(|| {
+            BasicError::Other(format!("No response to call {} of the batch", item.index))
+        })?;
+        Ok(serde_json::from_value(value.clone())?)
+    }
+
+    /// The raw results of every call.
+    pub fn values(&self) -> &[JsonValue] {
+        &self.values
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        decode_limits::DecodeLimits,
+        rpc::http_client_with_limits,
+        DefaultConfig,
+    };
+    use sp_core::H256;
+
+    #[test]
+    fn calls_are_collected_and_results_taken_by_item() {
+        let client =
+            http_client_with_limits("http://127.0.0.1:1", &DecodeLimits::default())
+                .unwrap();
+        let rpc = Rpc::<DefaultConfig>::new_http(client);
+        let mut batch = rpc.batch();
+        let hash = batch.block_hash(Some(7u32.into()));
+        let storage = batch.storage(&StorageKey(vec![1, 2]), Some(H256::zero()));
+        assert_eq!(batch.len(), 2);
+        assert_eq!(
+            batch.calls[0],
+            RpcRequest::new("chain_getBlockHash", vec![7.into()])
+        );
+        assert_eq!(
+            batch.calls[1],
+            RpcRequest::new(
+                "state_getStorage",
+                vec!["0x0102".into(), to_json(&H256::zero())]
+            )
+        );
+
+        let response = BatchResponse {
+            values: vec![to_json(&H256::repeat_byte(1)), JsonValue::Null],
+        };
+        assert_eq!(response.get(hash).unwrap(), Some(H256::repeat_byte(1)));
+        assert_eq!(response.get(storage).unwrap(), None);
+
+        let short = BatchResponse { values: Vec::new() };
+        assert!(short.get(hash).is_err());
+    }
+}
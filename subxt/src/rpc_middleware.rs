@@ -0,0 +1,607 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Middleware which sits between [`crate::rpc::Rpc`] and the underlying RPC client.
+
+use crate::rpc::{
+    JsonValue,
+    RpcError,
+    Transport,
+};
+use futures::future::{
+    self,
+    Either,
+};
+use futures_timer::Delay;
+use jsonrpsee::types::ParamsSer;
+use std::{
+    collections::{
+        BTreeMap,
+        HashMap,
+    },
+    sync::{
+        Arc,
+        Mutex,
+    },
+    time::Duration,
+};
+
+/// The method calls which only read data, and so can safely be made again if they fail.
+const IDEMPOTENT_METHODS: &[&str] = &[
+    "chain_getBlock",
+    "chain_getBlockHash",
+    "chain_getFinalizedHead",
+    "chain_getHeader",
+    "childstate_getKeysPaged",
+    "childstate_getStorage",
+    "state_call",
+    "state_getKeysPaged",
+    "state_getMetadata",
+    "state_getReadProof",
+    "state_getRuntimeVersion",
+    "state_getStorage",
+    "state_queryStorageAt",
+    "system_chain",
+    "system_health",
+    "system_properties",
+];
+
+/// The method calls whose result is fixed once given the hash of a block, along with
+/// the position of the block hash among their parameters.
+const IMMUTABLE_AT_BLOCK_METHODS: &[(&str, usize)] = &[
+    ("chain_getBlock", 0),
+    ("chain_getHeader", 0),
+    ("childstate_getKeysPaged", 4),
+    ("childstate_getStorage", 2),
+    ("state_call", 2),
+    ("state_getKeysPaged", 3),
+    ("state_getMetadata", 0),
+    ("state_getReadProof", 1),
+    ("state_getRuntimeVersion", 0),
+    ("state_getStorage", 1),
+    ("state_queryStorageAt", 1),
+];
+
+/// A JSON-RPC method call, as seen by [`RpcMiddleware`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct RpcRequest {
+    /// The name of the method being called.
+    pub method: String,
+    /// The parameters to the call: an array of positional parameters, an object of
+    /// named parameters, or `null` if there are none.
+    pub params: JsonValue,
+}
+
+impl RpcRequest {
+    /// Create a call to `method` with the given positional parameters.
+    pub fn new<M: Into<String>>(method: M, params: Vec<JsonValue>) -> Self {
+        Self {
+            method: method.into(),
+            params: JsonValue::Array(params),
+        }
+    }
+
+    pub(crate) fn from_params(method: &str, params: Option<ParamsSer<'_>>) -> Self {
+        let params = match params {
+            None => JsonValue::Null,
+            Some(ParamsSer::Array(params)) => JsonValue::Array(params),
+            Some(ParamsSer::ArrayRef(params)) => JsonValue::Array(params.to_vec()),
+            Some(ParamsSer::Map(params)) => {
+                JsonValue::Object(
+                    params
+                        .into_iter()
+                        .map(|(name, value)| (name.to_owned(), value))
+                        .collect(),
+                )
+            }
+        };
+        Self {
+            method: method.to_owned(),
+            params,
+        }
+    }
+
+    pub(crate) fn to_params(&self) -> Option<ParamsSer<'_>> {
+        match &self.params {
+            JsonValue::Null => None,
+            JsonValue::Array(params) => Some(ParamsSer::ArrayRef(params)),
+            JsonValue::Object(params) => {
+                Some(ParamsSer::Map(
+                    params
+                        .iter()
+                        .map(|(name, value)| (name.as_str(), value.clone()))
+                        .collect(),
+                ))
+            }
+            other => Some(ParamsSer::Array(vec![other.clone()])),
+        }
+    }
+}
+
+/// A layer wrapped around the method calls made by [`crate::rpc::Rpc`].
+///
+/// Each middleware is handed the request along with the [`Next`] layer. It can inspect or
+/// rewrite the request before passing it on, inspect or rewrite the response on its way
+/// back, or answer the request itself without calling [`Next::run`] at all (for caching,
+/// or to inject faults in tests).
+///
+/// Middleware is applied to method calls only; subscriptions are passed straight through
+/// to the underlying client.
+#[async_trait::async_trait]
+pub trait RpcMiddleware: Send + Sync + 'static {
+    /// Handle a method call, usually by forwarding it to `next`.
+    async fn request(
+        &self,
+        request: RpcRequest,
+        next: Next<'_>,
+    ) -> Result<JsonValue, RpcError>;
+}
+
+/// The remaining middleware layers, followed by the RPC client itself.
+#[derive(Clone, Copy)]
+pub struct Next<'a> {
+    client: &'a Transport,
+    layers: &'a [Arc<dyn RpcMiddleware>],
+}
+
+impl<'a> Next<'a> {
+    pub(crate) fn new(
+        client: &'a Transport,
+        layers: &'a [Arc<dyn RpcMiddleware>],
+    ) -> Self {
+        Self { client, layers }
+    }
+
+    /// Hand the request to the next layer, and return its response.
+    pub async fn run(self, request: RpcRequest) -> Result<JsonValue, RpcError> {
+        match self.layers.split_first() {
+            Some((layer, layers)) => {
+                let next = Next {
+                    client: self.client,
+                    layers,
+                };
+                layer.request(request, next).await
+            }
+            None => {
+                self.client
+                    .request(&request.method, request.to_params())
+                    .await
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for Next<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Next")
+            .field("layers", &self.layers.len())
+            .finish()
+    }
+}
+
+/// How to go about retrying method calls which fail.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// How many times to retry a call before handing back the error.
+    pub max_retries: u32,
+    /// How long to wait before the first retry. The wait doubles before each
+    /// subsequent retry.
+    pub initial_delay: Duration,
+    /// The longest to wait between retries.
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// How long to wait before the given retry, counting from 1.
+    fn delay(&self, retry: u32) -> Duration {
+        let factor = 1u32
+            .checked_shl(retry.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        self.initial_delay
+            .checked_mul(factor)
+            .map_or(self.max_delay, |delay| delay.min(self.max_delay))
+    }
+}
+
+/// Middleware which gives up on method calls that take too long, and retries calls
+/// that only read data (such as `state_getStorage` and `chain_getHeader`) when they
+/// time out or fail to reach the node.
+///
+/// Errors returned by the node itself are handed back straight away, since trying
+/// again won't change its answer. Neither is a lost connection retried here; that's
+/// down to the reconnect policy (see [`crate::ClientBuilder::set_reconnect_policy`]).
+#[derive(Clone, Debug, Default)]
+pub struct RetryMiddleware {
+    timeout: Option<Duration>,
+    policy: Option<RetryPolicy>,
+}
+
+impl RetryMiddleware {
+    /// Create middleware which applies the given timeout to every call, and retries
+    /// the calls which can be according to `policy`. Either can be left out.
+    pub fn new(timeout: Option<Duration>, policy: Option<RetryPolicy>) -> Self {
+        Self { timeout, policy }
+    }
+
+    async fn attempt(
+        &self,
+        request: RpcRequest,
+        next: Next<'_>,
+    ) -> Result<JsonValue, RpcError> {
+        let timeout = match self.timeout {
+            Some(timeout) => timeout,
+            None => return next.run(request).await,
+        };
+        let response = next.run(request);
+        futures::pin_mut!(response);
+        match future::select(response, Delay::new(timeout)).await {
+            Either::Left((response, _)) => response,
+            Either::Right(_) => Err(RpcError::RequestTimeout),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl RpcMiddleware for RetryMiddleware {
+    async fn request(
+        &self,
+        request: RpcRequest,
+        next: Next<'_>,
+    ) -> Result<JsonValue, RpcError> {
+        let policy = match self.policy {
+            Some(policy) if IDEMPOTENT_METHODS.contains(&request.method.as_str()) => {
+                policy
+            }
+            _ => return self.attempt(request, next).await,
+        };
+        let mut retry = 0;
+        loop {
+            let attempt = self.attempt(request.clone(), next);
+            #[cfg(feature = "tracing")]
+            let attempt = tracing::Instrument::instrument(
+                attempt,
+                tracing::debug_span!(
+                    "rpc_attempt",
+                    method = %request.method,
+                    attempt = retry + 1
+                ),
+            );
+            match attempt.await {
+                Err(RpcError::RequestTimeout) | Err(RpcError::Transport(_))
+                    if retry < policy.max_retries =>
+                {
+                    retry += 1;
+                    log::debug!(
+                        "Retrying {} (retry {} of {})",
+                        request.method,
+                        retry,
+                        policy.max_retries
+                    );
+                    Delay::new(policy.delay(retry)).await;
+                }
+                res => return res,
+            }
+        }
+    }
+}
+
+/// Middleware which remembers the results of method calls made at a given block hash,
+/// such as fetching a header or block by its hash, or storage at a block, and answers
+/// them again from memory. These never change, since a block hash identifies the
+/// contents of the block and the state after it.
+///
+/// Calls made at the latest block (without a block hash) aren't cached, and neither
+/// are `null` results, since the block may just not be known to the node yet. Once the
+/// cache is full, the least recently used results are forgotten first.
+#[derive(Debug)]
+pub struct CacheMiddleware {
+    cache: Mutex<LruCache>,
+}
+
+impl CacheMiddleware {
+    /// Create middleware which remembers up to `capacity` results.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            cache: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+
+    /// The key to cache the result of the request under, if it can be cached.
+    fn cache_key(request: &RpcRequest) -> Option<(String, String)> {
+        let (_, at) = IMMUTABLE_AT_BLOCK_METHODS
+            .iter()
+            .find(|(method, _)| *method == request.method)?;
+        match request.params.as_array()?.get(*at)? {
+            JsonValue::String(_) => {
+                Some((request.method.clone(), request.params.to_string()))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl RpcMiddleware for CacheMiddleware {
+    async fn request(
+        &self,
+        request: RpcRequest,
+        next: Next<'_>,
+    ) -> Result<JsonValue, RpcError> {
+        let key = match Self::cache_key(&request) {
+            Some(key) => key,
+            None => return next.run(request).await,
+        };
+        if let Some(value) = self.cache.lock().expect("cache lock poisoned").get(&key) {
+            return Ok(value)
+        }
+        let value = next.run(request).await?;
+        if !value.is_null() {
+            self.cache
+                .lock()
+                .expect("cache lock poisoned")
+                .insert(key, value.clone());
+        }
+        Ok(value)
+    }
+}
+
+/// Results of method calls, keyed by method and parameters, which forgets the least
+/// recently used ones once full.
+#[derive(Debug)]
+struct LruCache {
+    capacity: usize,
+    // The results, along with when they were last used.
+    entries: HashMap<(String, String), (JsonValue, u64)>,
+    // The keys of the results, by when they were last used.
+    by_use: BTreeMap<u64, (String, String)>,
+    clock: u64,
+}
+
+impl LruCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            entries: HashMap::new(),
+            by_use: BTreeMap::new(),
+            clock: 0,
+        }
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    fn get(&mut self, key: &(String, String)) -> Option<JsonValue> {
+        let now = self.tick();
+        let (value, last_used) = self.entries.get_mut(key)?;
+        let key = self
+            .by_use
+            .remove(last_used)
+            .expect("entries are in by_use; qed");
+        *last_used = now;
+        self.by_use.insert(now, key);
+        Some(value.clone())
+    }
+
+    fn insert(&mut self, key: (String, String), value: JsonValue) {
+        let now = self.tick();
+        if let Some((_, last_used)) = self.entries.insert(key.clone(), (value, now)) {
+            self.by_use.remove(&last_used);
+        }
+        self.by_use.insert(now, key);
+        while self.entries.len() > self.capacity {
+            let oldest = *self
+                .by_use
+                .keys()
+                .next()
+                .expect("by_use has an entry for each entry; qed");
+            if let Some(key) = self.by_use.remove(&oldest) {
+                self.entries.remove(&key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        decode_limits::DecodeLimits,
+        rpc::{
+            http_client_with_limits,
+            rpc_params,
+            MockRpcClient,
+        },
+    };
+    use std::sync::atomic::{
+        AtomicU32,
+        Ordering,
+    };
+
+    /// Answers calls itself, failing the given number of times first.
+    struct Flaky {
+        failures: u32,
+        slow: bool,
+        calls: AtomicU32,
+    }
+
+    #[async_trait::async_trait]
+    impl RpcMiddleware for Flaky {
+        async fn request(
+            &self,
+            _request: RpcRequest,
+            _next: Next<'_>,
+        ) -> Result<JsonValue, RpcError> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            if call >= self.failures {
+                return Ok(JsonValue::Bool(true))
+            }
+            if self.slow {
+                Delay::new(Duration::from_secs(10)).await;
+            }
+            let unreachable =
+                std::io::Error::new(std::io::ErrorKind::Other, "unreachable");
+            Err(RpcError::Transport(unreachable.into()))
+        }
+    }
+
+    async fn call(
+        method: &str,
+        retry: RetryMiddleware,
+        flaky: Arc<Flaky>,
+    ) -> Result<JsonValue, RpcError> {
+        // Never called, since the middleware answers every request itself.
+        let client =
+            http_client_with_limits("http://127.0.0.1:1", &DecodeLimits::default())
+                .unwrap();
+        let transport = Transport::Http(Arc::new(client));
+        let layers: Vec<Arc<dyn RpcMiddleware>> = vec![Arc::new(retry), flaky];
+        Next::new(&transport, &layers)
+            .run(RpcRequest::new(method, Vec::new()))
+            .await
+    }
+
+    fn flaky(failures: u32, slow: bool) -> Arc<Flaky> {
+        Arc::new(Flaky {
+            failures,
+            slow,
+            calls: AtomicU32::new(0),
+        })
+    }
+
+    fn policy(max_retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_retries,
+            initial_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(1),
+        }
+    }
+
+    #[async_std::test]
+    async fn only_idempotent_calls_are_retried() {
+        let retry = RetryMiddleware::new(None, Some(policy(2)));
+
+        let storage = flaky(2, false);
+        assert!(call("state_getStorage", retry.clone(), storage.clone())
+            .await
+            .is_ok());
+        assert_eq!(storage.calls.load(Ordering::SeqCst), 3);
+
+        let too_flaky = flaky(3, false);
+        assert!(call("chain_getHeader", retry.clone(), too_flaky.clone())
+            .await
+            .is_err());
+        assert_eq!(too_flaky.calls.load(Ordering::SeqCst), 3);
+
+        let submit = flaky(1, false);
+        assert!(call("author_submitExtrinsic", retry, submit.clone())
+            .await
+            .is_err());
+        assert_eq!(submit.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[async_std::test]
+    async fn slow_calls_time_out_and_are_retried() {
+        let retry =
+            RetryMiddleware::new(Some(Duration::from_millis(10)), Some(policy(1)));
+        let slow = flaky(1, true);
+        assert!(call("state_getStorage", retry, slow.clone()).await.is_ok());
+        assert_eq!(slow.calls.load(Ordering::SeqCst), 2);
+
+        let no_retries = RetryMiddleware::new(Some(Duration::from_millis(10)), None);
+        assert!(matches!(
+            call("state_getStorage", no_retries, flaky(1, true)).await,
+            Err(RpcError::RequestTimeout)
+        ));
+    }
+
+    #[async_std::test]
+    async fn calls_at_a_block_are_answered_from_the_cache() {
+        let mock = MockRpcClient::new();
+        mock.set_response("chain_getHeader", "header");
+        mock.set_response("state_getStorage", JsonValue::Null);
+        let transport = &Transport::Mock(mock.clone());
+        let layers: &[Arc<dyn RpcMiddleware>] = &[Arc::new(CacheMiddleware::new(1))];
+        let call = |method: &str, params: Vec<JsonValue>| {
+            Next::new(transport, layers).run(RpcRequest::new(method, params))
+        };
+
+        call("chain_getHeader", vec!["0x01".into()]).await.unwrap();
+        call("chain_getHeader", vec!["0x01".into()]).await.unwrap();
+        assert_eq!(mock.requests().len(), 1);
+
+        // At the latest block.
+        call("chain_getHeader", vec![]).await.unwrap();
+        call("chain_getHeader", vec![]).await.unwrap();
+        assert_eq!(mock.requests().len(), 3);
+
+        // Not known yet.
+        call("state_getStorage", vec!["0xaa".into(), "0x01".into()])
+            .await
+            .unwrap();
+        call("state_getStorage", vec!["0xaa".into(), "0x01".into()])
+            .await
+            .unwrap();
+        assert_eq!(mock.requests().len(), 5);
+
+        // Evicts the first header, since the cache only holds one result.
+        call("chain_getHeader", vec!["0x02".into()]).await.unwrap();
+        call("chain_getHeader", vec!["0x01".into()]).await.unwrap();
+        assert_eq!(mock.requests().len(), 7);
+    }
+
+    #[test]
+    fn the_least_recently_used_results_are_evicted() {
+        let key = |n: u8| ("method".to_owned(), n.to_string());
+        let mut cache = LruCache::new(2);
+        cache.insert(key(1), 1.into());
+        cache.insert(key(2), 2.into());
+        assert_eq!(cache.get(&key(1)), Some(1.into()));
+        cache.insert(key(3), 3.into());
+        assert_eq!(cache.get(&key(2)), None);
+        assert_eq!(cache.get(&key(1)), Some(1.into()));
+        assert_eq!(cache.get(&key(3)), Some(3.into()));
+    }
+
+    #[test]
+    fn params_survive_the_round_trip() {
+        let request =
+            RpcRequest::from_params("state_getStorage", rpc_params![1u8, "two"]);
+        assert_eq!(
+            request,
+            RpcRequest::new("state_getStorage", vec![1u8.into(), "two".into()])
+        );
+        assert!(matches!(
+            request.to_params(),
+            Some(ParamsSer::ArrayRef(params)) if params.len() == 2
+        ));
+
+        let request = RpcRequest::from_params("system_health", None);
+        assert_eq!(request.params, JsonValue::Null);
+        assert!(request.to_params().is_none());
+    }
+}
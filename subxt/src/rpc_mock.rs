@@ -0,0 +1,373 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A stand-in for a node, which answers requests with canned responses, so that code
+//! using a [`crate::Client`] can be tested without running one.
+
+use crate::{
+    reconnect::Notifications,
+    rpc::{
+        to_json_value,
+        JsonValue,
+        RpcError,
+        RuntimeVersion,
+        SubstrateTransactionStatus,
+    },
+    rpc_middleware::RpcRequest,
+};
+use codec::Encode;
+use frame_metadata::RuntimeMetadataPrefixed;
+use futures::StreamExt;
+use serde::Serialize;
+use sp_core::{
+    storage::{
+        StorageData,
+        StorageKey,
+    },
+    Bytes,
+};
+use std::{
+    collections::{
+        HashMap,
+        VecDeque,
+    },
+    sync::{
+        Arc,
+        Mutex,
+    },
+};
+
+/// Answers requests with canned responses instead of sending them to a node. Pass it to
+/// [`crate::ClientBuilder::set_mock_client`] or [`crate::rpc::Rpc::new_mock`].
+///
+/// Clones share their responses, so responses can be added after the client has been
+/// built. A [`crate::Client`] needs at least the metadata, genesis hash and runtime
+/// version to be built:
+///
+/// ```no_run
+/// # async fn run(metadata: frame_metadata::RuntimeMetadataPrefixed, version: subxt::rpc::RuntimeVersion) {
+/// use subxt::{ClientBuilder, DefaultConfig, MockRpcClient};
+///
+/// let mock = MockRpcClient::new();
+/// mock.set_metadata(&metadata);
+/// mock.set_genesis_hash(subxt::sp_core::H256::zero());
+/// mock.set_runtime_version(&version);
+/// let client = ClientBuilder::new()
+///     .set_mock_client(mock.clone())
+///     .build::<DefaultConfig>()
+///     .await
+///     .unwrap();
+/// # }
+/// ```
+///
+/// Requests with no response to answer them fail with [`RpcError::Custom`].
+#[derive(Clone, Debug, Default)]
+pub struct MockRpcClient {
+    state: Arc<Mutex<MockState>>,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    /// Responses to answer the next matching request only, in the order they were added.
+    once: Vec<MockResponse>,
+    /// Responses to answer every matching request. Later ones take precedence.
+    always: Vec<MockResponse>,
+    /// The notifications to send to each of the next subscriptions to a method.
+    subscriptions: HashMap<String, VecDeque<Vec<JsonValue>>>,
+    /// Every method call received, in order.
+    requests: Vec<RpcRequest>,
+}
+
+#[derive(Debug)]
+struct MockResponse {
+    method: String,
+    /// Answers only requests whose positional parameters start with these.
+    params: Vec<JsonValue>,
+    result: Result<JsonValue, String>,
+}
+
+impl MockResponse {
+    fn matches(&self, request: &RpcRequest) -> bool {
+        if self.method != request.method {
+            return false
+        }
+        match &request.params {
+            JsonValue::Array(params) => params.starts_with(&self.params),
+            _ => self.params.is_empty(),
+        }
+    }
+}
+
+impl MockRpcClient {
+    /// Create a new [`MockRpcClient`] without any responses.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answer every call of `method` with `result`.
+    pub fn set_response<R: Serialize>(&self, method: &str, result: R) {
+        self.set_response_for(method, Vec::new(), result)
+    }
+
+    /// Answer every call of `method` whose positional parameters start with `params`
+    /// with `result`. If several responses match a call, the one added last is used.
+    pub fn set_response_for<R: Serialize>(
+        &self,
+        method: &str,
+        params: Vec<JsonValue>,
+        result: R,
+    ) {
+        let response = MockResponse {
+            method: method.into(),
+            params,
+            result: Ok(to_json(result)),
+        };
+        self.lock().always.push(response);
+    }
+
+    /// Answer only the next call of `method` with `result`. This takes precedence over
+    /// the responses added with [`MockRpcClient::set_response`].
+    pub fn push_response<R: Serialize>(&self, method: &str, result: R) {
+        self.push_response_for(method, Vec::new(), result)
+    }
+
+    /// Answer only the next call of `method` whose positional parameters start with
+    /// `params` with `result`.
+    pub fn push_response_for<R: Serialize>(
+        &self,
+        method: &str,
+        params: Vec<JsonValue>,
+        result: R,
+    ) {
+        self.push_result(method, params, Ok(to_json(result)))
+    }
+
+    /// Fail the next call of `method` with the given message.
+    pub fn push_error<E: Into<String>>(&self, method: &str, message: E) {
+        self.push_error_for(method, Vec::new(), message)
+    }
+
+    /// Fail the next call of `method` whose positional parameters start with `params`
+    /// with the given message.
+    pub fn push_error_for<E: Into<String>>(
+        &self,
+        method: &str,
+        params: Vec<JsonValue>,
+        message: E,
+    ) {
+        self.push_result(method, params, Err(message.into()))
+    }
+
+    fn push_result(
+        &self,
+        method: &str,
+        params: Vec<JsonValue>,
+        result: Result<JsonValue, String>,
+    ) {
+        let response = MockResponse {
+            method: method.into(),
+            params,
+            result,
+        };
+        self.lock().once.push(response);
+    }
+
+    /// Send the given notifications to the next subscription made with `method`, after
+    /// which the subscription ends.
+    pub fn push_subscription<N: Serialize>(&self, method: &str, notifications: Vec<N>) {
+        let notifications = notifications.into_iter().map(to_json).collect();
+        self.lock()
+            .subscriptions
+            .entry(method.into())
+            .or_default()
+            .push_back(notifications);
+    }
+
+    /// Answer requests for the metadata with the given metadata.
+    pub fn set_metadata(&self, metadata: &RuntimeMetadataPrefixed) {
+        self.set_response("state_getMetadata", Bytes(metadata.encode()))
+    }
+
+    /// Answer requests for the hash of the genesis block with the given hash.
+    pub fn set_genesis_hash<H: Serialize>(&self, hash: H) {
+        self.set_response_for("chain_getBlockHash", vec![0u32.into()], hash)
+    }
+
+    /// Answer requests for the runtime version with the given version.
+    pub fn set_runtime_version(&self, version: &RuntimeVersion) {
+        self.set_response("state_getRuntimeVersion", version)
+    }
+
+    /// Answer requests for the header of the block with the given hash.
+    pub fn set_header<H: Serialize, Header: Serialize>(&self, hash: H, header: &Header) {
+        self.set_response_for("chain_getHeader", vec![to_json(hash)], header)
+    }
+
+    /// Answer requests for the value at the given storage key, at any block.
+    pub fn set_storage(&self, key: &StorageKey, value: Option<Vec<u8>>) {
+        self.set_response_for(
+            "state_getStorage",
+            vec![to_json(key)],
+            value.map(StorageData),
+        )
+    }
+
+    /// Report the given progress of the next transaction submitted and watched.
+    pub fn push_transaction_statuses<Hash: Serialize, BlockHash: Serialize>(
+        &self,
+        statuses: Vec<SubstrateTransactionStatus<Hash, BlockHash>>,
+    ) {
+        self.push_subscription("author_submitAndWatchExtrinsic", statuses)
+    }
+
+    /// Every method call received so far, in the order they were received.
+    pub fn requests(&self) -> Vec<RpcRequest> {
+        self.lock().requests.clone()
+    }
+
+    pub(crate) fn request(&self, request: RpcRequest) -> Result<JsonValue, RpcError> {
+        let mut state = self.lock();
+        let result = match state.once.iter().position(|r| r.matches(&request)) {
+            Some(index) => state.once.remove(index).result,
+            None => {
+                state
+                    .always
+                    .iter()
+                    .rev()
+                    .find(|r| r.matches(&request))
+                    .map(|r| r.result.clone())
+                    .unwrap_or_else(|| {
+                        Err(format!("No mock response for {}", request.method))
+                    })
+            }
+        };
+        state.requests.push(request);
+        result.map_err(RpcError::Custom)
+    }
+
+    pub(crate) fn subscribe(
+        &self,
+        request: &RpcRequest,
+    ) -> Result<Notifications, RpcError> {
+        let notifications = self
+            .lock()
+            .subscriptions
+            .get_mut(&request.method)
+            .and_then(VecDeque::pop_front)
+            .ok_or_else(|| {
+                RpcError::Custom(format!("No mock subscription for {}", request.method))
+            })?;
+        Ok(futures::stream::iter(notifications.into_iter().map(Ok)).boxed())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().expect("mock client lock poisoned")
+    }
+}
+
+fn to_json<R: Serialize>(value: R) -> JsonValue {
+    to_json_value(value).expect("mock responses should serialize to JSON")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn responses_are_matched_by_method_and_params() {
+        let mock = MockRpcClient::new();
+        mock.set_response("system_chain", "Avail");
+        mock.set_response_for("chain_getBlockHash", vec![1u32.into()], "0x01");
+        mock.push_response("system_chain", "Other");
+
+        let request = |method: &str, params| RpcRequest::new(method, params);
+        assert_eq!(
+            mock.request(request("system_chain", vec![])).unwrap(),
+            JsonValue::from("Other")
+        );
+        assert_eq!(
+            mock.request(request("system_chain", vec![])).unwrap(),
+            JsonValue::from("Avail")
+        );
+        assert_eq!(
+            mock.request(request("chain_getBlockHash", vec![1u32.into()]))
+                .unwrap(),
+            JsonValue::from("0x01")
+        );
+        assert!(mock
+            .request(request("chain_getBlockHash", vec![2u32.into()]))
+            .is_err());
+        assert_eq!(mock.requests().len(), 4);
+    }
+
+    #[test]
+    fn subscriptions_send_their_notifications_and_end() {
+        let mock = MockRpcClient::new();
+        mock.push_subscription("chain_subscribeNewHeads", vec![1u32, 2]);
+
+        let request = RpcRequest::new("chain_subscribeNewHeads", vec![]);
+        let notifications: Vec<_> =
+            futures::executor::block_on(mock.subscribe(&request).unwrap().collect());
+        assert_eq!(
+            notifications
+                .into_iter()
+                .map(Result::unwrap)
+                .collect::<Vec<_>>(),
+            vec![JsonValue::from(1u32), JsonValue::from(2u32)]
+        );
+        // Each subscription is only answered once.
+        assert!(mock.subscribe(&request).is_err());
+    }
+
+    #[async_std::test]
+    async fn a_client_can_be_built_without_a_node() {
+        use frame_metadata::v14::{
+            ExtrinsicMetadata,
+            RuntimeMetadataLastVersion,
+        };
+        use scale_info::meta_type;
+
+        let extrinsic = ExtrinsicMetadata {
+            ty: meta_type::<()>(),
+            version: 0,
+            signed_extensions: vec![],
+        };
+        let v14 = RuntimeMetadataLastVersion::new(vec![], extrinsic, meta_type::<()>());
+        let mock = MockRpcClient::new();
+        mock.set_metadata(&v14.into());
+        mock.set_genesis_hash(sp_core::H256::repeat_byte(1));
+        mock.set_runtime_version(&RuntimeVersion {
+            spec_version: 1,
+            transaction_version: 1,
+            other: Default::default(),
+        });
+        let key = StorageKey(vec![1, 2, 3]);
+        mock.set_storage(&key, Some(vec![4]));
+
+        let client = crate::ClientBuilder::new()
+            .set_mock_client(mock.clone())
+            .build::<crate::DefaultConfig>()
+            .await
+            .unwrap();
+        assert_eq!(client.genesis(), &sp_core::H256::repeat_byte(1));
+        let value = client.rpc().storage(&key, None).await.unwrap();
+        assert_eq!(value, Some(StorageData(vec![4])));
+        assert!(mock
+            .requests()
+            .iter()
+            .any(|request| request.method == "state_getStorage"));
+    }
+}
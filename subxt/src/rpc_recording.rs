@@ -0,0 +1,239 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Recording the traffic between a client and a node, to be replayed later by a
+//! [`MockRpcClient`] for testing without a node.
+
+use crate::{
+    error::BasicError,
+    reconnect::Notifications,
+    rpc::{
+        JsonValue,
+        MockRpcClient,
+        RpcError,
+    },
+    rpc_middleware::RpcRequest,
+};
+use futures::StreamExt;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{
+        BufRead,
+        BufReader,
+        BufWriter,
+        Write,
+    },
+    path::Path,
+    sync::{
+        atomic::{
+            AtomicU64,
+            Ordering,
+        },
+        Arc,
+        Mutex,
+    },
+};
+
+/// A line of a recording.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum Entry {
+    /// A method call, and its result or the error it failed with.
+    Call {
+        method: String,
+        params: JsonValue,
+        result: Result<JsonValue, String>,
+    },
+    /// A subscription being made.
+    Subscribe {
+        id: u64,
+        method: String,
+        params: JsonValue,
+    },
+    /// A notification sent to the subscription with the given id.
+    Notification { id: u64, value: JsonValue },
+}
+
+/// Writes the method calls made by a client, along with their results, and the
+/// notifications of its subscriptions, to a file as they happen. Set it up with
+/// [`crate::ClientBuilder::set_recording`].
+///
+/// The file holds a JSON object per line, and can be replayed with
+/// [`MockRpcClient::from_recording`].
+pub struct RpcRecorder {
+    writer: Mutex<BufWriter<File>>,
+    next_subscription: AtomicU64,
+}
+
+impl std::fmt::Debug for RpcRecorder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RpcRecorder").finish()
+    }
+}
+
+impl RpcRecorder {
+    /// Record to the file at the given path, replacing it if it exists.
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self, BasicError> {
+        Ok(Self {
+            writer: Mutex::new(BufWriter::new(File::create(path)?)),
+            next_subscription: AtomicU64::new(0),
+        })
+    }
+
+    pub(crate) fn record_call(
+        &self,
+        request: &RpcRequest,
+        result: &Result<JsonValue, RpcError>,
+    ) {
+        self.write(&Entry::Call {
+            method: request.method.clone(),
+            params: request.params.clone(),
+            result: result
+                .as_ref()
+                .map(Clone::clone)
+                .map_err(ToString::to_string),
+        })
+    }
+
+    /// Record the subscription, and each notification it receives.
+    pub(crate) fn record_subscription(
+        self: &Arc<Self>,
+        request: &RpcRequest,
+        notifications: Notifications,
+    ) -> Notifications {
+        let id = self.next_subscription.fetch_add(1, Ordering::SeqCst);
+        self.write(&Entry::Subscribe {
+            id,
+            method: request.method.clone(),
+            params: request.params.clone(),
+        });
+        let recorder = self.clone();
+        notifications
+            .inspect(move |notification| {
+                if let Ok(value) = notification {
+                    recorder.write(&Entry::Notification {
+                        id,
+                        value: value.clone(),
+                    })
+                }
+            })
+            .boxed()
+    }
+
+    fn write(&self, entry: &Entry) {
+        let mut writer = self.writer.lock().expect("recorder lock poisoned");
+        // A failure to record shouldn't fail the request being recorded.
+        let res = serde_json::to_writer(&mut *writer, entry)
+            .map_err(std::io::Error::from)
+            .and_then(|_| writer.write_all(b"\n"))
+            .and_then(|_| writer.flush());
+        if let Err(e) = res {
+            log::warn!("Failed to record RPC traffic: {}", e);
+        }
+    }
+}
+
+impl MockRpcClient {
+    /// Create a [`MockRpcClient`] which replays the traffic recorded by an
+    /// [`RpcRecorder`]: each recorded call is answered once, with the result it
+    /// originally had, and each recorded subscription receives the notifications it
+    /// originally did.
+    pub fn from_recording<P: AsRef<Path>>(path: P) -> Result<Self, BasicError> {
+        let mock = MockRpcClient::new();
+        let mut subscriptions: Vec<(u64, String, Vec<JsonValue>)> = Vec::new();
+        let mut index = HashMap::new();
+        for line in BufReader::new(File::open(path)?).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue
+            }
+            match serde_json::from_str(&line)? {
+                Entry::Call {
+                    method,
+                    params,
+                    result,
+                } => {
+                    let params = match params {
+                        JsonValue::Array(params) => params,
+                        _ => Vec::new(),
+                    };
+                    match result {
+                        Ok(result) => mock.push_response_for(&method, params, result),
+                        Err(message) => mock.push_error_for(&method, params, message),
+                    }
+                }
+                Entry::Subscribe { id, method, .. } => {
+                    index.insert(id, subscriptions.len());
+                    subscriptions.push((id, method, Vec::new()));
+                }
+                Entry::Notification { id, value } => {
+                    let subscription = index.get(&id).ok_or_else(|| {
+                        BasicError::Other(format!(
+                            "Recorded notification for unknown subscription {}",
+                            id
+                        ))
+                    })?;
+                    subscriptions[*subscription].2.push(value);
+                }
+            }
+        }
+        for (_, method, notifications) in subscriptions {
+            mock.push_subscription(&method, notifications);
+        }
+        Ok(mock)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::stream;
+
+    #[async_std::test]
+    async fn recorded_traffic_is_replayed() {
+        let dir = tempdir::TempDir::new("subxt-recording").unwrap();
+        let path = dir.path().join("session.jsonl");
+
+        let recorder = Arc::new(RpcRecorder::create(&path).unwrap());
+        let call = RpcRequest::new("chain_getBlockHash", vec![1u32.into()]);
+        recorder.record_call(&call, &Ok("0x01".into()));
+        recorder.record_call(&call, &Err(RpcError::Custom("gone".into())));
+        let subscribe = RpcRequest::new("chain_subscribeNewHeads", vec![]);
+        let notifications = stream::iter(vec![Ok(1u32.into()), Ok(2u32.into())]).boxed();
+        let recorded: Vec<_> = recorder
+            .record_subscription(&subscribe, notifications)
+            .collect()
+            .await;
+        assert_eq!(recorded.len(), 2);
+        drop(recorder);
+
+        let mock = MockRpcClient::from_recording(&path).unwrap();
+        assert_eq!(mock.request(call.clone()).unwrap(), JsonValue::from("0x01"));
+        assert!(mock.request(call).is_err());
+        let replayed: Vec<_> = mock
+            .subscribe(&subscribe)
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(replayed, vec![JsonValue::from(1u32), JsonValue::from(2u32)]);
+    }
+}
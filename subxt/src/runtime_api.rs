@@ -0,0 +1,70 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Calling runtime APIs, such as `TransactionPaymentApi_query_info`, through the
+//! `state_call` RPC method.
+//!
+//! The metadata this version of subxt works with doesn't describe the runtime APIs, so
+//! the method name, argument and return types have to be given by the caller.
+
+use crate::{
+    error::BasicError,
+    rpc::Rpc,
+    Config,
+};
+use codec::{
+    Decode,
+    Encode,
+};
+
+/// Client for calling runtime APIs.
+pub struct RuntimeApiClient<'a, T: Config> {
+    rpc: &'a Rpc<T>,
+}
+
+impl<'a, T: Config> RuntimeApiClient<'a, T> {
+    /// Create a new [`RuntimeApiClient`].
+    pub fn new(rpc: &'a Rpc<T>) -> Self {
+        Self { rpc }
+    }
+
+    /// Call a runtime API method, such as `"AccountNonceApi_account_nonce"`, at the given
+    /// block (or the latest one), and decode its result.
+    ///
+    /// The arguments of a runtime API are SCALE encoded one after another, which is
+    /// exactly how a tuple of them encodes; pass `()` for methods without arguments.
+    pub async fn call<A: Encode, R: Decode>(
+        &self,
+        method: &str,
+        args: A,
+        at: Option<T::Hash>,
+    ) -> Result<R, BasicError> {
+        let bytes = self.call_raw(method, &args.encode(), at).await?;
+        self.rpc.decode_limits().decode(&bytes)
+    }
+
+    /// Call a runtime API method with already encoded arguments, returning the encoded
+    /// result.
+    pub async fn call_raw(
+        &self,
+        method: &str,
+        args: &[u8],
+        at: Option<T::Hash>,
+    ) -> Result<Vec<u8>, BasicError> {
+        let bytes = self.rpc.state_call(method, args, at).await?;
+        Ok(bytes.0)
+    }
+}
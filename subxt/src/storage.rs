@@ -20,15 +20,38 @@ use codec::{
     Decode,
     Encode,
 };
-use sp_core::storage::{
-    StorageChangeSet,
-    StorageData,
-    StorageKey,
+use futures::{
+    stream,
+    Stream,
+    StreamExt,
+    TryStreamExt,
 };
+use sp_core::{
+    storage::{
+        ChildInfo,
+        PrefixedStorageKey,
+        StorageChangeSet,
+        StorageData,
+        StorageKey,
+    },
+    Bytes,
+    Hasher,
+};
+use sp_runtime::traits::Header;
 pub use sp_runtime::traits::SignedExtension;
-use std::marker::PhantomData;
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+    sync::Arc,
+};
 
 use crate::{
+    decode_limits::DecodeLimits,
+    dynamic::{
+        self,
+        DynamicError,
+        Value,
+    },
     error::BasicError,
     metadata::{
         Metadata,
@@ -38,6 +61,15 @@ use crate::{
     Config,
     StorageHasher,
 };
+use frame_metadata::{
+    StorageEntryMetadata,
+    StorageEntryModifier,
+    StorageEntryType,
+};
+use scale_info::{
+    form::PortableForm,
+    TypeDef,
+};
 
 /// Storage entry trait.
 pub trait StorageEntry {
@@ -47,6 +79,10 @@ pub trait StorageEntry {
     const STORAGE: &'static str;
     /// Type of the storage entry value.
     type Value: Decode;
+    /// The hash of the entry in the metadata that it was generated from, for checking
+    /// that it's unchanged with [`Metadata::validate_storage()`]. `None` for entries
+    /// which weren't generated by the [`crate::subxt`] macro.
+    const METADATA_HASH: Option<[u8; 32]> = None;
     /// Get the key data for the storage.
     fn key(&self) -> StorageEntryKey;
 }
@@ -68,6 +104,12 @@ impl StorageKeyPrefix {
     }
 }
 
+impl From<StorageKey> for StorageKeyPrefix {
+    fn from(key: StorageKey) -> Self {
+        Self(key.0)
+    }
+}
+
 /// Storage key.
 pub enum StorageEntryKey {
     /// Plain key.
@@ -130,33 +172,57 @@ impl StorageMapKey {
     }
 }
 
+/// The changes made to some storage entries by a block, as returned by
+/// [`StorageClient::query_changes`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct StorageChanges<Hash, V> {
+    /// The hash of the block which made the changes.
+    pub block: Hash,
+    /// The entries which changed, along with their new values, or `None` for entries
+    /// which were removed.
+    pub changes: Vec<(StorageKey, Option<V>)>,
+}
+
+/// How many pages of values [`StorageClient::stream`] fetches ahead by default.
+pub const DEFAULT_STORAGE_PREFETCH: usize = 4;
+
 /// Client for querying runtime storage.
 pub struct StorageClient<'a, T: Config> {
     rpc: &'a Rpc<T>,
-    metadata: &'a Metadata,
+    metadata: Arc<Metadata>,
     iter_page_size: u32,
+    prefetch: usize,
 }
 
 impl<'a, T: Config> Clone for StorageClient<'a, T> {
     fn clone(&self) -> Self {
         Self {
             rpc: self.rpc,
-            metadata: self.metadata,
+            metadata: self.metadata.clone(),
             iter_page_size: self.iter_page_size,
+            prefetch: self.prefetch,
         }
     }
 }
 
 impl<'a, T: Config> StorageClient<'a, T> {
     /// Create a new [`StorageClient`]
-    pub fn new(rpc: &'a Rpc<T>, metadata: &'a Metadata, iter_page_size: u32) -> Self {
+    pub fn new(rpc: &'a Rpc<T>, metadata: Arc<Metadata>, iter_page_size: u32) -> Self {
         Self {
             rpc,
             metadata,
             iter_page_size,
+            prefetch: DEFAULT_STORAGE_PREFETCH,
         }
     }
 
+    /// Set how many pages of values [`StorageClient::stream`] may fetch ahead of the
+    /// one being consumed.
+    pub fn set_prefetch(mut self, pages: usize) -> Self {
+        self.prefetch = pages.max(1);
+        self
+    }
+
     /// Fetch the value under an unhashed storage key
     pub async fn fetch_unhashed<V: Decode>(
         &self,
@@ -164,12 +230,19 @@ impl<'a, T: Config> StorageClient<'a, T> {
         hash: Option<T::Hash>,
     ) -> Result<Option<V>, BasicError> {
         if let Some(data) = self.rpc.storage(&key, hash).await? {
-            Ok(Some(Decode::decode(&mut &data.0[..])?))
+            Ok(Some(self.rpc.decode_limits().decode(&data.0)?))
         } else {
             Ok(None)
         }
     }
 
+    /// Check that the storage entry hasn't changed in the current runtime since it was
+    /// generated, so that its keys and value will be encoded correctly. See
+    /// [`Metadata::validate_storage()`].
+    pub fn validate<F: StorageEntry>(&self) -> Result<(), MetadataError> {
+        self.metadata.validate_storage::<F>()
+    }
+
     /// Fetch the raw encoded value under the raw storage key.
     pub async fn fetch_raw(
         &self,
@@ -207,6 +280,37 @@ impl<'a, T: Config> StorageClient<'a, T> {
         }
     }
 
+    /// Fetch several entries at once with a single `state_queryStorageAt` call, which
+    /// is much quicker than fetching them one at a time. The values are returned in the
+    /// same order as the entries, with `None` for those which aren't in storage.
+    pub async fn fetch_many<F: StorageEntry>(
+        &self,
+        stores: &[F],
+        hash: Option<T::Hash>,
+    ) -> Result<Vec<Option<F::Value>>, BasicError> {
+        if stores.is_empty() {
+            return Ok(Vec::new())
+        }
+        let keys: Vec<StorageKey> = stores
+            .iter()
+            .map(|store| store.key().final_key(StorageKeyPrefix::new::<F>()))
+            .collect();
+        let mut values: HashMap<StorageKey, Option<StorageData>> = HashMap::new();
+        for change_set in self.rpc.query_storage_at(&keys, hash).await? {
+            values.extend(change_set.changes);
+        }
+        keys.iter()
+            .map(|key| {
+                match values.get(key) {
+                    Some(Some(data)) => {
+                        Ok(Some(self.rpc.decode_limits().decode(&data.0)?))
+                    }
+                    _ => Ok(None),
+                }
+            })
+            .collect()
+    }
+
     /// Query historical storage entries
     pub async fn query_storage(
         &self,
@@ -217,6 +321,52 @@ impl<'a, T: Config> StorageClient<'a, T> {
         self.rpc.query_storage(keys, from, to).await
     }
 
+    /// The changes made to the given entries by each block from `from` up to `to` (or
+    /// the best block if `None`), decoded, via `state_queryStorage`. The first item
+    /// holds the values of the entries at `from`; blocks which changed none of them
+    /// are left out.
+    ///
+    /// # Note
+    ///
+    /// Nodes only answer this for blocks whose state they still have, so an archive
+    /// node is needed for anything but recent blocks.
+    pub async fn query_changes<F: StorageEntry>(
+        &self,
+        stores: &[F],
+        from: T::Hash,
+        to: Option<T::Hash>,
+    ) -> Result<Vec<StorageChanges<T::Hash, F::Value>>, BasicError> {
+        let keys = stores
+            .iter()
+            .map(|store| store.key().final_key(StorageKeyPrefix::new::<F>()))
+            .collect();
+        self.rpc
+            .query_storage(keys, from, to)
+            .await?
+            .into_iter()
+            .map(|change_set| decode_changes(self.rpc.decode_limits(), change_set))
+            .collect()
+    }
+
+    /// Subscribe to changes of the given entries, via `state_subscribeStorage`. The
+    /// node first reports their current values, and then the changes made by each new
+    /// block which changes any of them.
+    pub async fn subscribe<F: StorageEntry>(
+        &self,
+        stores: &[F],
+    ) -> Result<
+        impl Stream<Item = Result<StorageChanges<T::Hash, F::Value>, BasicError>>,
+        BasicError,
+    > {
+        let keys = stores
+            .iter()
+            .map(|store| store.key().final_key(StorageKeyPrefix::new::<F>()))
+            .collect();
+        let limits = *self.rpc.decode_limits();
+        let subscription = self.rpc.subscribe_storage(Some(keys)).await?;
+        Ok(subscription.map(move |change_set| decode_changes(&limits, change_set?)))
+    }
+
     /// Fetch up to `count` keys for a storage map in lexicographic order.
     ///
     /// Supports pagination by passing a value to `start_key`.
@@ -234,6 +384,485 @@ impl<'a, T: Config> StorageClient<'a, T> {
         Ok(keys)
     }
 
+    /// Fetch all of the raw key-value pairs whose keys start with the given prefix.
+    ///
+    /// The pairs are requested all at once via `state_getPairs` where possible. Nodes
+    /// refuse this when they don't serve unsafe RPC methods, or when the response would
+    /// be too large; the pairs are then fetched page by page instead.
+    pub async fn fetch_pairs_raw(
+        &self,
+        prefix: StorageKey,
+        hash: Option<T::Hash>,
+    ) -> Result<Vec<(StorageKey, StorageData)>, BasicError> {
+        let hash = if let Some(hash) = hash {
+            hash
+        } else {
+            self.rpc
+                .block_hash(None)
+                .await?
+                .expect("didn't pass a block number; qed")
+        };
+        match self.rpc.storage_pairs(&prefix, Some(hash)).await {
+            Err(BasicError::Rpc(e)) => {
+                log::debug!(
+                    "state_getPairs failed ({}); fetching storage pairs by page",
+                    e
+                );
+            }
+            result => return result,
+        }
+        self.stream(prefix, Some(hash)).try_collect().await
+    }
+
+    /// Stream all of the raw key-value pairs whose keys start with the given prefix.
+    ///
+    /// Keys are requested a page at a time via `state_getKeysPaged`, and the values of
+    /// each page via `state_queryStorageAt`. Up to [`StorageClient::set_prefetch`]
+    /// pages are in flight at once, so that the next pages are being fetched while
+    /// earlier ones are consumed. All pairs are read at the same block, which is the
+    /// latest one when no hash is given.
+    pub fn stream(
+        &self,
+        prefix: StorageKey,
+        hash: Option<T::Hash>,
+    ) -> impl Stream<Item = Result<(StorageKey, StorageData), BasicError>> + 'a {
+        self.stream_range(prefix, hash, None, None)
+    }
+
+    /// Like [`StorageClient::stream`], but splits the keys into `shards` ranges, which
+    /// are scanned concurrently. The pairs of the different ranges are interleaved, in
+    /// whichever order they arrive.
+    ///
+    /// The ranges split the keys by the first byte after the prefix. For storage maps,
+    /// this is the first byte of the hashed map key, so with a hasher such as
+    /// `Blake2_128Concat` or `Twox64Concat` the entries are spread evenly across the
+    /// ranges. Each range fetches up to [`StorageClient::set_prefetch`] pages ahead, so
+    /// up to `shards` times that many pages may be in flight at once.
+    pub fn par_iter(
+        &self,
+        prefix: StorageKey,
+        shards: usize,
+        hash: Option<T::Hash>,
+    ) -> impl Stream<Item = Result<(StorageKey, StorageData), BasicError>> + 'a {
+        let client = self.clone();
+        let bounds = shard_bounds(shards);
+        // Resolve the block first, so that every range is read at the same one.
+        stream::once(async move {
+            let hash = match hash {
+                Some(hash) => hash,
+                None => {
+                    client
+                        .rpc
+                        .block_hash(None)
+                        .await?
+                        .expect("didn't pass a block number; qed")
+                }
+            };
+            let ranges = bounds.into_iter().map(|(start, end)| {
+                let start_key = (start > 0).then(|| {
+                    let mut key = prefix.0.clone();
+                    key.push(start);
+                    StorageKey(key)
+                });
+                Box::pin(client.stream_range(prefix.clone(), Some(hash), start_key, end))
+            });
+            Ok::<_, BasicError>(stream::select_all(ranges))
+        })
+        .try_flatten()
+    }
+
+    /// Stream the pairs whose keys start with `prefix`, from after `start_key` up to
+    /// the first key whose byte after the prefix is `end` or more.
+    fn stream_range(
+        &self,
+        prefix: StorageKey,
+        hash: Option<T::Hash>,
+        start_key: Option<StorageKey>,
+        end: Option<u8>,
+    ) -> impl Stream<Item = Result<(StorageKey, StorageData), BasicError>> + 'a {
+        let rpc = self.rpc;
+        let page_size = self.iter_page_size;
+        let prefix_len = prefix.0.len();
+        let pages = stream::try_unfold(
+            (hash, start_key, false),
+            move |(hash, start_key, done): (
+                Option<T::Hash>,
+                Option<StorageKey>,
+                bool,
+            )| {
+                let prefix = prefix.clone();
+                async move {
+                    if done {
+                        return Ok(None)
+                    }
+                    let hash = match hash {
+                        Some(hash) => hash,
+                        None => {
+                            rpc.block_hash(None)
+                                .await?
+                                .expect("didn't pass a block number; qed")
+                        }
+                    };
+                    let mut keys = rpc
+                        .storage_keys_paged(
+                            Some(prefix.into()),
+                            page_size,
+                            start_key,
+                            Some(hash),
+                        )
+                        .await?;
+                    if keys.is_empty() {
+                        return Ok(None)
+                    }
+                    let mut done = (keys.len() as u32) < page_size;
+                    if let Some(end) = end {
+                        if let Some(past_end) = keys.iter().position(|key| {
+                            key.0.get(prefix_len).map_or(false, |byte| *byte >= end)
+                        }) {
+                            keys.truncate(past_end);
+                            done = true;
+                        }
+                        if keys.is_empty() {
+                            return Ok(None)
+                        }
+                    }
+                    let start_key = keys.last().cloned();
+                    Ok::<_, BasicError>(Some((
+                        (hash, keys),
+                        (Some(hash), start_key, done),
+                    )))
+                }
+            },
+        );
+        pages
+            .map_ok(move |(hash, keys)| {
+                async move {
+                    let pairs = rpc
+                        .query_storage_at(&keys, Some(hash))
+                        .await?
+                        .into_iter()
+                        .flat_map(|change_set| change_set.changes)
+                        .filter_map(|(key, data)| {
+                            data.map(|data| Ok::<_, BasicError>((key, data)))
+                        })
+                        .collect::<Vec<_>>();
+                    Ok::<_, BasicError>(stream::iter(pairs))
+                }
+            })
+            .try_buffered(self.prefetch)
+            .try_flatten()
+    }
+
+    /// Stream and decode all of the key-value pairs of a storage map. See
+    /// [`StorageClient::stream`].
+    pub fn stream_pairs<F: StorageEntry>(
+        &self,
+        hash: Option<T::Hash>,
+    ) -> impl Stream<Item = Result<(StorageKey, F::Value), BasicError>> + 'a {
+        let rpc = self.rpc;
+        let prefix = StorageKeyPrefix::new::<F>().to_storage_key();
+        self.stream(prefix, hash).and_then(move |(key, data)| {
+            futures::future::ready(
+                rpc.decode_limits()
+                    .decode(&data.0)
+                    .map(|value| (key, value)),
+            )
+        })
+    }
+
+    /// Fetch the values of the given keys along with a proof of them, which is checked
+    /// against the state root of the block. Values are `None` if the proof shows there
+    /// is no such entry.
+    ///
+    /// The header of the block is fetched too, and must hash to the block hash. This
+    /// means that the values can be trusted as much as the block hash itself, even
+    /// when the node serving them isn't.
+    pub async fn read_proof(
+        &self,
+        keys: &[StorageKey],
+        at: Option<T::Hash>,
+    ) -> Result<Vec<(StorageKey, Option<StorageData>)>, BasicError> {
+        let at = if let Some(hash) = at {
+            hash
+        } else {
+            self.rpc
+                .block_hash(None)
+                .await?
+                .expect("didn't pass a block number; qed")
+        };
+        let header = self.rpc.header(Some(at)).await?.ok_or_else(|| {
+            BasicError::Other(format!("Block header not found for {:?}", at))
+        })?;
+        if header.hash() != at {
+            return Err(BasicError::InvalidStorageProof(format!(
+                "header returned for block {:?} hashes to {:?}",
+                at,
+                header.hash()
+            )))
+        }
+        let proof = self.rpc.read_proof(keys.to_vec(), Some(at)).await?;
+        if proof.at != at {
+            return Err(BasicError::InvalidStorageProof(format!(
+                "proof is for block {:?} instead of {:?}",
+                proof.at, at
+            )))
+        }
+        let values =
+            verify_read_proof::<T::Hashing>(header.state_root(), &proof.proof, keys)?;
+        Ok(keys.iter().cloned().zip(values).collect())
+    }
+
+    /// Fetch a [`StorageEntry`] along with a proof of it; see
+    /// [`StorageClient::read_proof`].
+    pub async fn fetch_verified<F: StorageEntry>(
+        &self,
+        key: &F,
+        hash: Option<T::Hash>,
+    ) -> Result<Option<F::Value>, BasicError> {
+        let key = key.key().final_key(StorageKeyPrefix::new::<F>());
+        let (_, value) = self
+            .read_proof(&[key], hash)
+            .await?
+            .pop()
+            .expect("one value per key; qed");
+        value
+            .map(|data| self.rpc.decode_limits().decode(&data.0))
+            .transpose()
+    }
+
+    /// Fetch and decode all of the key-value pairs of a storage map.
+    pub async fn fetch_pairs<F: StorageEntry>(
+        &self,
+        hash: Option<T::Hash>,
+    ) -> Result<Vec<(StorageKey, F::Value)>, BasicError> {
+        let prefix = StorageKeyPrefix::new::<F>().to_storage_key();
+        self.fetch_pairs_raw(prefix, hash)
+            .await?
+            .into_iter()
+            .map(|(key, data)| Ok((key, self.rpc.decode_limits().decode(&data.0)?)))
+            .collect()
+    }
+
+    /// Decode the map key of a [`StorageEntry`] from its final storage key.
+    ///
+    /// This is only possible if every hasher of the entry keeps the original key data
+    /// around, i.e. is one of `Blake2_128Concat`, `Twox64Concat` or `Identity`. For
+    /// double and N-maps, `K` is a tuple of the individual keys.
+    pub fn decode_key<F: StorageEntry, K: Decode>(
+        &self,
+        key: &StorageKey,
+    ) -> Result<K, BasicError> {
+        let entry = self.metadata.pallet(F::PALLET)?.storage(F::STORAGE)?;
+        let (hashers, key_ty) = match entry.ty {
+            StorageEntryType::Map {
+                ref hashers,
+                ref key,
+                ..
+            } => (hashers, key.id()),
+            StorageEntryType::Plain(_) => {
+                return Err(BasicError::Other(format!(
+                    "{}::{} is not a storage map",
+                    F::PALLET,
+                    F::STORAGE
+                )))
+            }
+        };
+        let types = &self.metadata.runtime_metadata().types;
+        let key_tys = if hashers.len() == 1 {
+            vec![key_ty]
+        } else {
+            match types.resolve(key_ty).map(|ty| ty.type_def()) {
+                Some(TypeDef::Tuple(tuple)) if tuple.fields().len() == hashers.len() => {
+                    tuple.fields().iter().map(|f| f.id()).collect()
+                }
+                _ => {
+                    return Err(BasicError::Other(format!(
+                        "{}::{} has {} hashers which do not match its key type",
+                        F::PALLET,
+                        F::STORAGE,
+                        hashers.len()
+                    )))
+                }
+            }
+        };
+
+        let prefix = StorageKeyPrefix::new::<F>();
+        let mut input = key.0.strip_prefix(&prefix.0[..]).ok_or_else(|| {
+            BasicError::Other(format!(
+                "Key does not belong to {}::{}",
+                F::PALLET,
+                F::STORAGE
+            ))
+        })?;
+        let mut raw = Vec::with_capacity(input.len());
+        for (hasher, ty) in hashers.iter().zip(key_tys) {
+            let hash_len = match hasher {
+                StorageHasher::Blake2_128Concat => 16,
+                StorageHasher::Twox64Concat => 8,
+                StorageHasher::Identity => 0,
+                other => {
+                    return Err(BasicError::Other(format!(
+                        "Cannot decode a key hashed with {:?}",
+                        other
+                    )))
+                }
+            };
+            if input.len() < hash_len {
+                return Err(codec::Error::from("Storage key is too short").into())
+            }
+            input = &input[hash_len..];
+            let before = input;
+            crate::events::decode_and_consume_type(ty, types, &mut input)?;
+            raw.extend_from_slice(&before[..before.len() - input.len()]);
+        }
+        if !input.is_empty() {
+            return Err(codec::Error::from("Storage key has trailing bytes").into())
+        }
+        self.rpc.decode_limits().decode(&raw)
+    }
+
+    /// Build the storage key of an entry given by pallet and entry name. For maps, the
+    /// keys are given as [`Value`]s: one for each hasher, or a single one (which may
+    /// be a tuple) for maps with a single hasher.
+    pub fn dynamic_key(
+        &self,
+        pallet: &str,
+        entry: &str,
+        keys: &[Value],
+    ) -> Result<StorageKey, BasicError> {
+        let (prefix, entry) = self.dynamic_entry(pallet, entry)?;
+        let mut bytes = sp_core::twox_128(prefix.as_bytes()).to_vec();
+        bytes.extend(&sp_core::twox_128(entry.name.as_bytes())[..]);
+
+        let (hashers, key_ty) = match entry.ty {
+            StorageEntryType::Plain(_) if keys.is_empty() => return Ok(StorageKey(bytes)),
+            StorageEntryType::Plain(_) => {
+                return Err(DynamicError::WrongLength {
+                    expected: 0,
+                    found: keys.len(),
+                }
+                .into())
+            }
+            StorageEntryType::Map {
+                ref hashers,
+                ref key,
+                ..
+            } => (hashers, key.id()),
+        };
+        let types = &self.metadata.runtime_metadata().types;
+        // The types of the individual keys of double and N-maps.
+        let key_tys = || -> Result<Vec<u32>, DynamicError> {
+            match types.resolve(key_ty).map(|ty| ty.type_def()) {
+                Some(TypeDef::Tuple(tuple)) if tuple.fields().len() == keys.len() => {
+                    Ok(tuple.fields().iter().map(|f| f.id()).collect())
+                }
+                Some(TypeDef::Tuple(tuple)) => {
+                    Err(DynamicError::WrongLength {
+                        expected: tuple.fields().len(),
+                        found: keys.len(),
+                    })
+                }
+                _ => {
+                    Err(DynamicError::WrongLength {
+                        expected: 1,
+                        found: keys.len(),
+                    })
+                }
+            }
+        };
+
+        if let [hasher] = &hashers[..] {
+            let mut encoded = Vec::new();
+            if let [key] = keys {
+                dynamic::encode_value(key, key_ty, types, &mut encoded)?;
+            } else {
+                for (key, ty) in keys.iter().zip(key_tys()?) {
+                    dynamic::encode_value(key, ty, types, &mut encoded)?;
+                }
+            }
+            bytes.extend(StorageEntryKey::hash(hasher, &encoded));
+        } else {
+            if keys.len() != hashers.len() {
+                return Err(DynamicError::WrongLength {
+                    expected: hashers.len(),
+                    found: keys.len(),
+                }
+                .into())
+            }
+            for ((key, ty), hasher) in keys.iter().zip(key_tys()?).zip(hashers) {
+                let mut encoded = Vec::new();
+                dynamic::encode_value(key, ty, types, &mut encoded)?;
+                bytes.extend(StorageEntryKey::hash(hasher, &encoded));
+            }
+        }
+        Ok(StorageKey(bytes))
+    }
+
+    /// Fetch a storage entry given by pallet and entry name, and decode its value
+    /// according to the metadata; see [`StorageClient::dynamic_key`] for the keys.
+    ///
+    /// Entries with a default value return it when there is nothing in storage.
+    pub async fn dynamic(
+        &self,
+        pallet: &str,
+        entry: &str,
+        keys: &[Value],
+        hash: Option<T::Hash>,
+    ) -> Result<Option<Value>, BasicError> {
+        let key = self.dynamic_key(pallet, entry, keys)?;
+        let (_, entry) = self.dynamic_entry(pallet, entry)?;
+        let value_ty = match entry.ty {
+            StorageEntryType::Plain(ref ty) => ty.id(),
+            StorageEntryType::Map { ref value, .. } => value.id(),
+        };
+        let data = match self.rpc.storage(&key, hash).await? {
+            Some(data) => data.0,
+            None if entry.modifier == StorageEntryModifier::Default => {
+                entry.default.clone()
+            }
+            None => return Ok(None),
+        };
+
+        self.rpc.decode_limits().check_size(data.len())?;
+        let types = &self.metadata.runtime_metadata().types;
+        let input = &mut &data[..];
+        let value = dynamic::decode_value(value_ty, types, input)?;
+        if !input.is_empty() {
+            return Err(codec::Error::from("Storage value has trailing bytes").into())
+        }
+        Ok(Some(value))
+    }
+
+    /// The storage prefix of the pallet, and the metadata of the entry.
+    fn dynamic_entry(
+        &self,
+        pallet: &str,
+        entry: &str,
+    ) -> Result<(&str, &StorageEntryMetadata<PortableForm>), DynamicError> {
+        let not_found = || {
+            DynamicError::StorageNotFound {
+                pallet: pallet.to_string(),
+                entry: entry.to_string(),
+            }
+        };
+        let storage = self
+            .metadata
+            .runtime_metadata()
+            .pallets
+            .iter()
+            .find(|p| p.name == pallet)
+            .ok_or_else(|| DynamicError::PalletNotFound(pallet.to_string()))?
+            .storage
+            .as_ref()
+            .ok_or_else(not_found)?;
+        let entry = storage
+            .entries
+            .iter()
+            .find(|e| e.name == entry)
+            .ok_or_else(not_found)?;
+        Ok((&storage.prefix, entry))
+    }
+
     /// Returns an iterator of key value pairs.
     pub async fn iter<F: StorageEntry>(
         &self,
@@ -258,6 +887,158 @@ impl<'a, T: Config> StorageClient<'a, T> {
     }
 }
 
+/// Split the possible values of a byte into `shards` ranges, each given by its first
+/// value and the first value after it (or `None` for the last range).
+fn shard_bounds(shards: usize) -> Vec<(u8, Option<u8>)> {
+    let shards = shards.clamp(1, 256);
+    let start = |shard: usize| (shard * 256 / shards) as u8;
+    (0..shards)
+        .map(|shard| {
+            let end = (shard + 1 < shards).then(|| start(shard + 1));
+            (start(shard), end)
+        })
+        .collect()
+}
+
+/// Client for reading a child trie, such as the ones the contracts pallet keeps the
+/// storage of each contract in.
+pub struct ChildStorageClient<'a, T: Config> {
+    rpc: &'a Rpc<T>,
+    child_key: PrefixedStorageKey,
+    iter_page_size: u32,
+}
+
+impl<'a, T: Config> Clone for ChildStorageClient<'a, T> {
+    fn clone(&self) -> Self {
+        Self {
+            rpc: self.rpc,
+            child_key: self.child_key.clone(),
+            iter_page_size: self.iter_page_size,
+        }
+    }
+}
+
+impl<'a, T: Config> ChildStorageClient<'a, T> {
+    /// Create a new [`ChildStorageClient`] for the given child trie.
+    pub fn new(rpc: &'a Rpc<T>, child_info: &ChildInfo, iter_page_size: u32) -> Self {
+        Self {
+            rpc,
+            child_key: child_info.prefixed_storage_key(),
+            iter_page_size,
+        }
+    }
+
+    /// The key of the child trie in the main trie.
+    pub fn child_key(&self) -> &PrefixedStorageKey {
+        &self.child_key
+    }
+
+    /// Fetch the raw value under a key of the child trie.
+    pub async fn fetch_raw(
+        &self,
+        key: &StorageKey,
+        hash: Option<T::Hash>,
+    ) -> Result<Option<StorageData>, BasicError> {
+        self.rpc.child_storage(&self.child_key, key, hash).await
+    }
+
+    /// Fetch and decode the value under a key of the child trie.
+    pub async fn fetch<V: Decode>(
+        &self,
+        key: &StorageKey,
+        hash: Option<T::Hash>,
+    ) -> Result<Option<V>, BasicError> {
+        if let Some(data) = self.fetch_raw(key, hash).await? {
+            Ok(Some(self.rpc.decode_limits().decode(&data.0)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Fetch up to `count` keys of the child trie with the given prefix, starting
+    /// after `start_key`.
+    pub async fn fetch_keys(
+        &self,
+        prefix: Option<StorageKey>,
+        count: u32,
+        start_key: Option<StorageKey>,
+        hash: Option<T::Hash>,
+    ) -> Result<Vec<StorageKey>, BasicError> {
+        self.rpc
+            .child_storage_keys_paged(&self.child_key, prefix, count, start_key, hash)
+            .await
+    }
+
+    /// Fetch all of the keys of the child trie with the given prefix, a page at a time.
+    pub async fn fetch_all_keys(
+        &self,
+        prefix: Option<StorageKey>,
+        hash: Option<T::Hash>,
+    ) -> Result<Vec<StorageKey>, BasicError> {
+        let hash = if let Some(hash) = hash {
+            hash
+        } else {
+            self.rpc
+                .block_hash(None)
+                .await?
+                .expect("didn't pass a block number; qed")
+        };
+        let mut keys = Vec::new();
+        loop {
+            let page = self
+                .fetch_keys(
+                    prefix.clone(),
+                    self.iter_page_size,
+                    keys.last().cloned(),
+                    Some(hash),
+                )
+                .await?;
+            let is_last_page = (page.len() as u32) < self.iter_page_size;
+            keys.extend(page);
+            if is_last_page {
+                return Ok(keys)
+            }
+        }
+    }
+}
+
+/// Check a proof of storage entries, as returned by `state_getReadProof`, against the
+/// state root of a block. Returns the value of each of the keys, or `None` if the proof
+/// shows there is no such entry.
+pub fn verify_read_proof<H: Hasher>(
+    state_root: &H::Out,
+    proof: &[Bytes],
+    keys: &[StorageKey],
+) -> Result<Vec<Option<StorageData>>, BasicError> {
+    let nodes = proof.iter().map(|node| node.0.clone()).collect();
+    let db = sp_trie::StorageProof::new(nodes).into_memory_db::<H>();
+    keys.iter()
+        .map(|key| {
+            sp_trie::read_trie_value::<sp_trie::LayoutV1<H>, _>(&db, state_root, &key.0)
+                .map(|value| value.map(StorageData))
+                .map_err(|e| BasicError::InvalidStorageProof(e.to_string()))
+        })
+        .collect()
+}
+
+/// Decode the values in a set of storage changes.
+fn decode_changes<Hash, V: Decode>(
+    limits: &DecodeLimits,
+    change_set: StorageChangeSet<Hash>,
+) -> Result<StorageChanges<Hash, V>, BasicError> {
+    let changes = change_set
+        .changes
+        .into_iter()
+        .map(|(key, data)| {
+            Ok((key, data.map(|data| limits.decode(&data.0)).transpose()?))
+        })
+        .collect::<Result<_, BasicError>>()?;
+    Ok(StorageChanges {
+        block: change_set.block,
+        changes,
+    })
+}
+
 /// Iterates over key value pairs in a map.
 pub struct KeyIter<'a, T: Config, F: StorageEntry> {
     client: StorageClient<'a, T>,
@@ -273,7 +1054,7 @@ impl<'a, T: Config, F: StorageEntry> KeyIter<'a, T, F> {
     pub async fn next(&mut self) -> Result<Option<(StorageKey, F::Value)>, BasicError> {
         loop {
             if let Some((k, v)) = self.buffer.pop() {
-                return Ok(Some((k, Decode::decode(&mut &v.0[..])?)))
+                return Ok(Some((k, self.client.rpc.decode_limits().decode(&v.0)?)))
             } else {
                 let keys = self
                     .client
@@ -302,4 +1083,270 @@ impl<'a, T: Config, F: StorageEntry> KeyIter<'a, T, F> {
             }
         }
     }
+
+    /// Decode the map keys as `K` instead of returning the raw [`StorageKey`]s.
+    ///
+    /// See [`StorageClient::decode_key`] for which entries this works for.
+    pub fn with_keys<K: Decode>(self) -> DecodedKeyIter<'a, T, F, K> {
+        DecodedKeyIter {
+            inner: self,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// Iterates over decoded key value pairs in a map.
+pub struct DecodedKeyIter<'a, T: Config, F: StorageEntry, K> {
+    inner: KeyIter<'a, T, F>,
+    _marker: PhantomData<K>,
+}
+
+impl<'a, T: Config, F: StorageEntry, K: Decode> DecodedKeyIter<'a, T, F, K> {
+    /// Returns the next decoded key and value from a map.
+    pub async fn next(&mut self) -> Result<Option<(K, F::Value)>, BasicError> {
+        match self.inner.next().await? {
+            Some((key, value)) => {
+                Ok(Some((self.inner.client.decode_key::<F, K>(&key)?, value)))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sp_runtime::traits::BlakeTwo256;
+    use sp_trie::{
+        LayoutV1,
+        MemoryDB,
+        TrieDBMut,
+        TrieMut,
+    };
+
+    /// Build a trie from the given entries, returning its root and all of its nodes.
+    fn trie(entries: &[(&[u8], &[u8])]) -> (sp_core::H256, Vec<Bytes>) {
+        let mut db = MemoryDB::<BlakeTwo256>::default();
+        let mut root = Default::default();
+        {
+            let mut trie = TrieDBMut::<LayoutV1<BlakeTwo256>>::new(&mut db, &mut root);
+            for (key, value) in entries {
+                trie.insert(key, value).unwrap();
+            }
+        }
+        let nodes = db
+            .drain()
+            .into_values()
+            .map(|(node, _)| Bytes(node))
+            .collect();
+        (root, nodes)
+    }
+
+    #[test]
+    fn read_proofs_are_checked_against_the_state_root() {
+        let (root, proof) =
+            trie(&[(b"alice", b"10"), (b"bob", b"20"), (b"carol", b"30")]);
+        let keys = [
+            StorageKey(b"bob".to_vec()),
+            StorageKey(b"dave".to_vec()),
+            StorageKey(b"alice".to_vec()),
+        ];
+
+        let values = verify_read_proof::<BlakeTwo256>(&root, &proof, &keys).unwrap();
+        assert_eq!(
+            values,
+            vec![
+                Some(StorageData(b"20".to_vec())),
+                None,
+                Some(StorageData(b"10".to_vec()))
+            ]
+        );
+
+        // A different state root isn't backed by the proof:
+        let (other_root, _) = trie(&[(b"bob", b"21")]);
+        assert!(matches!(
+            verify_read_proof::<BlakeTwo256>(&other_root, &proof, &keys),
+            Err(BasicError::InvalidStorageProof(_))
+        ));
+    }
+
+    #[test]
+    fn incomplete_read_proofs_are_rejected() {
+        let (root, proof) =
+            trie(&[(b"alice", b"10"), (b"bob", b"20"), (b"carol", b"30")]);
+        let root_node = proof
+            .into_iter()
+            .filter(|node| BlakeTwo256::hash(&node.0) == root)
+            .collect::<Vec<_>>();
+        assert_eq!(root_node.len(), 1);
+
+        let keys = [StorageKey(b"bob".to_vec())];
+        assert!(matches!(
+            verify_read_proof::<BlakeTwo256>(&root, &root_node, &keys),
+            Err(BasicError::InvalidStorageProof(_))
+        ));
+    }
+
+    #[test]
+    fn byte_values_are_split_into_shards() {
+        assert_eq!(
+            shard_bounds(4),
+            vec![
+                (0, Some(64)),
+                (64, Some(128)),
+                (128, Some(192)),
+                (192, None)
+            ]
+        );
+        assert_eq!(shard_bounds(0), vec![(0, None)]);
+        assert_eq!(
+            shard_bounds(3),
+            vec![(0, Some(85)), (85, Some(170)), (170, None)]
+        );
+        assert_eq!(shard_bounds(1000).len(), 256);
+    }
+
+    #[test]
+    fn dynamic_keys_report_how_many_keys_were_given() {
+        use crate::rpc::{
+            MockRpcClient,
+            Rpc,
+        };
+        use frame_metadata::{
+            v14::{
+                ExtrinsicMetadata,
+                PalletMetadata,
+                PalletStorageMetadata,
+                RuntimeMetadataLastVersion,
+            },
+            RuntimeMetadataPrefixed,
+        };
+        use scale_info::meta_type;
+        use std::convert::TryFrom;
+
+        let entry = |name, key| {
+            frame_metadata::v14::StorageEntryMetadata {
+                name,
+                modifier: StorageEntryModifier::Optional,
+                ty: StorageEntryType::Map {
+                    hashers: vec![StorageHasher::Blake2_128Concat],
+                    key,
+                    value: meta_type::<u64>(),
+                },
+                default: vec![0],
+                docs: vec![],
+            }
+        };
+        let pallets = vec![PalletMetadata {
+            name: "Test",
+            storage: Some(PalletStorageMetadata {
+                prefix: "Test",
+                entries: vec![
+                    entry("Single", meta_type::<u32>()),
+                    entry("Pair", meta_type::<(u32, u32)>()),
+                ],
+            }),
+            calls: None,
+            event: None,
+            constants: vec![],
+            error: None,
+            index: 7,
+        }];
+        let extrinsic = ExtrinsicMetadata {
+            ty: meta_type::<()>(),
+            version: 0,
+            signed_extensions: vec![],
+        };
+        let v14 = RuntimeMetadataLastVersion::new(pallets, extrinsic, meta_type::<()>());
+        let runtime_metadata: RuntimeMetadataPrefixed = v14.into();
+        let metadata = Arc::new(Metadata::try_from(runtime_metadata).unwrap());
+        let rpc = Rpc::<crate::DefaultConfig>::new_mock(MockRpcClient::new());
+        let storage = StorageClient::new(&rpc, metadata, 10);
+
+        let keys = [Value::uint(1u32), Value::uint(2u32), Value::uint(3u32)];
+        assert!(matches!(
+            storage.dynamic_key("Test", "Single", &keys),
+            Err(BasicError::Dynamic(DynamicError::WrongLength {
+                expected: 1,
+                found: 3
+            }))
+        ));
+        assert!(matches!(
+            storage.dynamic_key("Test", "Pair", &keys),
+            Err(BasicError::Dynamic(DynamicError::WrongLength {
+                expected: 2,
+                found: 3
+            }))
+        ));
+        assert!(storage.dynamic_key("Test", "Pair", &keys[..2]).is_ok());
+    }
+
+    #[async_std::test]
+    async fn shards_scan_their_own_range_of_keys() {
+        use crate::rpc::{
+            MockRpcClient,
+            Rpc,
+        };
+        use frame_metadata::{
+            v14::{
+                ExtrinsicMetadata,
+                RuntimeMetadataLastVersion,
+            },
+            RuntimeMetadataPrefixed,
+        };
+        use scale_info::meta_type;
+        use std::convert::TryFrom;
+
+        let extrinsic = ExtrinsicMetadata {
+            ty: meta_type::<()>(),
+            version: 0,
+            signed_extensions: vec![],
+        };
+        let v14 = RuntimeMetadataLastVersion::new(vec![], extrinsic, meta_type::<()>());
+        let runtime_metadata: RuntimeMetadataPrefixed = v14.into();
+        let metadata = Arc::new(Metadata::try_from(runtime_metadata).unwrap());
+
+        let hash = sp_core::H256::repeat_byte(9);
+        let pair =
+            |key: &[u8], value: u8| (StorageKey(key.to_vec()), StorageData(vec![value]));
+        let mock = MockRpcClient::new();
+        // The node hands back every key after the start key, including those of
+        // the next range.
+        mock.set_response_for(
+            "state_getKeysPaged",
+            vec!["0xaa".into(), 10.into(), serde_json::Value::Null],
+            vec![StorageKey(vec![0xaa, 0x01]), StorageKey(vec![0xaa, 0x90])],
+        );
+        mock.set_response_for(
+            "state_getKeysPaged",
+            vec!["0xaa".into(), 10.into(), "0xaa80".into()],
+            vec![StorageKey(vec![0xaa, 0x90])],
+        );
+        for (key, value) in [pair(&[0xaa, 0x01], 1), pair(&[0xaa, 0x90], 2)] {
+            mock.set_response_for(
+                "state_queryStorageAt",
+                vec![serde_json::to_value(vec![&key]).unwrap()],
+                vec![StorageChangeSet {
+                    block: hash,
+                    changes: vec![(key, Some(value))],
+                }],
+            );
+        }
+        let rpc = Rpc::<crate::DefaultConfig>::new_mock(mock.clone());
+
+        let mut pairs: Vec<_> = StorageClient::new(&rpc, metadata, 10)
+            .par_iter(StorageKey(vec![0xaa]), 2, Some(hash))
+            .try_collect()
+            .await
+            .unwrap();
+        pairs.sort();
+        assert_eq!(pairs, vec![pair(&[0xaa, 0x01], 1), pair(&[0xaa, 0x90], 2)]);
+        assert_eq!(
+            mock.requests()
+                .iter()
+                .filter(|request| request.method == "state_getKeysPaged")
+                .count(),
+            2
+        );
+    }
 }
@@ -0,0 +1,319 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Logging of the calls submitted by a [`crate::Client`].
+
+use crate::{
+    events::decode_and_consume_type,
+    Call,
+    Metadata,
+};
+use scale_info::TypeDef;
+use std::collections::HashSet;
+
+/// The log target that submitted calls are logged under.
+pub const SUBMISSION_LOG_TARGET: &str = "subxt::submission";
+
+/// Configures the logging of calls as they are submitted.
+///
+/// Each submission is logged at `info` level under [`SUBMISSION_LOG_TARGET`], with the
+/// pallet and call name, the transaction hash and nonce, and the SCALE encoded value of
+/// each argument, named according to the metadata. Arguments can be redacted by name.
+#[derive(Clone, Debug, Default)]
+pub struct SubmissionLogging {
+    redact_all: bool,
+    redacted: HashSet<String>,
+}
+
+impl SubmissionLogging {
+    /// Log submitted calls along with all of their arguments.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Don't log the value of arguments with the given name, in any call.
+    pub fn redact<N: Into<String>>(mut self, argument: N) -> Self {
+        self.redacted.insert(argument.into());
+        self
+    }
+
+    /// Don't log the value of any arguments.
+    pub fn redact_all(mut self) -> Self {
+        self.redact_all = true;
+        self
+    }
+
+    fn is_redacted(&self, argument: &str) -> bool {
+        self.redact_all || self.redacted.contains(argument)
+    }
+
+    fn redacts_any(&self) -> bool {
+        self.redact_all || !self.redacted.is_empty()
+    }
+
+    /// Log the submission of a call.
+    pub(crate) fn log<C: Call, H: std::fmt::Debug, N: std::fmt::Debug>(
+        &self,
+        metadata: &Metadata,
+        call: &C,
+        ext_hash: &H,
+        nonce: &N,
+    ) {
+        if !log::log_enabled!(target: SUBMISSION_LOG_TARGET, log::Level::Info) {
+            return
+        }
+        log::info!(
+            target: SUBMISSION_LOG_TARGET,
+            "Submitted {}::{} (tx hash: {:?}, nonce: {:?}) with arguments: {}",
+            call.pallet_name(),
+            call.function_name(),
+            ext_hash,
+            nonce,
+            self.format_arguments(metadata, call),
+        );
+    }
+
+    /// Render the arguments of a call as `name: 0x..` pairs, using the metadata to
+    /// find the name and extent of each argument.
+    fn format_arguments<C: Call>(&self, metadata: &Metadata, call: &C) -> String {
+        let encoded = call.encode();
+        let fields = match call_fields(metadata, call.pallet_name(), call.function_name())
+        {
+            Some(fields) => fields,
+            // Without the names of the arguments we can't tell which bytes belong to a
+            // redacted one, so show none of them.
+            None if self.redacts_any() => return render_length(&encoded),
+            None => return format!("0x{}", hex::encode(&encoded)),
+        };
+
+        let types = &metadata.runtime_metadata().types;
+        let cursor = &mut &encoded[..];
+        let mut arguments = Vec::with_capacity(fields.len());
+        let names = fields
+            .iter()
+            .enumerate()
+            .map(|(index, (name, _))| name.clone().unwrap_or_else(|| index.to_string()))
+            .collect::<Vec<_>>();
+        for (index, (name, (_, type_id))) in names.iter().zip(fields).enumerate() {
+            let start_len = cursor.len();
+            if decode_and_consume_type(type_id, types, cursor).is_err() {
+                // The metadata doesn't match the call, so whatever is left may hold this
+                // argument or any after it; only show it if none of those are redacted.
+                let rest = &encoded[encoded.len() - start_len..];
+                let rendered = if names[index..].iter().any(|name| self.is_redacted(name))
+                {
+                    render_length(rest)
+                } else {
+                    format!("0x{}", hex::encode(rest))
+                };
+                arguments.push(format!("{}..: {}", name, rendered));
+                break
+            }
+            let start = encoded.len() - start_len;
+            let end = encoded.len() - cursor.len();
+            let value = &encoded[start..end];
+            arguments.push(format!("{}: {}", name, self.render_value(name, value)));
+        }
+        format!("{{ {} }}", arguments.join(", "))
+    }
+
+    fn render_value(&self, argument: &str, bytes: &[u8]) -> String {
+        if self.is_redacted(argument) {
+            "<redacted>".into()
+        } else {
+            format!("0x{}", hex::encode(bytes))
+        }
+    }
+}
+
+fn render_length(bytes: &[u8]) -> String {
+    format!("<{} bytes>", bytes.len())
+}
+
+/// The names (if any) and type IDs of the arguments of a call, according to the metadata.
+fn call_fields(
+    metadata: &Metadata,
+    pallet_name: &str,
+    call_name: &str,
+) -> Option<Vec<(Option<String>, u32)>> {
+    let pallet = metadata
+        .runtime_metadata()
+        .pallets
+        .iter()
+        .find(|pallet| pallet.name == pallet_name)?;
+    let calls_ty = metadata.resolve_type(pallet.calls.as_ref()?.ty.id())?;
+    let variant = match calls_ty.type_def() {
+        TypeDef::Variant(variants) => {
+            variants
+                .variants()
+                .iter()
+                .find(|variant| variant.name() == call_name)?
+        }
+        _ => return None,
+    };
+    Some(
+        variant
+            .fields()
+            .iter()
+            .map(|field| (field.name().cloned(), field.ty().id()))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codec::Encode;
+    use frame_metadata::{
+        v14::{
+            ExtrinsicMetadata,
+            PalletCallMetadata,
+            PalletMetadata,
+            RuntimeMetadataLastVersion,
+        },
+        RuntimeMetadataPrefixed,
+    };
+    use scale_info::{
+        meta_type,
+        TypeInfo,
+    };
+    use std::convert::TryFrom;
+
+    #[allow(dead_code)]
+    #[derive(TypeInfo)]
+    enum TestCall {
+        #[allow(non_camel_case_types)]
+        transfer { dest: u32, memo: Vec<u8> },
+    }
+
+    #[derive(Encode)]
+    struct Transfer {
+        dest: u32,
+        memo: Vec<u8>,
+    }
+
+    impl Call for Transfer {
+        const PALLET: &'static str = "Test";
+        const FUNCTION: &'static str = "transfer";
+    }
+
+    fn metadata() -> Metadata {
+        let pallets = vec![PalletMetadata {
+            name: "Test",
+            storage: None,
+            calls: Some(PalletCallMetadata {
+                ty: meta_type::<TestCall>(),
+            }),
+            event: None,
+            constants: vec![],
+            error: None,
+            index: 0,
+        }];
+        let extrinsic = ExtrinsicMetadata {
+            ty: meta_type::<()>(),
+            version: 0,
+            signed_extensions: vec![],
+        };
+        let v14 = RuntimeMetadataLastVersion::new(pallets, extrinsic, meta_type::<()>());
+        let runtime_metadata: RuntimeMetadataPrefixed = v14.into();
+        Metadata::try_from(runtime_metadata).unwrap()
+    }
+
+    #[test]
+    fn arguments_are_named_and_redacted() {
+        let metadata = metadata();
+        let call = Transfer {
+            dest: 1,
+            memo: vec![0xab],
+        };
+
+        assert_eq!(
+            SubmissionLogging::new().format_arguments(&metadata, &call),
+            "{ dest: 0x01000000, memo: 0x04ab }"
+        );
+        assert_eq!(
+            SubmissionLogging::new()
+                .redact("memo")
+                .format_arguments(&metadata, &call),
+            "{ dest: 0x01000000, memo: <redacted> }"
+        );
+        assert_eq!(
+            SubmissionLogging::new()
+                .redact_all()
+                .format_arguments(&metadata, &call),
+            "{ dest: <redacted>, memo: <redacted> }"
+        );
+    }
+
+    #[test]
+    fn arguments_which_fail_to_decode_are_not_leaked() {
+        // Claims a 4 byte `memo`, but the bytes are missing.
+        #[derive(Encode)]
+        struct Truncated {
+            dest: u32,
+            memo_len: u8,
+        }
+
+        impl Call for Truncated {
+            const PALLET: &'static str = "Test";
+            const FUNCTION: &'static str = "transfer";
+        }
+
+        let metadata = metadata();
+        let call = Truncated {
+            dest: 1,
+            memo_len: 4 << 2,
+        };
+
+        assert_eq!(
+            SubmissionLogging::new().format_arguments(&metadata, &call),
+            "{ dest: 0x01000000, memo..: 0x10 }"
+        );
+        assert_eq!(
+            SubmissionLogging::new()
+                .redact("memo")
+                .format_arguments(&metadata, &call),
+            "{ dest: 0x01000000, memo..: <1 bytes> }"
+        );
+    }
+
+    #[test]
+    fn calls_missing_from_the_metadata_are_not_leaked() {
+        #[derive(Encode)]
+        struct Unknown {
+            secret: u32,
+        }
+
+        impl Call for Unknown {
+            const PALLET: &'static str = "Test";
+            const FUNCTION: &'static str = "unknown";
+        }
+
+        let metadata = metadata();
+        let call = Unknown { secret: 1 };
+
+        assert_eq!(
+            SubmissionLogging::new().format_arguments(&metadata, &call),
+            "0x01000000"
+        );
+        assert_eq!(
+            SubmissionLogging::new()
+                .redact("secret")
+                .format_arguments(&metadata, &call),
+            "<4 bytes>"
+        );
+    }
+}
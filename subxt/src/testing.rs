@@ -0,0 +1,335 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Running a node to test against: spawn a node binary on free ports, wait for its RPC
+//! endpoint to come up, and kill it once done with.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), subxt::BasicError> {
+//! use subxt::{testing::TestNodeProcess, DefaultConfig};
+//!
+//! let node = TestNodeProcess::<DefaultConfig>::build("avail-node")
+//!     .with_authority("alice")
+//!     .scan_for_open_ports()
+//!     .spawn::<DefaultConfig>()
+//!     .await?;
+//! let block_hash = node.client().rpc().finalized_head().await?;
+//! // The node is killed once `node` is dropped.
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    error::BasicError,
+    Client,
+    ClientBuilder,
+    Config,
+};
+use futures_timer::Delay;
+use std::{
+    ffi::{
+        OsStr,
+        OsString,
+    },
+    net::TcpListener,
+    path::PathBuf,
+    process,
+    sync::atomic::{
+        AtomicU16,
+        Ordering,
+    },
+    time::Duration,
+};
+
+/// A node process spawned for testing, along with a client connected to it. The node
+/// is killed when this is dropped.
+pub struct TestNodeProcess<R: Config> {
+    proc: process::Child,
+    client: Client<R>,
+    ws_url: String,
+}
+
+impl<R> Drop for TestNodeProcess<R>
+where
+    R: Config,
+{
+    fn drop(&mut self) {
+        let _ = self.kill();
+    }
+}
+
+impl<R> TestNodeProcess<R>
+where
+    R: Config,
+{
+    /// Construct a builder for spawning the node binary at the given path.
+    pub fn build<S>(program: S) -> TestNodeProcessBuilder
+    where
+        S: AsRef<OsStr> + Clone,
+    {
+        TestNodeProcessBuilder::new(program)
+    }
+
+    /// Attempt to kill the running node process.
+    pub fn kill(&mut self) -> Result<(), BasicError> {
+        log::info!("Killing node process {}", self.proc.id());
+        if let Err(err) = self.proc.kill() {
+            log::error!("Error killing node process {}: {}", self.proc.id(), err);
+            return Err(err.into())
+        }
+        // Reap the process, so that it doesn't linger as a zombie.
+        let _ = self.proc.wait();
+        Ok(())
+    }
+
+    /// Returns the client connected to the running node.
+    pub fn client(&self) -> &Client<R> {
+        &self.client
+    }
+
+    /// Returns the URL of the node's websocket RPC endpoint.
+    pub fn ws_url(&self) -> &str {
+        &self.ws_url
+    }
+}
+
+/// The ports a node listens on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NodePorts {
+    /// The port for peer to peer networking.
+    pub p2p: u16,
+    /// The port of the HTTP RPC endpoint.
+    pub rpc: u16,
+    /// The port of the websocket RPC endpoint.
+    pub ws: u16,
+}
+
+/// Which ports the node should listen on.
+#[derive(Clone, Copy, Debug)]
+enum PortSelection {
+    /// Whichever the node listens on by default.
+    Default,
+    /// Free ports, found by scanning a range of ports.
+    Scan,
+    /// The given ports.
+    Fixed(NodePorts),
+}
+
+/// Construct a test node process.
+#[derive(Clone, Debug)]
+pub struct TestNodeProcessBuilder {
+    node_path: OsString,
+    chain: Option<String>,
+    authority: Option<String>,
+    base_path: Option<PathBuf>,
+    ports: PortSelection,
+    args: Vec<OsString>,
+    max_attempts: u32,
+}
+
+impl TestNodeProcessBuilder {
+    /// Create a builder for spawning the node binary at the given path.
+    pub fn new<P>(node_path: P) -> TestNodeProcessBuilder
+    where
+        P: AsRef<OsStr>,
+    {
+        Self {
+            node_path: node_path.as_ref().into(),
+            chain: None,
+            authority: None,
+            base_path: None,
+            ports: PortSelection::Default,
+            args: Vec::new(),
+            max_attempts: 6,
+        }
+    }
+
+    /// Run the given chain, which is either the name of a chain known to the node or
+    /// the path to a chain spec, rather than the development chain (`--dev`).
+    pub fn with_chain<S: Into<String>>(&mut self, chain: S) -> &mut Self {
+        self.chain = Some(chain.into());
+        self
+    }
+
+    /// Run the node as the given dev authority, e.g. `"alice"` for `--alice`.
+    pub fn with_authority<S: AsRef<str>>(&mut self, authority: S) -> &mut Self {
+        self.authority = Some(authority.as_ref().to_lowercase());
+        self
+    }
+
+    /// Keep the node's data in the given directory, rather than in a temporary one
+    /// which is removed once the node exits (`--tmp`).
+    pub fn with_base_path<P: Into<PathBuf>>(&mut self, base_path: P) -> &mut Self {
+        self.base_path = Some(base_path.into());
+        self
+    }
+
+    /// Listen on the given ports.
+    pub fn with_ports(&mut self, ports: NodePorts) -> &mut Self {
+        self.ports = PortSelection::Fixed(ports);
+        self
+    }
+
+    /// Enable port scanning to scan for open ports.
+    ///
+    /// Allows spawning multiple node instances for tests to run in parallel.
+    pub fn scan_for_open_ports(&mut self) -> &mut Self {
+        self.ports = PortSelection::Scan;
+        self
+    }
+
+    /// Pass an additional argument to the node.
+    pub fn with_arg<S: AsRef<OsStr>>(&mut self, arg: S) -> &mut Self {
+        self.args.push(arg.as_ref().into());
+        self
+    }
+
+    /// How many times to try connecting to the node before giving up. The wait between
+    /// attempts starts at a second, and doubles after each one.
+    pub fn with_max_connect_attempts(&mut self, attempts: u32) -> &mut Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    /// Spawn the node, and wait for its RPC endpoint to be ready.
+    pub async fn spawn<R>(&self) -> Result<TestNodeProcess<R>, BasicError>
+    where
+        R: Config,
+    {
+        let mut cmd = process::Command::new(&self.node_path);
+        cmd.env("RUST_LOG", "error");
+        match &self.chain {
+            Some(chain) => cmd.arg(format!("--chain={}", chain)),
+            None => cmd.arg("--dev"),
+        };
+        match &self.base_path {
+            Some(base_path) => cmd.arg("--base-path").arg(base_path),
+            None => cmd.arg("--tmp"),
+        };
+        if let Some(authority) = &self.authority {
+            cmd.arg(format!("--{}", authority));
+        }
+
+        let ports = match self.ports {
+            PortSelection::Default => None,
+            PortSelection::Scan => {
+                Some(next_open_ports().ok_or_else(|| {
+                    BasicError::Other("No available ports in the given port range".into())
+                })?)
+            }
+            PortSelection::Fixed(ports) => Some(ports),
+        };
+        let ws_port = match ports {
+            Some(ports) => {
+                cmd.arg(format!("--port={}", ports.p2p));
+                cmd.arg(format!("--rpc-port={}", ports.rpc));
+                cmd.arg(format!("--ws-port={}", ports.ws));
+                ports.ws
+            }
+            // the default Websockets port
+            None => 9944,
+        };
+        cmd.args(&self.args);
+
+        let ws_url = format!("ws://127.0.0.1:{}", ws_port);
+
+        let mut proc = cmd.spawn().map_err(|e| {
+            BasicError::Other(format!(
+                "Error spawning node '{}': {}",
+                self.node_path.to_string_lossy(),
+                e
+            ))
+        })?;
+        // wait for rpc to be initialized
+        let mut attempts = 1;
+        let mut wait = Duration::from_secs(1);
+        let client = loop {
+            Delay::new(wait).await;
+            log::info!(
+                "Connecting to node, attempt {}/{}",
+                attempts,
+                self.max_attempts
+            );
+            match ClientBuilder::new().set_url(ws_url.clone()).build().await {
+                Ok(client) => break Ok(client),
+                Err(_) if attempts < self.max_attempts => {
+                    attempts += 1;
+                    wait *= 2; // backoff
+                }
+                Err(err) => break Err(err),
+            }
+        };
+        match client {
+            Ok(client) => {
+                Ok(TestNodeProcess {
+                    proc,
+                    client,
+                    ws_url,
+                })
+            }
+            Err(err) => {
+                let err = format!(
+                    "Failed to connect to node rpc at {} after {} attempts: {}",
+                    ws_url, attempts, err
+                );
+                log::error!("{}", err);
+                let _ = proc.kill();
+                let _ = proc.wait();
+                Err(BasicError::Other(err))
+            }
+        }
+    }
+}
+
+/// The start of the port range to scan.
+const START_PORT: u16 = 9900;
+/// The end of the port range to scan.
+const END_PORT: u16 = 10000;
+/// The maximum number of ports to scan before giving up.
+const MAX_PORTS: u16 = 1000;
+/// Next available unclaimed port for test node endpoints.
+static PORT: AtomicU16 = AtomicU16::new(START_PORT);
+
+/// Returns the next set of 3 open ports.
+///
+/// Returns None if there are not 3 open ports available.
+fn next_open_ports() -> Option<NodePorts> {
+    let mut ports = Vec::new();
+    let mut ports_scanned = 0u16;
+    loop {
+        let _ = PORT.compare_exchange(
+            END_PORT,
+            START_PORT,
+            Ordering::SeqCst,
+            Ordering::SeqCst,
+        );
+        let next = PORT.fetch_add(1, Ordering::SeqCst);
+        if TcpListener::bind(("0.0.0.0", next)).is_ok() {
+            ports.push(next);
+            if ports.len() == 3 {
+                return Some(NodePorts {
+                    p2p: ports[0],
+                    rpc: ports[1],
+                    ws: ports[2],
+                })
+            }
+        }
+        ports_scanned += 1;
+        if ports_scanned == MAX_PORTS {
+            return None
+        }
+    }
+}
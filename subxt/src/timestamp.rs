@@ -0,0 +1,278 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Conversion between block numbers and the timestamps set by the `Timestamp` pallet.
+
+use crate::{
+    error::BasicError,
+    rpc::Rpc,
+    Config,
+};
+use sp_core::{
+    storage::StorageKey,
+    twox_128,
+};
+use sp_runtime::traits::Header;
+
+/// A block, along with the timestamp set in it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimestampedBlock<Hash> {
+    /// The number of the block.
+    pub number: u64,
+    /// The hash of the block.
+    pub hash: Hash,
+    /// The timestamp of the block, in milliseconds since the Unix epoch.
+    pub timestamp: u64,
+}
+
+/// Client for looking up blocks by their timestamp, and vice versa.
+///
+/// Lookups by timestamp perform a binary search over the block numbers of the
+/// chain, up to the best block, which fetches the timestamps of roughly
+/// `log2(best block number)` blocks.
+pub struct TimestampClient<'a, T: Config> {
+    rpc: &'a Rpc<T>,
+}
+
+impl<'a, T: Config> TimestampClient<'a, T> {
+    /// Create a new [`TimestampClient`].
+    pub fn new(rpc: &'a Rpc<T>) -> Self {
+        Self { rpc }
+    }
+
+    /// The timestamp of the block with the given hash, in milliseconds since the Unix
+    /// epoch. This is 0 for the genesis block.
+    pub async fn at(&self, hash: T::Hash) -> Result<u64, BasicError> {
+        let mut key = twox_128(b"Timestamp").to_vec();
+        key.extend(twox_128(b"Now"));
+        let timestamp = match self.rpc.storage(&StorageKey(key), Some(hash)).await? {
+            Some(data) => codec::Decode::decode(&mut &data.0[..])?,
+            None => 0,
+        };
+        Ok(timestamp)
+    }
+
+    /// The block with the given number, along with its timestamp.
+    pub async fn at_number(
+        &self,
+        number: u64,
+    ) -> Result<Option<TimestampedBlock<T::Hash>>, BasicError> {
+        let hash = match self.rpc.block_hash(Some(number.into())).await? {
+            Some(hash) => hash,
+            None => return Ok(None),
+        };
+        let timestamp = self.at(hash).await?;
+        Ok(Some(TimestampedBlock {
+            number,
+            hash,
+            timestamp,
+        }))
+    }
+
+    /// The first block with a timestamp at or after the given one, if any.
+    pub async fn first_block_at_or_after(
+        &self,
+        timestamp: u64,
+    ) -> Result<Option<TimestampedBlock<T::Hash>>, BasicError> {
+        let best = self.best_number().await?;
+        // Find the lowest block number in `lo..=hi` at or after the timestamp.
+        let (mut lo, mut hi) = (0, best);
+        let mut found = None;
+        while lo <= hi {
+            let mid = lo + (hi - lo) / 2;
+            let block = self.expect_block(mid).await?;
+            if block.timestamp >= timestamp {
+                found = Some(block);
+                if mid == 0 {
+                    break
+                }
+                hi = mid - 1;
+            } else {
+                lo = mid + 1;
+            }
+        }
+        Ok(found)
+    }
+
+    /// The last block with a timestamp at or before the given one, if any.
+    pub async fn last_block_at_or_before(
+        &self,
+        timestamp: u64,
+    ) -> Result<Option<TimestampedBlock<T::Hash>>, BasicError> {
+        match self.first_block_at_or_after(timestamp).await? {
+            Some(block) if block.timestamp == timestamp => Ok(Some(block)),
+            Some(block) if block.number == 0 => Ok(None),
+            Some(block) => self.at_number(block.number - 1).await,
+            None => {
+                let best = self.best_number().await?;
+                self.at_number(best).await
+            }
+        }
+    }
+
+    /// The block whose timestamp is closest to the given one.
+    pub async fn closest_block(
+        &self,
+        timestamp: u64,
+    ) -> Result<Option<TimestampedBlock<T::Hash>>, BasicError> {
+        let after = self.first_block_at_or_after(timestamp).await?;
+        let before = match after {
+            Some(block) if block.timestamp == timestamp => return Ok(Some(block)),
+            Some(block) if block.number == 0 => None,
+            Some(block) => self.at_number(block.number - 1).await?,
+            None => return self.last_block_at_or_before(timestamp).await,
+        };
+        Ok(match (before, after) {
+            (Some(before), Some(after)) => {
+                if timestamp - before.timestamp <= after.timestamp - timestamp {
+                    Some(before)
+                } else {
+                    Some(after)
+                }
+            }
+            (before, after) => before.or(after),
+        })
+    }
+
+    async fn best_number(&self) -> Result<u64, BasicError> {
+        let header = self
+            .rpc
+            .header(None)
+            .await?
+            .ok_or_else(|| BasicError::Other("Best block header not found".into()))?;
+        Ok((*header.number()).into())
+    }
+
+    async fn expect_block(
+        &self,
+        number: u64,
+    ) -> Result<TimestampedBlock<T::Hash>, BasicError> {
+        self.at_number(number).await?.ok_or_else(|| {
+            BasicError::Other(format!("Block number {} not found", number))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        rpc::MockRpcClient,
+        DefaultConfig,
+    };
+    use codec::Encode;
+    use sp_core::{
+        storage::StorageData,
+        H256,
+    };
+    use sp_runtime::Digest;
+
+    /// The timestamps of the blocks of the mocked chain, by block number.
+    const TIMESTAMPS: [u64; 6] = [1_000, 7_000, 13_000, 19_000, 25_000, 31_000];
+
+    fn block(number: u64) -> TimestampedBlock<H256> {
+        TimestampedBlock {
+            number,
+            hash: H256::repeat_byte(number as u8 + 1),
+            timestamp: TIMESTAMPS[number as usize],
+        }
+    }
+
+    fn mock_rpc() -> Rpc<DefaultConfig> {
+        let mock = MockRpcClient::new();
+        let mut key = twox_128(b"Timestamp").to_vec();
+        key.extend(twox_128(b"Now"));
+        let key = serde_json::to_value(StorageKey(key)).unwrap();
+        for number in 0..TIMESTAMPS.len() as u64 {
+            let block = block(number);
+            mock.set_response_for("chain_getBlockHash", vec![number.into()], block.hash);
+            mock.set_response_for(
+                "state_getStorage",
+                vec![key.clone(), serde_json::to_value(block.hash).unwrap()],
+                StorageData(block.timestamp.encode()),
+            );
+        }
+        let best = block(TIMESTAMPS.len() as u64 - 1);
+        let header = <DefaultConfig as Config>::Header::new(
+            best.number as u32,
+            Default::default(),
+            Default::default(),
+            block(best.number - 1).hash,
+            Digest::default(),
+        );
+        mock.set_response("chain_getHeader", header);
+        Rpc::new_mock(mock)
+    }
+
+    #[async_std::test]
+    async fn a_block_at_the_exact_time_is_found() {
+        let rpc = mock_rpc();
+        let client = TimestampClient::new(&rpc);
+        for number in 0..TIMESTAMPS.len() as u64 {
+            let timestamp = TIMESTAMPS[number as usize];
+            let expected = Some(block(number));
+            assert_eq!(
+                client.first_block_at_or_after(timestamp).await.unwrap(),
+                expected
+            );
+            assert_eq!(
+                client.last_block_at_or_before(timestamp).await.unwrap(),
+                expected
+            );
+            assert_eq!(client.closest_block(timestamp).await.unwrap(), expected);
+        }
+    }
+
+    #[async_std::test]
+    async fn a_time_between_blocks_is_found_on_either_side() {
+        let rpc = mock_rpc();
+        let client = TimestampClient::new(&rpc);
+        assert_eq!(
+            client.first_block_at_or_after(14_000).await.unwrap(),
+            Some(block(3))
+        );
+        assert_eq!(
+            client.last_block_at_or_before(14_000).await.unwrap(),
+            Some(block(2))
+        );
+        assert_eq!(client.closest_block(14_000).await.unwrap(), Some(block(2)));
+        assert_eq!(client.closest_block(18_000).await.unwrap(), Some(block(3)));
+    }
+
+    #[async_std::test]
+    async fn a_time_before_genesis_has_no_block_before_it() {
+        let rpc = mock_rpc();
+        let client = TimestampClient::new(&rpc);
+        assert_eq!(
+            client.first_block_at_or_after(500).await.unwrap(),
+            Some(block(0))
+        );
+        assert_eq!(client.last_block_at_or_before(500).await.unwrap(), None);
+        assert_eq!(client.closest_block(500).await.unwrap(), Some(block(0)));
+    }
+
+    #[async_std::test]
+    async fn a_time_after_the_best_block_has_no_block_after_it() {
+        let rpc = mock_rpc();
+        let client = TimestampClient::new(&rpc);
+        assert_eq!(client.first_block_at_or_after(40_000).await.unwrap(), None);
+        assert_eq!(
+            client.last_block_at_or_before(40_000).await.unwrap(),
+            Some(block(5))
+        );
+        assert_eq!(client.closest_block(40_000).await.unwrap(), Some(block(5)));
+    }
+}
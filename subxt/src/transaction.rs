@@ -14,7 +14,10 @@
 // You should have received a copy of the GNU General Public License
 // along with subxt.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::task::Poll;
+use std::{
+    task::Poll,
+    time::Duration,
+};
 
 use crate::PhantomDataSendSync;
 use codec::Decode;
@@ -27,8 +30,6 @@ use crate::{
         BasicError,
         Error,
         HasModuleError,
-        ModuleError,
-        RuntimeError,
         TransactionError,
     },
     events::{
@@ -37,19 +38,91 @@ use crate::{
         Events,
         RawEventDetails,
     },
-    rpc::SubstrateTransactionStatus,
+    metrics::Timer,
+    rpc::{
+        RpcSubscription,
+        ShutdownSignal,
+        SubstrateTransactionStatus,
+    },
     Config,
     Phase,
 };
 use derivative::Derivative;
 use futures::{
+    future::{
+        self,
+        BoxFuture,
+        Either,
+    },
+    Future,
+    FutureExt,
     Stream,
     StreamExt,
 };
-use jsonrpsee::core::{
-    client::Subscription as RpcSubscription,
-    Error as RpcError,
-};
+use futures_timer::Delay;
+use jsonrpsee::core::Error as RpcError;
+
+/// When to sign and submit a transaction again, if it looks like it won't make it into
+/// a block. See [`crate::SubmittableExtrinsic::sign_and_submit_then_watch_with_resubmit()`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ResubmitPolicy {
+    /// The most times to resubmit the transaction before giving up on it, after which
+    /// statuses are handed back as they are.
+    pub max_resubmissions: u32,
+    /// Resubmit the transaction if it has been in the `Future` queue for this long,
+    /// or never if `None`.
+    pub future_timeout: Option<Duration>,
+    /// Sign every resubmission with the next nonce according to the node. Otherwise the
+    /// nonce is only refreshed after the transaction was `Usurped` (since its nonce has
+    /// then been used by another transaction), and resubmissions replace the original
+    /// transaction in the pool, provided that they pay a higher tip.
+    pub refresh_nonce: bool,
+}
+
+impl Default for ResubmitPolicy {
+    fn default() -> Self {
+        Self {
+            max_resubmissions: 3,
+            future_timeout: Some(Duration::from_secs(60)),
+            refresh_nonce: false,
+        }
+    }
+}
+
+type StatusSubscription<T> =
+    RpcSubscription<SubstrateTransactionStatus<<T as Config>::Hash, <T as Config>::Hash>>;
+
+type Resubmitting<'client, T> =
+    BoxFuture<'client, Result<(StatusSubscription<T>, <T as Config>::Hash), BasicError>>;
+
+/// Signs and submits a transaction again, given the number of the resubmission
+/// (starting at 1) and whether its nonce must be refreshed, and hands back the new
+/// subscription to its progress and its hash.
+pub(crate) type Resubmit<'client, T> =
+    Box<dyn FnMut(u32, bool) -> Resubmitting<'client, T> + Send + 'client>;
+
+/// The state of a transaction which is resubmitted according to a [`ResubmitPolicy`].
+struct Resubmission<'client, T: Config> {
+    policy: ResubmitPolicy,
+    resubmit: Resubmit<'client, T>,
+    resubmissions: u32,
+    pending: Option<Resubmitting<'client, T>>,
+    // Set while the transaction is in the `Future` queue.
+    future_timeout: Option<Delay>,
+}
+
+impl<'client, T: Config> Resubmission<'client, T> {
+    fn can_resubmit(&self) -> bool {
+        self.pending.is_none() && self.resubmissions < self.policy.max_resubmissions
+    }
+
+    fn start(&mut self, refresh_nonce: bool) {
+        self.resubmissions += 1;
+        self.future_timeout = None;
+        let refresh_nonce = refresh_nonce || self.policy.refresh_nonce;
+        self.pending = Some((self.resubmit)(self.resubmissions, refresh_nonce));
+    }
+}
 
 /// This struct represents a subscription to the progress of some transaction, and is
 /// returned from [`crate::SubmittableExtrinsic::sign_and_submit_then_watch()`].
@@ -59,6 +132,15 @@ pub struct TransactionProgress<'client, T: Config, E, Evs> {
     sub: Option<RpcSubscription<SubstrateTransactionStatus<T::Hash, T::Hash>>>,
     ext_hash: T::Hash,
     client: &'client Client<T>,
+    #[derivative(Debug = "ignore")]
+    shutdown: ShutdownSignal,
+    #[derivative(Debug = "ignore")]
+    resubmission: Option<Resubmission<'client, T>>,
+    // When the transaction was submitted, to measure its time to finality.
+    submitted: Timer,
+    #[cfg(feature = "tracing")]
+    #[derivative(Debug = "ignore")]
+    span: tracing::Span,
     _error: PhantomDataSendSync<(E, Evs)>,
 }
 
@@ -78,12 +160,110 @@ impl<'client, T: Config, E: Decode + HasModuleError, Evs: Decode>
     ) -> Self {
         Self {
             sub: Some(sub),
+            shutdown: client.rpc().shutdown_signal(),
             client,
             ext_hash,
+            resubmission: None,
+            submitted: Timer::start(),
+            #[cfg(feature = "tracing")]
+            span: tracing::debug_span!(
+                "transaction_progress",
+                ext_hash = ?ext_hash,
+                resubmissions = 0u32
+            ),
             _error: PhantomDataSendSync::new(),
         }
     }
 
+    /// Resubmit the transaction according to the given policy.
+    pub(crate) fn with_resubmit(
+        mut self,
+        policy: ResubmitPolicy,
+        resubmit: Resubmit<'client, T>,
+    ) -> Self {
+        self.resubmission = Some(Resubmission {
+            policy,
+            resubmit,
+            resubmissions: 0,
+            pending: None,
+            future_timeout: None,
+        });
+        self
+    }
+
+    /// The hash of the transaction being watched. This changes if the transaction is
+    /// resubmitted.
+    pub fn extrinsic_hash(&self) -> T::Hash {
+        self.ext_hash
+    }
+
+    /// Start resubmitting the transaction if the policy allows for it, returning
+    /// whether it does.
+    fn try_resubmit(&mut self, refresh_nonce: bool) -> bool {
+        match self.resubmission.as_mut() {
+            Some(resubmission) if resubmission.can_resubmit() => {
+                log::debug!(
+                    "Resubmitting transaction {:?} (resubmission {})",
+                    self.ext_hash,
+                    resubmission.resubmissions + 1
+                );
+                resubmission.start(refresh_nonce);
+                #[cfg(feature = "tracing")]
+                self.span
+                    .record("resubmissions", &resubmission.resubmissions);
+                self.sub = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Drive any resubmission in progress, returning an error if it failed.
+    fn poll_resubmission(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Result<(), BasicError>> {
+        let resubmission = match self.resubmission.as_mut() {
+            Some(resubmission) => resubmission,
+            None => return Poll::Ready(Ok(())),
+        };
+        if let Some(pending) = resubmission.pending.as_mut() {
+            let res = futures::ready!(pending.poll_unpin(cx));
+            resubmission.pending = None;
+            let (sub, ext_hash) = res?;
+            self.sub = Some(sub);
+            self.ext_hash = ext_hash;
+            #[cfg(feature = "tracing")]
+            self.span
+                .record("ext_hash", &tracing::field::debug(&ext_hash));
+        }
+        let timed_out = resubmission
+            .future_timeout
+            .as_mut()
+            .map_or(false, |timeout| timeout.poll_unpin(cx).is_ready());
+        if timed_out {
+            resubmission.future_timeout = None;
+            if self.try_resubmit(false) {
+                return self.poll_resubmission(cx)
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    /// Keep track of how long the transaction has been in the `Future` queue.
+    fn observe_status(&mut self, status: &SubstrateTransactionStatus<T::Hash, T::Hash>) {
+        if let Some(resubmission) = self.resubmission.as_mut() {
+            match (status, resubmission.policy.future_timeout) {
+                (SubstrateTransactionStatus::Future, Some(timeout)) => {
+                    if resubmission.future_timeout.is_none() {
+                        resubmission.future_timeout = Some(Delay::new(timeout));
+                    }
+                }
+                _ => resubmission.future_timeout = None,
+            }
+        }
+    }
+
     /// Return the next transaction status when it's emitted. This just delegates to the
     /// [`futures::Stream`] implementation for [`TransactionProgress`], but allows you to
     /// avoid importing that trait if you don't otherwise need it.
@@ -104,7 +284,34 @@ impl<'client, T: Config, E: Decode + HasModuleError, Evs: Decode>
     /// may well indicate with some probability that the transaction will not make it into a block,
     /// there is no guarantee that this is true. Thus, we prefer to "play it safe" here. Use the lower
     /// level [`TransactionProgress::next_item()`] API if you'd like to handle these statuses yourself.
+    ///
+    /// **Note:** if a timeout was set with [`crate::ClientBuilder::set_transaction_timeout()`],
+    /// this gives up with [`TransactionError::TimedOut`] once it has elapsed.
     pub async fn wait_for_in_block(
+        self,
+    ) -> Result<TransactionInBlock<'client, T, E, Evs>, BasicError> {
+        let timeout = self.client.transaction_timeout();
+        with_deadline(self.in_block(), timeout).await
+    }
+
+    /// Like [`TransactionProgress::wait_for_in_block()`], but gives up with
+    /// [`TransactionError::TimedOut`] if the transaction isn't in a block within `timeout`.
+    pub async fn wait_for_in_block_timeout(
+        self,
+        timeout: Duration,
+    ) -> Result<TransactionInBlock<'client, T, E, Evs>, BasicError> {
+        with_deadline(self.in_block(), Some(timeout)).await
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "wait_for_in_block",
+            skip_all,
+            fields(ext_hash = ?self.ext_hash)
+        )
+    )]
+    async fn in_block(
         mut self,
     ) -> Result<TransactionInBlock<'client, T, E, Evs>, BasicError> {
         while let Some(status) = self.next_item().await {
@@ -134,7 +341,34 @@ impl<'client, T: Config, E: Decode + HasModuleError, Evs: Decode>
     /// may well indicate with some probability that the transaction will not make it into a block,
     /// there is no guarantee that this is true. Thus, we prefer to "play it safe" here. Use the lower
     /// level [`TransactionProgress::next_item()`] API if you'd like to handle these statuses yourself.
+    ///
+    /// **Note:** if a timeout was set with [`crate::ClientBuilder::set_transaction_timeout()`],
+    /// this gives up with [`TransactionError::TimedOut`] once it has elapsed.
     pub async fn wait_for_finalized(
+        self,
+    ) -> Result<TransactionInBlock<'client, T, E, Evs>, BasicError> {
+        let timeout = self.client.transaction_timeout();
+        with_deadline(self.finalized(), timeout).await
+    }
+
+    /// Like [`TransactionProgress::wait_for_finalized()`], but gives up with
+    /// [`TransactionError::TimedOut`] if the transaction isn't finalized within `timeout`.
+    pub async fn wait_for_finalized_timeout(
+        self,
+        timeout: Duration,
+    ) -> Result<TransactionInBlock<'client, T, E, Evs>, BasicError> {
+        with_deadline(self.finalized(), Some(timeout)).await
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "wait_for_finalized",
+            skip_all,
+            fields(ext_hash = ?self.ext_hash)
+        )
+    )]
+    async fn finalized(
         mut self,
     ) -> Result<TransactionInBlock<'client, T, E, Evs>, BasicError> {
         while let Some(status) = self.next_item().await {
@@ -169,6 +403,69 @@ impl<'client, T: Config, E: Decode + HasModuleError, Evs: Decode>
         let evs = self.wait_for_finalized().await?.wait_for_success().await?;
         Ok(evs)
     }
+
+    /// Like [`TransactionProgress::wait_for_finalized_success()`], but gives up with
+    /// [`TransactionError::TimedOut`] if the transaction isn't finalized within `timeout`.
+    pub async fn wait_for_finalized_success_timeout(
+        self,
+        timeout: Duration,
+    ) -> Result<TransactionEvents<'client, T, Evs>, Error<E>> {
+        let evs = self
+            .wait_for_finalized_timeout(timeout)
+            .await?
+            .wait_for_success()
+            .await?;
+        Ok(evs)
+    }
+
+    /// Wait for the transaction to be in a block (but not necessarily finalized), and for
+    /// the transaction events to indicate that the transaction was successful. Returns the
+    /// events associated with the transaction, as well as a couple of other details (block
+    /// hash and extrinsic hash).
+    ///
+    /// **Note:** a block that the transaction is in may yet be retracted, in which case
+    /// the returned events may no longer reflect the state of the chain. Use
+    /// [`TransactionProgress::wait_for_finalized_success()`] if that matters.
+    ///
+    /// **Note:** consumes self. If you'd like to perform multiple actions as progress is made,
+    /// use [`TransactionProgress::next_item()`] instead.
+    pub async fn wait_for_in_block_success(
+        self,
+    ) -> Result<TransactionEvents<'client, T, Evs>, Error<E>> {
+        let evs = self.wait_for_in_block().await?.wait_for_success().await?;
+        Ok(evs)
+    }
+
+    /// Like [`TransactionProgress::wait_for_in_block_success()`], but gives up with
+    /// [`TransactionError::TimedOut`] if the transaction isn't in a block within `timeout`.
+    pub async fn wait_for_in_block_success_timeout(
+        self,
+        timeout: Duration,
+    ) -> Result<TransactionEvents<'client, T, Evs>, Error<E>> {
+        let evs = self
+            .wait_for_in_block_timeout(timeout)
+            .await?
+            .wait_for_success()
+            .await?;
+        Ok(evs)
+    }
+}
+
+/// Wait for `fut` to complete, or fail with [`TransactionError::TimedOut`] if a timeout
+/// is given and it elapses first.
+async fn with_deadline<R>(
+    fut: impl Future<Output = Result<R, BasicError>>,
+    timeout: Option<Duration>,
+) -> Result<R, BasicError> {
+    let timeout = match timeout {
+        Some(timeout) => timeout,
+        None => return fut.await,
+    };
+    futures::pin_mut!(fut);
+    match future::select(fut, Delay::new(timeout)).await {
+        Either::Left((res, _)) => res,
+        Either::Right(_) => Err(TransactionError::TimedOut(timeout).into()),
+    }
 }
 
 impl<'client, T: Config, E: Decode + HasModuleError, Evs: Decode> Stream
@@ -180,58 +477,95 @@ impl<'client, T: Config, E: Decode + HasModuleError, Evs: Decode> Stream
         mut self: std::pin::Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Option<Self::Item>> {
-        let sub = match self.sub.as_mut() {
-            Some(sub) => sub,
-            None => return Poll::Ready(None),
+        // The client has been shut down; stop listening for updates.
+        if self.shutdown.poll_shut_down(cx) {
+            self.sub = None;
+            self.resubmission = None;
+            return Poll::Ready(None)
+        }
+
+        #[cfg(feature = "tracing")]
+        let span = self.span.clone();
+        #[cfg(feature = "tracing")]
+        let _entered = span.enter();
+
+        let status = loop {
+            if let Err(e) = futures::ready!(self.poll_resubmission(cx)) {
+                self.resubmission = None;
+                return Poll::Ready(Some(Err(e)))
+            }
+            let sub = match self.sub.as_mut() {
+                Some(sub) => sub,
+                None => return Poll::Ready(None),
+            };
+            let status = futures::ready!(sub.poll_next_unpin(cx));
+            if let Some(Ok(status)) = &status {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(?status, "transaction status");
+                self.observe_status(status);
+                let resubmitted = match status {
+                    SubstrateTransactionStatus::Dropped => self.try_resubmit(false),
+                    // The nonce has been used by another transaction.
+                    SubstrateTransactionStatus::Usurped(_) => self.try_resubmit(true),
+                    _ => false,
+                };
+                if resubmitted {
+                    continue
+                }
+            }
+            break status
         };
 
-        sub.poll_next_unpin(cx)
-            .map_err(|e| e.into())
-            .map_ok(|status| {
-                match status {
-                    SubstrateTransactionStatus::Future => TransactionStatus::Future,
-                    SubstrateTransactionStatus::Ready => TransactionStatus::Ready,
-                    SubstrateTransactionStatus::Broadcast(peers) => {
-                        TransactionStatus::Broadcast(peers)
-                    }
-                    SubstrateTransactionStatus::InBlock(hash) => {
-                        TransactionStatus::InBlock(TransactionInBlock::new(
-                            hash,
-                            self.ext_hash,
-                            self.client,
-                        ))
-                    }
-                    SubstrateTransactionStatus::Retracted(hash) => {
-                        TransactionStatus::Retracted(hash)
-                    }
-                    SubstrateTransactionStatus::Usurped(hash) => {
-                        TransactionStatus::Usurped(hash)
-                    }
-                    SubstrateTransactionStatus::Dropped => TransactionStatus::Dropped,
-                    SubstrateTransactionStatus::Invalid => TransactionStatus::Invalid,
-                    // Only the following statuses are actually considered "final" (see the substrate
-                    // docs on `TransactionStatus`). Basically, either the transaction makes it into a
-                    // block, or we eventually give up on waiting for it to make it into a block.
-                    // Even `Dropped`/`Invalid`/`Usurped` transactions might make it into a block eventually.
-                    //
-                    // As an example, a transaction that is `Invalid` on one node due to having the wrong
-                    // nonce might still be valid on some fork on another node which ends up being finalized.
-                    // Equally, a transaction `Dropped` from one node may still be in the transaction pool,
-                    // and make it into a block, on another node. Likewise with `Usurped`.
-                    SubstrateTransactionStatus::FinalityTimeout(hash) => {
-                        self.sub = None;
-                        TransactionStatus::FinalityTimeout(hash)
-                    }
-                    SubstrateTransactionStatus::Finalized(hash) => {
-                        self.sub = None;
-                        TransactionStatus::Finalized(TransactionInBlock::new(
-                            hash,
-                            self.ext_hash,
-                            self.client,
-                        ))
+        Poll::Ready(status).map_err(|e| e.into()).map_ok(|status| {
+            match status {
+                SubstrateTransactionStatus::Future => TransactionStatus::Future,
+                SubstrateTransactionStatus::Ready => TransactionStatus::Ready,
+                SubstrateTransactionStatus::Broadcast(peers) => {
+                    TransactionStatus::Broadcast(peers)
+                }
+                SubstrateTransactionStatus::InBlock(hash) => {
+                    TransactionStatus::InBlock(TransactionInBlock::new(
+                        hash,
+                        self.ext_hash,
+                        self.client,
+                    ))
+                }
+                SubstrateTransactionStatus::Retracted(hash) => {
+                    TransactionStatus::Retracted(hash)
+                }
+                SubstrateTransactionStatus::Usurped(hash) => {
+                    TransactionStatus::Usurped(hash)
+                }
+                SubstrateTransactionStatus::Dropped => TransactionStatus::Dropped,
+                SubstrateTransactionStatus::Invalid => TransactionStatus::Invalid,
+                // Only the following statuses are actually considered "final" (see the substrate
+                // docs on `TransactionStatus`). Basically, either the transaction makes it into a
+                // block, or we eventually give up on waiting for it to make it into a block.
+                // Even `Dropped`/`Invalid`/`Usurped` transactions might make it into a block eventually.
+                //
+                // As an example, a transaction that is `Invalid` on one node due to having the wrong
+                // nonce might still be valid on some fork on another node which ends up being finalized.
+                // Equally, a transaction `Dropped` from one node may still be in the transaction pool,
+                // and make it into a block, on another node. Likewise with `Usurped`.
+                SubstrateTransactionStatus::FinalityTimeout(hash) => {
+                    self.sub = None;
+                    TransactionStatus::FinalityTimeout(hash)
+                }
+                SubstrateTransactionStatus::Finalized(hash) => {
+                    self.sub = None;
+                    if let (Some(metrics), Some(elapsed)) =
+                        (self.client.rpc().metrics(), self.submitted.elapsed())
+                    {
+                        metrics.time_to_finality(elapsed);
                     }
+                    TransactionStatus::Finalized(TransactionInBlock::new(
+                        hash,
+                        self.ext_hash,
+                        self.client,
+                    ))
                 }
-            })
+            }
+        })
     }
 }
 
@@ -330,6 +664,32 @@ impl<'client, T: Config, E: Decode, Evs: Decode> TransactionStatus<'client, T, E
     }
 }
 
+/// The outcome of [`crate::Client::submit_once()`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SubmissionStatus<Hash> {
+    /// The extrinsic has been submitted.
+    Submitted(Hash),
+    /// The extrinsic was already in the transaction pool, so it wasn't submitted again.
+    InPool(Hash),
+    /// The extrinsic was already included in a block, so it wasn't submitted again.
+    InBlock {
+        /// The hash of the extrinsic.
+        extrinsic_hash: Hash,
+        /// The hash of the block it was included in.
+        block_hash: Hash,
+    },
+}
+
+impl<Hash: Copy> SubmissionStatus<Hash> {
+    /// Returns the hash of the extrinsic.
+    pub fn extrinsic_hash(&self) -> Hash {
+        match self {
+            SubmissionStatus::Submitted(hash) | SubmissionStatus::InPool(hash) => *hash,
+            SubmissionStatus::InBlock { extrinsic_hash, .. } => *extrinsic_hash,
+        }
+    }
+}
+
 /// This struct represents a transaction that has made it into a block.
 #[derive(Derivative)]
 #[derivative(Debug(bound = ""))]
@@ -389,18 +749,10 @@ impl<'client, T: Config, E: Decode + HasModuleError, Evs: Decode>
             let ev = ev?;
             if &ev.pallet == "System" && &ev.variant == "ExtrinsicFailed" {
                 let dispatch_error = E::decode(&mut &*ev.data)?;
-                if let Some((pallet_idx, error_idx)) =
-                    dispatch_error.module_error_indices()
-                {
-                    let details = self.client.metadata().error(pallet_idx, error_idx)?;
-                    return Err(Error::Module(ModuleError {
-                        pallet: details.pallet().to_string(),
-                        error: details.error().to_string(),
-                        description: details.description().to_vec(),
-                    }))
-                } else {
-                    return Err(Error::Runtime(RuntimeError(dispatch_error)))
-                }
+                return Err(Error::from_dispatch_error(
+                    &self.client.metadata(),
+                    dispatch_error,
+                ))
             }
         }
 
@@ -413,6 +765,14 @@ impl<'client, T: Config, E: Decode + HasModuleError, Evs: Decode>
     ///
     /// **Note:** This has to download block details from the node and decode events
     /// from them.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "fetch_events",
+            skip_all,
+            fields(block_hash = ?self.block_hash, ext_hash = ?self.ext_hash)
+        )
+    )]
     pub async fn fetch_events(
         &self,
     ) -> Result<TransactionEvents<'client, T, Evs>, BasicError> {
@@ -527,3 +887,22 @@ impl<'client, T: Config, Evs: Decode> TransactionEvents<'client, T, Evs> {
         Ok(self.find::<Ev>().next().transpose()?.is_some())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[async_std::test]
+    async fn deadline_elapses_before_slow_future() {
+        let never = future::pending::<Result<(), BasicError>>();
+        let res = with_deadline(never, Some(Duration::from_millis(10))).await;
+        assert!(matches!(
+            res,
+            Err(BasicError::Transaction(TransactionError::TimedOut(_)))
+        ));
+
+        let ready = future::ready(Ok::<_, BasicError>(1));
+        let res = with_deadline(ready, Some(Duration::from_secs(60))).await;
+        assert!(matches!(res, Ok(1)));
+    }
+}
@@ -0,0 +1,302 @@
+// Copyright 2019-2022 Parity Technologies (UK) Ltd.
+// This file is part of subxt.
+//
+// subxt is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// subxt is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with subxt.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Types describing the weight of dispatchables, which understand both the
+//! one-dimensional weights of older runtimes and the two-dimensional weights
+//! (`ref_time` + `proof_size`) of newer ones.
+
+use crate::{
+    error::BasicError,
+    metadata::MetadataError,
+    Metadata,
+};
+use codec::{
+    Compact,
+    Decode,
+    Encode,
+};
+use scale_info::{
+    TypeDef,
+    TypeDefPrimitive,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// The weight of a dispatchable.
+///
+/// Older runtimes only measure the computation time (`ref_time`); when a weight
+/// from such a runtime is converted into this type, `proof_size` is zero.
+///
+/// The SCALE encoding of this type is that of the current, two-dimensional weight.
+/// Use [`Weight::decode_with_metadata()`] to decode a weight from any runtime.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Encode,
+    Decode,
+    Serialize,
+    Deserialize,
+)]
+#[serde(from = "WeightRepr")]
+pub struct Weight {
+    /// The computational time used to execute some logic, in picoseconds.
+    #[codec(compact)]
+    pub ref_time: u64,
+    /// The size of the storage proof needed to execute some logic, in bytes.
+    #[codec(compact)]
+    pub proof_size: u64,
+}
+
+impl Weight {
+    /// A weight of zero.
+    pub const fn zero() -> Self {
+        Self {
+            ref_time: 0,
+            proof_size: 0,
+        }
+    }
+
+    /// Construct a weight from its two components.
+    pub const fn from_parts(ref_time: u64, proof_size: u64) -> Self {
+        Self {
+            ref_time,
+            proof_size,
+        }
+    }
+
+    /// Construct a weight from a computational time only, as used by older runtimes.
+    pub const fn from_ref_time(ref_time: u64) -> Self {
+        Self::from_parts(ref_time, 0)
+    }
+
+    /// Add two weights together, saturating each component at its maximum value.
+    pub fn saturating_add(self, other: Self) -> Self {
+        Self {
+            ref_time: self.ref_time.saturating_add(other.ref_time),
+            proof_size: self.proof_size.saturating_add(other.proof_size),
+        }
+    }
+
+    /// Is either component of this weight greater than that of `other`?
+    pub fn any_gt(&self, other: &Self) -> bool {
+        self.ref_time > other.ref_time || self.proof_size > other.proof_size
+    }
+
+    /// Decode a weight whose shape is described by the type with the given id in the
+    /// metadata. This handles both plain `u64` weights and weight structs, whose
+    /// `ref_time` and `proof_size` fields may or may not be compact encoded.
+    pub fn decode_with_metadata(
+        metadata: &Metadata,
+        type_id: u32,
+        input: &mut &[u8],
+    ) -> Result<Self, BasicError> {
+        let ty = metadata
+            .resolve_type(type_id)
+            .ok_or(MetadataError::TypeNotFound(type_id))?;
+        match ty.type_def() {
+            TypeDef::Primitive(TypeDefPrimitive::U64) => {
+                Ok(Weight::from_ref_time(u64::decode(input)?))
+            }
+            TypeDef::Compact(_) => {
+                Ok(Weight::from_ref_time(<Compact<u64>>::decode(input)?.0))
+            }
+            TypeDef::Composite(composite) => {
+                let mut weight = Weight::zero();
+                for field in composite.fields() {
+                    let value = decode_u64(metadata, field.ty().id(), input)?;
+                    match field.name().map(|name| name.as_str()) {
+                        Some("ref_time") | None => weight.ref_time = value,
+                        Some("proof_size") => weight.proof_size = value,
+                        Some(_) => {}
+                    }
+                }
+                Ok(weight)
+            }
+            _ => {
+                Err(BasicError::Other(format!(
+                    "Type {} is not a weight type",
+                    type_id
+                )))
+            }
+        }
+    }
+}
+
+impl From<u64> for Weight {
+    fn from(ref_time: u64) -> Self {
+        Weight::from_ref_time(ref_time)
+    }
+}
+
+fn decode_u64(
+    metadata: &Metadata,
+    type_id: u32,
+    input: &mut &[u8],
+) -> Result<u64, BasicError> {
+    let ty = metadata
+        .resolve_type(type_id)
+        .ok_or(MetadataError::TypeNotFound(type_id))?;
+    match ty.type_def() {
+        TypeDef::Compact(_) => Ok(<Compact<u64>>::decode(input)?.0),
+        _ => Ok(u64::decode(input)?),
+    }
+}
+
+/// The JSON representations of a weight, as returned by the RPC methods of older
+/// and newer nodes.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum WeightRepr {
+    V1(u64),
+    V2 {
+        #[serde(alias = "refTime")]
+        ref_time: u64,
+        #[serde(default, alias = "proofSize")]
+        proof_size: u64,
+    },
+}
+
+impl From<WeightRepr> for Weight {
+    fn from(repr: WeightRepr) -> Self {
+        match repr {
+            WeightRepr::V1(ref_time) => Weight::from_ref_time(ref_time),
+            WeightRepr::V2 {
+                ref_time,
+                proof_size,
+            } => Weight::from_parts(ref_time, proof_size),
+        }
+    }
+}
+
+/// The class of a dispatchable.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, Hash, Encode, Decode, Serialize, Deserialize,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum DispatchClass {
+    /// A normal dispatch.
+    Normal,
+    /// An operational dispatch.
+    Operational,
+    /// A mandatory dispatch, which is always included in a block.
+    Mandatory,
+}
+
+impl Default for DispatchClass {
+    fn default() -> Self {
+        DispatchClass::Normal
+    }
+}
+
+/// Whether the sender of a dispatchable pays a fee for it.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, Hash, Encode, Decode, Serialize, Deserialize,
+)]
+pub enum Pays {
+    /// The transaction pays a fee.
+    Yes,
+    /// The transaction does not pay a fee.
+    No,
+}
+
+impl Default for Pays {
+    fn default() -> Self {
+        Pays::Yes
+    }
+}
+
+/// Information about a dispatchable, as included in the `ExtrinsicSuccess` and
+/// `ExtrinsicFailed` events.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Encode, Decode)]
+pub struct DispatchInfo {
+    /// The weight of the dispatchable.
+    pub weight: Weight,
+    /// The class of the dispatchable.
+    pub class: DispatchClass,
+    /// Whether the dispatchable pays a fee.
+    pub pays_fee: Pays,
+}
+
+impl DispatchInfo {
+    /// Decode the dispatch info of a runtime using either one or two-dimensional
+    /// weights, given the id of the `DispatchInfo` type in its metadata.
+    pub fn decode_with_metadata(
+        metadata: &Metadata,
+        type_id: u32,
+        input: &mut &[u8],
+    ) -> Result<Self, BasicError> {
+        let ty = metadata
+            .resolve_type(type_id)
+            .ok_or(MetadataError::TypeNotFound(type_id))?;
+        let weight_type_id = match ty.type_def() {
+            TypeDef::Composite(composite) => {
+                composite
+                    .fields()
+                    .iter()
+                    .find(|field| field.name().map(|n| n.as_str()) == Some("weight"))
+                    .map(|field| field.ty().id())
+            }
+            _ => None,
+        }
+        .ok_or_else(|| {
+            BasicError::Other(format!("Type {} is not a DispatchInfo type", type_id))
+        })?;
+
+        let weight = Weight::decode_with_metadata(metadata, weight_type_id, input)?;
+        let class = DispatchClass::decode(input)?;
+        let pays_fee = Pays::decode(input)?;
+        Ok(DispatchInfo {
+            weight,
+            class,
+            pays_fee,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weight_deserializes_from_v1_and_v2_json() {
+        let v1: Weight = serde_json::from_str("1234").unwrap();
+        assert_eq!(v1, Weight::from_ref_time(1234));
+
+        let v2: Weight =
+            serde_json::from_str(r#"{ "ref_time": 1234, "proof_size": 56 }"#).unwrap();
+        assert_eq!(v2, Weight::from_parts(1234, 56));
+
+        let v2_camel: Weight =
+            serde_json::from_str(r#"{ "refTime": 1234, "proofSize": 56 }"#).unwrap();
+        assert_eq!(v2_camel, v2);
+    }
+
+    #[test]
+    fn weight_encodes_as_compact_pair() {
+        let weight = Weight::from_parts(1, 2);
+        assert_eq!(weight.encode(), (Compact(1u64), Compact(2u64)).encode());
+        assert_eq!(Weight::decode(&mut &weight.encode()[..]).unwrap(), weight);
+    }
+}
@@ -15,16 +15,33 @@
 // along with subxt.  If not, see <http://www.gnu.org/licenses/>.
 
 use crate::{
+    pair_signer,
+    test_context,
     test_node_process,
     test_node_process_with,
-    utils::node_runtime::system,
+    utils::{
+        node_runtime,
+        node_runtime::system,
+        NodeRuntimeSignedExtra,
+    },
 };
 
+use futures::StreamExt;
 use sp_core::storage::{
     well_known_keys,
     StorageKey,
 };
 use sp_keyring::AccountKeyring;
+use sp_runtime::traits::Header;
+use std::time::Duration;
+use subxt::{
+    extrinsic::AssetTxPaymentParameters,
+    BasicError,
+    ClientBuilder,
+    DefaultConfig,
+    ExtrinsicOrHash,
+    SubmissionStatus,
+};
 
 #[async_std::test]
 async fn insert_key() {
@@ -131,3 +148,266 @@ async fn fetch_system_info() {
     assert_eq!(client.rpc().system_name().await.unwrap(), "Substrate Node");
     assert!(!client.rpc().system_version().await.unwrap().is_empty());
 }
+
+#[async_std::test]
+async fn call_runtime_api() {
+    let cxt = test_context().await;
+    let alice = AccountKeyring::Alice.to_account_id();
+
+    let nonce: u32 = cxt
+        .client()
+        .runtime_api()
+        .call("AccountNonceApi_account_nonce", &alice, None)
+        .await
+        .unwrap();
+    let account = cxt
+        .api
+        .storage()
+        .system()
+        .account(&alice, None)
+        .await
+        .unwrap();
+    assert_eq!(nonce, account.nonce);
+
+    // Unknown methods are refused by the node.
+    assert!(cxt
+        .client()
+        .runtime_api()
+        .call_raw("NoSuchApi_no_such_method", &[], None)
+        .await
+        .is_err());
+}
+
+#[async_std::test]
+async fn submit_once_does_not_resubmit() {
+    let ctx = test_context().await;
+    let signer = pair_signer(AccountKeyring::Alice.pair());
+    let extrinsic = ctx
+        .api
+        .tx()
+        .system()
+        .remark(vec![1, 2, 3])
+        .create_signed(&signer, Default::default())
+        .await
+        .unwrap();
+
+    let first = ctx.client().submit_once(&extrinsic, 10).await.unwrap();
+    assert!(matches!(first, SubmissionStatus::Submitted(_)));
+
+    let second = ctx.client().submit_once(&extrinsic, 10).await.unwrap();
+    assert!(!matches!(second, SubmissionStatus::Submitted(_)));
+    assert_eq!(first.extrinsic_hash(), second.extrinsic_hash());
+}
+
+#[async_std::test]
+async fn pending_extrinsics_are_decoded_and_removed() {
+    let ctx = test_context().await;
+    let mut signer = pair_signer(AccountKeyring::Alice.pair());
+
+    // Skip a nonce, so that the extrinsic stays in the future queue.
+    let nonce = ctx
+        .client()
+        .rpc()
+        .system_account_next_index(signer.account_id())
+        .await
+        .unwrap();
+    signer.set_nonce(nonce + 1);
+    let hash = ctx
+        .api
+        .tx()
+        .system()
+        .remark(vec![1, 2, 3])
+        .sign_and_submit(&signer)
+        .await
+        .unwrap();
+
+    let pending = ctx.client().pending_extrinsics().await.unwrap();
+    let (_, decoded) = pending
+        .iter()
+        .find(|(h, _)| *h == hash)
+        .expect("extrinsic is in the pool");
+    assert_eq!(decoded.pallet, "System");
+    assert_eq!(decoded.call, "remark");
+    assert_eq!(decoded.nonce, Some((nonce + 1) as u64));
+    assert!(decoded.address.is_some());
+
+    let removed = ctx
+        .client()
+        .rpc()
+        .remove_extrinsic(vec![ExtrinsicOrHash::Hash(hash)])
+        .await
+        .unwrap();
+    assert_eq!(removed, vec![hash]);
+    let pending = ctx.client().pending_extrinsics().await.unwrap();
+    assert!(pending.iter().all(|(h, _)| *h != hash));
+}
+
+#[async_std::test]
+async fn blocks_are_fetched_with_decoded_extrinsics() {
+    let ctx = test_context().await;
+    let signer = pair_signer(AccountKeyring::Alice.pair());
+    let in_block = ctx
+        .api
+        .tx()
+        .system()
+        .remark(vec![1, 2, 3])
+        .sign_and_submit_then_watch(&signer)
+        .await
+        .unwrap()
+        .wait_for_in_block()
+        .await
+        .unwrap();
+
+    let block = ctx
+        .client()
+        .blocks()
+        .at(Some(in_block.block_hash()))
+        .await
+        .unwrap()
+        .expect("block exists");
+    assert_eq!(block.hash(), in_block.block_hash());
+    let index = block
+        .extrinsic_hashes()
+        .position(|hash| hash == in_block.extrinsic_hash())
+        .expect("extrinsic is in the block");
+    let remark = block.extrinsics().nth(index).unwrap().unwrap();
+    assert_eq!(remark.pallet, "System");
+    assert_eq!(remark.call, "remark");
+    assert!(remark.address.is_some());
+
+    let events = block.events::<node_runtime::Event>().await.unwrap();
+    assert!(events
+        .iter_raw_for_extrinsic(index as u32)
+        .any(|ev| ev.unwrap().variant == "ExtrinsicSuccess"));
+}
+
+#[async_std::test]
+async fn finalized_blocks_are_subscribed_to_in_order() {
+    let ctx = test_context().await;
+    let mut blocks = ctx.client().blocks().subscribe_finalized().await.unwrap();
+
+    let first = blocks.next().await.unwrap().unwrap();
+    let second = blocks.next().await.unwrap().unwrap();
+    assert_eq!(second.number(), first.number() + 1);
+    assert_eq!(*second.header().parent_hash(), first.hash());
+    // Every block has at least the timestamp inherent.
+    assert!(second
+        .extrinsics()
+        .any(|xt| xt.unwrap().pallet == "Timestamp"));
+}
+
+#[async_std::test]
+async fn shutdown_refuses_new_requests() {
+    let node_process = test_node_process().await;
+    let client = node_process.client();
+    client.shutdown(Duration::from_secs(5)).await.unwrap();
+
+    assert!(client.is_shut_down());
+    let res = client.rpc().system_chain().await;
+    assert!(matches!(res, Err(BasicError::ShutDown)));
+}
+
+#[async_std::test]
+async fn fetch_headers_range() {
+    let node_process = test_node_process().await;
+    let client = node_process.client();
+    // Make sure that there are a few blocks to fetch.
+    let mut blocks = client.rpc().subscribe_blocks().await.unwrap();
+    while blocks.next().await.unwrap().unwrap().number < 3 {}
+
+    let headers: Vec<_> = client
+        .headers_range(0..=3, 2)
+        .map(|header| header.unwrap())
+        .collect()
+        .await;
+    let numbers: Vec<_> = headers.iter().map(|header| header.number).collect();
+    assert_eq!(numbers, vec![0, 1, 2, 3]);
+    for header in &headers {
+        let hash = client
+            .rpc()
+            .block_hash(Some(header.number.into()))
+            .await
+            .unwrap();
+        assert_eq!(hash, Some(header.hash()));
+    }
+}
+
+#[async_std::test]
+async fn build_with_supplied_metadata() {
+    let node_process = test_node_process().await;
+    let bytes = node_process
+        .client()
+        .rpc()
+        .metadata_bytes(None)
+        .await
+        .unwrap();
+
+    let client = ClientBuilder::new()
+        .set_url(node_process.ws_url())
+        .set_metadata_bytes(&bytes)
+        .build::<DefaultConfig>()
+        .await
+        .unwrap();
+    assert!(client.metadata().pallet("Balances").is_ok());
+    assert_eq!(
+        client.runtime_version(),
+        node_process.client().runtime_version()
+    );
+}
+
+#[async_std::test]
+async fn default_extra_is_used_without_explicit_params() {
+    let node_process = test_node_process().await;
+    let client = ClientBuilder::new()
+        .set_url(node_process.ws_url())
+        .set_default_extra(AssetTxPaymentParameters::new().set_tip(1_000))
+        .build::<DefaultConfig>()
+        .await
+        .unwrap();
+    let api: node_runtime::RuntimeApi<DefaultConfig, NodeRuntimeSignedExtra> =
+        client.clone().to_runtime_api();
+    let mut signer = pair_signer(AccountKeyring::Alice.pair());
+
+    // Skip a nonce, so that the extrinsic stays in the pool to be inspected.
+    let nonce = client
+        .rpc()
+        .system_account_next_index(signer.account_id())
+        .await
+        .unwrap();
+    signer.set_nonce(nonce + 1);
+    let hash = api
+        .tx()
+        .system()
+        .remark(vec![1, 2, 3])
+        .sign_and_submit(&signer)
+        .await
+        .unwrap();
+
+    let pending = client.pending_extrinsics().await.unwrap();
+    let (_, decoded) = pending
+        .iter()
+        .find(|(h, _)| *h == hash)
+        .expect("extrinsic is in the pool");
+    assert_eq!(decoded.tip, Some(1_000));
+}
+
+#[async_std::test]
+async fn build_with_unreachable_endpoints_first() {
+    let node_process = test_node_process().await;
+    let client = ClientBuilder::new()
+        .set_urls(vec!["ws://127.0.0.1:1", node_process.ws_url()])
+        .build::<DefaultConfig>()
+        .await
+        .unwrap();
+    assert_eq!(client.genesis(), node_process.client().genesis());
+}
+
+#[async_std::test]
+async fn refresh_metadata_without_upgrade() {
+    let node_process = test_node_process().await;
+    let client = node_process.client();
+    let spec_version = client.runtime_version().spec_version;
+
+    assert_eq!(client.refresh_metadata().await.unwrap(), None);
+    assert_eq!(client.runtime_version().spec_version, spec_version);
+}
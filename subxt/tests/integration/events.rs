@@ -16,6 +16,7 @@
 
 use crate::{
     node_runtime::{
+        self,
         balances,
         system,
     },
@@ -136,6 +137,43 @@ async fn balance_transfer_subscription() -> Result<(), subxt::BasicError> {
     Ok(())
 }
 
+// Check that the client can subscribe to events without going through the
+// generated API.
+#[async_std::test]
+async fn client_events_subscription() -> Result<(), subxt::BasicError> {
+    env_logger::try_init().ok();
+    let ctx = test_context().await;
+
+    let event_sub = ctx
+        .client()
+        .events()
+        .subscribe_finalized::<node_runtime::Event>()
+        .await?
+        .filter_events::<(balances::events::Transfer,)>();
+    futures::pin_mut!(event_sub);
+
+    let alice = pair_signer(AccountKeyring::Alice.pair());
+    let bob = AccountKeyring::Bob.to_account_id();
+    ctx.api
+        .tx()
+        .balances()
+        .transfer(bob.clone().into(), 10_000)
+        .sign_and_submit_then_watch(&alice)
+        .await?;
+
+    let details = event_sub.next().await.unwrap()?;
+    assert_eq!(details.event.to, bob);
+
+    // The same events can be fetched for the block afterwards.
+    let events = ctx
+        .client()
+        .events()
+        .at::<node_runtime::Event>(details.block_hash)
+        .await?;
+    assert!(events.find_first::<balances::events::Transfer>()?.is_some());
+    Ok(())
+}
+
 #[async_std::test]
 async fn missing_block_headers_will_be_filled_in() -> Result<(), subxt::BasicError> {
     // This function is not publically available to use, but contains
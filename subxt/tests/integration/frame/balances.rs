@@ -16,6 +16,7 @@
 
 use crate::{
     node_runtime::{
+        self,
         balances,
         runtime_types,
         system,
@@ -24,7 +25,10 @@ use crate::{
     pair_signer,
     test_context,
 };
-use codec::Decode;
+use codec::{
+    Decode,
+    Encode,
+};
 use sp_core::{
     sr25519::Pair,
     Pair as _,
@@ -35,7 +39,12 @@ use sp_runtime::{
     MultiAddress,
 };
 use subxt::{
+    extrinsic::AssetTxPaymentParameters,
+    DispatchErrorDetails,
+    DryRunOutcome,
     Error,
+    OfflineClient,
+    ResubmitPolicy,
     Signer,
 };
 
@@ -98,6 +107,151 @@ async fn tx_basic_transfer() -> Result<(), subxt::Error<DispatchError>> {
     Ok(())
 }
 
+#[async_std::test]
+async fn tx_mortal_transfer() -> Result<(), subxt::Error<DispatchError>> {
+    let alice = pair_signer(AccountKeyring::Alice.pair());
+    let bob = pair_signer(AccountKeyring::Bob.pair());
+    let cxt = test_context().await;
+    let api = &cxt.api;
+
+    let events = api
+        .tx()
+        .balances()
+        .transfer(bob.account_id().clone().into(), 10_000)
+        .sign_and_submit_then_watch_with_params(
+            &alice,
+            AssetTxPaymentParameters::new().set_mortal(32),
+        )
+        .await?
+        .wait_for_finalized_success()
+        .await?;
+    assert!(events.find_first::<balances::events::Transfer>()?.is_some());
+    Ok(())
+}
+
+#[async_std::test]
+async fn tx_transfer_with_resubmit() -> Result<(), subxt::Error<DispatchError>> {
+    let alice = pair_signer(AccountKeyring::Alice.pair());
+    let bob = pair_signer(AccountKeyring::Bob.pair());
+    let cxt = test_context().await;
+
+    let progress = cxt
+        .api
+        .tx()
+        .balances()
+        .transfer(bob.account_id().clone().into(), 10_000)
+        .sign_and_submit_then_watch_with_resubmit(
+            &alice,
+            ResubmitPolicy::default(),
+            |resubmissions| {
+                AssetTxPaymentParameters::new().set_tip(resubmissions as u128 * 1_000)
+            },
+        )
+        .await?;
+    let ext_hash = progress.extrinsic_hash();
+    let events = progress.wait_for_finalized_success().await?;
+    assert_eq!(events.extrinsic_hash(), ext_hash);
+    Ok(())
+}
+
+#[async_std::test]
+async fn tx_replaced_with_higher_tip() -> Result<(), subxt::Error<DispatchError>> {
+    let mut alice = pair_signer(AccountKeyring::Alice.pair());
+    let bob = pair_signer(AccountKeyring::Bob.pair());
+    let cxt = test_context().await;
+
+    // Skip a nonce, so that the transaction is stuck in the future queue.
+    let nonce = cxt
+        .client()
+        .rpc()
+        .system_account_next_index(alice.account_id())
+        .await?;
+    alice.set_nonce(nonce + 1);
+    let stuck = cxt
+        .api
+        .tx()
+        .balances()
+        .transfer(bob.account_id().clone().into(), 10_000)
+        .sign_and_submit(&alice)
+        .await?;
+
+    let replacement = cxt
+        .api
+        .tx()
+        .balances()
+        .transfer(bob.account_id().clone().into(), 20_000)
+        .replace_with_higher_tip(
+            &alice,
+            stuck,
+            AssetTxPaymentParameters::new().set_tip(1_000),
+        )
+        .await?;
+    assert_ne!(replacement.extrinsic_hash(), stuck);
+
+    let pending = cxt.client().rpc().pending_extrinsics().await?;
+    let hashes: Vec<_> = pending
+        .iter()
+        .map(|ext| {
+            <sp_runtime::traits::BlakeTwo256 as sp_runtime::traits::Hash>::hash(&ext.0)
+        })
+        .collect();
+    assert!(hashes.contains(&replacement.extrinsic_hash()));
+    assert!(!hashes.contains(&stuck));
+    Ok(())
+}
+
+#[async_std::test]
+async fn tx_signed_offline_then_submitted() -> Result<(), subxt::Error<DispatchError>> {
+    let alice = pair_signer(AccountKeyring::Alice.pair());
+    let bob = pair_signer(AccountKeyring::Bob.pair());
+    let cxt = test_context().await;
+
+    // The chain data and nonce are the only things that need carrying over.
+    let offline = OfflineClient::from_client(cxt.client());
+    let nonce = cxt
+        .api
+        .storage()
+        .system()
+        .account(alice.account_id(), None)
+        .await?
+        .nonce;
+
+    let call = balances::calls::Transfer {
+        dest: bob.account_id().clone().into(),
+        value: 10_000,
+    };
+    let signed = offline
+        .create_signed(&call, nonce, &alice, Default::default())
+        .await?;
+
+    let events = cxt
+        .client()
+        .submit_signed_then_watch::<DispatchError, node_runtime::Event>(signed.encode())
+        .await?
+        .wait_for_finalized_success()
+        .await?;
+    assert!(events.find_first::<balances::events::Transfer>()?.is_some());
+    Ok(())
+}
+
+#[async_std::test]
+async fn estimate_transfer_fee() {
+    let alice = pair_signer(AccountKeyring::Alice.pair());
+    let bob = pair_signer(AccountKeyring::Bob.pair());
+    let cxt = test_context().await;
+
+    let estimate = cxt
+        .api
+        .tx()
+        .balances()
+        .transfer(bob.account_id().clone().into(), 10_000)
+        .estimate_fee(&alice)
+        .await
+        .unwrap();
+    assert!(estimate.info.partial_fee > 0);
+    assert_eq!(estimate.details.final_fee(), estimate.info.partial_fee);
+}
+
 #[async_std::test]
 async fn multiple_transfers_work_nonce_incremented(
 ) -> Result<(), subxt::Error<DispatchError>> {
@@ -229,6 +383,214 @@ async fn transfer_error() {
     }
 }
 
+#[async_std::test]
+async fn transfer_in_block_success_and_error() {
+    let alice = pair_signer(AccountKeyring::Alice.pair());
+    let alice_addr = alice.account_id().clone().into();
+    let hans = pair_signer(Pair::generate().0);
+    let hans_address = hans.account_id().clone().into();
+    let ctx = test_context().await;
+
+    let events = ctx
+        .api
+        .tx()
+        .balances()
+        .transfer(hans_address, 100_000_000_000_000_000)
+        .sign_and_submit_then_watch(&alice)
+        .await
+        .unwrap()
+        .wait_for_in_block_success()
+        .await
+        .unwrap();
+    assert!(events.has::<balances::events::Transfer>().unwrap());
+
+    let res = ctx
+        .api
+        .tx()
+        .balances()
+        .transfer(alice_addr, 100_000_000_000_000_000)
+        .sign_and_submit_then_watch(&hans)
+        .await
+        .unwrap()
+        .wait_for_in_block_success()
+        .await;
+
+    if let Err(Error::Module(err)) = res {
+        assert_eq!(err.pallet, "Balances");
+        assert_eq!(err.error, "InsufficientBalance");
+    } else {
+        panic!("expected a runtime module error");
+    }
+}
+
+#[async_std::test]
+async fn unsigned_transfer_is_refused() {
+    let bob_address = AccountKeyring::Bob.to_account_id().into();
+    let ctx = test_context().await;
+
+    // Transfers must be signed, so the node rejects the extrinsic outright.
+    let res = ctx
+        .api
+        .tx()
+        .balances()
+        .transfer(bob_address, 10_000)
+        .submit_unsigned()
+        .await;
+    assert!(
+        matches!(res, Err(subxt::BasicError::Rpc(_))),
+        "got {:?}",
+        res
+    );
+}
+
+#[async_std::test]
+async fn batch_of_transfers() -> Result<(), subxt::Error<DispatchError>> {
+    let alice = pair_signer(AccountKeyring::Alice.pair());
+    let bob = AccountKeyring::Bob.to_account_id();
+    let charlie = AccountKeyring::Charlie.to_account_id();
+    let ctx = test_context().await;
+
+    let calls = vec![
+        ctx.api
+            .tx()
+            .balances()
+            .transfer(bob.clone().into(), 10_000)
+            .encoded()?,
+        ctx.api
+            .tx()
+            .balances()
+            .transfer(charlie.clone().into(), 20_000)
+            .encoded()?,
+    ];
+    let events = ctx
+        .api
+        .tx()
+        .batch(calls)
+        .sign_and_submit_then_watch(&alice)
+        .await?
+        .wait_for_finalized_success()
+        .await?;
+
+    let outcome = subxt::BatchOutcome::<DispatchError>::from_events(&events)?;
+    assert_eq!(outcome.completed, 2);
+    assert!(outcome.is_complete());
+    let transfers = events
+        .find::<balances::events::Transfer>()
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(transfers.len(), 2);
+    assert_eq!(transfers[1].to, charlie);
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn dynamic_transfer() -> Result<(), subxt::Error<DispatchError>> {
+    use subxt::dynamic::Value;
+
+    let alice = pair_signer(AccountKeyring::Alice.pair());
+    let bob = AccountKeyring::Bob.to_account_id();
+    let ctx = test_context().await;
+
+    let args = vec![
+        Value::variant("Id", vec![Value::bytes(&bob)]),
+        Value::uint(10_000u32),
+    ];
+    let events = ctx
+        .api
+        .tx()
+        .dynamic("Balances", "transfer", args)?
+        .sign_and_submit_then_watch(&alice)
+        .await?
+        .wait_for_finalized_success()
+        .await?;
+
+    let transfer = events
+        .find_first::<balances::events::Transfer>()?
+        .expect("transfer event");
+    assert_eq!(transfer.to, bob);
+    assert_eq!(transfer.amount, 10_000);
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn dry_run_transfer() {
+    let alice = pair_signer(AccountKeyring::Alice.pair());
+    let bob_address = AccountKeyring::Bob.to_account_id().into();
+    let hans = pair_signer(Pair::generate().0);
+    let ctx = test_context().await;
+
+    let outcome = ctx
+        .api
+        .tx()
+        .balances()
+        .transfer(bob_address, 10_000)
+        .dry_run(&alice, None)
+        .await
+        .unwrap();
+    assert!(outcome.is_success(), "{:?}", outcome);
+
+    // Alice can pay the fees, but not send more than she has.
+    let bob_address = AccountKeyring::Bob.to_account_id().into();
+    let outcome = ctx
+        .api
+        .tx()
+        .balances()
+        .transfer(bob_address, u128::MAX / 2)
+        .dry_run(&alice, None)
+        .await
+        .unwrap();
+    assert!(
+        matches!(
+            &outcome,
+            DryRunOutcome::Failed(DispatchErrorDetails::Module(error))
+                if error.pallet == "Balances"
+        ),
+        "{:?}",
+        outcome
+    );
+
+    // Hans has nothing with which to pay the fees.
+    let alice_address = alice.account_id().clone().into();
+    let res = ctx
+        .api
+        .tx()
+        .balances()
+        .transfer(alice_address, 10_000)
+        .dry_run(&hans, None)
+        .await;
+    assert!(matches!(res, Err(Error::Invalid(_))), "{:?}", res);
+}
+
+#[async_std::test]
+async fn dry_run_at_block_leaves_nonce_alone() {
+    let alice = pair_signer(AccountKeyring::Alice.pair());
+    let bob_address = AccountKeyring::Bob.to_account_id().into();
+    let ctx = test_context().await;
+    let rpc = ctx.client().rpc();
+
+    let nonce = rpc
+        .system_account_next_index(alice.account_id())
+        .await
+        .unwrap();
+    let best = rpc.block_hash(None).await.unwrap();
+    assert!(ctx
+        .api
+        .tx()
+        .balances()
+        .transfer(bob_address, 10_000)
+        .dry_run(&alice, best)
+        .await
+        .unwrap()
+        .is_success());
+    assert_eq!(
+        rpc.system_account_next_index(alice.account_id())
+            .await
+            .unwrap(),
+        nonce
+    );
+}
+
 #[async_std::test]
 async fn transfer_implicit_subscription() {
     env_logger::try_init().ok();
@@ -265,7 +627,8 @@ async fn transfer_implicit_subscription() {
 #[async_std::test]
 async fn constant_existential_deposit() {
     let cxt = test_context().await;
-    let balances_metadata = cxt.client().metadata().pallet("Balances").unwrap();
+    let metadata = cxt.client().metadata();
+    let balances_metadata = metadata.pallet("Balances").unwrap();
     let constant_metadata = balances_metadata.constant("ExistentialDeposit").unwrap();
     let existential_deposit = u128::decode(&mut &constant_metadata.value[..]).unwrap();
     assert_eq!(existential_deposit, 100_000_000_000_000);
@@ -277,4 +640,65 @@ async fn constant_existential_deposit() {
             .existential_deposit()
             .unwrap()
     );
+    assert_eq!(
+        existential_deposit,
+        cxt.client()
+            .constant::<u128>("Balances", "ExistentialDeposit")
+            .unwrap()
+    );
+}
+
+#[async_std::test]
+async fn tx_transfer_from_ed25519_and_ecdsa_accounts() -> Result<(), Error<DispatchError>>
+{
+    use sp_core::{
+        ecdsa,
+        ed25519,
+    };
+    use subxt::{
+        DefaultConfig,
+        EcdsaSigner,
+        Ed25519Signer,
+        PairSigner,
+    };
+    type Extra = crate::NodeRuntimeSignedExtra;
+
+    let alice = pair_signer(AccountKeyring::Alice.pair());
+    let bob = pair_signer(AccountKeyring::Bob.pair());
+    let ed25519_signer: Ed25519Signer<DefaultConfig, Extra> =
+        PairSigner::new(ed25519::Pair::from_string("//Alice", None).unwrap());
+    let ecdsa_signer: EcdsaSigner<DefaultConfig, Extra> =
+        PairSigner::new(ecdsa::Pair::from_string("//Alice", None).unwrap());
+    let cxt = test_context().await;
+    let api = &cxt.api;
+
+    // Fund both accounts, which then transfer on to Bob.
+    for account in [ed25519_signer.account_id(), ecdsa_signer.account_id()] {
+        api.tx()
+            .balances()
+            .transfer(account.clone().into(), 1_000_000_000_000_000)
+            .sign_and_submit_then_watch(&alice)
+            .await?
+            .wait_for_finalized_success()
+            .await?;
+    }
+    for signer in [
+        &ed25519_signer as &(dyn Signer<DefaultConfig, Extra> + Send + Sync),
+        &ecdsa_signer,
+    ] {
+        let event = api
+            .tx()
+            .balances()
+            .transfer(bob.account_id().clone().into(), 10_000)
+            .sign_and_submit_then_watch(signer)
+            .await?
+            .wait_for_finalized_success()
+            .await?
+            .find_first::<balances::events::Transfer>()
+            .expect("Failed to decode balances::events::Transfer")
+            .expect("Failed to find balances::events::Transfer");
+        assert_eq!(&event.from, signer.account_id());
+    }
+
+    Ok(())
 }
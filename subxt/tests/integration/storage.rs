@@ -137,3 +137,242 @@ async fn storage_n_map_storage_lookup() -> Result<(), subxt::Error<DispatchError
     assert_eq!(entry.map(|a| a.amount), Some(123));
     Ok(())
 }
+
+#[async_std::test]
+async fn storage_pairs_lookup() -> Result<(), subxt::Error<DispatchError>> {
+    use node_runtime::system::storage::Account;
+
+    let ctx = test_context().await;
+    let accounts = ctx.client().storage().fetch_pairs::<Account>(None).await?;
+
+    // The dev chain endows the well known accounts, including Alice and Bob.
+    assert!(accounts.len() > 2);
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn storage_iter_with_decoded_keys() -> Result<(), subxt::Error<DispatchError>> {
+    use node_runtime::system::storage::Account;
+    use sp_core::crypto::AccountId32;
+
+    let ctx = test_context().await;
+    let mut iter = ctx
+        .client()
+        .storage()
+        .iter::<Account>(None)
+        .await?
+        .with_keys::<AccountId32>();
+
+    let mut account_ids = Vec::new();
+    while let Some((account_id, _)) = iter.next().await? {
+        account_ids.push(account_id);
+    }
+
+    // The well known dev accounts are endowed, so their ids show up among the keys.
+    assert!(account_ids.contains(&AccountKeyring::Alice.to_account_id()));
+    assert!(account_ids.contains(&AccountKeyring::Bob.to_account_id()));
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn storage_stream_pairs() -> Result<(), subxt::Error<DispatchError>> {
+    use futures::TryStreamExt;
+    use node_runtime::system::storage::Account;
+
+    let ctx = test_context().await;
+    let hash = ctx.client().rpc().block_hash(None).await?;
+    let storage = ctx.client().storage().set_prefetch(2);
+
+    let streamed: Vec<_> = storage.stream_pairs::<Account>(hash).try_collect().await?;
+    let fetched = storage.fetch_pairs::<Account>(hash).await?;
+
+    assert!(streamed.len() > 2);
+    assert_eq!(
+        streamed.iter().map(|(key, _)| key).collect::<Vec<_>>(),
+        fetched.iter().map(|(key, _)| key).collect::<Vec<_>>()
+    );
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn storage_fetch_verified() -> Result<(), subxt::Error<DispatchError>> {
+    use node_runtime::system::storage::Account;
+
+    let ctx = test_context().await;
+    let hash = ctx.client().rpc().block_hash(None).await?;
+    let alice = AccountKeyring::Alice.to_account_id();
+    let nobody = sp_core::crypto::AccountId32::new([99; 32]);
+    let storage = ctx.client().storage();
+
+    let verified = storage.fetch_verified(&Account(&alice), hash).await?;
+    let fetched = storage.fetch(&Account(&alice), hash).await?;
+    assert_eq!(verified.map(|a| a.data.free), fetched.map(|a| a.data.free));
+
+    // The proof shows that there's no such account, too.
+    assert!(storage
+        .fetch_verified(&Account(&nobody), hash)
+        .await?
+        .is_none());
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn child_storage_of_missing_trie_is_empty(
+) -> Result<(), subxt::Error<DispatchError>> {
+    use sp_core::storage::{
+        ChildInfo,
+        StorageKey,
+    };
+
+    let ctx = test_context().await;
+    let child_info = ChildInfo::new_default(b"no such child trie");
+    let child_storage = ctx.client().child_storage(&child_info);
+
+    let value = child_storage
+        .fetch_raw(&StorageKey(b"key".to_vec()), None)
+        .await?;
+    assert!(value.is_none());
+    let keys = child_storage.fetch_all_keys(None, None).await?;
+    assert!(keys.is_empty());
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn storage_fetch_many() -> Result<(), subxt::Error<DispatchError>> {
+    use node_runtime::system::storage::Account;
+
+    let ctx = test_context().await;
+    let alice = AccountKeyring::Alice.to_account_id();
+    let bob = AccountKeyring::Bob.to_account_id();
+    let nobody = sp_core::crypto::AccountId32::new([99; 32]);
+    let entries = [Account(&alice), Account(&nobody), Account(&bob)];
+
+    let values = ctx.client().storage().fetch_many(&entries, None).await?;
+    assert_eq!(values.len(), 3);
+    assert!(values[0].is_some());
+    assert!(values[1].is_none());
+    assert!(values[2].is_some());
+
+    // The same values as looking the accounts up one at a time:
+    let bob_account = ctx.api.storage().system().account(&bob, None).await?;
+    assert_eq!(
+        values[2].as_ref().map(|a| a.data.free),
+        Some(bob_account.data.free)
+    );
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn storage_query_changes() -> Result<(), subxt::Error<DispatchError>> {
+    use node_runtime::system::storage::Account;
+
+    let ctx = test_context().await;
+    let signer = pair_signer(AccountKeyring::Alice.pair());
+    let alice = AccountKeyring::Alice.to_account_id();
+
+    // Bump the nonce of Alice, so that her account changes after genesis:
+    ctx.api
+        .tx()
+        .system()
+        .remark(vec![1, 2, 3])
+        .sign_and_submit_then_watch(&signer)
+        .await?
+        .wait_for_finalized_success()
+        .await?;
+
+    let genesis = *ctx.client().genesis();
+    let changes = ctx
+        .client()
+        .storage()
+        .query_changes(&[Account(&alice)], genesis, None)
+        .await?;
+
+    // The account at genesis, and then as changed by the remark:
+    assert!(changes.len() >= 2);
+    assert_eq!(changes[0].block, genesis);
+    let nonces: Vec<_> = changes
+        .iter()
+        .map(|change| change.changes[0].1.as_ref().map(|account| account.nonce))
+        .collect();
+    assert_eq!(nonces.first(), Some(&Some(0)));
+    assert_eq!(nonces.last(), Some(&Some(1)));
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn storage_subscription() -> Result<(), subxt::Error<DispatchError>> {
+    use futures::StreamExt;
+    use node_runtime::system::storage::Account;
+
+    let ctx = test_context().await;
+    let signer = pair_signer(AccountKeyring::Alice.pair());
+    let alice = AccountKeyring::Alice.to_account_id();
+
+    let storage = ctx.client().storage();
+    let entries = [Account(&alice)];
+    let changes = storage.subscribe(&entries).await?;
+    futures::pin_mut!(changes);
+
+    // The current value comes first:
+    let current = changes.next().await.unwrap()?;
+    assert_eq!(current.changes[0].1.as_ref().map(|a| a.nonce), Some(0));
+
+    ctx.api
+        .tx()
+        .system()
+        .remark(vec![1, 2, 3])
+        .sign_and_submit_then_watch(&signer)
+        .await?
+        .wait_for_in_block()
+        .await?;
+
+    let changed = changes.next().await.unwrap()?;
+    assert_eq!(changed.changes[0].1.as_ref().map(|a| a.nonce), Some(1));
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn storage_dynamic_lookup() -> Result<(), subxt::Error<DispatchError>> {
+    use node_runtime::system::storage::Account;
+    use subxt::dynamic::Value;
+
+    let ctx = test_context().await;
+    let hash = ctx.client().rpc().block_hash(None).await?;
+    let alice = AccountKeyring::Alice.to_account_id();
+    let storage = ctx.client().storage();
+
+    let account = storage
+        .dynamic("System", "Account", &[Value::bytes(&alice)], hash)
+        .await?
+        .expect("accounts have a default value");
+    let fetched = storage.fetch_or_default(&Account(&alice), hash).await?;
+    assert_eq!(
+        account.field("nonce").and_then(Value::as_u128),
+        Some(fetched.nonce as u128)
+    );
+    assert_eq!(
+        account
+            .field("data")
+            .and_then(|data| data.field("free"))
+            .and_then(Value::as_u128),
+        Some(fetched.data.free)
+    );
+
+    // Plain entries take no keys.
+    let now = storage.dynamic("Timestamp", "Now", &[], hash).await?;
+    assert!(now.and_then(|now| now.as_u128()).is_some());
+    assert!(storage
+        .dynamic("Timestamp", "Now", &[Value::uint(1u8)], hash)
+        .await
+        .is_err());
+
+    Ok(())
+}
@@ -47,7 +47,7 @@ pub async fn test_node_process_with(
     });
 
     let proc = TestNodeProcess::<DefaultConfig>::build(path.as_str())
-        .with_authority(key)
+        .with_authority(format!("{:?}", key))
         .scan_for_open_ports()
         .spawn::<DefaultConfig>()
         .await;
@@ -15,7 +15,6 @@
 // along with subxt.  If not, see <http://www.gnu.org/licenses/>.
 
 mod context;
-mod node_proc;
 
 pub use context::*;
-pub use node_proc::TestNodeProcess;
+pub use subxt::testing::TestNodeProcess;